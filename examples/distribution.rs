@@ -1,5 +1,10 @@
-use faro_sharding::shard_for;
-use std::collections::BTreeMap;
+use faro_sharding::seahash::StableSeaHasher;
+use faro_sharding::stats::DistributionReport;
+use faro_sharding::{FaroStrategy, ShardingStrategy};
+use std::fs::File;
+use std::hash::BuildHasher;
+use std::io::{self, BufRead, BufReader};
+use std::path::PathBuf;
 use structopt::*;
 
 #[derive(StructOpt)]
@@ -10,54 +15,234 @@ struct Options {
     #[structopt(long, default_value = "100")]
     locations: u64,
 
+    /// Number of synthetic `base-i` keys. Ignored with --input.
     #[structopt(long, default_value = "1000000")]
     keys: u64,
 
+    /// Read one key per line from this file instead of generating keys. `-` reads stdin.
+    #[structopt(long)]
+    input: Option<PathBuf>,
+
+    /// text, json, or csv.
+    #[structopt(long, default_value = "text")]
+    format: Format,
+
     #[structopt(long)]
     print_shard_counts: bool,
 }
 
-fn main() {
+enum Format {
+    Text,
+    Json,
+    Csv,
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            _ => Err(format!(
+                "unknown format {:?}, expected text, json, or csv",
+                s
+            )),
+        }
+    }
+}
+
+struct Run {
+    name: &'static str,
+    strategy: Box<dyn ShardingStrategy>,
+    shard_counts: Vec<u64>,
+    moved: u64,
+}
+
+fn main() -> io::Result<()> {
     let options = Options::from_args();
+    let hasher = StableSeaHasher::new();
 
-    println!(
-        "Testing distribution of {} keys across {} locations",
-        options.keys, options.locations
-    );
+    #[allow(unused_mut)]
+    let mut strategies: Vec<(&'static str, Box<dyn ShardingStrategy>)> =
+        vec![("faro", Box::new(FaroStrategy(hasher)))];
+    #[cfg(feature = "compare")]
+    {
+        use faro_sharding::compare::{JumpStrategy, RendezvousStrategy};
+        strategies.push(("jump", Box::new(JumpStrategy)));
+        strategies.push(("rendezvous", Box::new(RendezvousStrategy(hasher))));
+    }
+
+    let mut runs = strategies
+        .into_iter()
+        .map(|(name, strategy)| Run {
+            name,
+            strategy,
+            shard_counts: vec![0; options.locations as usize],
+            moved: 0,
+        })
+        .collect::<Vec<_>>();
 
-    let mut location_counts = BTreeMap::<u64, u64>::new();
-    for i in 0..options.keys {
-        let shard = shard_for(&format!("{}-{i}", options.base), options.locations);
-        *location_counts.entry(shard).or_default() += 1u64;
+    let mut shard_key = |key: &str| {
+        let key_hash = hasher.hash_one(key);
+        for run in &mut runs {
+            let shard = run.strategy.shard_for_hash(key_hash, options.locations);
+            run.shard_counts[shard as usize] += 1;
+            if run.strategy.shard_for_hash(key_hash, options.locations + 1) != shard {
+                run.moved += 1;
+            }
+        }
+    };
+
+    match &options.input {
+        Some(path) => {
+            let reader: Box<dyn BufRead> = if path.as_os_str() == "-" {
+                Box::new(io::stdin().lock())
+            } else {
+                Box::new(BufReader::new(File::open(path)?))
+            };
+            for line in reader.lines() {
+                shard_key(&line?);
+            }
+        }
+        None => {
+            for i in 0..options.keys {
+                shard_key(&format!("{}-{i}", options.base));
+            }
+        }
     }
 
-    let min = location_counts
-        .iter()
-        .min_by_key(|(_, c)| **c)
-        .expect("keys > 0");
+    let reports = runs
+        .into_iter()
+        .map(|run| {
+            (
+                run.name,
+                DistributionReport::from_counts(run.shard_counts),
+                run.moved,
+            )
+        })
+        .collect::<Vec<_>>();
 
-    let min_percent = 100. * *min.1 as f32 / options.keys as f32;
-    println!(
-        "Shard {} had the fewest keys - {} ({:.2}%)",
-        min.0, min.1, min_percent
-    );
+    match options.format {
+        Format::Text => print_text(&options, &reports),
+        Format::Json => print_json(&options, &reports),
+        Format::Csv => print_csv(&options, &reports),
+    }
+    Ok(())
+}
 
-    let max = location_counts
-        .iter()
-        .max_by_key(|(_, c)| **c)
-        .expect("keys > 0");
+fn percent(count: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.
+    } else {
+        100. * count as f64 / total as f64
+    }
+}
 
-    let max_percent = 100. * *max.1 as f32 / options.keys as f32;
+fn print_text(options: &Options, reports: &[(&str, DistributionReport, u64)]) {
+    let total = reports
+        .first()
+        .map_or(0, |(_, report, _)| report.total_keys());
     println!(
-        "Shard {} had the most keys - {} ({:.2}%)",
-        max.0, max.1, max_percent
+        "Testing distribution of {} keys across {} locations",
+        total, options.locations
     );
 
-    if options.print_shard_counts {
-        println!("\nShard counts:");
+    for (name, report, moved) in reports {
+        println!("\n{name}:");
+
+        let counts = &report.shard_counts;
+        let min = (0..)
+            .zip(counts)
+            .min_by_key(|(_, c)| **c)
+            .expect("locations > 0");
+        println!(
+            "Shard {} had the fewest keys - {} ({:.2}%)",
+            min.0,
+            min.1,
+            percent(*min.1, total)
+        );
+
+        let max = (0..)
+            .zip(counts)
+            .max_by_key(|(_, c)| **c)
+            .expect("locations > 0");
+        println!(
+            "Shard {} had the most keys - {} ({:.2}%)",
+            max.0,
+            max.1,
+            percent(*max.1, total)
+        );
+
+        println!(
+            "Coefficient of variation {:.4}, chi-square {:.2}",
+            report.coefficient_of_variation, report.chi_square
+        );
+        println!(
+            "Adding location #{} moved {} keys ({:.2}%)",
+            options.locations + 1,
+            moved,
+            percent(*moved, total)
+        );
 
-        for (shard, count) in location_counts {
-            println!("  #{shard} - {count}");
+        if options.print_shard_counts {
+            println!("\nShard counts:");
+
+            for (shard, count) in counts.iter().enumerate() {
+                println!("  #{shard} - {count}");
+            }
         }
     }
 }
+
+fn print_json(options: &Options, reports: &[(&str, DistributionReport, u64)]) {
+    let reports = reports
+        .iter()
+        .map(|(name, report, moved)| {
+            serde_json::json!({
+                "algorithm": name,
+                "locations": options.locations,
+                "keys": report.total_keys(),
+                "min": report.min,
+                "max": report.max,
+                "mean": report.mean,
+                "stddev": report.stddev,
+                "coefficient_of_variation": report.coefficient_of_variation,
+                "chi_square": report.chi_square,
+                "moved_keys": moved,
+                "shard_counts": report.shard_counts,
+            })
+        })
+        .collect::<Vec<_>>();
+    println!("{}", serde_json::Value::Array(reports));
+}
+
+fn print_csv(options: &Options, reports: &[(&str, DistributionReport, u64)]) {
+    if options.print_shard_counts {
+        println!("algorithm,shard,count");
+        for (name, report, _) in reports {
+            for (shard, count) in report.shard_counts.iter().enumerate() {
+                println!("{name},{shard},{count}");
+            }
+        }
+        return;
+    }
+
+    println!("algorithm,locations,keys,min,max,mean,stddev,coefficient_of_variation,chi_square,moved_keys");
+    for (name, report, moved) in reports {
+        println!(
+            "{},{},{},{},{},{},{},{},{},{}",
+            name,
+            options.locations,
+            report.total_keys(),
+            report.min,
+            report.max,
+            report.mean,
+            report.stddev,
+            report.coefficient_of_variation,
+            report.chi_square,
+            moved
+        );
+    }
+}
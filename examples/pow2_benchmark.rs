@@ -0,0 +1,47 @@
+use faro_sharding::{seahash::StableSeaHasher, shard_for, shard_for_pow2_fast};
+use std::time::Instant;
+use structopt::*;
+
+#[derive(StructOpt)]
+struct Options {
+    #[structopt(long, default_value = "1000000")]
+    keys: u64,
+
+    #[structopt(long, default_value = "4")]
+    log2_locations: u8,
+}
+
+fn main() {
+    let options = Options::from_args();
+    let locations = 1 << options.log2_locations;
+
+    println!(
+        "Sharding {} keys across {} locations",
+        options.keys, locations
+    );
+
+    let start = Instant::now();
+    let mut checksum = 0u64;
+    for i in 0..options.keys {
+        checksum = checksum.wrapping_add(shard_for(i, locations));
+    }
+    let faro = start.elapsed();
+    println!("shard_for:           {faro:?} (checksum {checksum})");
+
+    let start = Instant::now();
+    let mut checksum = 0u64;
+    for i in 0..options.keys {
+        checksum = checksum.wrapping_add(shard_for_pow2_fast(
+            i,
+            options.log2_locations,
+            &StableSeaHasher::new(),
+        ));
+    }
+    let fast = start.elapsed();
+    println!("shard_for_pow2_fast: {fast:?} (checksum {checksum})");
+
+    println!(
+        "shard_for_pow2_fast was {:.1}x faster",
+        faro.as_secs_f64() / fast.as_secs_f64()
+    );
+}
@@ -0,0 +1,155 @@
+//! `#[derive(ShardKey)]` for [faro_sharding](https://docs.rs/faro_sharding). Use it through the `derive` feature of `faro_sharding`, which re-exports it.
+
+#![deny(missing_docs)]
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::*;
+
+/// Derives `faro_sharding::ShardKey` with the canonical encoding described there.
+///
+/// Named fields are encoded sorted by name, each preceded by its name, so reordering fields does not change the encoding. Enum variants are encoded by name. Both accept `#[shard_key(rename = "...")]` to keep the old name after renaming, and fields accept `#[shard_key(skip)]`.
+#[proc_macro_derive(ShardKey, attributes(shard_key))]
+pub fn derive_shard_key(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(mut input: DeriveInput) -> Result<TokenStream> {
+    for param in input.generics.type_params_mut() {
+        param.bounds.push(parse_quote!(::faro_sharding::ShardKey));
+    }
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let (pattern, encode) = encode_fields(quote!(Self), &data.fields)?;
+            quote! {
+                let #pattern = self;
+                #encode
+            }
+        }
+        Data::Enum(data) => {
+            let arms = data
+                .variants
+                .iter()
+                .map(|variant| {
+                    let attrs = Attrs::parse(&variant.attrs)?;
+                    if attrs.skip {
+                        return Err(Error::new_spanned(variant, "variants cannot be skipped"));
+                    }
+                    let variant_name = attrs.rename.unwrap_or_else(|| variant.ident.to_string());
+                    let ident = &variant.ident;
+                    let (pattern, encode) = encode_fields(quote!(Self::#ident), &variant.fields)?;
+                    Ok(quote! {
+                        #pattern => {
+                            ::faro_sharding::ShardKey::encode_shard_key(#variant_name, out);
+                            #encode
+                        }
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            quote! {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+        Data::Union(data) => {
+            return Err(Error::new_spanned(
+                data.union_token,
+                "ShardKey cannot be derived for unions",
+            ))
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::faro_sharding::ShardKey for #name #ty_generics #where_clause {
+            fn encode_shard_key(&self, out: &mut ::std::vec::Vec<u8>) {
+                #body
+            }
+        }
+    })
+}
+
+/// Returns a pattern binding every encoded field of `path`, and the statements encoding them. Skipped fields are matched with `_`.
+fn encode_fields(path: TokenStream, fields: &Fields) -> Result<(TokenStream, TokenStream)> {
+    match fields {
+        Fields::Named(named) => {
+            let mut encoded = Vec::new();
+            let mut bindings = Vec::new();
+            for field in &named.named {
+                let attrs = Attrs::parse(&field.attrs)?;
+                let ident = field.ident.as_ref().unwrap();
+                if attrs.skip {
+                    bindings.push(quote!(#ident: _));
+                } else {
+                    bindings.push(quote!(#ident));
+                    let name = attrs.rename.unwrap_or_else(|| ident.to_string());
+                    encoded.push((name, ident));
+                }
+            }
+            encoded.sort_by(|(a, _), (b, _)| a.cmp(b));
+            if let Some(pair) = encoded.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+                return Err(Error::new_spanned(
+                    pair[1].1,
+                    format!("two fields are encoded as `{}`", pair[1].0),
+                ));
+            }
+
+            let encode = encoded.iter().map(|(name, ident)| {
+                quote! {
+                    ::faro_sharding::ShardKey::encode_shard_key(#name, out);
+                    ::faro_sharding::ShardKey::encode_shard_key(#ident, out);
+                }
+            });
+            Ok((quote!(#path { #(#bindings,)* }), quote!(#(#encode)*)))
+        }
+        Fields::Unnamed(unnamed) => {
+            let mut bindings = Vec::new();
+            let mut encode = Vec::new();
+            for (i, field) in unnamed.unnamed.iter().enumerate() {
+                let binding = Ident::new(&format!("field_{}", i), proc_macro2::Span::call_site());
+                if Attrs::parse(&field.attrs)?.skip {
+                    bindings.push(quote!(_));
+                    continue;
+                }
+                encode.push(quote! {
+                    ::faro_sharding::ShardKey::encode_shard_key(#binding, out);
+                });
+                bindings.push(quote!(#binding));
+            }
+            Ok((quote!(#path ( #(#bindings,)* )), quote!(#(#encode)*)))
+        }
+        Fields::Unit => Ok((path, TokenStream::new())),
+    }
+}
+
+#[derive(Default)]
+struct Attrs {
+    skip: bool,
+    rename: Option<String>,
+}
+
+impl Attrs {
+    fn parse(attrs: &[Attribute]) -> Result<Self> {
+        let mut parsed = Attrs::default();
+        for attr in attrs.iter().filter(|a| a.path().is_ident("shard_key")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("skip") {
+                    parsed.skip = true;
+                    Ok(())
+                } else if meta.path.is_ident("rename") {
+                    parsed.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `skip` or `rename = \"...\"`"))
+                }
+            })?;
+        }
+        Ok(parsed)
+    }
+}
@@ -0,0 +1,58 @@
+//! Node.js bindings for [faro_sharding](https://docs.rs/faro_sharding), built with napi-rs from the same repository so a Node service and a Rust service always compute the same shards.
+//!
+//! Every export uses the default hasher of `faro_sharding::shard_for`. A destination count of 0 throws instead of panicking.
+
+#![deny(missing_docs)]
+
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+
+/// The shard of a string or `Buffer` key.
+///
+/// A string agrees with `shard_for(key: &str, ..)` in Rust, and a `Buffer` with `shard_for_bytes`. The two differ for the same bytes, as Rust hashes strings with a terminator.
+#[napi(js_name = "shardFor")]
+pub fn shard_for(key: Either<String, Buffer>, locations: u32) -> Result<u32> {
+    let locations = check(locations)?;
+    let shard = match key {
+        Either::A(key) => faro_sharding::shard_for(key.as_str(), locations),
+        Either::B(key) => faro_sharding::shard_for_bytes(&key, locations),
+    };
+    Ok(shard as u32)
+}
+
+/// [shard_for] of every string key, in the same order.
+#[napi(js_name = "shardForMany")]
+pub fn shard_for_many(keys: Vec<String>, locations: u32) -> Result<Uint32Array> {
+    let locations = check(locations)?;
+    let shards = faro_sharding::shard_for_many_vec(
+        keys,
+        locations,
+        &faro_sharding::seahash::StableSeaHasher::new(),
+    );
+    Ok(Uint32Array::new(
+        shards.into_iter().map(|s| s as u32).collect(),
+    ))
+}
+
+fn check(locations: u32) -> Result<u64> {
+    if locations == 0 {
+        return Err(Error::from_reason("locations must be > 0"));
+    }
+    Ok(u64::from(locations))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn agrees_with_the_crate() {
+        // Do not change these values. See `pinning_default_shard`.
+        assert_eq!(shard_for(Either::A("foo".to_string()), 73).unwrap(), 49);
+        assert_eq!(
+            shard_for(Either::B(b"foo".to_vec().into()), 73).unwrap(),
+            69
+        );
+        assert!(shard_for(Either::A("foo".to_string()), 0).is_err());
+    }
+}
@@ -0,0 +1,89 @@
+#ifndef FARO_SHARDING_H
+#define FARO_SHARDING_H
+
+/* Generated by cbindgen from src/ffi.rs. Do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Result codes returned by every `faro_*` function. The values are stable.
+ */
+enum FaroStatus
+#if __STDC_VERSION__ >= 202311L
+  : int32_t
+#endif // __STDC_VERSION__ >= 202311L
+ {
+  /**
+   * Success. The out pointer was written.
+   */
+  FARO_STATUS_OK = 0,
+  /**
+   * A required pointer was null.
+   */
+  FARO_STATUS_NULL_POINTER = 1,
+  /**
+   * total_destinations was 0.
+   */
+  FARO_STATUS_ZERO_DESTINATIONS = 2,
+  /**
+   * A string key was not valid UTF-8.
+   */
+  FARO_STATUS_INVALID_UTF8 = 3,
+};
+#if __STDC_VERSION__ >= 202311L
+typedef enum FaroStatus FaroStatus;
+#else
+typedef int32_t FaroStatus;
+#endif // __STDC_VERSION__ >= 202311L
+
+/**
+ * Writes [crate::shard_for_bytes] of the `len` bytes at `key` to `out_shard`.
+ *
+ * `key` may be null if `len` is 0.
+ *
+ * # Safety
+ *
+ * `key` must point to `len` readable bytes, and `out_shard` must be valid for writes.
+ */
+FaroStatus faro_shard_for_bytes(const uint8_t *key,
+                                size_t len,
+                                uint64_t total_destinations,
+                                uint64_t *out_shard);
+
+/**
+ * Writes [crate::shard_for] of the UTF-8 string of `len` bytes at `key` to `out_shard`. Agrees with `shard_for(key: &str, ..)` in Rust.
+ *
+ * `key` may be null if `len` is 0. It does not need to be nul terminated.
+ *
+ * # Safety
+ *
+ * `key` must point to `len` readable bytes, and `out_shard` must be valid for writes.
+ */
+FaroStatus faro_shard_for_str(const char *key,
+                              size_t len,
+                              uint64_t total_destinations,
+                              uint64_t *out_shard);
+
+/**
+ * Writes [crate::shard_for_key_hash] of `key_hash` to `out_shard`.
+ *
+ * # Safety
+ *
+ * `out_shard` must be valid for writes.
+ */
+FaroStatus faro_shard_for_key_hash(uint64_t key_hash,
+                                   uint64_t total_destinations,
+                                   uint64_t *out_shard);
+
+/**
+ * A static, nul terminated description of `status`, a [FaroStatus] value.
+ *
+ * Takes a plain integer, since C can pass any value. Values that are not a [FaroStatus] get "unknown status".
+ */
+const char *faro_status_message(int32_t status);
+
+#endif  /* FARO_SHARDING_H */
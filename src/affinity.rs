@@ -0,0 +1,136 @@
+use crate::shard_with_hasher;
+use core::hash::*;
+
+/// A key with a part that decides its shard, so related keys are co-located.
+///
+/// Keys with equal affinities always land on the same shard, the shard of the affinity itself. For example, an order's line items can route by order id:
+///
+/// ```
+/// # use faro_sharding::*;
+/// # use std::hash::*;
+/// # use std::collections::hash_map::DefaultHasher;
+/// struct LineItemKey {
+///     order_id: u64,
+///     line: u32,
+/// }
+///
+/// impl AffinityKey for LineItemKey {
+///     fn affinity(&self) -> impl Hash + '_ {
+///         self.order_id
+///     }
+/// }
+///
+/// let hasher = BuildHasherDefault::<DefaultHasher>::default();
+/// let item = LineItemKey { order_id: 42, line: 3 };
+/// assert_eq!(
+///     shard_by_affinity_with_hasher(&item, 16, &hasher),
+///     shard_with_hasher(42u64, 16, &hasher),
+/// );
+/// ```
+///
+/// Tuples route by their first element, and [HashTag] routes strings by a `{...}` section.
+pub trait AffinityKey {
+    /// The part of the key that decides its shard.
+    fn affinity(&self) -> impl Hash + '_;
+}
+
+impl<T: AffinityKey + ?Sized> AffinityKey for &T {
+    fn affinity(&self) -> impl Hash + '_ {
+        (**self).affinity()
+    }
+}
+
+impl<A: Hash, B> AffinityKey for (A, B) {
+    fn affinity(&self) -> impl Hash + '_ {
+        &self.0
+    }
+}
+
+impl<A: Hash, B, C> AffinityKey for (A, B, C) {
+    fn affinity(&self) -> impl Hash + '_ {
+        &self.0
+    }
+}
+
+/// A string key routed by its hash tag, as in Redis Cluster.
+///
+/// If the key contains `{` followed later by `}` with something between them, only the first such section decides the shard. Otherwise the whole key does. So `{order:42}` and `{order:42}:line:3` are co-located, on the shard of `order:42`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct HashTag<'a>(pub &'a str);
+
+impl HashTag<'_> {
+    /// The section of the key that decides its shard.
+    pub fn tag(&self) -> &str {
+        let key = self.0;
+        let Some(open) = key.find('{') else {
+            return key;
+        };
+        match key[open + 1..].find('}') {
+            Some(len) if len > 0 => &key[open + 1..open + 1 + len],
+            _ => key,
+        }
+    }
+}
+
+impl AffinityKey for HashTag<'_> {
+    fn affinity(&self) -> impl Hash + '_ {
+        self.tag()
+    }
+}
+
+/// Returns the shard for `key`'s [AffinityKey::affinity], using the hasher provided.
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub fn shard_by_affinity_with_hasher(
+    key: &impl AffinityKey,
+    total_destinations: u64,
+    hasher: &impl BuildHasher,
+) -> u64 {
+    shard_with_hasher(key.affinity(), total_destinations, hasher)
+}
+
+/// [shard_by_affinity_with_hasher] using the default hasher of [crate::shard_for].
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+#[cfg(feature = "seahash")]
+pub fn shard_by_affinity(key: &impl AffinityKey, total_destinations: u64) -> u64 {
+    shard_by_affinity_with_hasher(
+        key,
+        total_destinations,
+        &crate::seahash::StableSeaHasher::new(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shard_for;
+
+    #[test]
+    fn related_keys_are_co_located() {
+        for order_id in 0..1_000u64 {
+            let expected = shard_for(order_id, 73);
+            for line in 0..5u32 {
+                assert_eq!(shard_by_affinity(&(order_id, line), 73), expected);
+                assert_eq!(shard_by_affinity(&(order_id, line, "sku"), 73), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn hash_tags() {
+        assert_eq!(HashTag("{order:42}:line:3").tag(), "order:42");
+        assert_eq!(HashTag("line:{order:42}").tag(), "order:42");
+        assert_eq!(HashTag("order:42").tag(), "order:42");
+        assert_eq!(HashTag("{}order:42").tag(), "{}order:42");
+        assert_eq!(HashTag("{order:42").tag(), "{order:42");
+        assert_eq!(
+            shard_by_affinity(&HashTag("{order:42}:line:3"), 73),
+            shard_for("order:42", 73)
+        );
+    }
+}
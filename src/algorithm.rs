@@ -0,0 +1,421 @@
+use crate::skip_ahead::skip_ahead_from_key_hash;
+use crate::{shard_for_key_hash_with_mixer, SplitMix64};
+use core::hash::*;
+
+/// A versioned key-to-shard mapping.
+///
+/// Each variant is a fixed mapping that will not change across crate versions. Record the algorithm data was written with, for example with its [Algorithm::name], so it can be read back with the same one.
+///
+/// With the `serde` feature, an algorithm is serialized as its [Algorithm::name].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+#[non_exhaustive]
+pub enum Algorithm {
+    /// [crate::shard_with_hasher]. The default.
+    #[default]
+    FaroV1,
+    /// [crate::v2::shard_with_hasher], with an unbiased move decision.
+    FaroV2,
+    /// [crate::shard_with_hasher_fast].
+    SkipAheadV1,
+    /// [crate::shard_with_mixer] with [SplitMix64]. Much faster than [Algorithm::FaroV1] for large destination counts.
+    FaroMixV1,
+}
+
+impl Algorithm {
+    /// Every algorithm, oldest first.
+    pub const ALL: &'static [Algorithm] = &[
+        Algorithm::FaroV1,
+        Algorithm::FaroV2,
+        Algorithm::SkipAheadV1,
+        Algorithm::FaroMixV1,
+    ];
+
+    /// A stable identifier for this algorithm, such as `"faro-v1"`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Algorithm::FaroV1 => "faro-v1",
+            Algorithm::FaroV2 => "faro-v2",
+            Algorithm::SkipAheadV1 => "skip-ahead-v1",
+            Algorithm::FaroMixV1 => "faro-mix-v1",
+        }
+    }
+
+    /// Returns the shard for an already hashed key under this algorithm.
+    ///
+    /// # Panics
+    ///
+    /// If total_destinations == 0.
+    pub fn shard_for_key_hash(
+        &self,
+        key_hash: u64,
+        total_destinations: u64,
+        hasher: &impl BuildHasher,
+    ) -> u64 {
+        assert_ne!(total_destinations, 0, "total_destinations must be > 0");
+
+        match self {
+            Algorithm::FaroV1 => crate::shard_for_key_hash(key_hash, total_destinations, hasher),
+            Algorithm::FaroV2 => {
+                crate::v2::shard_for_key_hash(key_hash, total_destinations, hasher)
+            }
+            Algorithm::SkipAheadV1 => {
+                skip_ahead_from_key_hash(key_hash, total_destinations, hasher)
+            }
+            Algorithm::FaroMixV1 => {
+                shard_for_key_hash_with_mixer(key_hash, total_destinations, &SplitMix64)
+            }
+        }
+    }
+}
+
+impl core::fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Error returned when parsing an unknown [Algorithm] name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UnknownAlgorithm;
+
+impl core::fmt::Display for UnknownAlgorithm {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "unknown sharding algorithm")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for UnknownAlgorithm {}
+
+impl core::str::FromStr for Algorithm {
+    type Err = UnknownAlgorithm;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Algorithm::ALL
+            .iter()
+            .find(|a| a.name() == name)
+            .copied()
+            .ok_or(UnknownAlgorithm)
+    }
+}
+
+/// Shards keys with a fixed [Algorithm] and hasher.
+///
+/// Configure one with [Sharder::builder] to pass around a single handle:
+///
+/// ```
+/// # use faro_sharding::*;
+/// let sharder = Sharder::builder()
+///     .seed(7)
+///     .algorithm(Algorithm::FaroV1)
+///     .destinations(50)
+///     .build();
+///
+/// assert_eq!(sharder.shard("foo"), shard_for_seeded("foo", 50, 7));
+/// assert_eq!(sharder.shards("foo", 3)[0], sharder.shard("foo"));
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sharder<S> {
+    algorithm: Algorithm,
+    hasher: S,
+    seed: Option<u64>,
+    destinations: Option<u64>,
+}
+
+#[cfg(feature = "seahash")]
+impl Sharder<crate::seahash::StableSeaHasher> {
+    /// Starts configuring a sharder, with the hasher of [crate::shard_for] and [Algorithm::FaroV1] unless changed.
+    pub fn builder() -> SharderBuilder<crate::seahash::StableSeaHasher> {
+        SharderBuilder::new(crate::seahash::StableSeaHasher::new())
+    }
+}
+
+impl<S: BuildHasher> Sharder<S> {
+    /// Creates a sharder using `algorithm`.
+    pub fn new(algorithm: Algorithm, hasher: S) -> Self {
+        Sharder {
+            algorithm,
+            hasher,
+            seed: None,
+            destinations: None,
+        }
+    }
+
+    /// The algorithm keys are sharded with.
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    /// The hasher keys are sharded with.
+    pub fn hasher(&self) -> &S {
+        &self.hasher
+    }
+
+    /// The keyspace seed, if one was configured.
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// The destination count used by [Sharder::shard], if one was configured.
+    pub fn destinations(&self) -> Option<u64> {
+        self.destinations
+    }
+
+    /// Hashes `key` as this sharder does, including the seed, for [Sharder::shard_for_key_hash].
+    pub fn key_hash(&self, key: impl Hash) -> u64 {
+        match self.seed {
+            Some(seed) => self.hasher.hash_one((seed, key)),
+            None => self.hasher.hash_one(key),
+        }
+    }
+
+    /// Returns the shard for `key` among the configured destinations.
+    ///
+    /// # Panics
+    ///
+    /// If no destination count was configured. See [SharderBuilder::destinations].
+    pub fn shard(&self, key: impl Hash) -> u64 {
+        self.shard_for(key, self.configured_destinations())
+    }
+
+    /// Returns up to `k` distinct shards for `key` among the configured destinations, in preference order, for placing replicas.
+    ///
+    /// The first is always [Sharder::shard]'s shard, and the rest follow [crate::shards_with_hasher].
+    ///
+    /// # Panics
+    ///
+    /// If no destination count was configured. See [SharderBuilder::destinations].
+    #[cfg(feature = "std")]
+    pub fn shards(&self, key: impl Hash, k: usize) -> Vec<u64> {
+        let total_destinations = self.configured_destinations();
+        if k == 0 {
+            return Vec::new();
+        }
+
+        let primary = self.shard(&key);
+        let rest = match self.seed {
+            Some(seed) => {
+                crate::shards_with_hasher((seed, &key), total_destinations, k, &self.hasher)
+            }
+            None => crate::shards_with_hasher(&key, total_destinations, k, &self.hasher),
+        };
+
+        core::iter::once(primary)
+            .chain(rest.into_iter().filter(|&shard| shard != primary))
+            .take(k)
+            .collect()
+    }
+
+    /// Returns the shard for `key`.
+    ///
+    /// # Panics
+    ///
+    /// If total_destinations == 0.
+    pub fn shard_for(&self, key: impl Hash, total_destinations: u64) -> u64 {
+        crate::observe_lookup("sharder", || {
+            self.algorithm
+                .shard_for_key_hash(self.key_hash(key), total_destinations, &self.hasher)
+        })
+    }
+
+    /// Returns the shard for an already hashed key, from [Sharder::key_hash].
+    ///
+    /// # Panics
+    ///
+    /// If total_destinations == 0.
+    pub fn shard_for_key_hash(&self, key_hash: u64, total_destinations: u64) -> u64 {
+        crate::observe_lookup("sharder", || {
+            self.algorithm
+                .shard_for_key_hash(key_hash, total_destinations, &self.hasher)
+        })
+    }
+
+    fn configured_destinations(&self) -> u64 {
+        self.destinations
+            .expect("Sharder was built without a destination count")
+    }
+}
+
+/// Configures a [Sharder]. Returned by [Sharder::builder].
+#[derive(Clone, Copy, Debug)]
+pub struct SharderBuilder<S> {
+    algorithm: Algorithm,
+    hasher: S,
+    seed: Option<u64>,
+    destinations: Option<u64>,
+}
+
+impl<S: BuildHasher> SharderBuilder<S> {
+    /// Starts configuring a sharder using `hasher` and [Algorithm::FaroV1].
+    pub fn new(hasher: S) -> Self {
+        SharderBuilder {
+            algorithm: Algorithm::default(),
+            hasher,
+            seed: None,
+            destinations: None,
+        }
+    }
+
+    /// Hashes keys with `hasher`.
+    pub fn hasher<H: BuildHasher>(self, hasher: H) -> SharderBuilder<H> {
+        SharderBuilder {
+            algorithm: self.algorithm,
+            hasher,
+            seed: self.seed,
+            destinations: self.destinations,
+        }
+    }
+
+    /// Shards within the keyspace identified by `seed`, as in [crate::shard_with_hasher_seeded].
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Shards with `algorithm`.
+    pub fn algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// The destination count for [Sharder::shard] and [Sharder::shards].
+    pub fn destinations(mut self, total_destinations: u64) -> Self {
+        self.destinations = Some(total_destinations);
+        self
+    }
+
+    /// Builds the sharder.
+    ///
+    /// # Panics
+    ///
+    /// If the destination count was set to 0.
+    pub fn build(self) -> Sharder<S> {
+        assert_ne!(self.destinations, Some(0), "total_destinations must be > 0");
+
+        Sharder {
+            algorithm: self.algorithm,
+            hasher: self.hasher,
+            seed: self.seed,
+            destinations: self.destinations,
+        }
+    }
+}
+
+impl<S: BuildHasher> crate::ShardingStrategy for Sharder<S> {
+    fn shard_for_hash(&self, key_hash: u64, total_destinations: u64) -> u64 {
+        self.shard_for_key_hash(key_hash, total_destinations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BuildSeaHasher;
+
+    #[test]
+    fn matches_the_underlying_functions() {
+        let v1 = Sharder::new(Algorithm::FaroV1, BuildSeaHasher);
+        let v2 = Sharder::new(Algorithm::FaroV2, BuildSeaHasher);
+        let fast = Sharder::new(Algorithm::SkipAheadV1, BuildSeaHasher);
+        let mix = Sharder::new(Algorithm::FaroMixV1, BuildSeaHasher);
+
+        for key in 0..1_000u64 {
+            assert_eq!(v1.shard_for(key, 73), crate::shard_for(key, 73));
+            assert_eq!(v2.shard_for(key, 73), crate::v2::shard_for(key, 73));
+            assert_eq!(
+                fast.shard_for(key, 73),
+                crate::shard_with_hasher_fast(key, 73, &BuildSeaHasher)
+            );
+            assert_eq!(
+                mix.shard_for(key, 73),
+                crate::shard_with_mixer(key, 73, &BuildSeaHasher, &SplitMix64)
+            );
+        }
+    }
+
+    #[test]
+    fn builder_matches_the_underlying_functions() {
+        let plain = Sharder::builder().destinations(73).build();
+        let seeded = Sharder::builder()
+            .hasher(BuildSeaHasher)
+            .seed(3)
+            .algorithm(Algorithm::FaroV2)
+            .destinations(73)
+            .build();
+
+        for key in 0..1_000u64 {
+            assert_eq!(plain.shard(key), crate::shard_for(key, 73));
+            assert_eq!(plain.shards(key, 4), crate::shards_for(key, 73, 4));
+            assert_eq!(seeded.shard(key), crate::v2::shard_for((3u64, key), 73));
+
+            let replicas = seeded.shards(key, 4);
+            assert_eq!(replicas[0], seeded.shard(key));
+            assert_eq!(replicas.len(), 4);
+            assert!(replicas.iter().all(|&r| r < 73));
+            assert!((1..4).all(|i| !replicas[..i].contains(&replicas[i])));
+        }
+    }
+
+    #[test]
+    fn every_algorithm_distributes_evenly() {
+        for &algorithm in Algorithm::ALL {
+            let sharder = Sharder::new(algorithm, BuildSeaHasher);
+            let mut counts = [0u64; 16];
+            for key in 0..32_000u64 {
+                counts[sharder.shard_for(key, 16) as usize] += 1;
+            }
+            assert!(
+                crate::stats::imbalance_ratio(&counts) < 1.05,
+                "{}: {:?}",
+                algorithm,
+                counts
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "without a destination count")]
+    fn shard_needs_destinations() {
+        Sharder::new(Algorithm::FaroV1, BuildSeaHasher).shard("foo");
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for algorithm in Algorithm::ALL {
+            assert_eq!(algorithm.name().parse(), Ok(*algorithm));
+        }
+        assert_eq!("faro-v3".parse::<Algorithm>(), Err(UnknownAlgorithm));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serializes_as_names() {
+        for algorithm in Algorithm::ALL {
+            let json = serde_json::to_string(algorithm).unwrap();
+            assert_eq!(json, format!("\"{}\"", algorithm.name()));
+            assert_eq!(
+                serde_json::from_str::<Algorithm>(&json).unwrap(),
+                *algorithm
+            );
+        }
+    }
+
+    #[test]
+    fn all_lists_every_algorithm() {
+        let mut listed = [false; 4];
+        for &algorithm in Algorithm::ALL {
+            // A new variant will not compile here until it has an index, and then fails below until it is in ALL.
+            let index = match algorithm {
+                Algorithm::FaroV1 => 0,
+                Algorithm::FaroV2 => 1,
+                Algorithm::SkipAheadV1 => 2,
+                Algorithm::FaroMixV1 => 3,
+            };
+            listed[index] = true;
+            assert_eq!(algorithm.name().parse(), Ok(algorithm));
+        }
+        assert_eq!(listed, [true; 4]);
+    }
+}
@@ -0,0 +1,154 @@
+//! Checking that keys actually live where the algorithm places them.
+//!
+//! ```
+//! # use faro_sharding::audit::audit_with_hasher;
+//! # use faro_sharding::shard_with_hasher;
+//! # use std::hash::BuildHasherDefault;
+//! # use std::collections::hash_map::DefaultHasher;
+//! let hasher = BuildHasherDefault::<DefaultHasher>::default();
+//! let wrong = (shard_with_hasher("b", 8, &hasher) + 1) % 8;
+//! let snapshot = vec![("a", shard_with_hasher("a", 8, &hasher)), ("b", wrong)];
+//!
+//! let report = audit_with_hasher(snapshot, 8, &hasher);
+//! assert_eq!(report.total_keys(), 2);
+//! assert_eq!(report.misplaced_keys(), 1);
+//! ```
+
+use crate::shard_with_hasher;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::hash::*;
+
+/// Audits `snapshot` against [shard_with_hasher] across `total_destinations`.
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub fn audit_with_hasher<K: Hash>(
+    snapshot: impl IntoIterator<Item = (K, u64)>,
+    total_destinations: u64,
+    hasher: &impl BuildHasher,
+) -> AuditReport<K> {
+    assert_ne!(total_destinations, 0, "total_destinations must be > 0");
+    audit_by(snapshot, |key| {
+        shard_with_hasher(key, total_destinations, hasher)
+    })
+}
+
+/// Audits `(key, current_shard)` pairs against `expected_shard`, which can look keys up in any topology, such as [crate::ShardRing::index_of].
+pub fn audit_by<K>(
+    snapshot: impl IntoIterator<Item = (K, u64)>,
+    expected_shard: impl Fn(&K) -> u64,
+) -> AuditReport<K> {
+    let mut report = AuditReport {
+        total_keys: 0,
+        misplaced: BTreeMap::new(),
+    };
+    for (key, current) in snapshot {
+        report.total_keys += 1;
+        let expected = expected_shard(&key);
+        if expected != current {
+            report
+                .misplaced
+                .entry((current, expected))
+                .or_insert_with(Vec::new)
+                .push(key);
+        }
+    }
+    report
+}
+
+/// The result of an audit: misplaced keys grouped by `(current, expected)` shard.
+///
+/// The [Display](fmt::Display) form is one tab-separated `current expected count` line per group, like [crate::migration::MigrationPlan].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuditReport<K> {
+    total_keys: u64,
+    misplaced: BTreeMap<(u64, u64), Vec<K>>,
+}
+
+impl<K> AuditReport<K> {
+    /// Number of keys audited, misplaced or not.
+    pub fn total_keys(&self) -> u64 {
+        self.total_keys
+    }
+
+    /// Number of keys not on their expected shard.
+    pub fn misplaced_keys(&self) -> u64 {
+        self.misplaced.values().map(|keys| keys.len() as u64).sum()
+    }
+
+    /// Whether every key is on its expected shard.
+    pub fn is_clean(&self) -> bool {
+        self.misplaced.is_empty()
+    }
+
+    /// The keys on `current` that belong on `expected`.
+    pub fn group(&self, current: u64, expected: u64) -> &[K] {
+        self.misplaced
+            .get(&(current, expected))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Every `((current, expected), keys)`, ordered by current then expected shard.
+    pub fn groups(&self) -> impl Iterator<Item = ((u64, u64), &[K])> {
+        self.misplaced
+            .iter()
+            .map(|(&route, keys)| (route, keys.as_slice()))
+    }
+
+    /// Every misplaced `(key, current, expected)`, in [AuditReport::groups] order.
+    pub fn misplaced(&self) -> impl Iterator<Item = (&K, u64, u64)> {
+        self.groups().flat_map(|((current, expected), keys)| {
+            keys.iter().map(move |key| (key, current, expected))
+        })
+    }
+}
+
+impl<K> fmt::Display for AuditReport<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for ((current, expected), keys) in self.groups() {
+            writeln!(f, "{}\t{}\t{}", current, expected, keys.len())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{shard_for, BuildSeaHasher, ShardRing};
+
+    #[test]
+    fn finds_misplaced_keys() {
+        let snapshot = (0..1_000u64).map(|key| {
+            let shard = shard_for(key, 10);
+            (
+                key,
+                if key % 100 == 0 {
+                    (shard + 3) % 10
+                } else {
+                    shard
+                },
+            )
+        });
+        let report = audit_with_hasher(snapshot, 10, &BuildSeaHasher);
+
+        assert_eq!(report.total_keys(), 1_000);
+        assert_eq!(report.misplaced_keys(), 10);
+        for (&key, current, expected) in report.misplaced() {
+            assert_eq!(key % 100, 0);
+            assert_eq!(expected, shard_for(key, 10));
+            assert_eq!(current, (expected + 3) % 10);
+            assert!(report.group(current, expected).contains(&key));
+        }
+        assert_eq!(report.to_string().lines().count(), report.groups().count());
+    }
+
+    #[test]
+    fn audits_any_topology() {
+        let ring = ShardRing::with_destinations(vec!["a", "b", "c"], BuildSeaHasher);
+        let snapshot = (0..100u64).map(|key| (key, ring.index_of(key)));
+        assert!(audit_by(snapshot, |key| ring.index_of(key)).is_clean());
+    }
+}
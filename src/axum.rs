@@ -0,0 +1,200 @@
+//! Shard routing for [axum](https://docs.rs/axum) handlers.
+//!
+//! Keys are sharded with [crate::shard_for], so handlers agree with every other service using the default hasher.
+//!
+//! - [ShardOf] extracts a key with another extractor, such as `Path<u64>`, and computes its shard.
+//! - [inject_shard] is middleware that does the same and adds the [Shard] and its [Backend] to the request extensions.
+//!
+//! ```
+//! use axum::extract::Path;
+//! use axum::{middleware, routing::get, Extension, Router};
+//! use faro_sharding::axum::{inject_shard, Backend, ShardOf, ShardRouting};
+//!
+//! async fn handler(
+//!     shard: ShardOf<Path<u64>>,
+//!     Extension(Backend(backend)): Extension<Backend<&'static str>>,
+//! ) -> String {
+//!     format!("user {} is on shard {} at {}", *shard.key, shard.shard, backend)
+//! }
+//!
+//! let routing = ShardRouting::new(vec!["db0:5432", "db1:5432", "db2:5432"]);
+//! let app: Router = Router::new()
+//!     .route("/users/{id}", get(handler))
+//!     .route_layer(middleware::from_fn_with_state(
+//!         routing.clone(),
+//!         inject_shard::<Path<u64>, &'static str>,
+//!     ))
+//!     .with_state(routing);
+//! ```
+
+use axum::extract::{FromRef, FromRequestParts, Request, State};
+use axum::http::request::Parts;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use std::hash::Hash;
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// The backends requests are routed across, in shard order. Cheap to clone.
+#[derive(Clone, Debug)]
+pub struct ShardRouting<B> {
+    backends: Arc<[B]>,
+}
+
+impl<B> ShardRouting<B> {
+    /// Routes across `backends`, where `backends[i]` serves shard `i`.
+    ///
+    /// # Panics
+    ///
+    /// If backends is empty.
+    pub fn new(backends: Vec<B>) -> Self {
+        assert!(!backends.is_empty(), "backends must not be empty");
+
+        ShardRouting {
+            backends: backends.into(),
+        }
+    }
+
+    /// The backends, in shard order.
+    pub fn backends(&self) -> &[B] {
+        &self.backends
+    }
+
+    /// The shard for `key`.
+    pub fn shard_for(&self, key: impl Hash) -> u64 {
+        crate::shard_for(key, self.backends.len() as u64)
+    }
+}
+
+/// The number of destinations [ShardOf] shards across. Provide it from your state with [FromRef].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ShardCount(pub u64);
+
+impl<B> FromRef<ShardRouting<B>> for ShardCount {
+    fn from_ref(routing: &ShardRouting<B>) -> Self {
+        ShardCount(routing.backends.len() as u64)
+    }
+}
+
+/// Extracts `K` and the shard of the key it dereferences to.
+///
+/// Works with any extractor that derefs to a hashable key, such as `Path<u64>` or `Extension<UserId>`. Rejects with `K`'s rejection.
+#[derive(Clone, Debug)]
+pub struct ShardOf<K> {
+    /// The extracted key.
+    pub key: K,
+    /// The shard of `*key`.
+    pub shard: u64,
+}
+
+impl<K, St> FromRequestParts<St> for ShardOf<K>
+where
+    K: FromRequestParts<St> + Deref,
+    K::Target: Hash,
+    ShardCount: FromRef<St>,
+    St: Send + Sync,
+{
+    type Rejection = K::Rejection;
+
+    async fn from_request_parts(parts: &mut Parts, state: &St) -> Result<Self, Self::Rejection> {
+        let key = K::from_request_parts(parts, state).await?;
+        let ShardCount(total_destinations) = ShardCount::from_ref(state);
+        let shard = crate::shard_for(&*key, total_destinations);
+        Ok(ShardOf { key, shard })
+    }
+}
+
+/// The shard of the request's key, added to the request extensions by [inject_shard].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Shard(pub u64);
+
+/// The backend for the request's shard, added to the request extensions by [inject_shard].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Backend<B>(pub B);
+
+/// Middleware for [axum::middleware::from_fn_with_state] that adds the [Shard] and [Backend] of the key extracted by `K`.
+///
+/// If `K` rejects the request, its rejection is returned and the inner service is not called.
+pub async fn inject_shard<K, B>(
+    State(routing): State<ShardRouting<B>>,
+    mut request: Request,
+    next: Next,
+) -> Response
+where
+    K: FromRequestParts<ShardRouting<B>> + Deref,
+    K::Target: Hash,
+    B: Clone + Send + Sync + 'static,
+{
+    let (mut parts, body) = request.into_parts();
+    let shard = match ShardOf::<K>::from_request_parts(&mut parts, &routing).await {
+        Ok(ShardOf { shard, .. }) => shard,
+        Err(rejection) => return rejection.into_response(),
+    };
+    request = Request::from_parts(parts, body);
+
+    request.extensions_mut().insert(Shard(shard));
+    request
+        .extensions_mut()
+        .insert(Backend(routing.backends[shard as usize].clone()));
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shard_for;
+    use axum::body::Body;
+    use axum::extract::Path;
+    use axum::http::StatusCode;
+    use axum::routing::get;
+    use axum::{middleware, Extension, Router};
+    use tower::ServiceExt;
+
+    async fn handler(
+        shard: ShardOf<Path<u64>>,
+        Extension(Shard(injected)): Extension<Shard>,
+        Extension(Backend(backend)): Extension<Backend<String>>,
+    ) -> String {
+        assert_eq!(shard.shard, injected);
+        format!("{} {}", shard.shard, backend)
+    }
+
+    fn app() -> Router {
+        let routing = ShardRouting::new((0..5).map(|i| format!("db{}", i)).collect());
+        Router::new()
+            .route("/users/{id}", get(handler))
+            .route_layer(middleware::from_fn_with_state(
+                routing.clone(),
+                inject_shard::<Path<u64>, String>,
+            ))
+            .with_state(routing)
+    }
+
+    async fn get_body(uri: &str) -> (StatusCode, String) {
+        let response = app()
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, String::from_utf8(bytes.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn injects_shard_and_backend() {
+        for id in [1u64, 42, 1337] {
+            let shard = shard_for(id, 5);
+            assert_eq!(
+                get_body(&format!("/users/{}", id)).await,
+                (StatusCode::OK, format!("{} db{}", shard, shard))
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_bad_keys() {
+        assert_eq!(get_body("/users/nope").await.0, StatusCode::BAD_REQUEST);
+    }
+}
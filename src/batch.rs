@@ -0,0 +1,213 @@
+use crate::shard_for_key_hash;
+use core::hash::*;
+#[cfg(feature = "std")]
+use std::collections::{BTreeMap, HashMap};
+
+/// Lazily shards every key in `keys`, yielding shards in the same order.
+///
+/// Equivalent to calling [crate::shard_with_hasher] on each key.
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub fn shard_for_many<'h, K: Hash>(
+    keys: impl IntoIterator<Item = K> + 'h,
+    total_destinations: u64,
+    hasher: &'h impl BuildHasher,
+) -> impl Iterator<Item = u64> + 'h {
+    assert_ne!(total_destinations, 0, "total_destinations must be > 0");
+
+    keys.into_iter()
+        .map(move |key| shard_for_key_hash(hasher.hash_one(key), total_destinations, hasher))
+}
+
+/// Writes the shard of each key in `keys` into `out`, in order, without allocating.
+///
+/// Stops when either runs out, and returns how many shards were written. Any remaining keys are not hashed, so a long iterator can be routed one buffer at a time.
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub fn shard_into<K: Hash>(
+    keys: impl IntoIterator<Item = K>,
+    total_destinations: u64,
+    out: &mut [u64],
+    hasher: &impl BuildHasher,
+) -> usize {
+    assert_ne!(total_destinations, 0, "total_destinations must be > 0");
+
+    let mut written = 0;
+    for (slot, key) in out.iter_mut().zip(keys) {
+        *slot = shard_for_key_hash(hasher.hash_one(key), total_destinations, hasher);
+        written += 1;
+    }
+    written
+}
+
+/// Writes the shard of each of `key_hashes` into the matching slot of `out`, without allocating.
+///
+/// Key hashes are as in [crate::shard_for_key_hash].
+///
+/// # Panics
+///
+/// If total_destinations == 0, or `key_hashes` and `out` differ in length.
+pub fn shard_key_hashes_into(
+    key_hashes: &[u64],
+    total_destinations: u64,
+    out: &mut [u64],
+    hasher: &impl BuildHasher,
+) {
+    assert_ne!(total_destinations, 0, "total_destinations must be > 0");
+    assert_eq!(
+        key_hashes.len(),
+        out.len(),
+        "key_hashes and out must be the same length"
+    );
+
+    for (slot, &key_hash) in out.iter_mut().zip(key_hashes) {
+        *slot = shard_for_key_hash(key_hash, total_destinations, hasher);
+    }
+}
+
+#[cfg(feature = "std")]
+/// [shard_for_many] collected into a `Vec`.
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub fn shard_for_many_vec<K: Hash>(
+    keys: impl IntoIterator<Item = K>,
+    total_destinations: u64,
+    hasher: &impl BuildHasher,
+) -> Vec<u64> {
+    shard_for_many(keys, total_destinations, hasher).collect()
+}
+
+/// Groups `keys` by shard in a single pass, keeping their relative order within each group.
+///
+/// Only shards that receive a key have an entry. `par_group_by_shard` does the same in parallel with the `rayon` feature.
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+#[cfg(feature = "std")]
+pub fn group_by_shard<K: Hash>(
+    keys: impl IntoIterator<Item = K>,
+    total_destinations: u64,
+    hasher: &impl BuildHasher,
+) -> HashMap<u64, Vec<K>> {
+    assert_ne!(total_destinations, 0, "total_destinations must be > 0");
+
+    let mut groups = HashMap::<u64, Vec<K>>::new();
+    for key in keys {
+        let shard = shard_for_key_hash(hasher.hash_one(&key), total_destinations, hasher);
+        groups.entry(shard).or_default().push(key);
+    }
+    groups
+}
+
+/// Lazily groups `keys` by shard, yielding `(shard, chunk)` as soon as a shard has `chunk_size` keys.
+///
+/// Once `keys` runs out, the partly filled chunks are yielded in shard order. At most `chunk_size - 1` keys per shard are held at once, so a long iterator can be fanned out to shards without grouping it all in memory. Keys keep their relative order within each shard.
+///
+/// # Panics
+///
+/// If total_destinations == 0 or chunk_size == 0.
+#[cfg(feature = "std")]
+pub fn group_by_shard_chunks<'h, K: Hash + 'h>(
+    keys: impl IntoIterator<Item = K> + 'h,
+    total_destinations: u64,
+    chunk_size: usize,
+    hasher: &'h impl BuildHasher,
+) -> impl Iterator<Item = (u64, Vec<K>)> + 'h {
+    assert_ne!(total_destinations, 0, "total_destinations must be > 0");
+    assert_ne!(chunk_size, 0, "chunk_size must be > 0");
+
+    let mut keys = keys.into_iter();
+    let mut pending = BTreeMap::<u64, Vec<K>>::new();
+    core::iter::from_fn(move || {
+        for key in keys.by_ref() {
+            let shard = shard_for_key_hash(hasher.hash_one(&key), total_destinations, hasher);
+            let chunk = pending.entry(shard).or_default();
+            chunk.push(key);
+            if chunk.len() == chunk_size {
+                return pending.remove_entry(&shard);
+            }
+        }
+        pending.pop_first()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{shard_with_hasher, BuildSeaHasher};
+
+    #[test]
+    fn matches_individual_calls() {
+        let keys = ["foo", "bar", "baz", "qux", "quux"];
+        let expected = keys
+            .iter()
+            .map(|k| shard_with_hasher(k, 73, &BuildSeaHasher))
+            .collect::<Vec<_>>();
+
+        assert_eq!(shard_for_many_vec(keys, 73, &BuildSeaHasher), expected);
+        assert_eq!(
+            shard_for_many(&keys, 73, &BuildSeaHasher).collect::<Vec<_>>(),
+            expected
+        );
+    }
+
+    #[test]
+    fn into_buffers_match_individual_calls() {
+        let keys = ["foo", "bar", "baz", "qux", "quux"];
+        let expected = shard_for_many_vec(keys, 73, &BuildSeaHasher);
+
+        let mut out = [u64::MAX; 3];
+        assert_eq!(shard_into(keys, 73, &mut out, &BuildSeaHasher), 3);
+        assert_eq!(out, expected[..3]);
+
+        let mut out = [u64::MAX; 7];
+        assert_eq!(shard_into(keys, 73, &mut out, &BuildSeaHasher), 5);
+        assert_eq!(out[..5], expected);
+        assert_eq!(out[5..], [u64::MAX; 2]);
+
+        let key_hashes = keys.map(|k| BuildSeaHasher.hash_one(k));
+        let mut out = [0; 5];
+        shard_key_hashes_into(&key_hashes, 73, &mut out, &BuildSeaHasher);
+        assert_eq!(out, expected[..]);
+    }
+
+    #[test]
+    fn groups_keep_input_order() {
+        let groups = group_by_shard(0..10_000u64, 7, &BuildSeaHasher);
+        assert_eq!(groups.len(), 7);
+
+        for (&shard, group) in &groups {
+            let expected = (0..10_000u64)
+                .filter(|key| shard_with_hasher(key, 7, &BuildSeaHasher) == shard)
+                .collect::<Vec<_>>();
+            assert_eq!(group, &expected);
+        }
+    }
+
+    #[test]
+    fn chunks_concatenate_to_groups() {
+        let groups = group_by_shard(0..10_000u64, 7, &BuildSeaHasher);
+
+        let mut chunked = HashMap::<u64, Vec<u64>>::new();
+        let mut trailing = Vec::new();
+        for (shard, chunk) in group_by_shard_chunks(0..10_000u64, 7, 100, &BuildSeaHasher) {
+            assert!(!chunk.is_empty() && chunk.len() <= 100);
+            assert!(
+                !trailing.contains(&shard),
+                "full chunk after trailing chunk"
+            );
+            if chunk.len() < 100 {
+                trailing.push(shard);
+            }
+            chunked.entry(shard).or_default().extend(chunk);
+        }
+        assert_eq!(chunked, groups);
+    }
+}
@@ -0,0 +1,58 @@
+use crate::shard_with_hasher;
+use std::collections::BTreeMap;
+use std::hash::*;
+
+/// Picks a single shard for a whole batch of keys: the shard most of them already belong to.
+///
+/// Returns the chosen shard and how many keys had to be overridden away from their own shard, or `None` for an empty batch. Ties go to the lowest shard index.
+///
+/// This deliberately violates Faro stability for individual keys in the batch, since overridden keys are placed on a shard they would never be assigned on their own. It trades that for keeping the batch on one shard, avoiding a distributed transaction.
+pub fn shard_for_batch_minimized(
+    keys: &[impl Hash],
+    total_destinations: u64,
+    hasher: &impl BuildHasher,
+) -> Option<(u64, usize)> {
+    let mut counts = BTreeMap::<u64, usize>::new();
+    for key in keys {
+        *counts
+            .entry(shard_with_hasher(key, total_destinations, hasher))
+            .or_default() += 1;
+    }
+
+    let (&shard, &count) = counts.iter().rev().max_by_key(|(_, &count)| count)?;
+    Some((shard, keys.len() - count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BuildSeaHasher;
+
+    fn keys_on(shard: u64, count: usize) -> impl Iterator<Item = u64> {
+        (0..)
+            .filter(move |key| shard_with_hasher(key, 8, &BuildSeaHasher) == shard)
+            .take(count)
+    }
+
+    #[test]
+    fn picks_the_majority_shard() {
+        let batch = keys_on(3, 2).chain(keys_on(5, 3)).collect::<Vec<_>>();
+        assert_eq!(
+            shard_for_batch_minimized(&batch, 8, &BuildSeaHasher),
+            Some((5, 2))
+        );
+    }
+
+    #[test]
+    fn ties_go_to_the_lowest_shard() {
+        let batch = keys_on(5, 1).chain(keys_on(3, 1)).collect::<Vec<_>>();
+        assert_eq!(
+            shard_for_batch_minimized(&batch, 8, &BuildSeaHasher),
+            Some((3, 1))
+        );
+        assert_eq!(
+            shard_for_batch_minimized(&[] as &[u64], 8, &BuildSeaHasher),
+            None
+        );
+    }
+}
@@ -0,0 +1,698 @@
+use faro_sharding::{
+    audit::audit_with_hasher,
+    golden, keys_for_shard,
+    migration::{diff_assignments, movement_graph, Planner},
+    seahash::StableSeaHasher,
+    shard_for, Algorithm, HasherKind, Sharder,
+};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::hint::black_box;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+use structopt::*;
+
+/// Answer operational questions about Faro Sharding.
+#[derive(StructOpt)]
+enum Command {
+    /// Print the shard for a key, or for each line of stdin.
+    Shard {
+        /// The key to shard. Required unless --stdin is given.
+        #[structopt(required_unless = "stdin")]
+        key: Option<String>,
+
+        #[structopt(long)]
+        locations: u64,
+
+        /// Read one key per line from stdin and print `key<TAB>shard` for each.
+        #[structopt(long, conflicts_with = "key")]
+        stdin: bool,
+    },
+
+    /// Summarize which keys move when scaling from one location count to another.
+    Plan {
+        #[structopt(long)]
+        from: u64,
+
+        #[structopt(long)]
+        to: u64,
+
+        /// File with one key per line.
+        #[structopt(long)]
+        keys: PathBuf,
+    },
+
+    /// Print the shard to shard flows of keys at each step through a list of location counts.
+    Movement {
+        /// Location counts to step through, in order.
+        #[structopt(long, use_delimiter = true)]
+        locations: Vec<u64>,
+
+        /// File with one key per line.
+        #[structopt(long)]
+        keys: PathBuf,
+
+        /// Print Graphviz DOT instead of JSON.
+        #[structopt(long)]
+        dot: bool,
+    },
+
+    /// Report keys in an assignment snapshot that are not on the shard `shard_for` expects.
+    ///
+    /// Exits with status 1 if any key is misplaced.
+    Audit {
+        /// A `key,shard` CSV file, or a JSON object of key to shard if the file ends in `.json`. The key is everything before the last comma.
+        #[structopt(long)]
+        snapshot: PathBuf,
+
+        #[structopt(long)]
+        locations: u64,
+
+        /// Also print `key<TAB>current<TAB>expected` for every misplaced key.
+        #[structopt(long)]
+        list: bool,
+    },
+
+    /// Summarize which keys move when changing the algorithm or hasher.
+    Diff {
+        #[structopt(long)]
+        locations: u64,
+
+        /// File with one key per line.
+        #[structopt(long)]
+        keys: PathBuf,
+
+        /// The current algorithm. One of faro-v1, faro-v2, skip-ahead-v1, faro-mix-v1.
+        #[structopt(long, default_value = "faro-v1")]
+        from: Algorithm,
+
+        /// The new algorithm.
+        #[structopt(long, default_value = "faro-v1")]
+        to: Algorithm,
+
+        /// The current hasher. seahash, or frozen-seahash, xxh3, or fnv if built with the `frozen`, `xxhash`, or `fnv` feature.
+        #[structopt(long, default_value = "seahash")]
+        from_hasher: HasherKind,
+
+        /// The new hasher.
+        #[structopt(long, default_value = "seahash")]
+        to_hasher: HasherKind,
+    },
+
+    /// Print keys that land on a given shard, one per line, for targeted load tests.
+    KeysForShard {
+        #[structopt(long)]
+        shard: u64,
+
+        #[structopt(long)]
+        locations: u64,
+
+        /// Number of keys to find.
+        #[structopt(long, default_value = "10")]
+        count: usize,
+
+        /// Keys are this prefix followed by a number.
+        #[structopt(long, default_value = "key-")]
+        prefix: String,
+
+        /// Give up after this many milliseconds, printing the keys found so far.
+        #[structopt(long, default_value = "10000")]
+        timeout_ms: u64,
+    },
+
+    /// Measure sharding throughput across a sweep of location counts.
+    Bench {
+        /// One of faro-v1, faro-v2, skip-ahead-v1, faro-mix-v1.
+        #[structopt(long, default_value = "faro-v1")]
+        algorithm: Algorithm,
+
+        /// Location counts to measure.
+        #[structopt(long, use_delimiter = true, default_value = "10,100,1000,10000,100000")]
+        locations: Vec<u64>,
+
+        /// Number of distinct keys sharded at each location count.
+        #[structopt(long, default_value = "10000")]
+        keys: u64,
+
+        /// Print a JSON array instead of a table.
+        #[structopt(long)]
+        json: bool,
+    },
+
+    /// Print JSON test vectors for porting an algorithm to another language.
+    Fixtures {
+        /// One of faro-v1, faro-v2, skip-ahead-v1, faro-mix-v1.
+        #[structopt(long, default_value = "faro-v1")]
+        algorithm: Algorithm,
+
+        /// Number of generated vectors, after the canonical ones.
+        #[structopt(long, default_value = "5000")]
+        count: usize,
+    },
+
+    /// Answer `GET /shard?key=...&locations=N` over HTTP.
+    ///
+    /// With --topology, `locations` may be omitted to shard across the destinations listed in the file, which is reloaded when it changes.
+    Serve {
+        #[structopt(long, default_value = "127.0.0.1:8080")]
+        listen: SocketAddr,
+
+        /// File with one destination name per line, in shard order. Blank lines and lines starting with `#` are ignored.
+        #[structopt(long)]
+        topology: Option<PathBuf>,
+    },
+}
+
+fn main() -> io::Result<()> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    match Command::from_args() {
+        Command::Shard {
+            key,
+            locations,
+            stdin,
+        } => {
+            check_locations(locations);
+
+            if stdin {
+                for line in io::stdin().lock().lines() {
+                    let line = line?;
+                    writeln!(out, "{}\t{}", line, shard_for(&line, locations))?;
+                }
+            } else {
+                let key = key.expect("structopt requires key without --stdin");
+                writeln!(out, "{}", shard_for(&key, locations))?;
+            }
+        }
+
+        Command::Plan { from, to, keys } => {
+            check_locations(from);
+            check_locations(to);
+
+            let keys = BufReader::new(File::open(keys)?)
+                .lines()
+                .collect::<io::Result<Vec<_>>>()?;
+            let plan = Planner::new(from, to, StableSeaHasher::new()).plan(keys);
+
+            let percent = if plan.total_keys() == 0 {
+                0.
+            } else {
+                100. * plan.moved_keys() as f64 / plan.total_keys() as f64
+            };
+            writeln!(
+                out,
+                "{} of {} keys move ({:.2}%) scaling from {} to {} locations",
+                plan.moved_keys(),
+                plan.total_keys(),
+                percent,
+                from,
+                to
+            )?;
+            write!(out, "{}", plan)?;
+        }
+
+        Command::Movement {
+            locations,
+            keys,
+            dot,
+        } => {
+            for &l in &locations {
+                check_locations(l);
+            }
+
+            let keys = BufReader::new(File::open(keys)?)
+                .lines()
+                .collect::<io::Result<Vec<_>>>()?;
+            let graph = movement_graph(keys, &locations, &StableSeaHasher::new());
+            if dot {
+                write!(out, "{}", graph.to_dot())?;
+            } else {
+                serde_json::to_writer(&mut out, &graph)?;
+                writeln!(out)?;
+            }
+        }
+
+        Command::Audit {
+            snapshot,
+            locations,
+            list,
+        } => {
+            check_locations(locations);
+
+            let snapshot = load_snapshot(&snapshot)?;
+            let report = audit_with_hasher(snapshot, locations, &StableSeaHasher::new());
+            writeln!(
+                out,
+                "{} of {} keys misplaced across {} locations",
+                report.misplaced_keys(),
+                report.total_keys(),
+                locations
+            )?;
+            write!(out, "{}", report)?;
+            if list {
+                for (key, current, expected) in report.misplaced() {
+                    writeln!(out, "{}\t{}\t{}", key, current, expected)?;
+                }
+            }
+            if !report.is_clean() {
+                out.flush()?;
+                std::process::exit(1);
+            }
+        }
+
+        Command::Diff {
+            locations,
+            keys,
+            from,
+            to,
+            from_hasher,
+            to_hasher,
+        } => {
+            check_locations(locations);
+
+            let keys = BufReader::new(File::open(keys)?)
+                .lines()
+                .collect::<io::Result<Vec<_>>>()?;
+            let old = Sharder::new(from, from_hasher.clone());
+            let new = Sharder::new(to, to_hasher.clone());
+            let plan = diff_assignments(keys, locations, &old, &new);
+            writeln!(
+                out,
+                "{} of {} keys move ({:.2}%) switching from {} with {} to {} with {} at {} locations",
+                plan.moved_keys(),
+                plan.total_keys(),
+                100. * plan.moved_fraction(),
+                from,
+                from_hasher,
+                to,
+                to_hasher,
+                locations
+            )?;
+            write!(out, "{}", plan)?;
+        }
+
+        Command::KeysForShard {
+            shard,
+            locations,
+            count,
+            prefix,
+            timeout_ms,
+        } => {
+            check_locations(locations);
+            if shard >= locations {
+                eprintln!("shard must be < locations");
+                std::process::exit(2);
+            }
+
+            let keys = keys_for_shard(
+                &prefix,
+                shard,
+                locations,
+                count,
+                Duration::from_millis(timeout_ms),
+                &StableSeaHasher::new(),
+            );
+            for key in &keys {
+                writeln!(out, "{}", key)?;
+            }
+            if keys.len() < count {
+                eprintln!("timed out after finding {} of {} keys", keys.len(), count);
+                std::process::exit(1);
+            }
+        }
+
+        Command::Bench {
+            algorithm,
+            locations,
+            keys,
+            json,
+        } => {
+            for &l in &locations {
+                check_locations(l);
+            }
+
+            let sharder = Sharder::new(algorithm, StableSeaHasher::new());
+            let keys = (0..keys).map(|i| format!("key-{}", i)).collect::<Vec<_>>();
+            let results = locations
+                .iter()
+                .map(|&l| {
+                    let start = Instant::now();
+                    for key in &keys {
+                        black_box(sharder.shard_for(black_box(key), l));
+                    }
+                    (l, keys.len() as f64 / start.elapsed().as_secs_f64())
+                })
+                .collect::<Vec<_>>();
+
+            if json {
+                let rows = results
+                    .iter()
+                    .map(|(l, keys_per_sec)| {
+                        json!({
+                            "algorithm": algorithm.name(),
+                            "locations": l,
+                            "keys_per_sec": keys_per_sec.round(),
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                serde_json::to_writer(&mut out, &rows)?;
+                writeln!(out)?;
+            } else {
+                writeln!(out, "{:>12}\t{:>14}", "locations", "keys/sec")?;
+                for (l, keys_per_sec) in results {
+                    writeln!(out, "{:>12}\t{:>14.0}", l, keys_per_sec)?;
+                }
+            }
+        }
+
+        Command::Fixtures { algorithm, count } => {
+            let fixtures = golden::fixtures(algorithm, &StableSeaHasher::new(), count);
+            let json = serde_json::json!({
+                "algorithm": algorithm.name(),
+                "hasher": "seahash",
+                "fixtures": fixtures,
+            });
+            serde_json::to_writer_pretty(&mut out, &json)?;
+            writeln!(out)?;
+        }
+
+        Command::Serve { listen, topology } => serve(listen, topology)?,
+    }
+
+    Ok(())
+}
+
+/// Reads `(key, shard)` pairs from a CSV or JSON snapshot, as described on [Command::Audit].
+fn load_snapshot(path: &Path) -> io::Result<Vec<(String, u64)>> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+
+    if path.extension().is_some_and(|e| e == "json") {
+        let map: BTreeMap<String, u64> = serde_json::from_reader(BufReader::new(File::open(path)?))
+            .map_err(|e| invalid(e.to_string()))?;
+        return Ok(map.into_iter().collect());
+    }
+
+    let mut snapshot = Vec::new();
+    for (number, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let parsed = line
+            .rsplit_once(',')
+            .and_then(|(key, shard)| Some((key.to_string(), shard.trim().parse().ok()?)));
+        match parsed {
+            Some(pair) => snapshot.push(pair),
+            // Allow a header row.
+            None if number == 0 => {}
+            None => return Err(invalid(format!("line {}: expected key,shard", number + 1))),
+        }
+    }
+    Ok(snapshot)
+}
+
+/// The destinations loaded from a topology file. `version` counts reloads.
+struct Topology {
+    destinations: Vec<String>,
+    version: u64,
+    modified: Option<SystemTime>,
+}
+
+fn load_topology(path: &Path, version: u64) -> io::Result<Topology> {
+    let modified = std::fs::metadata(path)?.modified().ok();
+    let destinations = BufReader::new(File::open(path)?)
+        .lines()
+        .filter(|line| {
+            line.as_ref()
+                .map_or(true, |l| !l.trim().is_empty() && !l.trim().starts_with('#'))
+        })
+        .map(|line| line.map(|l| l.trim().to_string()))
+        .collect::<io::Result<Vec<_>>>()?;
+    if destinations.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "topology has no destinations",
+        ));
+    }
+
+    Ok(Topology {
+        destinations,
+        version,
+        modified,
+    })
+}
+
+fn serve(listen: SocketAddr, topology_path: Option<PathBuf>) -> io::Result<()> {
+    let topology = match &topology_path {
+        Some(path) => Some(Arc::new(RwLock::new(Arc::new(load_topology(path, 1)?)))),
+        None => None,
+    };
+
+    if let (Some(path), Some(topology)) = (topology_path, topology.clone()) {
+        std::thread::spawn(move || loop {
+            std::thread::sleep(Duration::from_secs(1));
+            let current = Arc::clone(&topology.read().unwrap());
+            let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+            if modified == current.modified {
+                continue;
+            }
+            match load_topology(&path, current.version + 1) {
+                Ok(reloaded) => {
+                    eprintln!(
+                        "reloaded {} destinations from {}",
+                        reloaded.destinations.len(),
+                        path.display()
+                    );
+                    *topology.write().unwrap() = Arc::new(reloaded);
+                }
+                Err(e) => eprintln!("keeping previous topology, reload failed: {}", e),
+            }
+        });
+    }
+
+    let listener = TcpListener::bind(listen)?;
+    eprintln!("listening on http://{}", listener.local_addr()?);
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let topology = topology
+            .as_ref()
+            .map(|topology| Arc::clone(&topology.read().unwrap()));
+        std::thread::spawn(move || {
+            if let Err(e) = handle(stream, topology.as_deref()) {
+                eprintln!("request failed: {}", e);
+            }
+        });
+    }
+    Ok(())
+}
+
+/// The most a request line and its headers may take. Lookups are a short GET, so anything larger is not one.
+const MAX_REQUEST_BYTES: u64 = 8 * 1024;
+
+/// How long a connection may go without sending anything before it is dropped.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+fn handle(stream: TcpStream, topology: Option<&Topology>) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(&stream).take(MAX_REQUEST_BYTES);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Drain the headers. Lookups have no body.
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+    if reader.limit() == 0 {
+        return write_response(
+            &stream,
+            "431 Request Header Fields Too Large",
+            r#"{"error":"request too large"}"#.to_string(),
+        );
+    }
+
+    let (status, body) = respond(&request_line, topology);
+    write_response(&stream, status, body)
+}
+
+fn write_response(mut stream: &TcpStream, status: &str, body: String) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )
+}
+
+fn respond(request_line: &str, topology: Option<&Topology>) -> (&'static str, String) {
+    let error = |status, message: &str| (status, json!({ "error": message }).to_string());
+
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return error("400 Bad Request", "malformed request");
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    if path != "/shard" {
+        return error("404 Not Found", "only /shard is served");
+    }
+    if method != "GET" {
+        return error("405 Method Not Allowed", "use GET");
+    }
+
+    let mut key = None;
+    let mut locations = None;
+    for pair in query.split('&') {
+        let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+        match name {
+            "key" => key = Some(percent_decode(value)),
+            "locations" => locations = Some(value),
+            _ => {}
+        }
+    }
+    let Some(key) = key else {
+        return error("400 Bad Request", "key is required");
+    };
+
+    match (locations, topology) {
+        (Some(locations), _) => match locations.parse::<u64>() {
+            Ok(locations) if locations > 0 => (
+                "200 OK",
+                json!({ "shard": shard_for(&key, locations), "locations": locations }).to_string(),
+            ),
+            _ => error("400 Bad Request", "locations must be an integer > 0"),
+        },
+        (None, Some(topology)) => {
+            let locations = topology.destinations.len() as u64;
+            let shard = shard_for(&key, locations);
+            let response = json!({
+                "shard": shard,
+                "locations": locations,
+                "destination": topology.destinations[shard as usize],
+                "version": topology.version,
+            });
+            ("200 OK", response.to_string())
+        }
+        (None, None) => error(
+            "400 Bad Request",
+            "locations is required without --topology",
+        ),
+    }
+}
+
+/// Decodes `%XX` escapes and `+` in a query string value. Invalid escapes are kept as is.
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (b'+', _) => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn check_locations(locations: u64) {
+    if locations == 0 {
+        eprintln!("locations must be > 0");
+        std::process::exit(2);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(request_line: &str, topology: Option<&Topology>) -> serde_json::Value {
+        let (status, body) = respond(request_line, topology);
+        assert_eq!(status, "200 OK");
+        serde_json::from_str(&body).unwrap()
+    }
+
+    #[test]
+    fn answers_lookups() {
+        assert_eq!(
+            lookup("GET /shard?key=foo&locations=73 HTTP/1.1\r\n", None),
+            json!({ "shard": 49, "locations": 73 })
+        );
+
+        let topology = Topology {
+            destinations: vec!["a".to_string(), "b\"c".to_string()],
+            version: 3,
+            modified: None,
+        };
+        let shard = shard_for("foo", 2);
+        assert_eq!(
+            lookup("GET /shard?key=foo HTTP/1.1", Some(&topology)),
+            json!({
+                "shard": shard,
+                "locations": 2,
+                "destination": topology.destinations[shard as usize],
+                "version": 3,
+            })
+        );
+        assert_eq!(
+            lookup("GET /shard?key=a%2Fb+c&locations=73 HTTP/1.1", None)["shard"],
+            shard_for("a/b c", 73)
+        );
+    }
+
+    #[test]
+    fn rejects_bad_requests() {
+        let status = |line| respond(line, None).0;
+        assert_eq!(status(""), "400 Bad Request");
+        assert_eq!(status("GET /other?key=foo HTTP/1.1"), "404 Not Found");
+        assert_eq!(
+            status("POST /shard?key=foo HTTP/1.1"),
+            "405 Method Not Allowed"
+        );
+        assert_eq!(status("GET /shard?locations=3 HTTP/1.1"), "400 Bad Request");
+        assert_eq!(
+            status("GET /shard?key=foo&locations=0 HTTP/1.1"),
+            "400 Bad Request"
+        );
+        assert_eq!(status("GET /shard?key=foo HTTP/1.1"), "400 Bad Request");
+    }
+
+    #[test]
+    fn limits_request_size() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        client
+            .write_all(&vec![b'x'; MAX_REQUEST_BYTES as usize])
+            .unwrap();
+        handle(listener.accept().unwrap().0, None).unwrap();
+
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 431 "), "{}", response);
+    }
+
+    #[test]
+    fn decodes_query_values() {
+        assert_eq!(percent_decode("a%2Fb+c"), "a/b c");
+        assert_eq!(percent_decode("%F0%9F%A6%80"), "🦀");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz%4"), "%zz%4");
+    }
+}
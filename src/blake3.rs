@@ -0,0 +1,158 @@
+//! Faro Sharding with keyed BLAKE3, for keys chosen by untrusted users.
+//!
+//! With a public hash function, anyone can search for keys that land on one shard and overload it. Keyed with a secret, the key hash and every step of the chain are unpredictable without the secret, while servers sharing it still agree on every assignment.
+
+use crate::shard_with_hasher;
+use core::hash::*;
+
+/// [BuildHasher] for BLAKE3 in keyed mode. The 64-bit hash is the first 8 bytes of the output, little-endian.
+#[derive(Clone)]
+pub struct KeyedBlake3 {
+    secret: [u8; 32],
+}
+
+impl KeyedBlake3 {
+    /// Creates a hasher keyed with `secret`. Keep it secret from whoever chooses the keys.
+    pub fn new(secret: [u8; 32]) -> Self {
+        KeyedBlake3 { secret }
+    }
+}
+
+impl core::fmt::Debug for KeyedBlake3 {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("KeyedBlake3").finish_non_exhaustive()
+    }
+}
+
+impl BuildHasher for KeyedBlake3 {
+    type Hasher = Blake3Hasher;
+
+    fn build_hasher(&self) -> Self::Hasher {
+        Blake3Hasher(::blake3::Hasher::new_keyed(&self.secret))
+    }
+}
+
+impl crate::StableBuildHasher for KeyedBlake3 {}
+
+/// [Hasher] built by [KeyedBlake3].
+///
+/// Integers are written as little-endian bytes, so hashes agree across platforms.
+#[derive(Clone)]
+pub struct Blake3Hasher(::blake3::Hasher);
+
+impl core::fmt::Debug for Blake3Hasher {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Blake3Hasher").finish_non_exhaustive()
+    }
+}
+
+impl Hasher for Blake3Hasher {
+    fn finish(&self) -> u64 {
+        let hash = self.0.finalize();
+        let mut first = [0; 8];
+        first.copy_from_slice(&hash.as_bytes()[..8]);
+        u64::from_le_bytes(first)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    fn write_u8(&mut self, n: u8) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_u16(&mut self, n: u16) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_u128(&mut self, n: u128) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_usize(&mut self, n: usize) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_i8(&mut self, n: i8) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_i16(&mut self, n: i16) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_i32(&mut self, n: i32) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_i64(&mut self, n: i64) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_i128(&mut self, n: i128) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_isize(&mut self, n: isize) {
+        self.write(&n.to_le_bytes())
+    }
+}
+
+/// [shard_with_hasher] using BLAKE3 keyed with `secret`.
+///
+/// Builds a [KeyedBlake3] per call. Hold one and use [shard_with_hasher] directly to avoid copying the secret.
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub fn shard_for_keyed(key: impl Hash, total_destinations: u64, secret: &[u8; 32]) -> u64 {
+    shard_with_hasher(key, total_destinations, &KeyedBlake3::new(*secret))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pinned_values() {
+        // Do not change these values. See `pinning_default_shard`.
+        assert_eq!(shard_for_keyed("foo", 73, &[0; 32]), 15);
+        assert_eq!(shard_for_keyed("foo", 73, &[1; 32]), 72);
+    }
+
+    #[test]
+    fn writes_integers_little_endian() {
+        let keyed = KeyedBlake3::new([7; 32]);
+        let hash = |write: &dyn Fn(&mut Blake3Hasher)| {
+            let mut hasher = keyed.build_hasher();
+            write(&mut hasher);
+            hasher.finish()
+        };
+        let le = hash(&|h| h.write(&0x0102_0304_0506_0708u64.to_le_bytes()));
+        assert_eq!(hash(&|h| h.write_u64(0x0102_0304_0506_0708)), le);
+        assert_eq!(hash(&|h| h.write_i64(0x0102_0304_0506_0708)), le);
+        assert_eq!(hash(&|h| h.write_u16(0x0102)), hash(&|h| h.write(&[2, 1])));
+        assert_eq!(
+            hash(&|h| h.write_u128(1)),
+            hash(&|h| h.write(&1u128.to_le_bytes()))
+        );
+    }
+
+    #[test]
+    fn secret_changes_assignments() {
+        let same = (0..1_000u64)
+            .filter(|&key| shard_for_keyed(key, 10, &[0; 32]) == shard_for_keyed(key, 10, &[1; 32]))
+            .count();
+        // About 1 in 10 by chance.
+        assert!((50..150).contains(&same), "{}", same);
+    }
+}
@@ -0,0 +1,166 @@
+use crate::{shard_for_key_hash, shards_with_hasher};
+use std::hash::*;
+
+/// Returns the shard for `key`, spilling over to the next shard in its preference order if the preferred one is overloaded.
+///
+/// `loads[i]` is the current load of shard `i`. A shard is overloaded if its load has reached `ceil(cap_factor * (total_load + 1) / total_destinations)`, the average load after placing this key scaled by `cap_factor`. The preference order is [shards_with_hasher]'s, so the first choice is always [crate::shard_with_hasher]'s shard, and spills are deterministic for the same loads.
+///
+/// As in "Consistent Hashing with Bounded Loads" (Mirrokni et al.), a `cap_factor` of 1.25 or so keeps every shard close to average while moving few keys.
+///
+/// # Panics
+///
+/// If total_destinations == 0, `loads.len() != total_destinations`, or cap_factor < 1.
+pub fn shard_with_bounded_load(
+    key: impl Hash,
+    total_destinations: u64,
+    loads: &[u64],
+    cap_factor: f64,
+    hasher: &impl BuildHasher,
+) -> u64 {
+    assert_ne!(total_destinations, 0, "total_destinations must be > 0");
+    assert_eq!(
+        loads.len() as u64,
+        total_destinations,
+        "loads must have one entry per destination"
+    );
+    assert!(cap_factor >= 1.0, "cap_factor must be >= 1");
+
+    let total_load: u64 = loads.iter().sum();
+    let cap = (cap_factor * (total_load + 1) as f64 / total_destinations as f64).ceil() as u64;
+
+    let key_hash = hasher.hash_one(&key);
+    #[cfg(feature = "tracing")]
+    let _span = tracing::trace_span!("bounded_load", key_hash, total_destinations, cap).entered();
+
+    let preferred = shard_for_key_hash(key_hash, total_destinations, hasher);
+    if loads[preferred as usize] < cap {
+        #[cfg(feature = "tracing")]
+        tracing::trace!(shard = preferred, "preferred shard under cap");
+        return preferred;
+    }
+
+    // With cap_factor >= 1, some shard is below the average and therefore below the cap.
+    let shard = shards_with_hasher(
+        &key,
+        total_destinations,
+        total_destinations as usize,
+        hasher,
+    )
+    .into_iter()
+    .find(|&shard| loads[shard as usize] < cap)
+    .expect("some shard is below average load");
+
+    #[cfg(feature = "tracing")]
+    tracing::debug!(
+        key_hash,
+        candidate_shard = preferred,
+        candidate_load = loads[preferred as usize],
+        cap,
+        shard,
+        "spilled from overloaded shard"
+    );
+    shard
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{shard_for, BuildSeaHasher};
+
+    #[test]
+    fn unloaded_shards_use_preferred() {
+        let loads = [0; 10];
+        for key in 0..100u64 {
+            assert_eq!(
+                shard_with_bounded_load(key, 10, &loads, 1.25, &BuildSeaHasher),
+                shard_for(key, 10)
+            );
+        }
+    }
+
+    #[test]
+    fn skewed_keys_stay_under_the_cap() {
+        let mut loads = [0u64; 8];
+        // Half the requests are for one hot key.
+        for i in 0..8_000u64 {
+            let key = if i % 2 == 0 { 0 } else { i };
+            let shard = shard_with_bounded_load(key, 8, &loads, 1.25, &BuildSeaHasher);
+            loads[shard as usize] += 1;
+
+            let total: u64 = loads.iter().sum();
+            let cap = (1.25 * total as f64 / 8.).ceil() as u64;
+            assert!(loads.iter().all(|&l| l <= cap), "{:?}", loads);
+        }
+    }
+
+    #[test]
+    fn spills_deterministically() {
+        let mut loads = [5u64; 4];
+        let preferred = shard_for("foo", 4);
+        loads[preferred as usize] = 100;
+
+        let spilled = shard_with_bounded_load("foo", 4, &loads, 1.0, &BuildSeaHasher);
+        assert_ne!(spilled, preferred);
+        assert_eq!(
+            spilled,
+            shard_with_bounded_load("foo", 4, &loads, 1.0, &BuildSeaHasher)
+        );
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn traces_spills() {
+        use std::sync::{Arc, Mutex};
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata, Subscriber};
+
+        #[derive(Default)]
+        struct Fields(Vec<String>);
+
+        impl Visit for Fields {
+            fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+                self.0.push(format!("{}={:?}", field.name(), value));
+            }
+        }
+
+        #[derive(Clone, Default)]
+        struct Recorder(Arc<Mutex<Vec<Vec<String>>>>);
+
+        impl Subscriber for Recorder {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+            fn new_span(&self, _: &Attributes<'_>) -> Id {
+                Id::from_u64(1)
+            }
+            fn record(&self, _: &Id, _: &Record<'_>) {}
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+            fn event(&self, event: &Event<'_>) {
+                let mut fields = Fields::default();
+                event.record(&mut fields);
+                self.0.lock().unwrap().push(fields.0);
+            }
+            fn enter(&self, _: &Id) {}
+            fn exit(&self, _: &Id) {}
+        }
+
+        let mut loads = [5u64; 4];
+        let preferred = shard_for("foo", 4);
+        loads[preferred as usize] = 100;
+
+        let recorder = Recorder::default();
+        let shard = tracing::subscriber::with_default(recorder.clone(), || {
+            shard_with_bounded_load("foo", 4, &loads, 1.0, &BuildSeaHasher)
+        });
+
+        let events = recorder.0.lock().unwrap();
+        let spill = events
+            .iter()
+            .find(|e| e.contains(&"message=spilled from overloaded shard".to_string()))
+            .unwrap();
+        assert!(spill.contains(&format!("key_hash={}", BuildSeaHasher.hash_one("foo"))));
+        assert!(spill.contains(&format!("candidate_shard={}", preferred)));
+        assert!(spill.contains(&format!("shard={}", shard)));
+    }
+}
@@ -0,0 +1,143 @@
+use core::hash::*;
+use core::ops::RangeInclusive;
+
+/// Returns every shard `key` is assigned to for destination counts `1..=max_destinations`, along with the counts it holds for.
+///
+/// This walks the hash chain once, instead of calling [crate::shard_with_hasher] for each count.
+///
+/// ```
+/// # use faro_sharding::*;
+/// # use std::hash::BuildHasherDefault;
+/// # use std::collections::hash_map::DefaultHasher;
+/// let hasher = BuildHasherDefault::<DefaultHasher>::default();
+/// for (counts, shard) in shard_breakpoints("foo", 100, &hasher) {
+///     for n in counts {
+///         assert_eq!(shard_with_hasher("foo", n, &hasher), shard);
+///     }
+/// }
+/// ```
+pub fn shard_breakpoints<'h, S: BuildHasher>(
+    key: impl Hash,
+    max_destinations: u64,
+    hasher: &'h S,
+) -> ShardBreakpoints<'h, S> {
+    ShardBreakpoints {
+        hasher,
+        last_hash: hasher.hash_one(key),
+        shard: 0,
+        start: 1,
+        step: 1,
+        max_destinations,
+    }
+}
+
+/// Returns the smallest destination count at which `key` is on `shard`, or `None` if it never is.
+///
+/// A key can only move onto shard `n` when growing from `n` to `n + 1` destinations, so this is exact and needs only `shard` hashes. Every key starts on shard 0, at 1 destination. To find when a key leaves its current shard, see [shard_breakpoints].
+///
+/// ```
+/// # use faro_sharding::*;
+/// # use std::hash::BuildHasherDefault;
+/// # use std::collections::hash_map::DefaultHasher;
+/// let hasher = BuildHasherDefault::<DefaultHasher>::default();
+/// if let Some(count) = first_count_on_shard("foo", 7, &hasher) {
+///     assert_eq!(count, 8);
+///     assert_eq!(shard_with_hasher("foo", count, &hasher), 7);
+/// }
+/// ```
+pub fn first_count_on_shard(key: impl Hash, shard: u64, hasher: &impl BuildHasher) -> Option<u64> {
+    if shard == 0 {
+        return Some(1);
+    }
+
+    let mut last_hash = hasher.hash_one(key);
+    for _ in 0..shard {
+        last_hash = hasher.hash_one(last_hash);
+    }
+    last_hash.is_multiple_of(shard + 1).then_some(shard + 1)
+}
+
+/// Iterator returned by [shard_breakpoints].
+#[derive(Clone, Debug)]
+pub struct ShardBreakpoints<'h, S> {
+    hasher: &'h S,
+    last_hash: u64,
+    shard: u64,
+    start: u64,
+    step: u64,
+    max_destinations: u64,
+}
+
+impl<S: BuildHasher> Iterator for ShardBreakpoints<'_, S> {
+    type Item = (RangeInclusive<u64>, u64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.start > self.max_destinations {
+            return None;
+        }
+
+        while self.step < self.max_destinations {
+            let n = self.step;
+            let hash = self.hasher.hash_one(self.last_hash);
+            self.last_hash = hash;
+            self.step += 1;
+
+            if hash.is_multiple_of(n + 1) {
+                let item = (self.start..=n, self.shard);
+                self.shard = n;
+                self.start = n + 1;
+                return Some(item);
+            }
+        }
+
+        let item = (self.start..=self.max_destinations, self.shard);
+        self.start = self.max_destinations + 1;
+        Some(item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{shard_for, BuildSeaHasher};
+
+    #[test]
+    fn matches_individual_calls() {
+        let breakpoints = shard_breakpoints("foo", 73, &BuildSeaHasher).collect::<Vec<_>>();
+        assert_eq!(breakpoints.first().unwrap().0.start(), &1);
+        assert_eq!(breakpoints.last().unwrap().0.end(), &73);
+
+        for (counts, shard) in breakpoints {
+            for n in counts {
+                assert_eq!(shard_for("foo", n), shard);
+            }
+        }
+    }
+
+    #[test]
+    fn documented_example() {
+        // See the crate level example: "foo" is on shard 2 from 4 to 49 locations and moves to 49 at 50.
+        let breakpoints = shard_breakpoints("foo", 50, &BuildSeaHasher).collect::<Vec<_>>();
+        assert!(breakpoints
+            .iter()
+            .any(|(counts, shard)| *shard == 2 && counts.contains(&4) && counts.end() == &49));
+        assert_eq!(breakpoints.last(), Some(&(50..=50, 49)));
+    }
+
+    #[test]
+    fn first_count_matches_individual_calls() {
+        for key in 0..50u64 {
+            for shard in 0..40 {
+                let expected = (1..=80).find(|&n| shard_for(key, n) == shard);
+                assert_eq!(first_count_on_shard(key, shard, &BuildSeaHasher), expected);
+            }
+        }
+        assert_eq!(first_count_on_shard("foo", 2, &BuildSeaHasher), Some(3));
+        assert_eq!(first_count_on_shard("foo", 49, &BuildSeaHasher), Some(50));
+    }
+
+    #[test]
+    fn no_destinations_is_empty() {
+        assert_eq!(shard_breakpoints("foo", 0, &BuildSeaHasher).count(), 0);
+    }
+}
@@ -0,0 +1,122 @@
+use crate::shard_for_key_hash;
+use core::hash::*;
+
+/// [shard_bytes_with_hasher] using the default hasher of [crate::shard_for].
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+#[cfg(feature = "seahash")]
+pub fn shard_for_bytes(bytes: &[u8], total_destinations: u64) -> u64 {
+    shard_bytes_with_hasher(
+        bytes,
+        total_destinations,
+        &crate::seahash::StableSeaHasher::new(),
+    )
+}
+
+/// Returns the shard for a key given as raw bytes.
+///
+/// The key hash is a fresh hasher fed `bytes` with a single [Hasher::write] call and nothing else, so no length prefix or terminator is added as `impl Hash for [u8]` and `str` do. Any implementation that hashes the same bytes the same way and then follows [crate::shard_for_key_hash] gets the same shard.
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub fn shard_bytes_with_hasher(
+    bytes: &[u8],
+    total_destinations: u64,
+    hasher: &impl BuildHasher,
+) -> u64 {
+    let mut state = hasher.build_hasher();
+    state.write(bytes);
+    shard_for_key_hash(state.finish(), total_destinations, hasher)
+}
+
+/// [shard_reader_with_hasher] using the default hasher of [crate::shard_for].
+#[cfg(all(feature = "std", feature = "seahash"))]
+pub fn shard_for_reader(
+    reader: impl std::io::Read,
+    total_destinations: u64,
+) -> std::io::Result<u64> {
+    shard_reader_with_hasher(
+        reader,
+        total_destinations,
+        &crate::seahash::StableSeaHasher::new(),
+    )
+}
+
+/// Size of the chunks [shard_reader_with_hasher] reads.
+#[cfg(feature = "std")]
+pub(crate) const READ_CHUNK: usize = 64 * 1024;
+
+/// Returns the shard for a key read from `reader` until it ends, without holding the whole key in memory.
+///
+/// The key is fed to one hasher in chunks. For hashers whose output does not depend on how the input is split across [Hasher::write] calls, which includes [crate::seahash::StableSeaHasher], this is the same shard as [shard_bytes_with_hasher] of the whole key.
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+#[cfg(feature = "std")]
+pub fn shard_reader_with_hasher(
+    mut reader: impl std::io::Read,
+    total_destinations: u64,
+    hasher: &impl BuildHasher,
+) -> std::io::Result<u64> {
+    assert_ne!(total_destinations, 0, "total_destinations must be > 0");
+
+    let mut state = hasher.build_hasher();
+    let mut chunk = vec![0; READ_CHUNK];
+    loop {
+        match reader.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(read) => state.write(&chunk[..read]),
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(shard_for_key_hash(
+        state.finish(),
+        total_destinations,
+        hasher,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pinned_values() {
+        // Do not change these values. See `pinning_default_shard`.
+        assert_eq!(shard_for_bytes(b"", 73), 57);
+        assert_eq!(shard_for_bytes(b"foo", 73), 69);
+        assert_eq!(shard_for_bytes(&[0xff; 32], 73), 54);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn reader_matches_whole_key() {
+        // Spans several chunks, ending partway through one.
+        let key = (0..READ_CHUNK * 2 + 5)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            shard_for_reader(&key[..], 1000).unwrap(),
+            shard_for_bytes(&key, 1000)
+        );
+        assert_eq!(shard_for_reader(&b""[..], 73).unwrap(), 57);
+    }
+
+    #[test]
+    fn matches_single_write_key_hash() {
+        let hasher = crate::BuildSeaHasher;
+        for len in 0..64u8 {
+            let bytes = (0..len).collect::<Vec<_>>();
+            let key_hash = seahash::hash(&bytes);
+            assert_eq!(
+                shard_for_bytes(&bytes, 1000),
+                shard_for_key_hash(key_hash, 1000, &hasher)
+            );
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use crate::shard_for_key_hash;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::*;
+
+/// Memoizes the shards of recently used keys, for workloads that repeatedly look up a small set of hot keys.
+///
+/// Holds at most `capacity` key hashes, evicting the least recently used. Changing the destination count clears the cache.
+#[derive(Clone, Debug)]
+pub struct CachedSharder<S> {
+    hasher: S,
+    total_destinations: u64,
+    capacity: usize,
+    /// key_hash -> (shard, last use)
+    entries: HashMap<u64, (u64, u64)>,
+    /// last use -> key_hash
+    recency: BTreeMap<u64, u64>,
+    clock: u64,
+    hits: u64,
+    misses: u64,
+}
+
+impl<S: BuildHasher> CachedSharder<S> {
+    /// Creates an empty cache holding up to `capacity` keys.
+    ///
+    /// # Panics
+    ///
+    /// If total_destinations == 0 or capacity == 0.
+    pub fn new(capacity: usize, total_destinations: u64, hasher: S) -> Self {
+        assert_ne!(total_destinations, 0, "total_destinations must be > 0");
+        assert_ne!(capacity, 0, "capacity must be > 0");
+
+        CachedSharder {
+            hasher,
+            total_destinations,
+            capacity,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            clock: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// The destination count shards are computed for.
+    pub fn total_destinations(&self) -> u64 {
+        self.total_destinations
+    }
+
+    /// Changes the destination count, clearing the cache if it differs from the current one.
+    ///
+    /// # Panics
+    ///
+    /// If total_destinations == 0.
+    pub fn set_total_destinations(&mut self, total_destinations: u64) {
+        assert_ne!(total_destinations, 0, "total_destinations must be > 0");
+
+        if total_destinations != self.total_destinations {
+            self.total_destinations = total_destinations;
+            self.entries.clear();
+            self.recency.clear();
+        }
+    }
+
+    /// Returns the shard for `key`, from the cache if possible.
+    pub fn shard_for(&mut self, key: impl Hash) -> u64 {
+        let key_hash = self.hasher.hash_one(key);
+        self.shard_for_key_hash(key_hash)
+    }
+
+    /// Returns the shard for a key already hashed with this sharder's hasher, from the cache if possible.
+    pub fn shard_for_key_hash(&mut self, key_hash: u64) -> u64 {
+        self.clock += 1;
+
+        if let Some((shard, last_use)) = self.entries.get_mut(&key_hash) {
+            self.recency.remove(last_use);
+            self.recency.insert(self.clock, key_hash);
+            *last_use = self.clock;
+            self.hits += 1;
+            return *shard;
+        }
+
+        self.misses += 1;
+        if self.entries.len() == self.capacity {
+            let (_, evicted) = self.recency.pop_first().expect("cache is full");
+            self.entries.remove(&evicted);
+        }
+
+        let shard = shard_for_key_hash(key_hash, self.total_destinations, &self.hasher);
+        self.entries.insert(key_hash, (shard, self.clock));
+        self.recency.insert(self.clock, key_hash);
+        shard
+    }
+
+    /// Maximum number of cached keys.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Number of cached keys.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether nothing is cached.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Lookups answered from the cache.
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// Lookups that had to compute the shard.
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+
+    /// Resets [CachedSharder::hits] and [CachedSharder::misses] to zero, keeping cached shards.
+    pub fn reset_counters(&mut self) {
+        self.hits = 0;
+        self.misses = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shard_for;
+    use crate::BuildSeaHasher;
+
+    #[test]
+    fn agrees_with_shard_for() {
+        let mut cached = CachedSharder::new(8, 73, BuildSeaHasher);
+        for _ in 0..3 {
+            for key in 0..20u64 {
+                assert_eq!(cached.shard_for(key), shard_for(key, 73));
+            }
+        }
+        assert_eq!(cached.len(), 8);
+
+        cached.set_total_destinations(100);
+        assert!(cached.is_empty());
+        assert_eq!(cached.shard_for(7u64), shard_for(7u64, 100));
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let mut cached = CachedSharder::new(2, 16, BuildSeaHasher);
+        cached.shard_for("a");
+        cached.shard_for("b");
+        cached.shard_for("a");
+        cached.shard_for("c");
+        assert_eq!((cached.hits(), cached.misses()), (1, 3));
+
+        cached.shard_for("a");
+        assert_eq!((cached.hits(), cached.misses()), (2, 3));
+        cached.shard_for("b");
+        assert_eq!((cached.hits(), cached.misses()), (2, 4));
+
+        cached.reset_counters();
+        assert_eq!((cached.hits(), cached.misses()), (0, 0));
+        assert_eq!(cached.len(), 2);
+    }
+}
@@ -0,0 +1,53 @@
+use crate::shard_with_hasher;
+use core::hash::*;
+
+/// Returns the shard for a Change Data Capture event on the row `pk_bytes` of `table`.
+///
+/// Every change to the same row lands on the same shard, preserving per-row ordering downstream.
+pub fn shard_for_cdc(
+    table: &str,
+    pk_bytes: &[u8],
+    total_destinations: u64,
+    hasher: &impl BuildHasher,
+) -> u64 {
+    shard_with_hasher((table, pk_bytes), total_destinations, hasher)
+}
+
+/// [shard_for_cdc] for a textual primary key. Agrees with [shard_for_cdc] on `pk.as_bytes()`.
+pub fn shard_for_cdc_str_pk(
+    table: &str,
+    pk: &str,
+    total_destinations: u64,
+    hasher: &impl BuildHasher,
+) -> u64 {
+    shard_for_cdc(table, pk.as_bytes(), total_destinations, hasher)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BuildSeaHasher;
+
+    use proptest::*;
+
+    proptest! {
+        #[test]
+        fn same_row_is_stable_as_destinations_grow(table: String, pk: String) {
+            let mut last_shard = shard_for_cdc_str_pk(&table, &pk, 1, &BuildSeaHasher);
+            for n in 2..=64 {
+                let shard = shard_for_cdc(&table, pk.as_bytes(), n, &BuildSeaHasher);
+                prop_assert_eq!(shard, shard_for_cdc_str_pk(&table, &pk, n, &BuildSeaHasher));
+                prop_assert!(shard == last_shard || shard == n - 1);
+                last_shard = shard;
+            }
+        }
+    }
+
+    #[test]
+    fn table_is_part_of_the_key() {
+        let shards = (0..64)
+            .map(|i| shard_for_cdc_str_pk(&format!("table_{i}"), "1", 64, &BuildSeaHasher))
+            .collect::<std::collections::HashSet<_>>();
+        assert!(shards.len() > 1);
+    }
+}
@@ -0,0 +1,182 @@
+use crate::shard_for_key_hash;
+use std::collections::HashMap;
+use std::hash::*;
+use std::sync::mpsc::{self, Receiver, SendError, Sender};
+
+/// Picks a channel for each key, pinning keys to the channel they first used.
+///
+/// Shared by [ShardedSender] and [crate::tokio::ShardedSender].
+#[derive(Clone, Debug)]
+pub(crate) struct ChannelRouter<S> {
+    channels: u64,
+    pins: HashMap<u64, u64>,
+    hasher: S,
+}
+
+impl<S: BuildHasher> ChannelRouter<S> {
+    pub(crate) fn new(channels: usize, hasher: S) -> Self {
+        assert_ne!(channels, 0, "channels must be > 0");
+
+        ChannelRouter {
+            channels: channels as u64,
+            pins: HashMap::new(),
+            hasher,
+        }
+    }
+
+    pub(crate) fn route(&mut self, key: impl Hash) -> usize {
+        let key_hash = self.hasher.hash_one(key);
+        let (channels, hasher) = (self.channels, &self.hasher);
+        *self
+            .pins
+            .entry(key_hash)
+            .or_insert_with(|| shard_for_key_hash(key_hash, channels, hasher)) as usize
+    }
+
+    pub(crate) fn release(&mut self, key: impl Hash) -> bool {
+        self.pins.remove(&self.hasher.hash_one(key)).is_some()
+    }
+
+    pub(crate) fn grow(&mut self) {
+        self.channels += 1;
+    }
+
+    pub(crate) fn hasher(&self) -> &S {
+        &self.hasher
+    }
+}
+
+/// Sends each message on one of several [mpsc] channels, chosen by the shard of its key, so messages with the same key stay in order.
+///
+/// [ShardedSender::grow] adds a channel at runtime. Keys already sent keep their channel, so their streams stay in order on one receiver. Only keys not seen before can go to the new channel, placed as [crate::shard_with_hasher] places them. Once a key's stream is finished, [ShardedSender::release] forgets its channel so the key is placed again the next time it is sent.
+///
+/// ```
+/// # use faro_sharding::ShardedSender;
+/// # use std::hash::BuildHasherDefault;
+/// # use std::collections::hash_map::DefaultHasher;
+/// let hasher = BuildHasherDefault::<DefaultHasher>::default();
+/// let (mut sender, receivers) = ShardedSender::new(4, hasher);
+///
+/// let channel = sender.channel_for("user-17");
+/// sender.send("user-17", 1).unwrap();
+/// sender.send("user-17", 2).unwrap();
+/// assert_eq!(receivers[channel].try_iter().collect::<Vec<_>>(), [1, 2]);
+///
+/// let _new_receiver = sender.grow();
+/// assert_eq!(sender.channel_for("user-17"), channel);
+/// ```
+#[derive(Debug)]
+pub struct ShardedSender<T, S> {
+    senders: Vec<Sender<T>>,
+    router: ChannelRouter<S>,
+}
+
+impl<T, S: BuildHasher> ShardedSender<T, S> {
+    /// Creates `channels` channels, returning the sender and each channel's receiver in channel order.
+    ///
+    /// # Panics
+    ///
+    /// If channels == 0.
+    pub fn new(channels: usize, hasher: S) -> (Self, Vec<Receiver<T>>) {
+        let router = ChannelRouter::new(channels, hasher);
+        let (senders, receivers) = (0..channels).map(|_| mpsc::channel()).unzip();
+        (ShardedSender { senders, router }, receivers)
+    }
+
+    /// The channel `key`'s messages are sent on. Pins `key` to it if it was not already pinned.
+    pub fn channel_for(&mut self, key: impl Hash) -> usize {
+        self.router.route(key)
+    }
+
+    /// Sends `message` on `key`'s channel.
+    ///
+    /// Fails, returning the message, if that channel's receiver was dropped.
+    pub fn send(&mut self, key: impl Hash, message: T) -> Result<(), SendError<T>> {
+        let channel = self.router.route(key);
+        self.senders[channel].send(message)
+    }
+
+    /// Adds a channel, returning its receiver.
+    pub fn grow(&mut self) -> Receiver<T> {
+        let (sender, receiver) = mpsc::channel();
+        self.senders.push(sender);
+        self.router.grow();
+        receiver
+    }
+
+    /// Forgets `key`'s channel, so its next message is placed as if the key were new. Returns false if it was not pinned.
+    ///
+    /// Only release a key once its receiver has handled every message sent for it, or they may be handled out of order.
+    pub fn release(&mut self, key: impl Hash) -> bool {
+        self.router.release(key)
+    }
+
+    /// The number of channels.
+    pub fn len(&self) -> usize {
+        self.senders.len()
+    }
+
+    /// Always false, as there is at least one channel.
+    pub fn is_empty(&self) -> bool {
+        self.senders.is_empty()
+    }
+
+    /// The hasher keys are sharded with.
+    pub fn hasher(&self) -> &S {
+        self.router.hasher()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{shard_for, BuildSeaHasher};
+
+    #[test]
+    fn keeps_keys_on_their_channel() {
+        let (mut sender, receivers) = ShardedSender::new(4, BuildSeaHasher);
+        for seq in 0..100u64 {
+            sender.send(seq % 10, (seq % 10, seq)).unwrap();
+        }
+        let new_receiver = sender.grow();
+        for seq in 100..200u64 {
+            sender.send(seq % 10, (seq % 10, seq)).unwrap();
+        }
+        for key in 10..1_000u64 {
+            sender.send(key, (key, 0)).unwrap();
+        }
+        drop(sender);
+
+        assert!(new_receiver
+            .iter()
+            .all(|(key, _)| key >= 10 && shard_for(key, 5) == 4));
+        for (channel, receiver) in receivers.iter().enumerate() {
+            let messages = receiver.iter().collect::<Vec<_>>();
+            for key in 0..10 {
+                let stream = messages
+                    .iter()
+                    .filter(|m| m.0 == key)
+                    .map(|m| m.1)
+                    .collect::<Vec<_>>();
+                if shard_for(key, 4) == channel as u64 {
+                    assert_eq!(stream, (0..20).map(|i| key + 10 * i).collect::<Vec<_>>());
+                } else {
+                    assert!(stream.is_empty());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn released_keys_are_placed_again() {
+        let (mut sender, _receivers) = ShardedSender::<(), _>::new(1, BuildSeaHasher);
+        let key = (0..100u64).find(|&k| shard_for(k, 2) == 1).unwrap();
+        assert_eq!(sender.channel_for(key), 0);
+
+        let _new_receiver = sender.grow();
+        assert_eq!(sender.channel_for(key), 0);
+        assert!(sender.release(key));
+        assert!(!sender.release(key));
+        assert_eq!(sender.channel_for(key), 1);
+    }
+}
@@ -0,0 +1,144 @@
+//! Reference implementations of other consistent hashing algorithms, for evaluating Faro Sharding against them.
+//!
+//! Each algorithm has the same shape as [crate::shard_with_hasher] and a [ShardingStrategy], so they can be swapped in anywhere a [crate::FaroStrategy] is used.
+//!
+//! - [JumpHash](https://arxiv.org/abs/1406.2294) is O(log n) and, like Faro Sharding, only moves keys onto new destinations.
+//! - [Rendezvous](https://en.wikipedia.org/wiki/Rendezvous_hashing) (highest random weight) hashing is O(n) but can remove any destination, not just the last.
+
+use crate::ShardingStrategy;
+use core::hash::*;
+
+/// Shards `key` with JumpHash, seeded by `hasher.hash_one(key)`.
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub fn jump_shard_with_hasher(
+    key: impl Hash,
+    total_destinations: u64,
+    hasher: &impl BuildHasher,
+) -> u64 {
+    jump_shard_for_key_hash(hasher.hash_one(key), total_destinations)
+}
+
+/// JumpHash of `key_hash`, exactly as in the paper.
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub fn jump_shard_for_key_hash(key_hash: u64, total_destinations: u64) -> u64 {
+    assert_ne!(total_destinations, 0, "total_destinations must be > 0");
+
+    let mut key = key_hash;
+    let mut shard = 0;
+    let mut next = 0;
+    while next < total_destinations {
+        shard = next;
+        key = key.wrapping_mul(2862933555777941757).wrapping_add(1);
+        next = ((shard + 1) as f64 * ((1u64 << 31) as f64 / ((key >> 33) + 1) as f64)) as u64;
+    }
+    shard
+}
+
+/// Shards `key` with rendezvous hashing: the destination `i` with the greatest `hasher.hash_one((key_hash, i))` wins.
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub fn rendezvous_shard_with_hasher(
+    key: impl Hash,
+    total_destinations: u64,
+    hasher: &impl BuildHasher,
+) -> u64 {
+    rendezvous_shard_for_key_hash(hasher.hash_one(key), total_destinations, hasher)
+}
+
+/// Rendezvous hashing of a key already hashed with `hasher`. See [rendezvous_shard_with_hasher].
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub fn rendezvous_shard_for_key_hash(
+    key_hash: u64,
+    total_destinations: u64,
+    hasher: &impl BuildHasher,
+) -> u64 {
+    assert_ne!(total_destinations, 0, "total_destinations must be > 0");
+
+    (0..total_destinations)
+        .max_by_key(|&destination| {
+            (
+                hasher.hash_one((key_hash, destination)),
+                u64::MAX - destination,
+            )
+        })
+        .expect("total_destinations > 0")
+}
+
+/// [ShardingStrategy] implementing JumpHash.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JumpStrategy;
+
+impl ShardingStrategy for JumpStrategy {
+    fn shard_for_hash(&self, key_hash: u64, total_destinations: u64) -> u64 {
+        jump_shard_for_key_hash(key_hash, total_destinations)
+    }
+}
+
+/// [ShardingStrategy] implementing rendezvous hashing with the wrapped hasher.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RendezvousStrategy<S>(pub S);
+
+impl<S: BuildHasher> ShardingStrategy for RendezvousStrategy<S> {
+    fn shard_for_hash(&self, key_hash: u64, total_destinations: u64) -> u64 {
+        rendezvous_shard_for_key_hash(key_hash, total_destinations, &self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BuildSeaHasher;
+
+    #[test]
+    fn jump_matches_reference() {
+        // Outputs of the C++ implementation in the paper.
+        assert_eq!(jump_shard_for_key_hash(0, 1_000), 0);
+        assert_eq!(jump_shard_for_key_hash(1, 1_000), 549);
+        assert_eq!(jump_shard_for_key_hash(0xdead_beef, 1_000), 285);
+        assert_eq!(jump_shard_for_key_hash(u64::MAX, 1_000), 313);
+        assert_eq!(jump_shard_for_key_hash(123_456_789, 7), 0);
+        assert_eq!(jump_shard_for_key_hash(0xdead_beef, 7), 5);
+    }
+
+    #[test]
+    fn growth_only_moves_to_the_new_destination() {
+        for key in 0..1_000u64 {
+            let (mut jump, mut rendezvous) = (0, 0);
+            for total in 1..=64 {
+                let next_jump = jump_shard_with_hasher(key, total, &BuildSeaHasher);
+                assert!(next_jump == jump || next_jump == total - 1);
+                jump = next_jump;
+
+                let next_rendezvous = rendezvous_shard_with_hasher(key, total, &BuildSeaHasher);
+                assert!(next_rendezvous == rendezvous || next_rendezvous == total - 1);
+                rendezvous = next_rendezvous;
+            }
+        }
+    }
+
+    #[test]
+    fn strategies_agree_with_functions() {
+        for key in 0..100u64 {
+            let key_hash = BuildSeaHasher.hash_one(key);
+            assert_eq!(
+                JumpStrategy.shard_for_hash(key_hash, 17),
+                jump_shard_with_hasher(key, 17, &BuildSeaHasher)
+            );
+            assert_eq!(
+                RendezvousStrategy(BuildSeaHasher).shard_for_hash(key_hash, 17),
+                rendezvous_shard_with_hasher(key, 17, &BuildSeaHasher)
+            );
+        }
+    }
+}
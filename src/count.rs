@@ -0,0 +1,91 @@
+use crate::shard_with_hasher;
+use core::hash::*;
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Unsigned integer types that can be used as destination counts and shard indexes.
+///
+/// Implemented for `u8`, `u16`, `u32`, `u64`, and `usize`. This trait is sealed.
+pub trait DestinationCount: Copy + sealed::Sealed {
+    /// Widens to the `u64` the algorithm works in.
+    fn to_u64(self) -> u64;
+
+    /// Narrows a shard index back to this type. Only called with values below a count of this type, so it cannot truncate.
+    fn from_shard(shard: u64) -> Self;
+}
+
+macro_rules! impl_destination_count {
+    ($($t:ty),*) => {$(
+        impl sealed::Sealed for $t {}
+
+        impl DestinationCount for $t {
+            fn to_u64(self) -> u64 {
+                self as u64
+            }
+
+            fn from_shard(shard: u64) -> Self {
+                shard as $t
+            }
+        }
+    )*};
+}
+
+impl_destination_count!(u8, u16, u32, u64, usize);
+
+/// [shard_with_hasher] for destination counts of any unsigned integer type.
+///
+/// Always agrees with [shard_with_hasher] on the same count widened to `u64`.
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub fn shard_with_hasher_as<N: DestinationCount>(
+    key: impl Hash,
+    total_destinations: N,
+    hasher: &impl BuildHasher,
+) -> N {
+    N::from_shard(shard_with_hasher(key, total_destinations.to_u64(), hasher))
+}
+
+/// [crate::shard_for] with a `u32` destination count.
+#[cfg(feature = "seahash")]
+pub fn shard_for_u32(key: impl Hash, total_destinations: u32) -> u32 {
+    shard_with_hasher_as(
+        key,
+        total_destinations,
+        &crate::seahash::StableSeaHasher::new(),
+    )
+}
+
+/// [crate::shard_for] with a `usize` destination count.
+#[cfg(feature = "seahash")]
+pub fn shard_for_usize(key: impl Hash, total_destinations: usize) -> usize {
+    shard_with_hasher_as(
+        key,
+        total_destinations,
+        &crate::seahash::StableSeaHasher::new(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shard_for;
+
+    use proptest::*;
+
+    proptest! {
+        #[test]
+        fn agrees_with_u64(key: String, total_destinations in 1u16..=1024) {
+            let expected = shard_for(&key, u64::from(total_destinations));
+            prop_assert_eq!(u64::from(shard_for_u32(&key, u32::from(total_destinations))), expected);
+            prop_assert_eq!(shard_for_usize(&key, usize::from(total_destinations)) as u64, expected);
+            prop_assert_eq!(
+                u64::from(shard_with_hasher_as(&key, total_destinations, &crate::BuildSeaHasher)),
+                expected
+            );
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use crate::shard_with_hasher;
+use core::hash::*;
+
+/// Returns the counter shard that always holds `counter_name`.
+pub fn counter_shard_for(
+    counter_name: &str,
+    total_counter_shards: u64,
+    hasher: &impl BuildHasher,
+) -> u64 {
+    shard_with_hasher(counter_name, total_counter_shards, hasher)
+}
+
+/// Returns the counter shard to increment for one request against `counter_name`.
+///
+/// Different requests for the same counter spread across all counter shards, so a hot counter does not contend on a single shard. Read the total with [aggregate_counter_shards].
+pub fn counter_shard_for_request(
+    counter_name: &str,
+    request_id: impl Hash,
+    total_counter_shards: u64,
+    hasher: &impl BuildHasher,
+) -> u64 {
+    shard_with_hasher((counter_name, request_id), total_counter_shards, hasher)
+}
+
+/// Sums the values of every shard of a counter.
+pub fn aggregate_counter_shards(shard_values: &[i64]) -> i64 {
+    shard_values.iter().sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BuildSeaHasher;
+
+    #[test]
+    fn requests_fan_out_and_aggregate() {
+        let mut shards = [0i64; 8];
+        for request in 0..1000 {
+            shards[counter_shard_for_request("hits", request, 8, &BuildSeaHasher) as usize] += 1;
+        }
+
+        assert!(shards.iter().all(|&v| v > 0));
+        assert_eq!(aggregate_counter_shards(&shards), 1000);
+        assert_eq!(
+            counter_shard_for("hits", 8, &BuildSeaHasher),
+            shard_with_hasher("hits", 8, &BuildSeaHasher)
+        );
+    }
+}
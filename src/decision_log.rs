@@ -0,0 +1,102 @@
+use crate::shard_for_key_hash;
+use std::hash::*;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A single recorded routing decision.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DecisionEntry {
+    /// Nanoseconds since the Unix epoch when the decision was made.
+    pub timestamp_ns: u64,
+    /// Hash of the key that was routed.
+    pub key_hash: u64,
+    /// Destination count at the time of the decision.
+    pub total_destinations: u64,
+    /// The shard the key was routed to.
+    pub shard: u64,
+    /// Caller-provided name identifying the hasher that was used.
+    pub hasher_id: String,
+}
+
+/// An append-only log of routing decisions that can be replayed for auditing.
+#[derive(Clone, Debug, Default)]
+pub struct ShardingDecisionLog {
+    /// Recorded decisions, oldest first.
+    pub entries: Vec<DecisionEntry>,
+}
+
+impl ShardingDecisionLog {
+    /// Creates an empty log.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Shards `key` with `hasher`, records the decision, and returns the shard.
+    pub fn shard_for_and_record<K: Hash>(
+        &mut self,
+        key: &K,
+        total_destinations: u64,
+        hasher_id: &str,
+        hasher: &impl BuildHasher,
+    ) -> u64 {
+        let key_hash = hasher.hash_one(key);
+        let shard = shard_for_key_hash(key_hash, total_destinations, hasher);
+
+        let timestamp_ns = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64);
+        self.entries.push(DecisionEntry {
+            timestamp_ns,
+            key_hash,
+            total_destinations,
+            shard,
+            hasher_id: hasher_id.to_string(),
+        });
+
+        shard
+    }
+
+    /// Recomputes `entry` with `hasher` and returns whether it reaches the recorded shard.
+    pub fn replay_check(entry: &DecisionEntry, hasher: &impl BuildHasher) -> bool {
+        entry.total_destinations != 0
+            && shard_for_key_hash(entry.key_hash, entry.total_destinations, hasher) == entry.shard
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{shard_with_hasher, BuildSeaHasher};
+    use std::hash::BuildHasherDefault;
+
+    #[test]
+    fn replays_recorded_decisions() {
+        let mut log = ShardingDecisionLog::new();
+        let shard = log.shard_for_and_record(&"foo", 73, "seahash", &BuildSeaHasher);
+        assert_eq!(shard, shard_with_hasher("foo", 73, &BuildSeaHasher));
+
+        let entry = &log.entries[0];
+        assert_eq!(entry.hasher_id, "seahash");
+        assert!(ShardingDecisionLog::replay_check(entry, &BuildSeaHasher));
+
+        let mut tampered = entry.clone();
+        tampered.shard = (tampered.shard + 1) % 73;
+        assert!(!ShardingDecisionLog::replay_check(
+            &tampered,
+            &BuildSeaHasher
+        ));
+    }
+
+    #[test]
+    fn detects_a_different_hasher() {
+        let mut log = ShardingDecisionLog::new();
+        for key in 0..32 {
+            log.shard_for_and_record(&key, 1024, "seahash", &BuildSeaHasher);
+        }
+
+        let other = BuildHasherDefault::<std::collections::hash_map::DefaultHasher>::default();
+        assert!(log
+            .entries
+            .iter()
+            .any(|e| !ShardingDecisionLog::replay_check(e, &other)));
+    }
+}
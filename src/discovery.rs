@@ -0,0 +1,152 @@
+//! Keeping a [DynamicSharder] in sync with service discovery.
+//!
+//! Discovery systems report membership as an unordered set, but Faro Sharding assigns keys by position. [apply_membership] merges each report into the current list so that existing members keep their shard and new members are appended. [watch_membership] does this for every report from a [Stream], such as one fed by an etcd or Consul watch.
+
+use crate::DynamicSharder;
+use ::futures_util::{Stream, StreamExt};
+use std::hash::*;
+use std::pin::pin;
+
+/// What [apply_membership] changed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MembershipChange<D> {
+    /// The sharder's version after the change. Unchanged if nothing was added or removed.
+    pub version: u64,
+    /// Members appended as new shards, in the order they were appended.
+    pub added: Vec<D>,
+    /// Members removed from the end of the list.
+    pub removed: Vec<D>,
+    /// Members no longer reported that were kept, because removing them would shift every later member's shard.
+    ///
+    /// Route around them, for example with [crate::shard_for_available], until the members after them are also gone.
+    pub stranded: Vec<D>,
+}
+
+impl<D> MembershipChange<D> {
+    /// Whether the destination list changed.
+    pub fn changed(&self) -> bool {
+        !self.added.is_empty() || !self.removed.is_empty()
+    }
+}
+
+/// Merges `members` into `current`, returning the new list and what changed.
+fn merge<D: Clone + Ord>(current: &[D], members: &[D]) -> (Vec<D>, MembershipChange<D>) {
+    let mut destinations = current.to_vec();
+    let mut removed = Vec::new();
+    while destinations.len() > 1 && !members.contains(destinations.last().unwrap()) {
+        removed.push(destinations.pop().unwrap());
+    }
+
+    let stranded = destinations
+        .iter()
+        .filter(|d| !members.contains(d))
+        .cloned()
+        .collect();
+
+    let mut added = members
+        .iter()
+        .filter(|m| !destinations.contains(m))
+        .cloned()
+        .collect::<Vec<_>>();
+    // Reports are unordered, so sort to append the same way in every process.
+    added.sort();
+    added.dedup();
+    destinations.extend(added.iter().cloned());
+
+    let change = MembershipChange {
+        version: 0,
+        added,
+        removed,
+        stranded,
+    };
+    (destinations, change)
+}
+
+/// Updates `sharder` to the reported `members`, keeping every remaining member on its shard.
+///
+/// New members are appended in sorted order, so every process applying the same reports ends up with the same list. Members no longer reported are removed only from the end of the list, and the rest are reported as [MembershipChange::stranded]. The last member is never removed.
+pub fn apply_membership<D: Clone + Ord, S: BuildHasher>(
+    sharder: &DynamicSharder<D, S>,
+    members: &[D],
+) -> MembershipChange<D> {
+    let current = sharder.load();
+    let (_, mut change) = merge(current.destinations(), members);
+    if !change.changed() {
+        change.version = current.version();
+        return change;
+    }
+
+    change.version = sharder.update(|current| {
+        let (destinations, latest) = merge(current, members);
+        change = latest;
+        destinations
+    });
+    change
+}
+
+/// Calls [apply_membership] for every report from `updates`, until it ends.
+///
+/// `on_change` is called after every report that changed the list.
+pub async fn watch_membership<D: Clone + Ord, S: BuildHasher>(
+    sharder: &DynamicSharder<D, S>,
+    updates: impl Stream<Item = Vec<D>>,
+    mut on_change: impl FnMut(&MembershipChange<D>),
+) {
+    let mut updates = pin!(updates);
+    while let Some(members) = updates.next().await {
+        let change = apply_membership(sharder, &members);
+        if change.changed() {
+            on_change(&change);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BuildSeaHasher;
+    use ::futures_util::stream;
+
+    #[test]
+    fn appends_new_members_in_sorted_order() {
+        let sharder = DynamicSharder::new(vec!["b", "a"], BuildSeaHasher);
+        let change = apply_membership(&sharder, &["d", "a", "c", "b"]);
+        assert_eq!(change.added, ["c", "d"]);
+        assert_eq!(change.version, 1);
+        assert_eq!(sharder.load().destinations(), ["b", "a", "c", "d"]);
+
+        let unchanged = apply_membership(&sharder, &["a", "b", "c", "d"]);
+        assert!(!unchanged.changed());
+        assert_eq!(unchanged.version, 1);
+    }
+
+    #[test]
+    fn only_removes_from_the_end() {
+        let sharder = DynamicSharder::new(vec!["a", "b", "c", "d"], BuildSeaHasher);
+        let change = apply_membership(&sharder, &["a", "c"]);
+        assert_eq!(change.removed, ["d"]);
+        assert_eq!(change.stranded, ["b"]);
+        assert_eq!(sharder.load().destinations(), ["a", "b", "c"]);
+
+        let change = apply_membership(&sharder, &["e"]);
+        assert_eq!(change.removed, ["c", "b"]);
+        assert_eq!(change.stranded, ["a"]);
+        assert_eq!(change.added, ["e"]);
+        assert_eq!(sharder.load().destinations(), ["a", "e"]);
+    }
+
+    #[::tokio::test]
+    async fn watches_a_stream() {
+        let sharder = DynamicSharder::new(vec![1u64], BuildSeaHasher);
+        let updates = stream::iter([vec![1, 2], vec![2, 1], vec![3, 1, 2]]);
+
+        let mut changes = Vec::new();
+        watch_membership(&sharder, updates, |change| {
+            changes.push(change.added.clone())
+        })
+        .await;
+        assert_eq!(changes, [vec![2], vec![3]]);
+        assert_eq!(sharder.load().destinations(), [1, 2, 3]);
+        assert_eq!(sharder.load().version(), 2);
+    }
+}
@@ -0,0 +1,235 @@
+use crate::shard_with_hasher;
+use arc_swap::ArcSwap;
+use std::hash::*;
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// One version of a [DynamicSharder]'s destination list.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DestinationSet<D> {
+    destinations: Vec<D>,
+    version: u64,
+}
+
+impl<D> DestinationSet<D> {
+    /// The destinations, in shard order.
+    pub fn destinations(&self) -> &[D] {
+        &self.destinations
+    }
+
+    /// Starts at 0 and increases by one with every change.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+}
+
+/// A thread-safe sharder whose destination list can be replaced while other threads keep sharding.
+///
+/// Lookups never take a lock. Each one loads the current [DestinationSet] atomically, so it sees either the list before a change or after it, never a mix. Growing the list only moves keys onto the new destinations, as with [shard_with_hasher].
+///
+/// ```
+/// # use faro_sharding::DynamicSharder;
+/// # use std::hash::BuildHasherDefault;
+/// # use std::collections::hash_map::DefaultHasher;
+/// let hasher = BuildHasherDefault::<DefaultHasher>::default();
+/// let sharder = DynamicSharder::new(vec!["10.0.0.1", "10.0.0.2"], hasher);
+///
+/// std::thread::scope(|s| {
+///     s.spawn(|| sharder.push("10.0.0.3"));
+///     let node = sharder.route("user-17");
+///     assert!(node.shard() < node.destinations().len() as u64);
+/// });
+/// assert_eq!(sharder.load().version(), 1);
+/// ```
+#[derive(Debug)]
+pub struct DynamicSharder<D, S> {
+    current: ArcSwap<DestinationSet<D>>,
+    hasher: S,
+}
+
+impl<D, S: BuildHasher> DynamicSharder<D, S> {
+    /// Shards across `destinations`, at version 0.
+    ///
+    /// # Panics
+    ///
+    /// If destinations is empty.
+    pub fn new(destinations: Vec<D>, hasher: S) -> Self {
+        assert!(!destinations.is_empty(), "destinations must not be empty");
+
+        DynamicSharder {
+            current: ArcSwap::from_pointee(DestinationSet {
+                destinations,
+                version: 0,
+            }),
+            hasher,
+        }
+    }
+
+    /// The current destination list.
+    pub fn load(&self) -> Arc<DestinationSet<D>> {
+        self.current.load_full()
+    }
+
+    /// The shard for `key` in the current destination list.
+    pub fn shard_for(&self, key: impl Hash) -> u64 {
+        let current = self.current.load();
+        shard_with_hasher(key, current.destinations.len() as u64, &self.hasher)
+    }
+
+    /// The destination for `key`, along with the destination list it was chosen from.
+    pub fn route(&self, key: impl Hash) -> Routed<D> {
+        let set = self.load();
+        let shard = shard_with_hasher(key, set.destinations.len() as u64, &self.hasher);
+        Routed { set, shard }
+    }
+
+    /// Replaces the destination list, returning the new version.
+    ///
+    /// # Panics
+    ///
+    /// If destinations is empty.
+    pub fn replace(&self, destinations: Vec<D>) -> u64 {
+        assert!(!destinations.is_empty(), "destinations must not be empty");
+
+        let mut set = DestinationSet {
+            destinations,
+            version: 0,
+        };
+        loop {
+            let current = self.current.load();
+            set.version = current.version + 1;
+            let new = Arc::new(set);
+            let previous = self.current.compare_and_swap(&*current, Arc::clone(&new));
+            if Arc::ptr_eq(&previous, &current) {
+                return new.version;
+            }
+            // The failed swap dropped its reference, so this is the only one left.
+            set = match Arc::try_unwrap(new) {
+                Ok(set) => set,
+                Err(_) => unreachable!(),
+            };
+        }
+    }
+
+    /// Replaces the destination list with `f` of the current one, returning the new version.
+    ///
+    /// If another thread changes the list first, `f` is called again with the new list, like a compare-and-swap loop.
+    ///
+    /// # Panics
+    ///
+    /// If `f` returns an empty list.
+    pub fn update(&self, mut f: impl FnMut(&[D]) -> Vec<D>) -> u64 {
+        let mut version = 0;
+        self.current.rcu(|current| {
+            let destinations = f(&current.destinations);
+            assert!(!destinations.is_empty(), "destinations must not be empty");
+            version = current.version + 1;
+            DestinationSet {
+                destinations,
+                version,
+            }
+        });
+        version
+    }
+}
+
+impl<D: Clone, S: BuildHasher> DynamicSharder<D, S> {
+    /// Adds a destination as the new last shard, returning the new version.
+    pub fn push(&self, destination: D) -> u64 {
+        self.update(|current| {
+            let mut destinations = current.to_vec();
+            destinations.push(destination.clone());
+            destinations
+        })
+    }
+}
+
+/// A destination chosen by [DynamicSharder::route].
+///
+/// Derefs to the destination. It keeps its [DestinationSet] alive, so the destination stays valid after the sharder changes.
+#[derive(Clone, Debug)]
+pub struct Routed<D> {
+    set: Arc<DestinationSet<D>>,
+    shard: u64,
+}
+
+impl<D> Routed<D> {
+    /// The shard chosen.
+    pub fn shard(&self) -> u64 {
+        self.shard
+    }
+
+    /// The version of the destination list the shard was chosen from.
+    pub fn version(&self) -> u64 {
+        self.set.version
+    }
+
+    /// The destination list the shard was chosen from.
+    pub fn destinations(&self) -> &[D] {
+        &self.set.destinations
+    }
+}
+
+impl<D> Deref for Routed<D> {
+    type Target = D;
+
+    fn deref(&self) -> &D {
+        &self.set.destinations[self.shard as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{shard_for, BuildSeaHasher};
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    #[test]
+    fn readers_never_see_a_torn_list() {
+        // Every destination is tagged with the version of the list it is in.
+        let list = |version: u64| (0..2 + version % 5).map(|_| version).collect();
+        let sharder = DynamicSharder::new(list(0), BuildSeaHasher);
+        let done = AtomicBool::new(false);
+
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    let mut key = 0u64;
+                    while !done.load(Ordering::Relaxed) {
+                        let routed = sharder.route(key);
+                        assert_eq!(*routed, routed.version());
+                        assert_eq!(
+                            routed.shard(),
+                            shard_for(key, routed.destinations().len() as u64)
+                        );
+                        key += 1;
+                    }
+                });
+            }
+
+            for version in 1..=10_000 {
+                assert_eq!(sharder.replace(list(version)), version);
+            }
+            done.store(true, Ordering::Relaxed);
+        });
+    }
+
+    #[test]
+    fn concurrent_pushes_are_not_lost() {
+        let sharder = DynamicSharder::new(vec![0u64], BuildSeaHasher);
+        std::thread::scope(|s| {
+            for thread in 1..=4 {
+                let sharder = &sharder;
+                s.spawn(move || {
+                    for i in 0..100 {
+                        sharder.push(thread * 1_000 + i);
+                    }
+                });
+            }
+        });
+
+        let set = sharder.load();
+        assert_eq!(set.version(), 400);
+        assert_eq!(set.destinations().len(), 401);
+    }
+}
@@ -0,0 +1,75 @@
+use crate::shard_with_hasher;
+use std::hash::*;
+
+/// Error returned when an email address cannot be canonicalized.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EmailError {
+    /// The address does not contain exactly one `@` with text on both sides.
+    InvalidFormat,
+}
+
+impl std::fmt::Display for EmailError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EmailError::InvalidFormat => write!(f, "email must have the form local@domain"),
+        }
+    }
+}
+
+impl std::error::Error for EmailError {}
+
+/// [crate::shard_for] for the canonical form of an email address. See [shard_email_with_hasher].
+#[cfg(feature = "seahash")]
+pub fn shard_for_email(email: &str, total_destinations: u64) -> Result<u64, EmailError> {
+    shard_email_with_hasher(
+        email,
+        total_destinations,
+        &crate::seahash::StableSeaHasher::new(),
+    )
+}
+
+/// Returns the shard for an email address after canonicalizing it.
+///
+/// Both parts are lowercased and `+tag` subaddressing is stripped, so `User+tag@Example.COM` and `user@example.com` share a shard.
+pub fn shard_email_with_hasher(
+    email: &str,
+    total_destinations: u64,
+    hasher: &impl BuildHasher,
+) -> Result<u64, EmailError> {
+    let (local, domain) = email.split_once('@').ok_or(EmailError::InvalidFormat)?;
+    if domain.contains('@') {
+        return Err(EmailError::InvalidFormat);
+    }
+
+    let local = local.split('+').next().unwrap_or_default();
+    if local.is_empty() || domain.is_empty() {
+        return Err(EmailError::InvalidFormat);
+    }
+
+    let canonical = format!("{}@{}", local.to_lowercase(), domain.to_lowercase());
+    Ok(shard_with_hasher(canonical, total_destinations, hasher))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subaddress_and_case_are_ignored() {
+        assert_eq!(
+            shard_for_email("user+tag@Example.COM", 64),
+            shard_for_email("user@example.com", 64),
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_addresses() {
+        for email in ["user.example.com", "a@b@c", "@example.com", "user@"] {
+            assert_eq!(
+                shard_for_email(email, 64),
+                Err(EmailError::InvalidFormat),
+                "{email}"
+            );
+        }
+    }
+}
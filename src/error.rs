@@ -0,0 +1,18 @@
+/// Error returned by the non-panicking sharding functions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ShardError {
+    /// `total_destinations` was 0, so there is no shard to return.
+    ZeroDestinations,
+}
+
+impl core::fmt::Display for ShardError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ShardError::ZeroDestinations => write!(f, "total_destinations must be > 0"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ShardError {}
@@ -0,0 +1,114 @@
+use crate::shard_for_key_hash;
+use std::collections::HashMap;
+use std::hash::*;
+
+/// An in-memory event store that routes every event for an aggregate to the same shard.
+///
+/// Aggregates are identified by the hash of their id, so two ids with colliding 64-bit hashes would share an event stream.
+pub struct ShardedEventSourcing<E, S> {
+    shards: Vec<ShardLog<E>>,
+    hasher: S,
+}
+
+struct ShardLog<E> {
+    aggregates: HashMap<u64, Vec<E>>,
+    order: Vec<(u64, usize)>,
+}
+
+impl<E, S: BuildHasher> ShardedEventSourcing<E, S> {
+    /// Creates an empty store with `total_shards` shards.
+    ///
+    /// # Panics
+    ///
+    /// If total_shards == 0.
+    pub fn new(total_shards: u64, hasher: S) -> Self {
+        assert_ne!(total_shards, 0, "total_shards must be > 0");
+
+        ShardedEventSourcing {
+            shards: (0..total_shards)
+                .map(|_| ShardLog {
+                    aggregates: HashMap::new(),
+                    order: Vec::new(),
+                })
+                .collect(),
+            hasher,
+        }
+    }
+
+    /// Returns the shard holding `aggregate_id`'s events.
+    pub fn shard_for<K: Hash>(&self, aggregate_id: &K) -> u64 {
+        self.locate(aggregate_id).0
+    }
+
+    /// Appends `event` to the stream of `aggregate_id`.
+    pub fn append<K: Hash>(&mut self, aggregate_id: K, event: E) {
+        let (shard, aggregate) = self.locate(&aggregate_id);
+        let log = &mut self.shards[shard as usize];
+
+        let events = log.aggregates.entry(aggregate).or_default();
+        log.order.push((aggregate, events.len()));
+        events.push(event);
+    }
+
+    /// Returns every event for `aggregate_id`, in the order they were appended.
+    pub fn events_for_aggregate<K: Hash>(&self, aggregate_id: &K) -> &[E] {
+        let (shard, aggregate) = self.locate(aggregate_id);
+        self.shards[shard as usize]
+            .aggregates
+            .get(&aggregate)
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Iterates every event in `shard`, across all its aggregates, in the order they were appended.
+    ///
+    /// # Panics
+    ///
+    /// If shard is out of range.
+    pub fn events_for_shard(&self, shard: u64) -> impl Iterator<Item = &E> {
+        let log = &self.shards[shard as usize];
+        log.order
+            .iter()
+            .map(|(aggregate, i)| &log.aggregates[aggregate][*i])
+    }
+
+    fn locate<K: Hash>(&self, aggregate_id: &K) -> (u64, u64) {
+        let aggregate = self.hasher.hash_one(aggregate_id);
+        let shard = shard_for_key_hash(aggregate, self.shards.len() as u64, &self.hasher);
+        (shard, aggregate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BuildSeaHasher;
+
+    #[test]
+    fn preserves_order_per_aggregate_and_shard() {
+        let mut store = ShardedEventSourcing::new(4, BuildSeaHasher);
+        for i in 0..20 {
+            store.append(i % 5, (i % 5, i));
+        }
+
+        for aggregate in 0..5 {
+            let events = store.events_for_aggregate(&aggregate);
+            let expected = (0..20)
+                .filter(|i| i % 5 == aggregate)
+                .map(|i| (aggregate, i))
+                .collect::<Vec<_>>();
+            assert_eq!(events, expected);
+        }
+        assert_eq!(store.events_for_aggregate(&99), &[]);
+
+        let mut replayed = 0;
+        for shard in 0..4 {
+            let events = store.events_for_shard(shard).collect::<Vec<_>>();
+            assert!(events.windows(2).all(|w| w[0].1 < w[1].1));
+            assert!(events
+                .iter()
+                .all(|(aggregate, _)| store.shard_for(aggregate) == shard));
+            replayed += events.len();
+        }
+        assert_eq!(replayed, 20);
+    }
+}
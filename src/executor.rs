@@ -0,0 +1,169 @@
+use crate::shard_with_hasher;
+use std::hash::*;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Sender};
+use std::thread::{self, JoinHandle};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+struct Worker {
+    jobs: Sender<Job>,
+    thread: JoinHandle<()>,
+}
+
+impl Worker {
+    fn spawn(wait_for: Option<mpsc::Receiver<()>>) -> Self {
+        let (jobs, queue) = mpsc::channel::<Job>();
+        let thread = thread::spawn(move || {
+            if let Some(fences) = wait_for {
+                // Returns once every fence has been dropped.
+                while fences.recv().is_ok() {}
+            }
+            for job in queue {
+                // The panic is still reported by the panic hook. Catching it keeps the worker running the key's later jobs.
+                let _ = panic::catch_unwind(AssertUnwindSafe(job));
+            }
+        });
+        Worker { jobs, thread }
+    }
+}
+
+/// Runs jobs on worker threads, sending each job to the worker for its key.
+///
+/// Jobs with the same key run one at a time, in the order they were submitted. Adding a worker only moves keys onto it, and it does not start until the existing workers have finished every job submitted before it was added, so per-key ordering holds across growth too.
+///
+/// A job that panics does not stop its worker. Later jobs, for its key and the others on that worker, still run.
+pub struct ShardedExecutor<S> {
+    workers: Vec<Worker>,
+    hasher: S,
+}
+
+impl<S: BuildHasher> ShardedExecutor<S> {
+    /// Starts `worker_count` worker threads.
+    ///
+    /// # Panics
+    ///
+    /// If worker_count == 0.
+    pub fn new(worker_count: u64, hasher: S) -> Self {
+        assert_ne!(worker_count, 0, "worker_count must be > 0");
+
+        ShardedExecutor {
+            workers: (0..worker_count).map(|_| Worker::spawn(None)).collect(),
+            hasher,
+        }
+    }
+
+    /// Number of worker threads.
+    pub fn worker_count(&self) -> u64 {
+        self.workers.len() as u64
+    }
+
+    /// Queues `job` on the worker for `key`, returning that worker's index.
+    pub fn submit(&self, key: impl Hash, job: impl FnOnce() + Send + 'static) -> u64 {
+        let worker = shard_with_hasher(key, self.worker_count(), &self.hasher);
+        self.workers[worker as usize]
+            .jobs
+            .send(Box::new(job))
+            .expect("workers run until the executor is dropped");
+        worker
+    }
+
+    /// Starts a new last worker, returning its index.
+    ///
+    /// Jobs for keys that move to it are queued immediately but run only after the other workers have drained the jobs submitted before this call.
+    pub fn add_worker(&mut self) -> u64 {
+        let (fence, fences) = mpsc::channel();
+        for worker in &self.workers {
+            let fence = fence.clone();
+            worker
+                .jobs
+                .send(Box::new(move || drop(fence)))
+                .expect("workers run until the executor is dropped");
+        }
+        drop(fence);
+
+        self.workers.push(Worker::spawn(Some(fences)));
+        self.worker_count() - 1
+    }
+
+    /// Waits for every queued job to finish and stops the workers.
+    ///
+    /// Dropping the executor does the same.
+    pub fn join(self) {}
+}
+
+impl<S> Drop for ShardedExecutor<S> {
+    fn drop(&mut self) {
+        for Worker { jobs, thread } in self.workers.drain(..) {
+            drop(jobs);
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{shard_for, BuildSeaHasher};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[test]
+    fn keeps_per_key_order_across_growth() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut executor = ShardedExecutor::new(2, BuildSeaHasher);
+
+        let submit_all = |executor: &ShardedExecutor<_>, round: u64| {
+            for key in 0..20u64 {
+                let log = Arc::clone(&log);
+                executor.submit(key, move || {
+                    if round == 0 {
+                        // Make the old workers slow so the new worker would run first without the fence.
+                        std::thread::sleep(Duration::from_millis(1));
+                    }
+                    log.lock().unwrap().push((key, round));
+                });
+            }
+        };
+
+        submit_all(&executor, 0);
+        assert_eq!(executor.add_worker(), 2);
+        submit_all(&executor, 1);
+        executor.join();
+
+        let log = log.lock().unwrap();
+        assert_eq!(log.len(), 40);
+        for key in 0..20u64 {
+            let rounds = log
+                .iter()
+                .filter(|(k, _)| *k == key)
+                .map(|(_, r)| *r)
+                .collect::<Vec<_>>();
+            assert_eq!(rounds, [0, 1], "key {}", key);
+        }
+    }
+
+    #[test]
+    fn survives_panicking_jobs() {
+        let ran = Arc::new(Mutex::new(Vec::new()));
+        let mut executor = ShardedExecutor::new(1, BuildSeaHasher);
+
+        executor.submit("foo", || panic!("job failed"));
+        let log = Arc::clone(&ran);
+        executor.submit("foo", move || log.lock().unwrap().push("foo"));
+        executor.add_worker();
+        let log = Arc::clone(&ran);
+        executor.submit("bar", move || log.lock().unwrap().push("bar"));
+        executor.join();
+
+        let mut ran = ran.lock().unwrap().clone();
+        ran.sort();
+        assert_eq!(ran, ["bar", "foo"]);
+    }
+
+    #[test]
+    fn submit_returns_the_shard() {
+        let executor = ShardedExecutor::new(5, BuildSeaHasher);
+        assert_eq!(executor.submit("foo", || {}), shard_for("foo", 5));
+    }
+}
@@ -0,0 +1,103 @@
+//! Stable A/B test cohorts.
+//!
+//! [Experiment] splits users evenly between its variants with Faro Sharding. Adding a variant only pulls users into it, so no user ever switches between existing arms and their results stay comparable.
+
+use crate::shard_with_hasher;
+use std::hash::*;
+
+/// Assigns users to one of an experiment's variants.
+///
+/// Users are sharded together with the experiment name, so different experiments split users independently.
+#[derive(Clone, Debug)]
+pub struct Experiment<S> {
+    name: String,
+    variants: Vec<String>,
+    hasher: S,
+}
+
+impl<S: BuildHasher> Experiment<S> {
+    /// An experiment called `name` with `variants`, the first of which is usually the control.
+    ///
+    /// # Panics
+    ///
+    /// If variants is empty.
+    pub fn new(
+        name: impl Into<String>,
+        variants: impl IntoIterator<Item = impl Into<String>>,
+        hasher: S,
+    ) -> Self {
+        let variants = variants.into_iter().map(Into::into).collect::<Vec<_>>();
+        assert!(!variants.is_empty(), "variants must not be empty");
+
+        Experiment {
+            name: name.into(),
+            variants,
+            hasher,
+        }
+    }
+
+    /// The experiment name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The variants, in the order they were added.
+    pub fn variants(&self) -> &[String] {
+        &self.variants
+    }
+
+    /// Adds a new last variant, returning its index.
+    ///
+    /// About `1 / (n + 1)` of users move into it, from every existing variant equally. No other user changes variant.
+    pub fn add_variant(&mut self, variant: impl Into<String>) -> usize {
+        self.variants.push(variant.into());
+        self.variants.len() - 1
+    }
+
+    /// The index of the variant `user` is in.
+    pub fn variant_index(&self, user: impl Hash) -> usize {
+        shard_with_hasher((&self.name, user), self.variants.len() as u64, &self.hasher) as usize
+    }
+
+    /// The variant `user` is in.
+    pub fn variant(&self, user: impl Hash) -> &str {
+        &self.variants[self.variant_index(user)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BuildSeaHasher;
+
+    #[test]
+    fn adding_a_variant_only_pulls_users_into_it() {
+        let mut experiment = Experiment::new("checkout", ["control", "treatment"], BuildSeaHasher);
+        let before = (0..10_000u64)
+            .map(|user| experiment.variant(user).to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(experiment.add_variant("treatment-b"), 2);
+        let mut moved = 0;
+        for user in 0..10_000u64 {
+            let after = experiment.variant(user);
+            if after != before[user as usize] {
+                assert_eq!(after, "treatment-b");
+                moved += 1;
+            }
+        }
+        // About a third.
+        assert!((3_000..3_700).contains(&moved), "{}", moved);
+    }
+
+    #[test]
+    fn experiments_split_independently() {
+        let a = Experiment::new("a", ["control", "treatment"], BuildSeaHasher);
+        let b = Experiment::new("b", ["control", "treatment"], BuildSeaHasher);
+        let same = (0..10_000u64)
+            .filter(|&user| a.variant_index(user) == b.variant_index(user))
+            .count();
+        // About half by chance.
+        assert!((4_700..5_300).contains(&same), "{}", same);
+    }
+}
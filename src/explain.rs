@@ -0,0 +1,149 @@
+use std::fmt;
+use std::hash::*;
+
+/// Returns every step of the hash chain that assigns `key` to a shard, to see why it landed where it did.
+///
+/// ```
+/// # use faro_sharding::*;
+/// # use std::hash::BuildHasherDefault;
+/// # use std::collections::hash_map::DefaultHasher;
+/// let hasher = BuildHasherDefault::<DefaultHasher>::default();
+/// let explanation = explain_shard("foo", 50, &hasher);
+/// assert_eq!(explanation.shard, shard_with_hasher("foo", 50, &hasher));
+///
+/// // The destination counts at which "foo" moved, and where to.
+/// for step in explanation.changes() {
+///     println!("{} destinations: moved to {}", step.destinations, step.shard);
+/// }
+/// ```
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub fn explain_shard(
+    key: impl Hash,
+    total_destinations: u64,
+    hasher: &impl BuildHasher,
+) -> ShardExplanation {
+    assert_ne!(total_destinations, 0, "total_destinations must be > 0");
+
+    let key_hash = hasher.hash_one(key);
+    let mut last_hash = key_hash;
+    let mut shard = 0;
+    let steps = (1..total_destinations)
+        .map(|n| {
+            let hash = hasher.hash_one(last_hash);
+            let remainder = hash % (n + 1);
+            if remainder == 0 {
+                shard = n;
+            }
+            last_hash = hash;
+            ExplainStep {
+                destinations: n + 1,
+                hash,
+                remainder,
+                moved: remainder == 0,
+                shard,
+            }
+        })
+        .collect();
+
+    ShardExplanation {
+        key_hash,
+        total_destinations,
+        steps,
+        shard,
+    }
+}
+
+/// Every step [explain_shard] took.
+///
+/// The [Display](fmt::Display) form is a tab-separated table with one row per step, marking the steps where the key moved.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShardExplanation {
+    /// The hash of the key, which the chain starts from. At 1 destination the key is on shard 0.
+    pub key_hash: u64,
+    /// The destination count explained.
+    pub total_destinations: u64,
+    /// One step per destination count from 2 to `total_destinations`.
+    pub steps: Vec<ExplainStep>,
+    /// The final shard.
+    pub shard: u64,
+}
+
+impl ShardExplanation {
+    /// The steps where the key moved to the new shard.
+    pub fn changes(&self) -> impl Iterator<Item = &ExplainStep> {
+        self.steps.iter().filter(|step| step.moved)
+    }
+}
+
+/// Growing to [ExplainStep::destinations] destinations in [explain_shard].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ExplainStep {
+    /// The destination count after this step.
+    pub destinations: u64,
+    /// The hash of the previous step's hash, or of the key hash for the first step.
+    pub hash: u64,
+    /// `hash % destinations`. The key moves to the new shard when this is 0.
+    pub remainder: u64,
+    /// Whether the key moved to shard `destinations - 1`.
+    pub moved: bool,
+    /// The shard after this step.
+    pub shard: u64,
+}
+
+impl fmt::Display for ShardExplanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "key hash\t{:#018x}", self.key_hash)?;
+        writeln!(f, "destinations\thash\thash % destinations\tshard")?;
+        writeln!(f, "1\t\t\t0")?;
+        for step in &self.steps {
+            write!(
+                f,
+                "{}\t{:#018x}\t{}\t{}",
+                step.destinations, step.hash, step.remainder, step.shard
+            )?;
+            if step.moved {
+                write!(f, "\tmoved")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{shard_breakpoints, shard_for, BuildSeaHasher};
+
+    #[test]
+    fn agrees_with_shard_for_at_every_step() {
+        let explanation = explain_shard("foo", 73, &BuildSeaHasher);
+        assert_eq!(explanation.shard, shard_for("foo", 73));
+        assert_eq!(explanation.steps.len(), 72);
+        for step in &explanation.steps {
+            assert_eq!(step.shard, shard_for("foo", step.destinations));
+            assert_eq!(step.moved, step.shard == step.destinations - 1);
+        }
+
+        let changes = explanation
+            .changes()
+            .map(|step| step.shard)
+            .collect::<Vec<_>>();
+        let breakpoints = shard_breakpoints("foo", 73, &BuildSeaHasher)
+            .skip(1)
+            .map(|(_, shard)| shard)
+            .collect::<Vec<_>>();
+        assert_eq!(changes, breakpoints);
+    }
+
+    #[test]
+    fn single_destination_has_no_steps() {
+        let explanation = explain_shard("foo", 1, &BuildSeaHasher);
+        assert!(explanation.steps.is_empty());
+        assert_eq!(explanation.shard, 0);
+        assert_eq!(explanation.to_string().lines().count(), 3);
+    }
+}
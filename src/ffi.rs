@@ -0,0 +1,190 @@
+//! C ABI for sharding with the default hasher of [crate::shard_for], so non-Rust services get the same assignments.
+//!
+//! Every function returns a [FaroStatus] code and writes its result through an out pointer. Nothing panics across the boundary. The header is `include/faro_sharding.h`, generated with `cbindgen --config cbindgen.toml --output include/faro_sharding.h`.
+
+use core::ffi::c_char;
+
+/// Result codes returned by every `faro_*` function. The values are stable.
+#[repr(i32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FaroStatus {
+    /// Success. The out pointer was written.
+    Ok = 0,
+    /// A required pointer was null.
+    NullPointer = 1,
+    /// total_destinations was 0.
+    ZeroDestinations = 2,
+    /// A string key was not valid UTF-8.
+    InvalidUtf8 = 3,
+}
+
+/// Writes [crate::shard_for_bytes] of the `len` bytes at `key` to `out_shard`.
+///
+/// `key` may be null if `len` is 0.
+///
+/// # Safety
+///
+/// `key` must point to `len` readable bytes, and `out_shard` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn faro_shard_for_bytes(
+    key: *const u8,
+    len: usize,
+    total_destinations: u64,
+    out_shard: *mut u64,
+) -> FaroStatus {
+    let key = match bytes(key, len) {
+        Some(key) => key,
+        None => return FaroStatus::NullPointer,
+    };
+    write(out_shard, total_destinations, |total| {
+        crate::shard_for_bytes(key, total)
+    })
+}
+
+/// Writes [crate::shard_for] of the UTF-8 string of `len` bytes at `key` to `out_shard`. Agrees with `shard_for(key: &str, ..)` in Rust.
+///
+/// `key` may be null if `len` is 0. It does not need to be nul terminated.
+///
+/// # Safety
+///
+/// `key` must point to `len` readable bytes, and `out_shard` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn faro_shard_for_str(
+    key: *const c_char,
+    len: usize,
+    total_destinations: u64,
+    out_shard: *mut u64,
+) -> FaroStatus {
+    let key = match bytes(key.cast(), len) {
+        Some(key) => key,
+        None => return FaroStatus::NullPointer,
+    };
+    let key = match core::str::from_utf8(key) {
+        Ok(key) => key,
+        Err(_) => return FaroStatus::InvalidUtf8,
+    };
+    write(out_shard, total_destinations, |total| {
+        crate::shard_for(key, total)
+    })
+}
+
+/// Writes [crate::shard_for_key_hash] of `key_hash` to `out_shard`.
+///
+/// # Safety
+///
+/// `out_shard` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn faro_shard_for_key_hash(
+    key_hash: u64,
+    total_destinations: u64,
+    out_shard: *mut u64,
+) -> FaroStatus {
+    write(out_shard, total_destinations, |total| {
+        crate::shard_for_key_hash(key_hash, total, &crate::seahash::StableSeaHasher::new())
+    })
+}
+
+/// A static, nul terminated description of `status`, a [FaroStatus] value.
+///
+/// Takes a plain integer, since C can pass any value. Values that are not a [FaroStatus] get "unknown status".
+#[no_mangle]
+pub extern "C" fn faro_status_message(status: i32) -> *const c_char {
+    let message: &'static [u8] = match status {
+        s if s == FaroStatus::Ok as i32 => b"ok\0",
+        s if s == FaroStatus::NullPointer as i32 => b"null pointer\0",
+        s if s == FaroStatus::ZeroDestinations as i32 => b"total_destinations must be > 0\0",
+        s if s == FaroStatus::InvalidUtf8 as i32 => b"key is not valid UTF-8\0",
+        _ => b"unknown status\0",
+    };
+    message.as_ptr().cast()
+}
+
+unsafe fn bytes<'a>(ptr: *const u8, len: usize) -> Option<&'a [u8]> {
+    if len == 0 {
+        return Some(&[]);
+    }
+    if ptr.is_null() {
+        return None;
+    }
+    Some(core::slice::from_raw_parts(ptr, len))
+}
+
+unsafe fn write(
+    out_shard: *mut u64,
+    total_destinations: u64,
+    shard: impl FnOnce(u64) -> u64,
+) -> FaroStatus {
+    if out_shard.is_null() {
+        return FaroStatus::NullPointer;
+    }
+    if total_destinations == 0 {
+        return FaroStatus::ZeroDestinations;
+    }
+    out_shard.write(shard(total_destinations));
+    FaroStatus::Ok
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::ptr;
+
+    #[test]
+    fn agrees_with_the_crate() {
+        let mut shard = u64::MAX;
+        unsafe {
+            assert_eq!(
+                faro_shard_for_bytes(b"foo".as_ptr(), 3, 73, &mut shard),
+                FaroStatus::Ok
+            );
+            assert_eq!(shard, crate::shard_for_bytes(b"foo", 73));
+
+            assert_eq!(
+                faro_shard_for_str(b"foo".as_ptr().cast(), 3, 50, &mut shard),
+                FaroStatus::Ok
+            );
+            assert_eq!(shard, 49);
+
+            assert_eq!(
+                faro_shard_for_bytes(ptr::null(), 0, 73, &mut shard),
+                FaroStatus::Ok
+            );
+            assert_eq!(shard, crate::shard_for_bytes(b"", 73));
+        }
+    }
+
+    #[test]
+    fn errors_instead_of_panicking() {
+        let mut shard = u64::MAX;
+        unsafe {
+            assert_eq!(
+                faro_shard_for_bytes(b"foo".as_ptr(), 3, 0, &mut shard),
+                FaroStatus::ZeroDestinations
+            );
+            assert_eq!(
+                faro_shard_for_bytes(ptr::null(), 3, 73, &mut shard),
+                FaroStatus::NullPointer
+            );
+            assert_eq!(
+                faro_shard_for_key_hash(1, 73, ptr::null_mut()),
+                FaroStatus::NullPointer
+            );
+            assert_eq!(
+                faro_shard_for_str([0xffu8].as_ptr().cast(), 1, 73, &mut shard),
+                FaroStatus::InvalidUtf8
+            );
+        }
+        assert_eq!(shard, u64::MAX);
+    }
+
+    #[test]
+    fn describes_any_status() {
+        let message = |status| unsafe { core::ffi::CStr::from_ptr(faro_status_message(status)) };
+        assert_eq!(
+            message(FaroStatus::InvalidUtf8 as i32),
+            c"key is not valid UTF-8"
+        );
+        assert_eq!(message(-1), c"unknown status");
+        assert_eq!(message(4), c"unknown status");
+    }
+}
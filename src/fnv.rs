@@ -0,0 +1,159 @@
+//! Faro Sharding with 64-bit FNV-1a, a tiny hasher for constrained targets.
+//!
+//! FNV is much smaller and simpler than SeaHash but mixes less thoroughly, so check the distribution for your keys with [crate::stats] where std is available.
+
+use crate::{shard_bytes_with_hasher, shard_with_hasher};
+use core::hash::*;
+
+/// [BuildHasher] for 64-bit FNV-1a with the standard offset basis.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BuildFnvHasher;
+
+impl BuildHasher for BuildFnvHasher {
+    type Hasher = FnvHasher;
+
+    fn build_hasher(&self) -> Self::Hasher {
+        FnvHasher::default()
+    }
+}
+
+impl crate::StableBuildHasher for BuildFnvHasher {}
+
+/// [Hasher] built by [BuildFnvHasher].
+///
+/// Integers are written as little-endian bytes, so hashes agree across platforms.
+#[derive(Default)]
+pub struct FnvHasher(::fnv::FnvHasher);
+
+impl core::fmt::Debug for FnvHasher {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("FnvHasher").finish_non_exhaustive()
+    }
+}
+
+impl Hasher for FnvHasher {
+    fn finish(&self) -> u64 {
+        self.0.finish()
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.write(bytes);
+    }
+
+    fn write_u8(&mut self, n: u8) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_u16(&mut self, n: u16) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_u128(&mut self, n: u128) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_usize(&mut self, n: usize) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_i8(&mut self, n: i8) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_i16(&mut self, n: i16) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_i32(&mut self, n: i32) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_i64(&mut self, n: i64) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_i128(&mut self, n: i128) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_isize(&mut self, n: isize) {
+        self.write(&n.to_le_bytes())
+    }
+}
+
+/// [shard_with_hasher] using [BuildFnvHasher].
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub fn shard_for(key: impl Hash, total_destinations: u64) -> u64 {
+    shard_with_hasher(key, total_destinations, &BuildFnvHasher)
+}
+
+/// [shard_bytes_with_hasher] using [BuildFnvHasher].
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub fn shard_for_bytes(bytes: &[u8], total_destinations: u64) -> u64 {
+    shard_bytes_with_hasher(bytes, total_destinations, &BuildFnvHasher)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pinned_values() {
+        // Do not change these values. See `pinning_default_shard`.
+        assert_eq!(shard_for_bytes(b"foo", 73), 0);
+        assert_eq!(shard_for_bytes(b"", 73), 48);
+        assert_eq!(shard_for("foo", 73), 38);
+    }
+
+    #[test]
+    fn writes_integers_little_endian() {
+        let hash = |write: fn(&mut FnvHasher)| {
+            let mut hasher = BuildFnvHasher.build_hasher();
+            write(&mut hasher);
+            hasher.finish()
+        };
+        let bytes = |bytes: &[u8]| {
+            let mut hasher = ::fnv::FnvHasher::default();
+            hasher.write(bytes);
+            hasher.finish()
+        };
+        let le = bytes(&0x0102_0304_0506_0708u64.to_le_bytes());
+        assert_eq!(hash(|h| h.write_u64(0x0102_0304_0506_0708)), le);
+        assert_eq!(hash(|h| h.write_i64(0x0102_0304_0506_0708)), le);
+        assert_eq!(hash(|h| h.write_u16(0x0102)), bytes(&[2, 1]));
+        assert_eq!(hash(|h| h.write_u128(1)), bytes(&1u128.to_le_bytes()));
+    }
+
+    #[test]
+    fn default_hasher_entry_point_agrees() {
+        for key in 0..1_000u64 {
+            assert_eq!(
+                crate::shard_with_default_hasher::<FnvHasher>(key, 73),
+                shard_for(key, 73)
+            );
+        }
+    }
+
+    #[test]
+    fn distributes_evenly() {
+        let mut counts = [0u64; 16];
+        for key in 0..32_000u64 {
+            counts[shard_for(key, 16) as usize] += 1;
+        }
+        assert!(crate::stats::imbalance_ratio(&counts) < 1.1, "{:?}", counts);
+    }
+}
@@ -0,0 +1,122 @@
+/// Zero-sized marker for the on-disk format of persisted types.
+///
+/// Serialized as the integer `1`. Deserializing any other value fails, so a future format change can be detected rather than misread.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct FormatVersion;
+
+#[cfg(feature = "serde")]
+const CURRENT: u32 = 1;
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for FormatVersion {
+    fn serialize<Ser: serde::Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        serializer.serialize_u32(CURRENT)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for FormatVersion {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let version = <u32 as serde::Deserialize>::deserialize(deserializer)?;
+        if version != CURRENT {
+            return Err(serde::de::Error::custom(format_args!(
+                "unsupported format version {}, expected {}",
+                version, CURRENT
+            )));
+        }
+        Ok(FormatVersion)
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use crate::{BuildSeaHasher, ShardRing, TombstoneTopology, WeightedSharder};
+
+    #[test]
+    fn round_trips_with_version() {
+        let mut topology = TombstoneTopology::new(5, BuildSeaHasher);
+        topology.remove(2);
+
+        let json = serde_json::to_string(&topology).unwrap();
+        assert_eq!(
+            json,
+            r#"{"version":1,"states":["Live","Live","Removed","Live","Live"]}"#
+        );
+
+        let restored: TombstoneTopology<BuildSeaHasher> = serde_json::from_str(&json).unwrap();
+        for key in 0..100u64 {
+            assert_eq!(restored.shard_for(key), topology.shard_for(key));
+        }
+    }
+
+    #[test]
+    fn recomputes_tombstone_counts() {
+        let restored: TombstoneTopology<BuildSeaHasher> = serde_json::from_str(
+            r#"{"version":1,"states":["Removed","Draining","Live"],"live":7,"draining":9}"#,
+        )
+        .unwrap();
+        assert_eq!(restored.live_destinations(), 2);
+        assert_eq!(restored.assignment(0u64).write, 2);
+    }
+
+    #[test]
+    fn persists_weighted_slots_as_runs() {
+        let mut sharder = WeightedSharder::new(&[3, 2], BuildSeaHasher);
+        sharder.grow(0, 1);
+        sharder.set_weight(1, 1);
+
+        let json = serde_json::to_string(&sharder).unwrap();
+        assert_eq!(
+            json,
+            r#"{"version":1,"weights":[4,1],"slots":[[0,3],[1,2],[0,1]],"tombstones":[4]}"#
+        );
+
+        let restored: WeightedSharder<BuildSeaHasher> = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.weights(), sharder.weights());
+        for key in 0..100u64 {
+            assert_eq!(restored.shard_for(key), sharder.shard_for(key));
+        }
+    }
+
+    #[test]
+    fn rejects_inconsistent_weighted_sharders() {
+        for (json, error) in [
+            (
+                r#"{"version":1,"weights":[1],"slots":[[1,1]]}"#,
+                "slot owner 1 out of range",
+            ),
+            (
+                r#"{"version":1,"weights":[2],"slots":[[0,1]]}"#,
+                "weights do not match the live slots",
+            ),
+            (
+                r#"{"version":1,"weights":[0],"slots":[[0,1]],"tombstones":[0]}"#,
+                "at least one weight must be > 0",
+            ),
+            (
+                r#"{"version":1,"weights":[1],"slots":[[0,1]],"tombstones":[1]}"#,
+                "tombstone 1 out of range",
+            ),
+            (
+                r#"{"version":1,"weights":[1],"slots":[[0,1],[0,18446744073709551615]]}"#,
+                "slot count must be <=",
+            ),
+        ] {
+            let err = serde_json::from_str::<WeightedSharder<BuildSeaHasher>>(json).unwrap_err();
+            assert!(err.to_string().contains(error), "{}: {}", json, err);
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_version() {
+        let err = serde_json::from_str::<ShardRing<String, BuildSeaHasher>>(
+            r#"{"version":2,"destinations":["a"]}"#,
+        )
+        .unwrap_err();
+        assert!(
+            err.to_string().contains("unsupported format version 2"),
+            "{}",
+            err
+        );
+    }
+}
@@ -0,0 +1,268 @@
+//! The default hash function, vendored so that no dependency update can change it.
+//!
+//! [FrozenSeaHasher] is a copy of SeaHash 4.1's streaming hasher, and [BuildFrozenSeaHasher::new] hashes exactly like [crate::seahash::StableSeaHasher::new]. This module has no dependencies and will never be updated, so [shard_for] returns the same shards as [crate::shard_for] for as long as this crate exists, whatever happens to the `seahash` crate.
+
+use crate::{shard_bytes_with_hasher, shard_with_hasher, try_shard_with_hasher, ShardError};
+use core::hash::*;
+
+const DEFAULT_SEEDS: [u64; 4] = [
+    0x16f11fe89b0d677c,
+    0xb480a793d8e6c86c,
+    0x6fe2e5aaf078ebc9,
+    0x14f994a4c5259381,
+];
+
+/// [shard_with_hasher] using [BuildFrozenSeaHasher::new]. Returns the same shards as [crate::shard_for].
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub fn shard_for(key: impl Hash, total_destinations: u64) -> u64 {
+    shard_with_hasher(key, total_destinations, &BuildFrozenSeaHasher::new())
+}
+
+/// [shard_for] that returns an error instead of panicking.
+pub fn try_shard_for(key: impl Hash, total_destinations: u64) -> Result<u64, ShardError> {
+    try_shard_with_hasher(key, total_destinations, &BuildFrozenSeaHasher::new())
+}
+
+/// [shard_bytes_with_hasher] using [BuildFrozenSeaHasher::new]. Returns the same shards as [crate::shard_for_bytes].
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub fn shard_for_bytes(bytes: &[u8], total_destinations: u64) -> u64 {
+    shard_bytes_with_hasher(bytes, total_destinations, &BuildFrozenSeaHasher::new())
+}
+
+/// [BuildHasher] for [FrozenSeaHasher] with fixed seeds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BuildFrozenSeaHasher {
+    seeds: [u64; 4],
+}
+
+impl BuildFrozenSeaHasher {
+    /// The hasher used by [shard_for], with SeaHash's default seeds.
+    pub const fn new() -> Self {
+        Self::with_seeds(DEFAULT_SEEDS)
+    }
+
+    /// A hasher with custom seeds. Hashes like [crate::seahash::StableSeaHasher::with_seeds] with the same seeds.
+    pub const fn with_seeds(seeds: [u64; 4]) -> Self {
+        BuildFrozenSeaHasher { seeds }
+    }
+}
+
+impl Default for BuildFrozenSeaHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BuildHasher for BuildFrozenSeaHasher {
+    type Hasher = FrozenSeaHasher;
+
+    fn build_hasher(&self) -> FrozenSeaHasher {
+        FrozenSeaHasher::with_seeds(self.seeds)
+    }
+}
+
+impl crate::StableBuildHasher for BuildFrozenSeaHasher {}
+
+/// SeaHash's streaming hasher.
+///
+/// The input is read as little-endian 64-bit words, each mixed into one of four lanes in turn. Splitting the input across several [Hasher::write] calls does not change the hash.
+#[derive(Clone, Copy, Debug)]
+pub struct FrozenSeaHasher {
+    state: [u64; 4],
+    written: u64,
+    tail: u64,
+    tail_len: usize,
+}
+
+impl FrozenSeaHasher {
+    /// A hasher with the given lane seeds.
+    pub const fn with_seeds(seeds: [u64; 4]) -> Self {
+        FrozenSeaHasher {
+            state: seeds,
+            written: 0,
+            tail: 0,
+            tail_len: 0,
+        }
+    }
+
+    fn push(&mut self, word: u64) {
+        let [a, b, c, d] = self.state;
+        self.state = [b, c, d, diffuse(a ^ word)];
+        self.written += 8;
+    }
+}
+
+impl Default for FrozenSeaHasher {
+    fn default() -> Self {
+        Self::with_seeds(DEFAULT_SEEDS)
+    }
+}
+
+impl Hasher for FrozenSeaHasher {
+    fn finish(&self) -> u64 {
+        let first = if self.tail_len > 0 {
+            diffuse(self.state[0] ^ self.tail)
+        } else {
+            self.state[0]
+        };
+        diffuse(
+            first
+                ^ self.state[1]
+                ^ self.state[2]
+                ^ self.state[3]
+                ^ (self.written + self.tail_len as u64),
+        )
+    }
+
+    fn write(&mut self, mut bytes: &[u8]) {
+        if self.tail_len > 0 {
+            let taken = bytes.len().min(8 - self.tail_len);
+            self.tail |= read_le(&bytes[..taken]) << (8 * self.tail_len);
+            self.tail_len += taken;
+            bytes = &bytes[taken..];
+            if self.tail_len < 8 {
+                return;
+            }
+            self.push(self.tail);
+            self.tail = 0;
+            self.tail_len = 0;
+        }
+
+        let mut words = bytes.chunks_exact(8);
+        for word in &mut words {
+            self.push(read_le(word));
+        }
+        self.tail = read_le(words.remainder());
+        self.tail_len = words.remainder().len();
+    }
+
+    // SeaHash writes integers as little-endian bytes on every platform, except 128-bit integers which use the default native-endian bytes.
+
+    fn write_u8(&mut self, n: u8) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_u16(&mut self, n: u16) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_usize(&mut self, n: usize) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_i8(&mut self, n: i8) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_i16(&mut self, n: i16) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_i32(&mut self, n: i32) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_i64(&mut self, n: i64) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_isize(&mut self, n: isize) {
+        self.write(&n.to_le_bytes())
+    }
+}
+
+/// Up to 8 bytes as a little-endian integer, zero-padded.
+fn read_le(bytes: &[u8]) -> u64 {
+    let mut word = [0; 8];
+    word[..bytes.len()].copy_from_slice(bytes);
+    u64::from_le_bytes(word)
+}
+
+const fn diffuse(mut x: u64) -> u64 {
+    x = x.wrapping_mul(0x6eed0e9da4d94a4f);
+    let a = x >> 32;
+    let b = x >> 60;
+    x ^= a >> b;
+    x.wrapping_mul(0x6eed0e9da4d94a4f)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pinned_values() {
+        // Do not change these values. See `pinning_default_shard`.
+        assert_eq!(diffuse(0), 0);
+        assert_eq!(diffuse(1), 15197155197312260123);
+        assert_eq!(diffuse(0xDEADBEEF), 12110756357096144265);
+
+        let hasher = BuildFrozenSeaHasher::new();
+        let hash = |bytes: &[u8]| {
+            let mut state = hasher.build_hasher();
+            state.write(bytes);
+            state.finish()
+        };
+        assert_eq!(hash(b""), 14492805990617963705);
+        assert_eq!(hash(b"foo"), 4413582353838009230);
+        assert_eq!(hash(&[0xff; 41]), 12178334421421720233);
+
+        assert_eq!(shard_for("foo", 73), 49);
+        assert_eq!(shard_for("bar", 73), 14);
+        assert_eq!(shard_for_bytes(b"foo", 73), 69);
+        let seeded = BuildFrozenSeaHasher::with_seeds([1, 2, 3, 4]);
+        assert_eq!(shard_with_hasher("foo", 73, &seeded), 58);
+    }
+
+    #[test]
+    fn chunking_does_not_matter() {
+        let bytes: [u8; 100] = core::array::from_fn(|i| i as u8);
+        let hasher = BuildFrozenSeaHasher::new();
+        let mut whole = hasher.build_hasher();
+        whole.write(&bytes);
+
+        for split in [1, 3, 7, 8, 9, 31, 64] {
+            let mut chunked = hasher.build_hasher();
+            for chunk in bytes.chunks(split) {
+                chunked.write(chunk);
+            }
+            assert_eq!(chunked.finish(), whole.finish(), "chunks of {}", split);
+        }
+    }
+
+    #[cfg(feature = "seahash")]
+    #[test]
+    fn matches_seahash() {
+        let frozen = BuildFrozenSeaHasher::new();
+        let stable = crate::seahash::StableSeaHasher::new();
+        for len in 0..200u8 {
+            let bytes = (0..len).map(|b| b.wrapping_mul(37)).collect::<Vec<_>>();
+            assert_eq!(frozen.hash_one(&bytes), stable.hash_one(&bytes));
+            assert_eq!(
+                shard_for_bytes(&bytes, 1_000),
+                crate::shard_for_bytes(&bytes, 1_000)
+            );
+        }
+        for key in 0..1_000u64 {
+            assert_eq!(shard_for(key, 73), crate::shard_for(key, 73));
+            assert_eq!(
+                shard_for((key, "x", key as u8), 73),
+                crate::shard_for((key, "x", key as u8), 73)
+            );
+        }
+    }
+}
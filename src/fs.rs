@@ -0,0 +1,227 @@
+//! Sharding files across a directory tree.
+//!
+//! [DirectoryLayout] puts each file in `root/shard-NNNN/`, by its file name. Growing the directory count only relocates files into the new directories, and [DirectoryLayout::plan_growth] lists exactly which.
+
+use crate::shard_bytes_with_hasher;
+use std::ffi::{OsStr, OsString};
+use std::hash::*;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+/// Maps file names to shard directories under a root.
+///
+/// File names are sharded as bytes with [shard_bytes_with_hasher], so the same name lands in the same directory on every platform and in any language.
+#[derive(Clone, Debug)]
+pub struct DirectoryLayout<S> {
+    root: PathBuf,
+    directory_count: u64,
+    hasher: S,
+}
+
+impl<S: BuildHasher> DirectoryLayout<S> {
+    /// A layout of `directory_count` directories under `root`.
+    ///
+    /// # Panics
+    ///
+    /// If directory_count == 0.
+    pub fn new(root: impl Into<PathBuf>, directory_count: u64, hasher: S) -> Self {
+        assert_ne!(directory_count, 0, "directory_count must be > 0");
+
+        DirectoryLayout {
+            root: root.into(),
+            directory_count,
+            hasher,
+        }
+    }
+
+    /// The directory the shard directories are in.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Number of shard directories.
+    pub fn directory_count(&self) -> u64 {
+        self.directory_count
+    }
+
+    /// The shard `key` is in.
+    pub fn shard_of(&self, key: impl AsRef<OsStr>) -> u64 {
+        shard_bytes_with_hasher(
+            key.as_ref().as_encoded_bytes(),
+            self.directory_count,
+            &self.hasher,
+        )
+    }
+
+    /// The directory for `shard`, like `root/shard-0007`.
+    pub fn directory(&self, shard: u64) -> PathBuf {
+        self.root.join(format!("shard-{:04}", shard))
+    }
+
+    /// Where the file named `key` lives, like `root/shard-0007/key`.
+    ///
+    /// Fails with [io::ErrorKind::InvalidInput] unless `key` is a plain file name. A key with a separator, `.` or `..`, or a root would land outside its shard directory, where [DirectoryLayout::plan_growth] cannot find it.
+    pub fn path_for(&self, key: impl AsRef<OsStr>) -> io::Result<PathBuf> {
+        let key = key.as_ref();
+        let mut components = Path::new(key).components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(name)), None) if name == key => {
+                Ok(self.directory(self.shard_of(key)).join(key))
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{:?} is not a plain file name", key),
+            )),
+        }
+    }
+
+    /// Creates every shard directory that does not exist yet.
+    pub fn create_directories(&self) -> io::Result<()> {
+        for shard in 0..self.directory_count {
+            std::fs::create_dir_all(self.directory(shard))?;
+        }
+        Ok(())
+    }
+
+    /// Lists the files in the current directories that must relocate when growing to `directory_count`.
+    ///
+    /// Every move is into a new directory. Missing shard directories are treated as empty.
+    ///
+    /// # Panics
+    ///
+    /// If directory_count is less than the current directory count.
+    pub fn plan_growth(&self, directory_count: u64) -> io::Result<Vec<FileMove>> {
+        assert!(
+            directory_count >= self.directory_count,
+            "cannot shrink from {} to {} directories",
+            self.directory_count,
+            directory_count
+        );
+
+        let mut moves = Vec::new();
+        for shard in 0..self.directory_count {
+            let entries = match std::fs::read_dir(self.directory(shard)) {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+
+            for entry in entries {
+                let key = entry?.file_name();
+                let to_shard =
+                    shard_bytes_with_hasher(key.as_encoded_bytes(), directory_count, &self.hasher);
+                if to_shard == shard {
+                    continue;
+                }
+                moves.push(FileMove {
+                    from: self.directory(shard).join(&key),
+                    to: self.directory(to_shard).join(&key),
+                    key,
+                });
+            }
+        }
+        moves.sort_by(|a, b| a.from.cmp(&b.from));
+        Ok(moves)
+    }
+
+    /// Switches to `directory_count` directories and relocates the files that move, returning them.
+    ///
+    /// If relocating fails partway, the layout is unchanged and calling this again finishes the job.
+    ///
+    /// # Panics
+    ///
+    /// If directory_count is less than the current directory count.
+    pub fn grow(&mut self, directory_count: u64) -> io::Result<Vec<FileMove>> {
+        let moves = self.plan_growth(directory_count)?;
+        for file_move in &moves {
+            file_move.apply()?;
+        }
+        self.directory_count = directory_count;
+        Ok(moves)
+    }
+}
+
+/// A file that must relocate. See [DirectoryLayout::plan_growth].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileMove {
+    /// The file name.
+    pub key: OsString,
+    /// Where the file is.
+    pub from: PathBuf,
+    /// Where the file belongs after growing.
+    pub to: PathBuf,
+}
+
+impl FileMove {
+    /// Renames the file into place, creating its new directory if needed.
+    pub fn apply(&self) -> io::Result<()> {
+        if let Some(directory) = self.to.parent() {
+            std::fs::create_dir_all(directory)?;
+        }
+        std::fs::rename(&self.from, &self.to)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{shard_for_bytes, BuildSeaHasher};
+
+    fn scratch(name: &str) -> PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("faro_sharding-fs-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        root
+    }
+
+    #[test]
+    fn path_for_uses_padded_shard_directories() {
+        let layout = DirectoryLayout::new("/data", 50, BuildSeaHasher);
+        let shard = shard_for_bytes(b"foo.txt", 50);
+        assert_eq!(
+            layout.path_for("foo.txt").unwrap(),
+            PathBuf::from(format!("/data/shard-{:04}/foo.txt", shard))
+        );
+        assert_eq!(layout.directory(7), PathBuf::from("/data/shard-0007"));
+        assert_eq!(layout.directory(12345), PathBuf::from("/data/shard-12345"));
+    }
+
+    #[test]
+    fn path_for_rejects_anything_but_file_names() {
+        let layout = DirectoryLayout::new("/data", 50, BuildSeaHasher);
+        for key in ["", ".", "..", "a/b", "../etc", "/etc", "a/", "./a"] {
+            let err = layout.path_for(key).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "{:?}", key);
+        }
+        assert!(layout.path_for("a.b..c").is_ok());
+    }
+
+    #[test]
+    fn growth_only_moves_into_new_directories() {
+        let root = scratch("grow");
+        let mut layout = DirectoryLayout::new(&root, 4, BuildSeaHasher);
+        layout.create_directories().unwrap();
+        let keys = (0..200).map(|i| format!("file-{}", i)).collect::<Vec<_>>();
+        for key in &keys {
+            std::fs::write(layout.path_for(key).unwrap(), key).unwrap();
+        }
+
+        let planned = layout.plan_growth(7).unwrap();
+        let moved = layout.grow(7).unwrap();
+        assert_eq!(planned, moved);
+        assert!(!moved.is_empty());
+        for file_move in &moved {
+            assert!((4..7).any(|shard| file_move.to.starts_with(layout.directory(shard))));
+        }
+
+        for key in &keys {
+            assert_eq!(
+                std::fs::read_to_string(layout.path_for(key).unwrap()).unwrap(),
+                *key
+            );
+        }
+        assert!(layout.plan_growth(7).unwrap().is_empty());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,107 @@
+use crate::shard_for_key_hash;
+use std::collections::VecDeque;
+use std::hash::*;
+
+const RECENT_KEYS: usize = 64;
+
+/// Shards keys while enforcing that the destination count never decreases.
+///
+/// Faro Sharding only guarantees that keys stay put when destinations are added. This guard panics as soon as a caller shrinks the destination count, rather than letting keys silently move between existing destinations.
+///
+/// In debug builds it also remembers the shards of recently seen keys and asserts that each one either stayed on its previous shard or moved to a newly added destination.
+pub struct ShardingFuseGuard<S> {
+    current_destinations: u64,
+    hasher: S,
+    recent: VecDeque<Recent>,
+}
+
+struct Recent {
+    key_hash: u64,
+    destinations: u64,
+    shard: u64,
+}
+
+impl<S: BuildHasher> ShardingFuseGuard<S> {
+    /// Creates a guard starting at `initial_destinations`.
+    pub fn new(initial_destinations: u64, hasher: S) -> Self {
+        ShardingFuseGuard {
+            current_destinations: initial_destinations,
+            hasher,
+            recent: VecDeque::with_capacity(RECENT_KEYS),
+        }
+    }
+
+    /// The largest destination count seen so far.
+    pub fn current_destinations(&self) -> u64 {
+        self.current_destinations
+    }
+
+    /// Returns the shard for `key` among `new_destinations`, recording `new_destinations` as the current count.
+    ///
+    /// # Panics
+    ///
+    /// If `new_destinations` is less than the current destination count, or is 0.
+    pub fn shard_for<K: Hash>(&mut self, key: K, new_destinations: u64) -> u64 {
+        assert!(
+            new_destinations >= self.current_destinations,
+            "destination count decreased from {} to {new_destinations}; Faro Sharding only supports adding destinations",
+            self.current_destinations
+        );
+        self.current_destinations = new_destinations;
+
+        let key_hash = self.hasher.hash_one(key);
+        let shard = shard_for_key_hash(key_hash, new_destinations, &self.hasher);
+
+        if cfg!(debug_assertions) {
+            self.check_stability(key_hash, new_destinations, shard);
+        }
+
+        shard
+    }
+
+    fn check_stability(&mut self, key_hash: u64, destinations: u64, shard: u64) {
+        if let Some(i) = self.recent.iter().position(|r| r.key_hash == key_hash) {
+            let previous = self.recent.remove(i).unwrap();
+            assert!(
+                shard == previous.shard || shard >= previous.destinations,
+                "key hash {key_hash:#x} moved from shard {} to existing shard {shard} when growing from {} to {destinations} destinations",
+                previous.shard,
+                previous.destinations
+            );
+        } else if self.recent.len() == RECENT_KEYS {
+            self.recent.pop_back();
+        }
+
+        self.recent.push_front(Recent {
+            key_hash,
+            destinations,
+            shard,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{shard_with_hasher, BuildSeaHasher};
+
+    #[test]
+    fn matches_shard_with_hasher_while_growing() {
+        let mut guard = ShardingFuseGuard::new(1, BuildSeaHasher);
+        for n in 1..100 {
+            for key in ["foo", "bar", "baz"] {
+                assert_eq!(
+                    guard.shard_for(key, n),
+                    shard_with_hasher(key, n, &BuildSeaHasher)
+                );
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "destination count decreased from 10 to 9")]
+    fn panics_on_shrink() {
+        let mut guard = ShardingFuseGuard::new(10, BuildSeaHasher);
+        guard.shard_for("foo", 9);
+    }
+}
@@ -0,0 +1,111 @@
+use crate::shard_with_hasher;
+use std::collections::HashMap;
+use std::hash::*;
+use std::sync::RwLock;
+
+/// Per-key gauges, smoothed with an exponential moving average and grouped by shard.
+pub struct ShardedGauge<K, S> {
+    shards: Vec<RwLock<HashMap<K, f64>>>,
+    alpha: f64,
+    hasher: S,
+}
+
+impl<K: Hash + Eq, S: BuildHasher> ShardedGauge<K, S> {
+    /// Creates an empty gauge set over `total_shards` shards.
+    ///
+    /// Each recorded value is weighted by `alpha` against the key's previous average. The first value recorded for a key is taken as is.
+    ///
+    /// # Panics
+    ///
+    /// If total_shards == 0 or alpha is not within `0.0..=1.0`.
+    pub fn new(total_shards: u64, alpha: f64, hasher: S) -> Self {
+        assert_ne!(total_shards, 0, "total_shards must be > 0");
+        assert!((0.0..=1.0).contains(&alpha), "alpha must be within 0..=1");
+
+        ShardedGauge {
+            shards: (0..total_shards).map(|_| Default::default()).collect(),
+            alpha,
+            hasher,
+        }
+    }
+
+    /// Folds `value` into the moving average for `key`.
+    pub fn record(&self, key: &K, value: f64)
+    where
+        K: Clone,
+    {
+        let shard = shard_with_hasher(key, self.shards.len() as u64, &self.hasher);
+        let mut gauges = self.shards[shard as usize].write().unwrap();
+        match gauges.get_mut(key) {
+            Some(average) => *average = self.alpha * value + (1.0 - self.alpha) * *average,
+            None => {
+                gauges.insert(key.clone(), value);
+            }
+        }
+    }
+
+    /// Returns the current average of every key across all shards.
+    pub fn snapshot(&self) -> Vec<(K, f64)>
+    where
+        K: Clone,
+    {
+        self.shards
+            .iter()
+            .flat_map(|shard| {
+                let gauges = shard.read().unwrap();
+                gauges
+                    .iter()
+                    .map(|(k, v)| (k.clone(), *v))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// Returns the mean of the averages of every key in `shard`, or `0.0` if it has none.
+    ///
+    /// # Panics
+    ///
+    /// If shard is out of range.
+    pub fn shard_avg(&self, shard: u64) -> f64 {
+        let gauges = self.shards[shard as usize].read().unwrap();
+        if gauges.is_empty() {
+            return 0.0;
+        }
+        gauges.values().sum::<f64>() / gauges.len() as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BuildSeaHasher;
+
+    #[test]
+    fn smooths_and_aggregates() {
+        let gauge = ShardedGauge::new(1, 0.5, BuildSeaHasher);
+        gauge.record(&"a", 10.0);
+        gauge.record(&"a", 20.0);
+        gauge.record(&"b", 3.0);
+
+        let mut snapshot = gauge.snapshot();
+        snapshot.sort_by(|a, b| a.0.cmp(b.0));
+        assert_eq!(snapshot, vec![("a", 15.0), ("b", 3.0)]);
+        assert_eq!(gauge.shard_avg(0), 9.0);
+    }
+
+    #[test]
+    fn keys_land_in_their_shard() {
+        let gauge = ShardedGauge::new(8, 1.0, BuildSeaHasher);
+        for key in 0..100u64 {
+            gauge.record(&key, key as f64);
+        }
+
+        for shard in 0..8 {
+            let keys = (0..100u64)
+                .filter(|k| shard_with_hasher(k, 8, &BuildSeaHasher) == shard)
+                .collect::<Vec<_>>();
+            let expected = keys.iter().sum::<u64>() as f64 / keys.len() as f64;
+            assert_eq!(gauge.shard_avg(shard), expected);
+        }
+    }
+}
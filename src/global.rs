@@ -0,0 +1,102 @@
+//! A process-wide default [Sharder], configured once at startup, for code that cannot be handed one.
+//!
+//! ```
+//! # use faro_sharding::{global, Sharder};
+//! // At startup.
+//! global::set(Sharder::builder().seed(7).destinations(50).build()).unwrap();
+//!
+//! // Anywhere else.
+//! let shard = global::shard("user-17").unwrap();
+//! assert!(shard < 50);
+//! ```
+//!
+//! Setting the sharder twice, or sharding before it is set, returns a [GlobalError] instead of panicking.
+
+use crate::seahash::StableSeaHasher;
+use crate::Sharder;
+use std::fmt;
+use std::hash::Hash;
+use std::sync::OnceLock;
+
+static DEFAULT: OnceLock<Sharder<StableSeaHasher>> = OnceLock::new();
+
+/// Error returned when the global [Sharder] is misconfigured.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum GlobalError {
+    /// [set] was called after the sharder was already set.
+    AlreadySet,
+    /// The sharder was used before [set] was called.
+    NotSet,
+    /// [shard] was called, but the sharder was built without a destination count.
+    NoDestinationCount,
+    /// [shard_for] was called with 0 destinations.
+    ZeroDestinations,
+}
+
+impl fmt::Display for GlobalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GlobalError::AlreadySet => write!(f, "the global sharder was already set"),
+            GlobalError::NotSet => write!(f, "the global sharder was used before being set"),
+            GlobalError::NoDestinationCount => {
+                write!(
+                    f,
+                    "the global sharder was built without a destination count"
+                )
+            }
+            GlobalError::ZeroDestinations => write!(f, "total_destinations must be > 0"),
+        }
+    }
+}
+
+impl std::error::Error for GlobalError {}
+
+/// Sets the global sharder. Only the first call succeeds.
+pub fn set(sharder: Sharder<StableSeaHasher>) -> Result<(), GlobalError> {
+    DEFAULT.set(sharder).map_err(|_| GlobalError::AlreadySet)
+}
+
+/// The global sharder.
+pub fn get() -> Result<&'static Sharder<StableSeaHasher>, GlobalError> {
+    DEFAULT.get().ok_or(GlobalError::NotSet)
+}
+
+/// [Sharder::shard] with the global sharder.
+pub fn shard(key: impl Hash) -> Result<u64, GlobalError> {
+    let sharder = get()?;
+    let total_destinations = sharder
+        .destinations()
+        .ok_or(GlobalError::NoDestinationCount)?;
+    Ok(sharder.shard_for(key, total_destinations))
+}
+
+/// [Sharder::shard_for] with the global sharder.
+pub fn shard_for(key: impl Hash, total_destinations: u64) -> Result<u64, GlobalError> {
+    let sharder = get()?;
+    if total_destinations == 0 {
+        return Err(GlobalError::ZeroDestinations);
+    }
+    Ok(sharder.shard_for(key, total_destinations))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Algorithm;
+
+    // The global sharder can only be set once per process, so every check is in this one test.
+    #[test]
+    fn set_once_then_shard() {
+        assert_eq!(get().err(), Some(GlobalError::NotSet));
+        assert_eq!(shard("foo"), Err(GlobalError::NotSet));
+
+        let sharder = Sharder::builder().algorithm(Algorithm::FaroV2).build();
+        set(sharder).unwrap();
+        assert_eq!(set(sharder), Err(GlobalError::AlreadySet));
+
+        assert_eq!(shard("foo"), Err(GlobalError::NoDestinationCount));
+        assert_eq!(shard_for("foo", 0), Err(GlobalError::ZeroDestinations));
+        assert_eq!(shard_for("foo", 73), Ok(crate::v2::shard_for("foo", 73)));
+    }
+}
@@ -0,0 +1,318 @@
+//! Golden test vectors for pinning a hasher's key to shard mapping.
+//!
+//! Changing how a hasher hashes, even by accident, reshuffles every key. Generate the vectors once, save them next to your tests, and validate against them in CI, the way this crate's `pinning_default_shard` test pins [crate::shard_for]:
+//!
+//! ```
+//! # use faro_sharding::golden;
+//! # use std::hash::BuildHasherDefault;
+//! # use std::collections::hash_map::DefaultHasher;
+//! let hasher = BuildHasherDefault::<DefaultHasher>::default();
+//!
+//! // Once: std::fs::write("tests/golden.tsv", golden::to_tsv(&golden::generate(&hasher)))
+//! let saved = golden::to_tsv(&golden::generate(&hasher));
+//!
+//! // In a test: include_str!("golden.tsv")
+//! let vectors = golden::from_tsv(&saved).unwrap();
+//! golden::validate(&vectors, &hasher).unwrap();
+//! ```
+
+use crate::{shard_with_hasher, Algorithm};
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+
+/// Keys in the canonical set. None contain a tab or newline, so they round trip through [to_tsv].
+const KEYS: &[&str] = &[
+    "",
+    "0",
+    "a",
+    "foo",
+    "bar",
+    "baz",
+    "user:42",
+    "tenant/1234/object",
+    "größe",
+    "🦀",
+    "a key long enough to span several blocks of most hashers' inputs",
+];
+
+/// Destination counts in the canonical set.
+const LOCATIONS: &[u64] = &[1, 2, 3, 7, 10, 16, 50, 64, 73, 100, 1_000, 10_000];
+
+/// The expected shard of `key` at `locations` destinations.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct GoldenVector {
+    /// The key, sharded as a `&str`.
+    pub key: String,
+    /// The destination count.
+    pub locations: u64,
+    /// The shard the key must map to.
+    pub shard: u64,
+}
+
+/// The canonical set of vectors for `hasher`: every canonical key at every canonical destination count.
+///
+/// The canonical set never changes, so vectors generated by different versions of this crate can be compared.
+pub fn generate(hasher: &impl BuildHasher) -> Vec<GoldenVector> {
+    KEYS.iter()
+        .flat_map(|&key| {
+            LOCATIONS.iter().map(move |&locations| GoldenVector {
+                key: key.to_string(),
+                locations,
+                shard: shard_with_hasher(key, locations, hasher),
+            })
+        })
+        .collect()
+}
+
+/// A [GoldenVector] that `hasher` no longer maps to the saved shard.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GoldenMismatch {
+    /// The saved vector.
+    pub expected: GoldenVector,
+    /// The shard `hasher` returns now.
+    pub actual: u64,
+}
+
+impl fmt::Display for GoldenMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?} at {} locations moved from shard {} to {}",
+            self.expected.key, self.expected.locations, self.expected.shard, self.actual
+        )
+    }
+}
+
+impl std::error::Error for GoldenMismatch {}
+
+/// Checks that `hasher` still maps every vector in `saved` to its shard, returning the first that does not.
+pub fn validate(saved: &[GoldenVector], hasher: &impl BuildHasher) -> Result<(), GoldenMismatch> {
+    for vector in saved {
+        let actual = shard_with_hasher(vector.key.as_str(), vector.locations, hasher);
+        if actual != vector.shard {
+            return Err(GoldenMismatch {
+                expected: vector.clone(),
+                actual,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Formats vectors as one `key<TAB>locations<TAB>shard` line each.
+pub fn to_tsv(vectors: &[GoldenVector]) -> String {
+    vectors
+        .iter()
+        .map(|v| format!("{}\t{}\t{}\n", v.key, v.locations, v.shard))
+        .collect()
+}
+
+/// A line of [from_tsv] input that is not `key<TAB>locations<TAB>shard`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseGoldenError {
+    /// 1-based line number.
+    pub line: usize,
+}
+
+impl fmt::Display for ParseGoldenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {} is not key<TAB>locations<TAB>shard", self.line)
+    }
+}
+
+impl std::error::Error for ParseGoldenError {}
+
+/// Parses vectors formatted by [to_tsv].
+pub fn from_tsv(tsv: &str) -> Result<Vec<GoldenVector>, ParseGoldenError> {
+    tsv.lines()
+        .enumerate()
+        .map(|(i, line)| {
+            let error = ParseGoldenError { line: i + 1 };
+            let mut fields = line.rsplitn(3, '\t');
+            let shard = fields
+                .next()
+                .and_then(|s| s.parse().ok())
+                .ok_or(error.clone())?;
+            let locations = fields
+                .next()
+                .and_then(|s| s.parse().ok())
+                .ok_or(error.clone())?;
+            let key = fields.next().ok_or(error)?;
+            Ok(GoldenVector {
+                key: key.to_string(),
+                locations,
+                shard,
+            })
+        })
+        .collect()
+}
+
+/// A cross-language test vector: the shard of raw key bytes under one [Algorithm].
+///
+/// The key is hashed with a single [Hasher::write] of its bytes, as in [crate::shard_bytes_with_hasher], so an implementation in another language only needs the same hash function, not Rust's [Hash](std::hash::Hash) encoding.
+///
+/// With the `serde` feature it serializes with `key_hex`, `key` as a string if the bytes are UTF-8 and `null` otherwise, `locations`, `key_hash` as a decimal string since it may not fit in a double, and `shard`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Fixture {
+    /// The key bytes.
+    pub key: Vec<u8>,
+    /// The destination count.
+    pub locations: u64,
+    /// The hash of the key bytes, to check the hash function separately from the algorithm.
+    pub key_hash: u64,
+    /// The shard the key must map to.
+    pub shard: u64,
+}
+
+/// The canonical vectors, then `count` generated ones, for porting `algorithm` with `hasher` to another language.
+///
+/// Generated keys are 0 to 64 pseudo-random bytes, at destination counts from 1 to 10,000 weighted towards small counts. The generator never changes, so the same arguments always give the same keys and destination counts.
+pub fn fixtures(algorithm: Algorithm, hasher: &impl BuildHasher, count: usize) -> Vec<Fixture> {
+    let canonical = KEYS.iter().flat_map(|key| {
+        LOCATIONS
+            .iter()
+            .map(move |&locations| (key.as_bytes().to_vec(), locations))
+    });
+    let mut seed = 0;
+    let generated = (0..count).map(move |_| {
+        let len = splitmix64(&mut seed) % 65;
+        let key = (0..len).map(|_| splitmix64(&mut seed) as u8).collect();
+        let r = splitmix64(&mut seed);
+        let locations = match r % 4 {
+            0 | 1 => 1 + (r >> 8) % 16,
+            2 => 1 + (r >> 8) % 1_000,
+            _ => 1 + (r >> 8) % 10_000,
+        };
+        (key, locations)
+    });
+
+    canonical
+        .chain(generated)
+        .map(|(key, locations)| {
+            let mut state = hasher.build_hasher();
+            state.write(&key);
+            let key_hash = state.finish();
+            Fixture {
+                shard: algorithm.shard_for_key_hash(key_hash, locations, hasher),
+                key,
+                locations,
+                key_hash,
+            }
+        })
+        .collect()
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Fixture {
+    fn serialize<Ser: serde::Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        #[derive(serde::Serialize)]
+        struct PersistedFixture<'a> {
+            key_hex: String,
+            key: Option<&'a str>,
+            locations: u64,
+            key_hash: String,
+            shard: u64,
+        }
+
+        PersistedFixture {
+            key_hex: self.key.iter().map(|b| format!("{:02x}", b)).collect(),
+            key: std::str::from_utf8(&self.key).ok(),
+            locations: self.locations,
+            key_hash: self.key_hash.to_string(),
+            shard: self.shard,
+        }
+        .serialize(serializer)
+    }
+}
+
+/// One step of SplitMix64, which keeps [fixtures] independent of any dependency's random number generator.
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{shard_for, BuildSeaHasher};
+
+    #[test]
+    fn agrees_with_the_default_pins() {
+        let vectors = generate(&BuildSeaHasher);
+        assert_eq!(vectors.len(), KEYS.len() * LOCATIONS.len());
+
+        let foo_73 = vectors
+            .iter()
+            .find(|v| v.key == "foo" && v.locations == 73)
+            .unwrap();
+        assert_eq!(foo_73.shard, 49);
+        assert_eq!(foo_73.shard, shard_for("foo", 73));
+    }
+
+    #[test]
+    fn round_trips_and_detects_changes() {
+        let vectors = generate(&BuildSeaHasher);
+        let parsed = from_tsv(&to_tsv(&vectors)).unwrap();
+        assert_eq!(parsed, vectors);
+        assert_eq!(validate(&parsed, &BuildSeaHasher), Ok(()));
+
+        let other = crate::seahash::StableSeaHasher::with_seeds([1, 2, 3, 4]);
+        let mismatch = validate(&parsed, &other).unwrap_err();
+        let expected = &mismatch.expected;
+        assert_eq!(
+            mismatch.actual,
+            shard_with_hasher(expected.key.as_str(), expected.locations, &other)
+        );
+        assert_ne!(mismatch.actual, expected.shard);
+    }
+
+    #[test]
+    fn fixtures_agree_with_shard_bytes() {
+        let fixtures = fixtures(Algorithm::FaroV1, &BuildSeaHasher, 500);
+        assert_eq!(fixtures.len(), KEYS.len() * LOCATIONS.len() + 500);
+        for f in &fixtures {
+            assert_eq!(
+                f.shard,
+                crate::shard_bytes_with_hasher(&f.key, f.locations, &BuildSeaHasher)
+            );
+            assert!(f.locations >= 1 && f.locations <= 10_000);
+        }
+
+        // Do not change these values. Ports check against fixtures generated by earlier versions.
+        let first = &fixtures[KEYS.len() * LOCATIONS.len()];
+        assert_eq!(
+            (first.key.len(), first.key[0], first.locations, first.shard),
+            (35, 244, 9188, 5601)
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn fixtures_serialize() {
+        let fixtures = fixtures(Algorithm::FaroV2, &BuildSeaHasher, 10);
+        let parsed = serde_json::to_value(&fixtures).unwrap();
+
+        let parsed = parsed.as_array().unwrap();
+        assert_eq!(parsed.len(), fixtures.len());
+        for (p, f) in parsed.iter().zip(&fixtures) {
+            assert_eq!(p["key_hash"], f.key_hash.to_string());
+            assert_eq!(p["shard"], f.shard);
+            assert_eq!(p["key_hex"].as_str().unwrap().len(), 2 * f.key.len());
+            match std::str::from_utf8(&f.key) {
+                Ok(key) => assert_eq!(p["key"], key),
+                Err(_) => assert!(p["key"].is_null()),
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        assert_eq!(
+            from_tsv("foo\t73\t49\nfoo\t73\n"),
+            Err(ParseGoldenError { line: 2 })
+        );
+    }
+}
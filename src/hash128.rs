@@ -0,0 +1,84 @@
+use core::hash::*;
+
+/// A [Hasher] that can also produce a 128-bit hash.
+pub trait Hasher128: Hasher {
+    /// Returns the 128-bit hash of the values written so far.
+    fn finish128(&self) -> u128;
+}
+
+/// Faro Sharding over a chain of 128-bit hashes.
+///
+/// With 64-bit hashes, `hash % (n + 1)` has a bias of up to `n / 2^64` toward low remainders, and distinct keys' chains can collide and merge. Both are negligible for most destination counts, but shrink by a further factor of `2^64` here. The chain carries 128 bits between steps, so a key's shards share nothing with its [crate::shard_with_hasher] shards, even with the same hasher; switching existing data to it moves nearly every key.
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub fn shard_with_hasher128<S>(key: impl Hash, total_destinations: u64, hasher: &S) -> u64
+where
+    S: BuildHasher,
+    S::Hasher: Hasher128,
+{
+    assert_ne!(total_destinations, 0, "total_destinations must be > 0");
+
+    let mut state = hasher.build_hasher();
+    key.hash(&mut state);
+    let mut last_hash = state.finish128();
+
+    let mut shard = 0;
+    for n in 1..total_destinations {
+        let mut state = hasher.build_hasher();
+        state.write_u128(last_hash);
+        last_hash = state.finish128();
+
+        if last_hash.is_multiple_of(u128::from(n) + 1) {
+            shard = n;
+        }
+    }
+    shard
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use seahash::SeaHasher;
+
+    /// Two independently seeded SeaHashers side by side.
+    struct SeaHasher128(SeaHasher, SeaHasher);
+
+    impl Hasher for SeaHasher128 {
+        fn write(&mut self, bytes: &[u8]) {
+            self.0.write(bytes);
+            self.1.write(bytes);
+        }
+
+        fn finish(&self) -> u64 {
+            self.0.finish()
+        }
+    }
+
+    impl Hasher128 for SeaHasher128 {
+        fn finish128(&self) -> u128 {
+            (u128::from(self.0.finish()) << 64) | u128::from(self.1.finish())
+        }
+    }
+
+    struct BuildSeaHasher128;
+
+    impl BuildHasher for BuildSeaHasher128 {
+        type Hasher = SeaHasher128;
+
+        fn build_hasher(&self) -> SeaHasher128 {
+            SeaHasher128(SeaHasher::new(), SeaHasher::with_seeds(1, 2, 3, 4))
+        }
+    }
+
+    #[test]
+    fn growth_only_moves_to_the_new_destination() {
+        assert_eq!(
+            crate::stability::verify_with(0..1_000u64, 64, |&key, total| {
+                shard_with_hasher128(key, total, &BuildSeaHasher128)
+            }),
+            Ok(())
+        );
+    }
+}
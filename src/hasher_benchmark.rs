@@ -0,0 +1,104 @@
+use crate::{shard_with_hasher, stats};
+use std::hash::*;
+use std::time::Instant;
+
+/// Results for one hasher in a [ShardingHasherBenchmark].
+#[derive(Clone, Debug, PartialEq)]
+pub struct HasherBenchmarkResult {
+    /// Name the hasher was added under.
+    pub name: String,
+    /// Millions of keys sharded per second.
+    pub throughput_mkeys_per_sec: f64,
+    /// See [stats::imbalance_ratio].
+    pub imbalance_ratio: f64,
+    /// See [stats::entropy_efficiency].
+    pub entropy_efficiency: f64,
+}
+
+type ShardFn<K> = Box<dyn Fn(&K, u64) -> u64 + Send + Sync>;
+
+/// Compares the speed and distribution quality of several hashers on a caller-provided key set.
+pub struct ShardingHasherBenchmark<K> {
+    keys: Vec<K>,
+    total_destinations: u64,
+    hashers: Vec<(String, ShardFn<K>)>,
+}
+
+impl<K: Hash> ShardingHasherBenchmark<K> {
+    /// Creates a benchmark over `keys`.
+    ///
+    /// # Panics
+    ///
+    /// If total_destinations == 0.
+    pub fn new(keys: Vec<K>, total_destinations: u64) -> Self {
+        assert_ne!(total_destinations, 0, "total_destinations must be > 0");
+
+        ShardingHasherBenchmark {
+            keys,
+            total_destinations,
+            hashers: Vec::new(),
+        }
+    }
+
+    /// Adds a hasher to compare.
+    pub fn add_hasher(&mut self, name: String, hasher: impl BuildHasher + Send + Sync + 'static) {
+        self.hashers.push((
+            name,
+            Box::new(move |key, total| shard_with_hasher(key, total, &hasher)),
+        ));
+    }
+
+    /// Shards every key with every hasher, returning results fastest first.
+    pub fn run(&self) -> Vec<HasherBenchmarkResult> {
+        let mut results = self
+            .hashers
+            .iter()
+            .map(|(name, shard)| {
+                let mut counts = vec![0; self.total_destinations as usize];
+
+                let start = Instant::now();
+                for key in &self.keys {
+                    counts[shard(key, self.total_destinations) as usize] += 1;
+                }
+                let elapsed = start.elapsed().as_secs_f64();
+
+                HasherBenchmarkResult {
+                    name: name.clone(),
+                    throughput_mkeys_per_sec: self.keys.len() as f64 / elapsed / 1e6,
+                    imbalance_ratio: stats::imbalance_ratio(&counts),
+                    entropy_efficiency: stats::entropy_efficiency(&counts),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        results.sort_by(|a, b| {
+            b.throughput_mkeys_per_sec
+                .total_cmp(&a.throughput_mkeys_per_sec)
+        });
+        results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BuildSeaHasher;
+
+    #[test]
+    fn reports_every_hasher_sorted_by_throughput() {
+        let mut benchmark = ShardingHasherBenchmark::new((0..10_000).collect(), 16);
+        benchmark.add_hasher("seahash".to_string(), BuildSeaHasher);
+        benchmark.add_hasher(
+            "sip".to_string(),
+            BuildHasherDefault::<std::collections::hash_map::DefaultHasher>::default(),
+        );
+
+        let results = benchmark.run();
+        assert_eq!(results.len(), 2);
+        assert!(results[0].throughput_mkeys_per_sec >= results[1].throughput_mkeys_per_sec);
+        for result in results {
+            assert!(result.imbalance_ratio < 1.2, "{result:?}");
+            assert!(result.entropy_efficiency > 0.99, "{result:?}");
+        }
+    }
+}
@@ -0,0 +1,253 @@
+use core::hash::*;
+
+/// One of the bundled hashers, chosen at runtime, for example from a config file.
+///
+/// [HasherKind] is itself a [BuildHasher], so it works with [crate::shard_with_hasher], [crate::Sharder], and every other `_with_hasher` API, and hashes exactly like the backend it names. Which variants exist depends on the enabled features.
+///
+/// ```
+/// # use faro_sharding::{shard_for, shard_with_hasher, HasherKind};
+/// let hasher: HasherKind = "seahash".parse().unwrap();
+/// assert_eq!(shard_with_hasher("foo", 73, &hasher), shard_for("foo", 73));
+/// ```
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum HasherKind {
+    /// [crate::seahash::StableSeaHasher::new], the hasher of [crate::shard_for].
+    #[cfg(feature = "seahash")]
+    SeaHash,
+    /// [crate::frozen::BuildFrozenSeaHasher::new].
+    #[cfg(feature = "frozen")]
+    FrozenSeaHash,
+    /// [crate::xxh3::BuildXxh3Hasher].
+    #[cfg(feature = "xxhash")]
+    Xxh3,
+    /// [crate::fnv::BuildFnvHasher].
+    #[cfg(feature = "fnv")]
+    Fnv,
+    /// [crate::blake3::KeyedBlake3]. It needs a secret, so it has no name to parse; load the secret separately and build this variant directly.
+    #[cfg(feature = "blake3")]
+    Blake3(crate::blake3::KeyedBlake3),
+}
+
+impl HasherKind {
+    /// Every hasher that can be parsed from its [HasherKind::name].
+    pub const ALL: &'static [HasherKind] = &[
+        #[cfg(feature = "seahash")]
+        HasherKind::SeaHash,
+        #[cfg(feature = "frozen")]
+        HasherKind::FrozenSeaHash,
+        #[cfg(feature = "xxhash")]
+        HasherKind::Xxh3,
+        #[cfg(feature = "fnv")]
+        HasherKind::Fnv,
+    ];
+
+    /// A stable identifier for this hasher, such as `"seahash"`.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            #[cfg(feature = "seahash")]
+            HasherKind::SeaHash => "seahash",
+            #[cfg(feature = "frozen")]
+            HasherKind::FrozenSeaHash => "frozen-seahash",
+            #[cfg(feature = "xxhash")]
+            HasherKind::Xxh3 => "xxh3",
+            #[cfg(feature = "fnv")]
+            HasherKind::Fnv => "fnv",
+            #[cfg(feature = "blake3")]
+            HasherKind::Blake3(_) => "blake3",
+        }
+    }
+}
+
+impl core::fmt::Display for HasherKind {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Error returned when parsing an unknown [HasherKind] name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UnknownHasher;
+
+impl core::fmt::Display for UnknownHasher {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "unknown hasher")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for UnknownHasher {}
+
+impl core::str::FromStr for HasherKind {
+    type Err = UnknownHasher;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        HasherKind::ALL
+            .iter()
+            .find(|h| h.name() == name)
+            .cloned()
+            .ok_or(UnknownHasher)
+    }
+}
+
+impl BuildHasher for HasherKind {
+    type Hasher = KindHasher;
+
+    fn build_hasher(&self) -> KindHasher {
+        match *self {
+            #[cfg(feature = "seahash")]
+            HasherKind::SeaHash => {
+                KindHasher::SeaHash(crate::seahash::StableSeaHasher::new().build_hasher())
+            }
+            #[cfg(feature = "frozen")]
+            HasherKind::FrozenSeaHash => {
+                KindHasher::FrozenSeaHash(crate::frozen::BuildFrozenSeaHasher::new().build_hasher())
+            }
+            #[cfg(feature = "xxhash")]
+            HasherKind::Xxh3 => KindHasher::Xxh3(crate::xxh3::BuildXxh3Hasher.build_hasher()),
+            #[cfg(feature = "fnv")]
+            HasherKind::Fnv => KindHasher::Fnv(crate::fnv::BuildFnvHasher.build_hasher()),
+            #[cfg(feature = "blake3")]
+            HasherKind::Blake3(ref keyed) => KindHasher::Blake3(keyed.build_hasher()),
+        }
+    }
+}
+
+impl crate::StableBuildHasher for HasherKind {}
+
+/// [Hasher] built by [HasherKind].
+// Hashers live briefly on the stack, and boxing would need an allocator.
+#[allow(clippy::large_enum_variant)]
+pub enum KindHasher {
+    /// Built by [HasherKind::SeaHash].
+    #[cfg(feature = "seahash")]
+    SeaHash(::seahash::SeaHasher),
+    /// Built by [HasherKind::FrozenSeaHash].
+    #[cfg(feature = "frozen")]
+    FrozenSeaHash(crate::frozen::FrozenSeaHasher),
+    /// Built by [HasherKind::Xxh3].
+    #[cfg(feature = "xxhash")]
+    Xxh3(crate::xxh3::Xxh3Hasher),
+    /// Built by [HasherKind::Fnv].
+    #[cfg(feature = "fnv")]
+    Fnv(crate::fnv::FnvHasher),
+    /// Built by [HasherKind::Blake3].
+    #[cfg(feature = "blake3")]
+    Blake3(crate::blake3::Blake3Hasher),
+}
+
+impl core::fmt::Debug for KindHasher {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("KindHasher").finish_non_exhaustive()
+    }
+}
+
+/// Forwards each [Hasher] method to the wrapped hasher, so overridden methods hash the same as they do unwrapped.
+macro_rules! forward {
+    ($($method:ident($($arg:ident: $ty:ty),*) $(-> $ret:ty)?;)*) => {$(
+        fn $method(&mut self, $($arg: $ty),*) $(-> $ret)? {
+            match *self {
+                #[cfg(feature = "seahash")]
+                KindHasher::SeaHash(ref mut h) => h.$method($($arg),*),
+                #[cfg(feature = "frozen")]
+                KindHasher::FrozenSeaHash(ref mut h) => h.$method($($arg),*),
+                #[cfg(feature = "xxhash")]
+                KindHasher::Xxh3(ref mut h) => h.$method($($arg),*),
+                #[cfg(feature = "fnv")]
+                KindHasher::Fnv(ref mut h) => h.$method($($arg),*),
+                #[cfg(feature = "blake3")]
+                KindHasher::Blake3(ref mut h) => h.$method($($arg),*),
+            }
+        }
+    )*};
+}
+
+impl Hasher for KindHasher {
+    fn finish(&self) -> u64 {
+        match *self {
+            #[cfg(feature = "seahash")]
+            KindHasher::SeaHash(ref h) => h.finish(),
+            #[cfg(feature = "frozen")]
+            KindHasher::FrozenSeaHash(ref h) => h.finish(),
+            #[cfg(feature = "xxhash")]
+            KindHasher::Xxh3(ref h) => h.finish(),
+            #[cfg(feature = "fnv")]
+            KindHasher::Fnv(ref h) => h.finish(),
+            #[cfg(feature = "blake3")]
+            KindHasher::Blake3(ref h) => h.finish(),
+        }
+    }
+
+    forward! {
+        write(bytes: &[u8]);
+        write_u8(i: u8);
+        write_u16(i: u16);
+        write_u32(i: u32);
+        write_u64(i: u64);
+        write_u128(i: u128);
+        write_usize(i: usize);
+        write_i8(i: i8);
+        write_i16(i: i16);
+        write_i32(i: i32);
+        write_i64(i: i64);
+        write_i128(i: i128);
+        write_isize(i: isize);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shard_with_hasher;
+
+    fn assert_same_shards(kind: &HasherKind, backend: &impl BuildHasher) {
+        for key in 0..1_000u64 {
+            assert_eq!(
+                shard_with_hasher((key, "key"), 73, kind),
+                shard_with_hasher((key, "key"), 73, backend),
+                "{}",
+                kind
+            );
+        }
+    }
+
+    #[test]
+    fn hashes_like_each_backend() {
+        for kind in HasherKind::ALL {
+            match *kind {
+                #[cfg(feature = "seahash")]
+                HasherKind::SeaHash => {
+                    assert_same_shards(kind, &crate::seahash::StableSeaHasher::new())
+                }
+                #[cfg(feature = "frozen")]
+                HasherKind::FrozenSeaHash => {
+                    assert_same_shards(kind, &crate::frozen::BuildFrozenSeaHasher::new())
+                }
+                #[cfg(feature = "xxhash")]
+                HasherKind::Xxh3 => assert_same_shards(kind, &crate::xxh3::BuildXxh3Hasher),
+                #[cfg(feature = "fnv")]
+                HasherKind::Fnv => assert_same_shards(kind, &crate::fnv::BuildFnvHasher),
+                #[cfg(feature = "blake3")]
+                HasherKind::Blake3(_) => unreachable!(),
+            }
+        }
+
+        #[cfg(feature = "blake3")]
+        {
+            let keyed = crate::blake3::KeyedBlake3::new([7; 32]);
+            assert_same_shards(&HasherKind::Blake3(keyed.clone()), &keyed);
+        }
+    }
+
+    #[test]
+    fn parses_names() {
+        for kind in HasherKind::ALL {
+            assert_eq!(
+                kind.to_string().parse::<HasherKind>().unwrap().name(),
+                kind.name()
+            );
+        }
+        assert_eq!("blake3".parse::<HasherKind>().err(), Some(UnknownHasher));
+        assert_eq!("md5".parse::<HasherKind>().err(), Some(UnknownHasher));
+    }
+}
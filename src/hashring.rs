@@ -0,0 +1,172 @@
+//! A drop-in replacement for the [hashring](https://docs.rs/hashring) crate's `HashRing`, backed by Faro Sharding.
+//!
+//! Change `use hashring::HashRing` to `use faro_sharding::hashring::HashRing` and call sites keep working. Adding a node only moves keys onto it, and removing a node only moves its own keys, instead of the uneven arcs a ring with one point per node gives.
+//!
+//! Differences from `hashring`:
+//!
+//! - Nodes are compared with [Eq] rather than by hash, so `T: Eq` is required.
+//! - [HashRing::get] walks the Faro chain, so it is O(n) in the number of nodes ever added rather than O(log n).
+//! - Removed nodes are kept as tombstones, see [crate::TombstoneTopology]. Re-adding a node gives it a new position.
+
+use crate::seahash::StableSeaHasher;
+use crate::{shards_with_hasher, TombstoneTopology};
+use std::hash::*;
+
+/// Assigns keys to nodes. See the [module docs](self).
+#[derive(Clone, Debug)]
+pub struct HashRing<T, S = StableSeaHasher> {
+    nodes: Vec<Option<T>>,
+    topology: TombstoneTopology<S>,
+}
+
+impl<T> Default for HashRing<T> {
+    fn default() -> Self {
+        HashRing::new()
+    }
+}
+
+impl<T> HashRing<T> {
+    /// Creates an empty ring using the default hasher of [crate::shard_for].
+    pub fn new() -> HashRing<T> {
+        HashRing::with_hasher(StableSeaHasher::new())
+    }
+}
+
+impl<T, S: BuildHasher> HashRing<T, S> {
+    /// Creates an empty ring hashing keys with `hash_builder`.
+    pub fn with_hasher(hash_builder: S) -> HashRing<T, S> {
+        HashRing {
+            nodes: Vec::new(),
+            topology: TombstoneTopology::new(0, hash_builder),
+        }
+    }
+
+    /// Number of nodes.
+    pub fn len(&self) -> usize {
+        self.topology.live_destinations() as usize
+    }
+
+    /// Whether there are no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T: Eq, S: BuildHasher> HashRing<T, S> {
+    /// Adds `node`. Only keys that now belong to it move.
+    pub fn add(&mut self, node: T) {
+        self.topology.push();
+        self.nodes.push(Some(node));
+    }
+
+    /// Adds every node in `nodes`, in order.
+    pub fn batch_add(&mut self, nodes: Vec<T>) {
+        for node in nodes {
+            self.add(node);
+        }
+    }
+
+    /// Removes `node`, returning it if it was present. Only its keys move.
+    pub fn remove(&mut self, node: &T) -> Option<T> {
+        let index = self.nodes.iter().position(|n| n.as_ref() == Some(node))?;
+        self.topology.remove(index as u64);
+        self.nodes[index].take()
+    }
+
+    /// The node responsible for `key`, or `None` if there are no nodes.
+    pub fn get<U: Hash>(&self, key: &U) -> Option<&T> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let key_hash = self.topology.hasher().hash_one(key);
+        let index = self.topology.shard_for_key_hash(key_hash);
+
+        #[cfg(feature = "tracing")]
+        tracing::trace!(key_hash, shard = index, "hash ring lookup");
+        self.nodes[index as usize].as_ref()
+    }
+
+    /// The node responsible for `key` followed by up to `replicas` other nodes, in preference order, or `None` if there are no nodes.
+    ///
+    /// As in `hashring`, the result is capped at the number of nodes.
+    pub fn get_with_replicas<U: Hash>(&self, key: &U, replicas: usize) -> Option<Vec<T>>
+    where
+        T: Clone,
+    {
+        if self.is_empty() {
+            return None;
+        }
+
+        let primary = self.topology.shard_for(key);
+        let total = self.topology.total_destinations();
+        let others = shards_with_hasher(key, total, total as usize, self.topology.hasher())
+            .into_iter()
+            .filter(|&i| i != primary && !self.topology.is_removed(i));
+
+        Some(
+            std::iter::once(primary)
+                .chain(others)
+                .take((replicas + 1).min(self.len()))
+                .map(|i| self.nodes[i as usize].clone().expect("live node"))
+                .collect(),
+        )
+    }
+}
+
+impl<T, S> IntoIterator for HashRing<T, S> {
+    type Item = T;
+    type IntoIter = std::iter::Flatten<std::vec::IntoIter<Option<T>>>;
+
+    /// Iterates the nodes in the order they were added.
+    fn into_iter(self) -> Self::IntoIter {
+        self.nodes.into_iter().flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shard_for;
+
+    #[test]
+    fn hashring_call_sites() {
+        let mut ring = HashRing::new();
+        assert_eq!(ring.get(&"foo"), None);
+        assert_eq!(ring.get_with_replicas(&"foo", 2), None);
+
+        ring.batch_add(vec!["a", "b", "c"]);
+        ring.add("d");
+        assert_eq!(ring.len(), 4);
+        assert_eq!(
+            ring.get(&"foo"),
+            Some(&["a", "b", "c", "d"][shard_for("foo", 4) as usize])
+        );
+
+        let replicas = ring.get_with_replicas(&"foo", 1).unwrap();
+        assert_eq!(replicas.len(), 2);
+        assert_eq!(Some(&replicas[0]), ring.get(&"foo"));
+        assert_ne!(replicas[0], replicas[1]);
+        assert_eq!(ring.get_with_replicas(&"foo", 10).unwrap().len(), 4);
+
+        assert_eq!(ring.remove(&"b"), Some("b"));
+        assert_eq!(ring.remove(&"b"), None);
+        assert_eq!(ring.into_iter().collect::<Vec<_>>(), ["a", "c", "d"]);
+    }
+
+    #[test]
+    fn removing_only_moves_the_removed_nodes_keys() {
+        let mut ring = HashRing::new();
+        ring.batch_add((0..8).collect());
+        let before = (0..1_000u64)
+            .map(|k| *ring.get(&k).unwrap())
+            .collect::<Vec<_>>();
+
+        ring.remove(&3);
+        for (k, before) in (0..1_000u64).zip(before) {
+            let after = *ring.get(&k).unwrap();
+            assert!(after == before || before == 3);
+            assert_ne!(after, 3);
+        }
+    }
+}
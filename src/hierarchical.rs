@@ -0,0 +1,152 @@
+use crate::shard_with_hasher;
+use std::hash::*;
+
+/// Shards keys first to a region and then to a node within that region.
+///
+/// Each layer is seeded separately, so where a key lands within its region is independent of which region it chose. Adding a node to one region only moves keys within that region onto the new node, and adding a region only moves keys onto the new region.
+#[derive(Clone, Debug)]
+pub struct HierarchicalSharder<S> {
+    nodes_per_region: Vec<u64>,
+    region_seed: u64,
+    node_seed: u64,
+    hasher: S,
+}
+
+impl<S: BuildHasher> HierarchicalSharder<S> {
+    /// Creates a sharder with one region per entry in `nodes_per_region`.
+    ///
+    /// # Panics
+    ///
+    /// If there are no regions or any region has 0 nodes.
+    pub fn new(nodes_per_region: &[u64], hasher: S) -> Self {
+        Self::with_seeds(nodes_per_region, 0, 1, hasher)
+    }
+
+    /// Like [HierarchicalSharder::new], with explicit seeds for the region and node layers.
+    ///
+    /// # Panics
+    ///
+    /// If there are no regions, any region has 0 nodes, or the seeds are equal.
+    pub fn with_seeds(
+        nodes_per_region: &[u64],
+        region_seed: u64,
+        node_seed: u64,
+        hasher: S,
+    ) -> Self {
+        assert!(
+            !nodes_per_region.is_empty(),
+            "must have at least one region"
+        );
+        assert!(
+            nodes_per_region.iter().all(|&n| n > 0),
+            "every region must have > 0 nodes"
+        );
+        assert_ne!(region_seed, node_seed, "layer seeds must differ");
+
+        HierarchicalSharder {
+            nodes_per_region: nodes_per_region.to_vec(),
+            region_seed,
+            node_seed,
+            hasher,
+        }
+    }
+
+    /// Returns `(region, node)` for `key`, where `node` indexes within the region.
+    pub fn shard_for(&self, key: impl Hash) -> (u64, u64) {
+        let region = shard_with_hasher((self.region_seed, &key), self.regions(), &self.hasher);
+        let node = shard_with_hasher(
+            (self.node_seed, &key),
+            self.nodes_per_region[region as usize],
+            &self.hasher,
+        );
+        (region, node)
+    }
+
+    /// Adds a region with `nodes` nodes, returning its index.
+    ///
+    /// # Panics
+    ///
+    /// If nodes == 0.
+    pub fn push_region(&mut self, nodes: u64) -> u64 {
+        assert_ne!(nodes, 0, "every region must have > 0 nodes");
+        self.nodes_per_region.push(nodes);
+        self.regions() - 1
+    }
+
+    /// Adds a node to `region`, returning its index within the region.
+    ///
+    /// # Panics
+    ///
+    /// If region is out of range.
+    pub fn push_node(&mut self, region: u64) -> u64 {
+        let nodes = &mut self.nodes_per_region[region as usize];
+        *nodes += 1;
+        *nodes - 1
+    }
+
+    /// Number of regions.
+    pub fn regions(&self) -> u64 {
+        self.nodes_per_region.len() as u64
+    }
+
+    /// Number of nodes in `region`, or `None` if it is out of range.
+    pub fn nodes_in(&self, region: u64) -> Option<u64> {
+        self.nodes_per_region.get(region as usize).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BuildSeaHasher;
+
+    #[test]
+    fn adding_a_node_only_moves_keys_in_its_region() {
+        let mut sharder = HierarchicalSharder::new(&[3, 5, 4], BuildSeaHasher);
+        let before = (0..10_000u64)
+            .map(|k| sharder.shard_for(k))
+            .collect::<Vec<_>>();
+
+        assert_eq!(sharder.push_node(1), 5);
+
+        for (key, before) in (0..10_000u64).zip(before) {
+            let after = sharder.shard_for(key);
+            if after != before {
+                assert_eq!(before.0, 1);
+                assert_eq!(after, (1, 5));
+            }
+        }
+    }
+
+    #[test]
+    fn adding_a_region_only_moves_keys_to_it() {
+        let mut sharder = HierarchicalSharder::new(&[3, 5], BuildSeaHasher);
+        let before = (0..10_000u64)
+            .map(|k| sharder.shard_for(k))
+            .collect::<Vec<_>>();
+
+        assert_eq!(sharder.push_region(2), 2);
+
+        for (key, before) in (0..10_000u64).zip(before) {
+            let after = sharder.shard_for(key);
+            assert!(after == before || after.0 == 2);
+        }
+    }
+
+    #[test]
+    fn nodes_are_balanced_within_a_region() {
+        let sharder = HierarchicalSharder::new(&[2, 4], BuildSeaHasher);
+
+        let mut counts = [0u64; 4];
+        for key in 0..40_000u64 {
+            if let (1, node) = sharder.shard_for(key) {
+                counts[node as usize] += 1;
+            }
+        }
+        assert!(
+            crate::stats::imbalance_ratio(&counts) < 1.05,
+            "{:?}",
+            counts
+        );
+    }
+}
@@ -0,0 +1,240 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::*;
+
+/// Finds the keys responsible for a disproportionate share of each shard's load.
+///
+/// Every request is counted in a count-min sketch, and the `top_k` keys with the highest estimates are kept as candidates. Estimates never undercount, and only overcount when keys collide in every row of the sketch. Per-shard totals are exact.
+///
+/// The detector does not shard keys itself: pass the shard each request was routed to, such as from [crate::PoolRouter::shard_of] or `Routed::shard` with the `arc-swap` feature, so it works with any router and across topology changes. To spread a hot key's load, see [crate::shards_with_hasher].
+///
+/// ```
+/// # use faro_sharding::HotKeyDetector;
+/// # use std::hash::BuildHasherDefault;
+/// # use std::collections::hash_map::DefaultHasher;
+/// let hasher = BuildHasherDefault::<DefaultHasher>::default();
+/// let mut detector = HotKeyDetector::new(10, 1024, 4, hasher);
+/// for i in 0..1_000u64 {
+///     detector.record(&"celebrity".to_string(), 3);
+///     detector.record(&format!("user-{}", i), i % 4);
+/// }
+///
+/// let hot = detector.hot_keys(0.5);
+/// assert_eq!(hot[0].key, "celebrity");
+/// assert_eq!(hot[0].shard, 3);
+/// ```
+#[derive(Clone, Debug)]
+pub struct HotKeyDetector<K, S> {
+    top_k: usize,
+    width: usize,
+    counters: Vec<Vec<u64>>,
+    /// key -> (shard, estimate, id)
+    candidates: HashMap<K, (u64, u64, u64)>,
+    /// (estimate, id) -> key, coldest first
+    by_estimate: BTreeMap<(u64, u64), K>,
+    next_id: u64,
+    shard_loads: BTreeMap<u64, u64>,
+    hasher: S,
+}
+
+impl<K: Hash + Eq + Clone, S: BuildHasher> HotKeyDetector<K, S> {
+    /// Tracks the `top_k` heaviest keys, with a sketch of `sketch_depth` rows of `sketch_width` counters.
+    ///
+    /// # Panics
+    ///
+    /// If any argument is 0.
+    pub fn new(top_k: usize, sketch_width: usize, sketch_depth: usize, hasher: S) -> Self {
+        assert_ne!(top_k, 0, "top_k must be > 0");
+        assert_ne!(sketch_width, 0, "sketch_width must be > 0");
+        assert_ne!(sketch_depth, 0, "sketch_depth must be > 0");
+
+        HotKeyDetector {
+            top_k,
+            width: sketch_width,
+            counters: vec![vec![0; sketch_width]; sketch_depth],
+            candidates: HashMap::with_capacity(top_k),
+            by_estimate: BTreeMap::new(),
+            next_id: 0,
+            shard_loads: BTreeMap::new(),
+            hasher,
+        }
+    }
+
+    /// Records one request for `key`, which was routed to `shard`.
+    pub fn record(&mut self, key: &K, shard: u64) {
+        self.record_weighted(key, shard, 1);
+    }
+
+    /// Records `weight` units of load, such as bytes or CPU time, for `key` on `shard`.
+    ///
+    /// Counts saturate at [u64::MAX] instead of overflowing.
+    pub fn record_weighted(&mut self, key: &K, shard: u64, weight: u64) {
+        let load = self.shard_loads.entry(shard).or_default();
+        *load = load.saturating_add(weight);
+
+        let mut estimate = u64::MAX;
+        for row in 0..self.counters.len() {
+            let column = self.column(row, key);
+            let counter = &mut self.counters[row][column];
+            *counter = counter.saturating_add(weight);
+            estimate = estimate.min(*counter);
+        }
+
+        if let Some((last_shard, last_estimate, id)) = self.candidates.get_mut(key) {
+            let tracked = self.by_estimate.remove(&(*last_estimate, *id)).unwrap();
+            self.by_estimate.insert((estimate, *id), tracked);
+            *last_shard = shard;
+            *last_estimate = estimate;
+            return;
+        }
+
+        if self.candidates.len() == self.top_k {
+            let (&(coldest_estimate, _), _) = self.by_estimate.first_key_value().unwrap();
+            if estimate <= coldest_estimate {
+                return;
+            }
+            let (_, coldest) = self.by_estimate.pop_first().unwrap();
+            self.candidates.remove(&coldest);
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+        self.candidates.insert(key.clone(), (shard, estimate, id));
+        self.by_estimate.insert((estimate, id), key.clone());
+    }
+
+    /// Returns the estimated load recorded for `key`.
+    pub fn estimated_count(&self, key: &K) -> u64 {
+        (0..self.counters.len())
+            .map(|row| self.counters[row][self.column(row, key)])
+            .min()
+            .unwrap_or_default()
+    }
+
+    /// Returns the exact load recorded for `shard`.
+    pub fn shard_load(&self, shard: u64) -> u64 {
+        self.shard_loads.get(&shard).copied().unwrap_or_default()
+    }
+
+    /// Returns the exact load recorded across every shard, saturating at [u64::MAX].
+    pub fn total_load(&self) -> u64 {
+        self.shard_loads
+            .values()
+            .fold(0, |total, &load| total.saturating_add(load))
+    }
+
+    /// Returns the tracked keys carrying at least `min_share` of their shard's load, heaviest first.
+    ///
+    /// A `min_share` of 0.0 returns every tracked key.
+    pub fn hot_keys(&self, min_share: f64) -> Vec<HotKey<K>> {
+        let mut hot = self
+            .candidates
+            .iter()
+            .map(|(key, &(shard, estimated_count, _))| HotKey {
+                key: key.clone(),
+                shard,
+                estimated_count,
+                share_of_shard: estimated_count as f64 / self.shard_load(shard).max(1) as f64,
+            })
+            .filter(|hot| hot.share_of_shard >= min_share)
+            .collect::<Vec<_>>();
+        hot.sort_by_key(|hot| Reverse(hot.estimated_count));
+        hot
+    }
+
+    /// Forgets everything recorded, such as at the start of a new reporting window.
+    pub fn clear(&mut self) {
+        for row in &mut self.counters {
+            row.fill(0);
+        }
+        self.candidates.clear();
+        self.by_estimate.clear();
+        self.shard_loads.clear();
+    }
+
+    fn column(&self, row: usize, key: &K) -> usize {
+        (self.hasher.hash_one((row, key)) % self.width as u64) as usize
+    }
+}
+
+/// A key reported by [HotKeyDetector::hot_keys].
+#[derive(Clone, Debug, PartialEq)]
+pub struct HotKey<K> {
+    /// The key.
+    pub key: K,
+    /// The shard the key was last routed to.
+    pub shard: u64,
+    /// Estimated load recorded for the key. Never less than the true load.
+    pub estimated_count: u64,
+    /// `estimated_count` as a fraction of the shard's load.
+    pub share_of_shard: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{shard_for, BuildSeaHasher};
+
+    #[test]
+    fn finds_hot_keys_among_uniform_traffic() {
+        let mut detector = HotKeyDetector::new(8, 2048, 4, BuildSeaHasher);
+        for i in 0..20_000u64 {
+            let key = if i % 10 == 0 { 1_000_000 } else { i % 2_000 };
+            detector.record(&key, shard_for(key, 16));
+            if i % 50 == 0 {
+                detector.record(&2_000_000, shard_for(2_000_000u64, 16));
+            }
+        }
+
+        let hot = detector.hot_keys(0.1);
+        let keys = hot.iter().map(|hot| hot.key).collect::<Vec<_>>();
+        assert_eq!(keys, [1_000_000, 2_000_000]);
+        assert!(hot[0].estimated_count >= 2_000);
+        assert_eq!(hot[0].shard, shard_for(1_000_000u64, 16));
+        assert_eq!(detector.total_load(), 20_400);
+    }
+
+    #[test]
+    fn weights_and_clear() {
+        let mut detector = HotKeyDetector::new(2, 64, 2, BuildSeaHasher);
+        detector.record_weighted(&"big", 0, 1_000);
+        detector.record(&"small", 0);
+        detector.record(&"other", 1);
+        assert_eq!(detector.estimated_count(&"big"), 1_000);
+        assert_eq!(detector.shard_load(0), 1_001);
+        assert_eq!(detector.hot_keys(0.0).len(), 2);
+        assert_eq!(detector.hot_keys(0.0)[0].key, "big");
+
+        detector.clear();
+        assert_eq!(detector.total_load(), 0);
+        assert!(detector.hot_keys(0.0).is_empty());
+    }
+
+    #[test]
+    fn evicts_the_coldest_candidate() {
+        let mut detector = HotKeyDetector::new(3, 1024, 4, BuildSeaHasher);
+        for (key, count) in [(1u64, 5), (2, 3), (3, 7)] {
+            detector.record_weighted(&key, 0, count);
+        }
+        detector.record_weighted(&4, 0, 2);
+        detector.record_weighted(&2, 0, 3);
+        detector.record_weighted(&5, 0, 9);
+
+        let keys = detector
+            .hot_keys(0.0)
+            .iter()
+            .map(|hot| hot.key)
+            .collect::<Vec<_>>();
+        assert_eq!(keys, [5, 3, 2]);
+    }
+
+    #[test]
+    fn saturates_counts() {
+        let mut detector = HotKeyDetector::new(2, 64, 2, BuildSeaHasher);
+        detector.record_weighted(&"big", 0, u64::MAX);
+        detector.record_weighted(&"big", 1, 1);
+        assert_eq!(detector.estimated_count(&"big"), u64::MAX);
+        assert_eq!(detector.shard_load(0), u64::MAX);
+        assert_eq!(detector.total_load(), u64::MAX);
+    }
+}
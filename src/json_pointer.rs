@@ -0,0 +1,92 @@
+use crate::shard_with_hasher;
+use serde_json::Value;
+use std::hash::*;
+
+/// Error returned by [shard_for_json_pointer].
+#[derive(Debug)]
+pub enum JsonPointerError {
+    /// The input was not valid JSON.
+    InvalidJson(serde_json::Error),
+    /// The pointer did not match any value in the document.
+    NotFound,
+}
+
+impl std::fmt::Display for JsonPointerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JsonPointerError::InvalidJson(e) => write!(f, "invalid json: {e}"),
+            JsonPointerError::NotFound => write!(f, "json pointer did not match any value"),
+        }
+    }
+}
+
+impl std::error::Error for JsonPointerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            JsonPointerError::InvalidJson(e) => Some(e),
+            JsonPointerError::NotFound => None,
+        }
+    }
+}
+
+/// Returns the shard for the value at RFC 6901 `pointer` within `json`.
+///
+/// The value is serialized as canonical JSON (object keys sorted, no whitespace) before hashing, so formatting and key order in the request body do not affect the shard.
+pub fn shard_for_json_pointer(
+    json: &str,
+    pointer: &str,
+    total_destinations: u64,
+    hasher: &impl BuildHasher,
+) -> Result<u64, JsonPointerError> {
+    let document: Value = serde_json::from_str(json).map_err(JsonPointerError::InvalidJson)?;
+    let value = document
+        .pointer(pointer)
+        .ok_or(JsonPointerError::NotFound)?;
+
+    let key = canonicalize(value).to_string();
+    Ok(shard_with_hasher(key, total_destinations, hasher))
+}
+
+fn canonicalize(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries = map.iter().collect::<Vec<_>>();
+            entries.sort_unstable_by_key(|(k, _)| *k);
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| (k.clone(), canonicalize(v)))
+                    .collect(),
+            )
+        }
+        Value::Array(items) => Value::Array(items.iter().map(canonicalize).collect()),
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BuildSeaHasher;
+
+    #[test]
+    fn key_order_and_whitespace_are_ignored() {
+        let a = r#"{"user": {"id": 7, "org": "acme"}}"#;
+        let b = r#"{"user":{"org":"acme","id":7}}"#;
+
+        assert_eq!(
+            shard_for_json_pointer(a, "/user", 64, &BuildSeaHasher).unwrap(),
+            shard_for_json_pointer(b, "/user", 64, &BuildSeaHasher).unwrap(),
+        );
+        assert_eq!(
+            shard_for_json_pointer(a, "/user/id", 64, &BuildSeaHasher).unwrap(),
+            shard_with_hasher("7", 64, &BuildSeaHasher),
+        );
+    }
+
+    #[test]
+    fn missing_pointer_is_not_found() {
+        let result = shard_for_json_pointer(r#"{"a": 1}"#, "/b", 64, &BuildSeaHasher);
+        assert!(matches!(result, Err(JsonPointerError::NotFound)));
+    }
+}
@@ -0,0 +1,122 @@
+//! A [rdkafka](https://docs.rs/rdkafka) partitioner using Faro Sharding.
+//!
+//! librdkafka's default partitioners take the key hash modulo the partition count, so adding partitions to a topic reshuffles keys among the existing ones. [FaroPartitioner] only moves keys onto the new partitions.
+
+use crate::shard_bytes_with_hasher;
+use rdkafka::producer::{DeliveryResult, Partitioner, ProducerContext};
+use rdkafka::ClientContext;
+use std::hash::BuildHasher;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Partitions keyed messages with [shard_bytes_with_hasher].
+///
+/// A keyed message always goes to its key's partition, even when that partition has no leader, so per-key ordering is kept. Keyless messages round-robin over the available partitions. librdkafka only calls a custom partitioner for keyless messages when `sticky.partitioning.linger.ms` is 0.
+#[derive(Debug, Default)]
+pub struct FaroPartitioner<S> {
+    hasher: S,
+    next_keyless: AtomicUsize,
+}
+
+impl<S> FaroPartitioner<S> {
+    /// Creates a partitioner hashing keys with `hasher`.
+    pub fn new(hasher: S) -> Self {
+        FaroPartitioner {
+            hasher,
+            next_keyless: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl<S: BuildHasher> Partitioner for FaroPartitioner<S> {
+    fn partition(
+        &self,
+        _topic_name: &str,
+        key: Option<&[u8]>,
+        partition_cnt: i32,
+        is_partition_available: impl Fn(i32) -> bool,
+    ) -> i32 {
+        if partition_cnt <= 0 {
+            return -1;
+        }
+
+        match key {
+            Some(key) => shard_bytes_with_hasher(key, partition_cnt as u64, &self.hasher) as i32,
+            None => {
+                let start = self.next_keyless.fetch_add(1, Ordering::Relaxed);
+                (0..partition_cnt)
+                    .map(|i| ((start + i as usize) % partition_cnt as usize) as i32)
+                    .find(|&p| is_partition_available(p))
+                    .unwrap_or((start % partition_cnt as usize) as i32)
+            }
+        }
+    }
+}
+
+/// A [ProducerContext] that registers a [FaroPartitioner] and otherwise behaves like rdkafka's `DefaultProducerContext`.
+///
+/// ```no_run
+/// # use faro_sharding::kafka::FaroProducerContext;
+/// # use faro_sharding::seahash::StableSeaHasher;
+/// use rdkafka::config::ClientConfig;
+/// use rdkafka::producer::BaseProducer;
+///
+/// let producer: BaseProducer<_, _> = ClientConfig::new()
+///     .set("bootstrap.servers", "localhost:9092")
+///     .create_with_context(FaroProducerContext::new(StableSeaHasher::new()))
+///     .unwrap();
+/// ```
+#[derive(Debug, Default)]
+pub struct FaroProducerContext<S> {
+    partitioner: FaroPartitioner<S>,
+}
+
+impl<S> FaroProducerContext<S> {
+    /// Creates a context partitioning with `hasher`.
+    pub fn new(hasher: S) -> Self {
+        FaroProducerContext {
+            partitioner: FaroPartitioner::new(hasher),
+        }
+    }
+}
+
+impl<S: Send + Sync> ClientContext for FaroProducerContext<S> {}
+
+impl<S: BuildHasher + Send + Sync> ProducerContext<FaroPartitioner<S>> for FaroProducerContext<S> {
+    type DeliveryOpaque = ();
+
+    fn delivery(&self, _: &DeliveryResult<'_>, _: Self::DeliveryOpaque) {}
+
+    fn get_custom_partitioner(&self) -> Option<&FaroPartitioner<S>> {
+        Some(&self.partitioner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{shard_for_bytes, BuildSeaHasher};
+
+    #[test]
+    fn keyed_messages_use_shard_for_bytes() {
+        let partitioner = FaroPartitioner::new(BuildSeaHasher);
+        for key in [&b"foo"[..], b"bar", b""] {
+            assert_eq!(
+                partitioner.partition("topic", Some(key), 12, |_| false),
+                shard_for_bytes(key, 12) as i32
+            );
+        }
+        assert_eq!(
+            partitioner.partition("topic", Some(b"foo"), 0, |_| true),
+            -1
+        );
+    }
+
+    #[test]
+    fn keyless_messages_skip_unavailable_partitions() {
+        let partitioner = FaroPartitioner::new(BuildSeaHasher);
+        let partitions = (0..6)
+            .map(|_| partitioner.partition("topic", None, 3, |p| p != 1))
+            .collect::<Vec<_>>();
+        assert_eq!(partitions, [0, 2, 2, 0, 2, 2]);
+    }
+}
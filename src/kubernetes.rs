@@ -0,0 +1,77 @@
+use crate::shard_with_hasher;
+use std::hash::*;
+
+/// Returns the shard for a Kubernetes resource identified by kind, namespace, and name.
+///
+/// The key is `"kind/namespace/name"` lowercased, so `Pod` and `pod` route identically.
+pub fn shard_for_k8s_resource(
+    namespace: &str,
+    name: &str,
+    kind: &str,
+    total_destinations: u64,
+    hasher: &impl BuildHasher,
+) -> u64 {
+    let key = format!("{kind}/{namespace}/{name}").to_lowercase();
+    shard_with_hasher(key, total_destinations, hasher)
+}
+
+/// [crate::shard_for] for a label selector, ignoring the order of its requirements.
+///
+/// `app=web,tier=frontend` and `tier=frontend, app=web` land on the same shard.
+#[cfg(feature = "seahash")]
+pub fn shard_for_k8s_label_selector(selector: &str, total_destinations: u64) -> u64 {
+    crate::shard_for(normalize_label_selector(selector), total_destinations)
+}
+
+#[cfg(feature = "seahash")]
+fn normalize_label_selector(selector: &str) -> String {
+    let mut requirements = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in selector.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                requirements.push(&selector[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    requirements.push(&selector[start..]);
+
+    let mut requirements = requirements
+        .into_iter()
+        .map(str::trim)
+        .filter(|r| !r.is_empty())
+        .collect::<Vec<_>>();
+    requirements.sort_unstable();
+    requirements.join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resource_is_case_insensitive() {
+        let hasher = crate::BuildSeaHasher;
+        assert_eq!(
+            shard_for_k8s_resource("Default", "Web-0", "Pod", 64, &hasher),
+            shard_for_k8s_resource("default", "web-0", "pod", 64, &hasher),
+        );
+    }
+
+    #[test]
+    fn label_selector_ignores_order() {
+        assert_eq!(
+            normalize_label_selector("tier in (a,b), app=web"),
+            "app=web,tier in (a,b)"
+        );
+        assert_eq!(
+            shard_for_k8s_label_selector("app=web,tier=frontend", 64),
+            shard_for_k8s_label_selector("tier=frontend, app=web", 64),
+        );
+    }
+}
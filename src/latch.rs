@@ -0,0 +1,88 @@
+use crate::shard_with_hasher;
+use std::hash::*;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+
+/// One countdown latch per shard, for waiting on per-shard parallel work.
+#[derive(Clone, Debug)]
+pub struct ShardedCountdownLatch {
+    counters: Vec<Arc<AtomicI64>>,
+}
+
+impl ShardedCountdownLatch {
+    /// Creates a latch where every shard starts at `count_per_shard`.
+    ///
+    /// # Panics
+    ///
+    /// If total_shards == 0 or count_per_shard > i64::MAX.
+    pub fn new(total_shards: u64, count_per_shard: u64) -> Self {
+        assert_ne!(total_shards, 0, "total_shards must be > 0");
+        let count = i64::try_from(count_per_shard).expect("count_per_shard must fit in i64");
+
+        ShardedCountdownLatch {
+            counters: (0..total_shards)
+                .map(|_| Arc::new(AtomicI64::new(count)))
+                .collect(),
+        }
+    }
+
+    /// Counts down the latch of the shard for `key`.
+    pub fn count_down<K: Hash>(&self, key: K, hasher: &impl BuildHasher) {
+        let shard = shard_with_hasher(key, self.counters.len() as u64, hasher);
+        self.counters[shard as usize].fetch_sub(1, Ordering::AcqRel);
+    }
+
+    /// Spins until `shard`'s count reaches zero.
+    ///
+    /// # Panics
+    ///
+    /// If shard is out of range.
+    pub fn await_shard(&self, shard: u64) {
+        let counter = &self.counters[shard as usize];
+        while counter.load(Ordering::Acquire) > 0 {
+            std::hint::spin_loop();
+        }
+    }
+
+    /// Whether every shard's count has reached zero.
+    pub fn all_done(&self) -> bool {
+        self.counters.iter().all(|c| c.load(Ordering::Acquire) <= 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BuildSeaHasher;
+
+    #[test]
+    fn releases_when_every_shard_counts_down() {
+        let latch = ShardedCountdownLatch::new(4, 3);
+        let keys_for = |shard| {
+            (0..)
+                .filter(move |key| shard_with_hasher(key, 4, &BuildSeaHasher) == shard)
+                .take(3)
+        };
+
+        for key in keys_for(0) {
+            latch.count_down(key, &BuildSeaHasher);
+        }
+        latch.await_shard(0);
+        assert!(!latch.all_done());
+
+        std::thread::scope(|s| {
+            for shard in 1..4 {
+                let latch = &latch;
+                s.spawn(move || {
+                    for key in keys_for(shard) {
+                        latch.count_down(key, &BuildSeaHasher);
+                    }
+                });
+            }
+            for shard in 1..4 {
+                latch.await_shard(shard);
+            }
+        });
+        assert!(latch.all_done());
+    }
+}
@@ -1,3 +1,4 @@
+#![cfg_attr(not(feature = "std"), no_std)]
 #![deny(missing_docs)]
 
 //! Faro Sharding is a technique for sharding keys such that adding new destinations does not move data between existing destinations. Only between existing and the new destination.
@@ -17,43 +18,554 @@
 //!
 //! Faro Sharding shows high quality even distribution among locations. With 1,000,000 keys and 100 locations, the largest has 10229 keys (1.02%) and the smallest has 9761 keys (0.98%).
 //!
-//! If you want to evaluate the distribution with your own hashing function, you can modify `examples/distribution.rs` to use your hasher.
+//! At small destination counts such as 8 to 16, the largest and smallest shards still differ by a few percent. That is sampling noise rather than bias: every shard receives exactly `1/N` of keys in expectation, so the counts vary like any uniform random assignment, about `sqrt(keys / N)` keys per shard. No rule that places each key on its own can do better, including probing several candidates per key; we measured multi-probe variants at 8, 12 and 16 destinations with no improvement. To even out load rather than key counts, use [shard_with_bounded_load] with current loads, or [WeightedSharder] with weights from [stats::recommend_weights].
+//!
+//! If you want to evaluate the distribution with your own hashing function, you can modify `examples/distribution.rs` to use your hasher. Enable the `compare` feature to report JumpHash and rendezvous hashing side by side, using the reference implementations in `faro_sharding::compare`. Pass `--input keys.txt` (or `--input -` for stdin) to measure real keys, and `--format json` or `--format csv` to chart the results.
 //!
 //! # Algorithm
 //!
 //! Inspired by [JumpHash](https://arxiv.org/abs/1406.2294), Faro Sharding sequentially hashes the initial key and then the results of those hashes. Different from JumpHash, Faro Sharding only changes the shard for a key when `hash % i == 0`.
 //!
 //! Faro Sharding can only move a key to a shard on the step corresponding to that shard number. That means that the only movement happens to shard N on step N. Additionally, approximately 1/N of keys are moved on step N, resulting in approximately even distribution.
+//!
+//! # no_std
+//!
+//! The core functions such as [shard_with_hasher] only need `core`. To use them without std, disable default features and re-enable `seahash` if wanted. Types that allocate or lock require the `std` feature.
+//!
+//! The core functions never allocate. For bulk routing with a fixed memory budget, [shard_into] and [shard_key_hashes_into] write shards into a caller-provided buffer.
 
-use std::hash::*;
+use core::hash::*;
 
 #[cfg(feature = "seahash")]
-mod with_seahash {
-    use crate::shard_with_hasher;
-    use seahash::*;
-    use std::hash::*;
+pub mod seahash {
+    //! The default hasher, used by [shard_for] and the other functions without a hasher parameter.
+
+    use crate::{shard_with_hasher, try_shard_with_hasher, ShardError};
+    use ::seahash::SeaHasher;
+    use core::hash::*;
 
-    /// [shard_with_hasher] using [SeaHasher].
+    /// [shard_with_hasher] using [StableSeaHasher].
     pub fn shard_for(key: impl Hash, total_destinations: u64) -> u64 {
-        shard_with_hasher(key, total_destinations, &BuildSeaHasher)
+        shard_with_hasher(key, total_destinations, &StableSeaHasher::new())
     }
 
-    struct BuildSeaHasher;
+    /// [shard_for] that returns an error instead of panicking.
+    pub fn try_shard_for(key: impl Hash, total_destinations: u64) -> Result<u64, ShardError> {
+        try_shard_with_hasher(key, total_destinations, &StableSeaHasher::new())
+    }
 
-    impl BuildHasher for BuildSeaHasher {
+    /// [BuildHasher] for [SeaHasher] with fixed seeds.
+    ///
+    /// [StableSeaHasher::new] is exactly the hasher used by [shard_for], and its output will not change between versions of this crate.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub struct StableSeaHasher {
+        seeds: [u64; 4],
+    }
+
+    impl StableSeaHasher {
+        /// The hasher used by [shard_for], with SeaHash's default seeds.
+        pub const fn new() -> Self {
+            Self::with_seeds([
+                0x16f11fe89b0d677c,
+                0xb480a793d8e6c86c,
+                0x6fe2e5aaf078ebc9,
+                0x14f994a4c5259381,
+            ])
+        }
+
+        /// A hasher with custom seeds, giving an independent mapping.
+        pub const fn with_seeds(seeds: [u64; 4]) -> Self {
+            StableSeaHasher { seeds }
+        }
+    }
+
+    impl Default for StableSeaHasher {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl BuildHasher for StableSeaHasher {
         type Hasher = SeaHasher;
 
         fn build_hasher(&self) -> Self::Hasher {
-            SeaHasher::new()
+            let [k1, k2, k3, k4] = self.seeds;
+            SeaHasher::with_seeds(k1, k2, k3, k4)
         }
     }
+
+    impl crate::StableBuildHasher for StableSeaHasher {}
 }
 #[cfg(feature = "seahash")]
-pub use with_seahash::shard_for;
+pub use self::seahash::{shard_for, try_shard_for};
+
+/// Tests predate [seahash::StableSeaHasher] and refer to the default hasher by this name, as both a type and a value.
+#[cfg(all(test, feature = "seahash"))]
+type BuildSeaHasher = seahash::StableSeaHasher;
+#[cfg(all(test, feature = "seahash"))]
+#[allow(non_upper_case_globals)]
+const BuildSeaHasher: BuildSeaHasher = seahash::StableSeaHasher::new();
+
+mod affinity;
+#[cfg(feature = "seahash")]
+pub use affinity::shard_by_affinity;
+pub use affinity::{shard_by_affinity_with_hasher, AffinityKey, HashTag};
+
+mod algorithm;
+pub use algorithm::{Algorithm, Sharder, SharderBuilder, UnknownAlgorithm};
+
+#[cfg(feature = "std")]
+pub mod audit;
+
+#[cfg(feature = "axum")]
+pub mod axum;
+
+mod batch;
+#[cfg(feature = "std")]
+pub use batch::{group_by_shard, group_by_shard_chunks, shard_for_many_vec};
+pub use batch::{shard_for_many, shard_into, shard_key_hashes_into};
+
+#[cfg(feature = "std")]
+mod batch_minimized;
+#[cfg(feature = "std")]
+pub use batch_minimized::shard_for_batch_minimized;
+
+#[cfg(feature = "blake3")]
+pub mod blake3;
+
+#[cfg(feature = "std")]
+mod bounded;
+#[cfg(feature = "std")]
+pub use bounded::shard_with_bounded_load;
+
+mod breakpoints;
+pub use breakpoints::{first_count_on_shard, shard_breakpoints, ShardBreakpoints};
+
+mod bytes;
+pub use bytes::shard_bytes_with_hasher;
+#[cfg(feature = "seahash")]
+pub use bytes::shard_for_bytes;
+#[cfg(all(feature = "std", feature = "seahash"))]
+pub use bytes::shard_for_reader;
+#[cfg(feature = "std")]
+pub use bytes::shard_reader_with_hasher;
+
+#[cfg(feature = "std")]
+mod cached;
+#[cfg(feature = "std")]
+pub use cached::CachedSharder;
+
+mod cdc;
+pub use cdc::{shard_for_cdc, shard_for_cdc_str_pk};
+
+#[cfg(feature = "std")]
+mod channel;
+#[cfg(feature = "std")]
+pub use channel::ShardedSender;
+
+#[cfg(feature = "compare")]
+pub mod compare;
+
+mod count;
+#[cfg(feature = "seahash")]
+pub use count::{shard_for_u32, shard_for_usize};
+pub use count::{shard_with_hasher_as, DestinationCount};
+
+mod counter;
+pub use counter::{aggregate_counter_shards, counter_shard_for, counter_shard_for_request};
+
+#[cfg(feature = "std")]
+mod decision_log;
+#[cfg(feature = "std")]
+pub use decision_log::{DecisionEntry, ShardingDecisionLog};
+
+#[cfg(feature = "discovery")]
+pub mod discovery;
+
+#[cfg(feature = "arc-swap")]
+mod dynamic;
+#[cfg(feature = "arc-swap")]
+pub use dynamic::{DestinationSet, DynamicSharder, Routed};
+
+#[cfg(feature = "std")]
+mod email;
+#[cfg(all(feature = "std", feature = "seahash"))]
+pub use email::shard_for_email;
+#[cfg(feature = "std")]
+pub use email::{shard_email_with_hasher, EmailError};
+
+mod error;
+pub use error::ShardError;
+
+#[cfg(feature = "std")]
+mod event_sourcing;
+#[cfg(feature = "std")]
+pub use event_sourcing::ShardedEventSourcing;
+
+#[cfg(feature = "std")]
+mod executor;
+#[cfg(feature = "std")]
+pub use executor::ShardedExecutor;
+
+#[cfg(feature = "std")]
+pub mod experiments;
+
+#[cfg(feature = "std")]
+mod explain;
+#[cfg(feature = "std")]
+pub use explain::{explain_shard, ExplainStep, ShardExplanation};
+
+#[cfg(feature = "serde")]
+mod format_version;
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+#[cfg(feature = "fnv")]
+pub mod fnv;
+
+#[cfg(feature = "frozen")]
+pub mod frozen;
+
+#[cfg(feature = "std")]
+pub mod fs;
+
+#[cfg(feature = "std")]
+mod fuse_guard;
+#[cfg(feature = "std")]
+pub use fuse_guard::ShardingFuseGuard;
+
+#[cfg(feature = "std")]
+mod gauge;
+#[cfg(feature = "std")]
+pub use gauge::ShardedGauge;
+
+#[cfg(all(feature = "std", feature = "seahash"))]
+pub mod global;
+
+#[cfg(feature = "std")]
+pub mod golden;
+
+mod hash128;
+pub use hash128::{shard_with_hasher128, Hasher128};
+
+#[cfg(any(
+    feature = "seahash",
+    feature = "frozen",
+    feature = "xxhash",
+    feature = "fnv",
+    feature = "blake3"
+))]
+mod hasher_kind;
+#[cfg(any(
+    feature = "seahash",
+    feature = "frozen",
+    feature = "xxhash",
+    feature = "fnv",
+    feature = "blake3"
+))]
+pub use hasher_kind::{HasherKind, KindHasher, UnknownHasher};
+
+#[cfg(all(feature = "std", feature = "seahash"))]
+pub mod hashring;
+
+#[cfg(feature = "std")]
+mod hasher_benchmark;
+#[cfg(feature = "std")]
+pub use hasher_benchmark::{HasherBenchmarkResult, ShardingHasherBenchmark};
+
+#[cfg(feature = "std")]
+mod hierarchical;
+#[cfg(feature = "std")]
+pub use hierarchical::HierarchicalSharder;
+
+#[cfg(feature = "std")]
+mod hot_keys;
+#[cfg(feature = "std")]
+pub use hot_keys::{HotKey, HotKeyDetector};
+
+#[cfg(feature = "serde_json")]
+mod json_pointer;
+#[cfg(feature = "serde_json")]
+pub use json_pointer::{shard_for_json_pointer, JsonPointerError};
+
+#[cfg(feature = "kafka")]
+pub mod kafka;
+
+#[cfg(feature = "std")]
+mod kubernetes;
+#[cfg(all(feature = "std", feature = "seahash"))]
+pub use kubernetes::shard_for_k8s_label_selector;
+#[cfg(feature = "std")]
+pub use kubernetes::shard_for_k8s_resource;
+
+#[cfg(feature = "std")]
+mod latch;
+#[cfg(feature = "std")]
+pub use latch::ShardedCountdownLatch;
+
+#[cfg(feature = "std")]
+mod load;
+#[cfg(feature = "std")]
+pub use load::{LoadProvider, ShardLoadEstimator};
+
+#[cfg(feature = "std")]
+mod load_test;
+#[cfg(feature = "std")]
+pub use load_test::{LoadTestSnapshot, ShardingLoadTest};
+
+mod mac;
+pub use mac::{parse_mac, shard_mac_with_hasher, MacError};
+#[cfg(feature = "seahash")]
+pub use mac::{shard_for_mac, shard_for_mac_str};
+
+mod merkle;
+pub use merkle::shard_for_merkle_path;
+
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "metrics")]
+use self::metrics::observe_lookup;
+#[cfg(not(feature = "metrics"))]
+#[inline(always)]
+fn observe_lookup(_component: &'static str, lookup: impl FnOnce() -> u64) -> u64 {
+    lookup()
+}
+
+#[cfg(feature = "std")]
+pub mod migration;
+
+mod mix;
+pub use mix::{
+    shard_for_const, shard_for_key_hash_with_mixer, shard_key_hashes_with_mixer, shard_with_mixer,
+    Mix64, SplitMix64,
+};
+
+mod moves;
+#[cfg(feature = "seahash")]
+pub use moves::shard_for_shrinking;
+pub use moves::{
+    expected_moved_fraction, measured_moved_fraction, moves_between, shard_pair,
+    shard_with_hasher_shrinking, shards_between, ShardPair,
+};
+
+#[cfg(feature = "object_store")]
+pub mod object_store;
+
+#[cfg(feature = "std")]
+mod overrides;
+#[cfg(feature = "std")]
+pub use overrides::{OverrideConflict, Overrides};
+
+#[cfg(feature = "rayon")]
+mod parallel;
+#[cfg(feature = "rayon")]
+pub use parallel::{par_group_by_shard, par_shards};
+
+#[cfg(feature = "std")]
+mod pool;
+#[cfg(feature = "std")]
+pub use pool::{PooledConnection, ShardedPool};
+
+mod pow2;
+pub use pow2::shard_for_pow2_fast;
+
+#[cfg(feature = "std")]
+mod priority_queue;
+#[cfg(feature = "std")]
+pub use priority_queue::ShardedPriorityQueue;
+
+mod random;
+pub use random::random_shard;
+
+mod range;
+pub use range::{KeyPrefix, RangeSharder};
+
+pub mod redis;
+
+#[cfg(feature = "std")]
+mod registry;
+#[cfg(feature = "std")]
+pub use registry::{RegistryError, ShardingConfig, ShardingRegistry};
+
+#[cfg(feature = "std")]
+mod proxy;
+#[cfg(feature = "std")]
+pub use proxy::ShardingProxy;
+
+mod sample;
+#[cfg(feature = "seahash")]
+pub use sample::in_sample;
+pub use sample::{in_sample_with_hasher, PPM};
+
+mod seeded;
+#[cfg(feature = "seahash")]
+pub use seeded::shard_for_seeded;
+pub use seeded::shard_with_hasher_seeded;
+
+#[cfg(feature = "std")]
+mod reverse;
+#[cfg(feature = "std")]
+pub use reverse::keys_for_shard;
+
+#[cfg(feature = "roaring")]
+pub mod roaring;
+
+#[cfg(feature = "std")]
+mod routing_table;
+#[cfg(feature = "std")]
+pub use routing_table::{RoutingTable, RoutingTableError, RoutingTableView};
+
+#[cfg(feature = "std")]
+mod router;
+#[cfg(feature = "std")]
+pub use router::{PoolRouter, ShardRouter};
+
+mod shard_id;
+pub use shard_id::{shard_id_with_hasher, FixedShardId, ShardId};
+
+#[cfg(feature = "std")]
+mod shard_key;
+/// Derives [ShardKey]. See [ShardKey] for the encoding and attributes.
+#[cfg(feature = "derive")]
+pub use faro_sharding_derive::ShardKey;
+#[cfg(all(feature = "std", feature = "seahash"))]
+pub use shard_key::shard_for_shard_key;
+#[cfg(feature = "std")]
+pub use shard_key::{shard_key_bytes, shard_key_with_hasher, ShardKey, SHARD_KEY_ENCODING_VERSION};
+
+#[cfg(feature = "std")]
+mod shard_map;
+#[cfg(feature = "std")]
+pub use shard_map::ShardMap;
+
+#[cfg(feature = "std")]
+pub mod simulate;
+
+mod skip_ahead;
+pub use skip_ahead::shard_with_hasher_fast;
+
+#[cfg(feature = "std")]
+mod slots;
+
+#[cfg(feature = "std")]
+mod split;
+#[cfg(feature = "std")]
+pub use split::{SplitNode, SplitTopology};
+
+mod stability;
+pub use stability::{verify_stability, verify_strategy_stability, StabilityViolation};
+
+mod stable;
+pub use stable::{shard_with_hasher_unchecked, shard_with_stable_hasher, StableBuildHasher};
+
+mod stable_key;
+#[cfg(feature = "seahash")]
+pub use stable_key::shard_for_stable_key;
+pub use stable_key::{shard_stable_key_with_hasher, StableKey};
+
+mod state;
+pub use state::ShardState;
+
+#[cfg(feature = "std")]
+pub mod stats;
+
+#[cfg(feature = "std")]
+mod replicas;
+#[cfg(all(feature = "std", feature = "seahash"))]
+pub use replicas::{least_loaded_for, shard_for_available, shards_for};
+#[cfg(feature = "std")]
+pub use replicas::{
+    least_loaded_with_hasher, shard_with_hasher_available, shards_with_domains, shards_with_hasher,
+};
+
+#[cfg(feature = "std")]
+mod ring;
+#[cfg(feature = "std")]
+pub use ring::ShardRing;
+
+mod scheme;
+pub use scheme::ShardingScheme;
+
+#[cfg(feature = "semver")]
+mod semver;
+#[cfg(all(feature = "semver", feature = "seahash"))]
+pub use crate::semver::shard_for_semver;
+#[cfg(feature = "semver")]
+pub use crate::semver::{shard_semver_with_hasher, SemverError};
+
+#[cfg(feature = "sqlx")]
+pub mod sqlx;
+
+#[cfg(feature = "std")]
+mod store;
+#[cfg(feature = "std")]
+pub use store::{MemoryStore, RepairHook, ShardedStore};
+
+mod strategy;
+pub use strategy::{FaroStrategy, ShardingStrategy};
+
+#[cfg(feature = "std")]
+mod tenant;
+#[cfg(feature = "std")]
+pub use tenant::TenantSharder;
+
+#[cfg(feature = "proptest-support")]
+pub mod testing;
+
+mod truncated;
+pub use truncated::{shard_for_16bit, shard_for_8bit};
+
+#[cfg(feature = "std")]
+mod tombstone;
+#[cfg(feature = "std")]
+pub use tombstone::{Assignment, DestinationState, TombstoneTopology};
+
+#[cfg(feature = "std")]
+mod topology;
+#[cfg(feature = "std")]
+pub use topology::{EpochDiff, Topology, TopologyChange};
+
+#[cfg(feature = "std")]
+mod time_bucket;
+#[cfg(feature = "std")]
+pub use time_bucket::TimeBucketSharder;
+
+#[cfg(feature = "tokio")]
+pub mod tokio;
+
+#[cfg(feature = "tonic")]
+pub mod tonic;
+
+#[cfg(feature = "tower")]
+pub mod tower;
+
+#[cfg(feature = "std")]
+mod trie;
+#[cfg(feature = "std")]
+pub use trie::ShardedTrie;
+
+pub mod v2;
+
+#[cfg(feature = "std")]
+mod virtual_shards;
+#[cfg(feature = "std")]
+pub use virtual_shards::VirtualShards;
+
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(feature = "std")]
+mod weighted;
+#[cfg(feature = "std")]
+pub use weighted::WeightedSharder;
+
+#[cfg(feature = "xxhash")]
+pub mod xxh3;
 
 /// Returns the index of the shard for the provided key using the hasher provided.
 ///
-/// If you want a stable output, your hasher must return the exact same hash given the same input. Do not use a hasher with random state.
+/// If you want a stable output, your hasher must return the exact same hash given the same input. Do not use a hasher with random state. This is not checked; [shard_with_stable_hasher] checks it at compile time with [StableBuildHasher].
 ///
 /// # Panics
 ///
@@ -63,18 +575,60 @@ pub fn shard_with_hasher(
     total_destinations: u64,
     hasher: &impl BuildHasher,
 ) -> u64 {
-    let mut final_shard = 0;
-    let mut last_hash = hasher.hash_one(key);
+    shard_for_key_hash(hasher.hash_one(key), total_destinations, hasher)
+}
 
-    assert_ne!(total_destinations, 0, "total_destinations must be > 0");
+/// [shard_with_hasher] using a fresh `H` for every hash, via [BuildHasherDefault].
+///
+/// For deterministic hashers that implement [Default], such as `FnvHasher` or `XxHash64`, without writing a [BuildHasher] for them.
+///
+/// ```
+/// # use faro_sharding::*;
+/// # use std::hash::BuildHasherDefault;
+/// # use std::collections::hash_map::DefaultHasher;
+/// assert_eq!(
+///     shard_with_default_hasher::<DefaultHasher>("foo", 50),
+///     shard_with_hasher("foo", 50, &BuildHasherDefault::<DefaultHasher>::default()),
+/// );
+/// ```
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub fn shard_with_default_hasher<H: Hasher + Default>(
+    key: impl Hash,
+    total_destinations: u64,
+) -> u64 {
+    shard_with_hasher(key, total_destinations, &BuildHasherDefault::<H>::default())
+}
 
-    for n in 1..total_destinations {
-        let hash = hasher.hash_one(last_hash);
-        if hash % (n + 1) == 0 {
-            final_shard = n;
-        }
-        last_hash = hash;
+/// [shard_with_hasher] that returns an error instead of panicking.
+pub fn try_shard_with_hasher(
+    key: impl Hash,
+    total_destinations: u64,
+    hasher: &impl BuildHasher,
+) -> Result<u64, ShardError> {
+    if total_destinations == 0 {
+        return Err(ShardError::ZeroDestinations);
     }
+    Ok(shard_with_hasher(key, total_destinations, hasher))
+}
+
+/// Continues the hash chain of [shard_with_hasher] from an already computed hash of the key.
+///
+/// `shard_for_key_hash(hasher.hash_one(key), n, hasher)` is always equal to `shard_with_hasher(key, n, hasher)`.
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub fn shard_for_key_hash(
+    key_hash: u64,
+    total_destinations: u64,
+    hasher: &impl BuildHasher,
+) -> u64 {
+    assert_ne!(total_destinations, 0, "total_destinations must be > 0");
+
+    let final_shard = ShardState::from_key_hash(key_hash).extend_to(total_destinations, hasher);
 
     debug_assert!(final_shard < total_destinations);
     final_shard
@@ -102,7 +656,7 @@ mod tests {
             let base_shard = shard_for(&base, locations);
 
             for n in 0..1024 {
-                let shard = shard_for(&format!("{base}-{n}"), locations);
+                let shard = shard_for(format!("{base}-{n}"), locations);
                 if shard != base_shard {
                     return Ok(());
                 }
@@ -111,6 +665,12 @@ mod tests {
         }
     }
 
+    #[test]
+    fn try_shard_for_reports_zero_destinations() {
+        assert_eq!(try_shard_for("foo", 0), Err(ShardError::ZeroDestinations));
+        assert_eq!(try_shard_for("foo", 73), Ok(shard_for("foo", 73)));
+    }
+
     #[test]
     fn pinning_default_shard() {
         // It is critical that the default `shard_for` implementation always returns the same shards for correctness of our user's systems. Do not remove or change the values here. Adding new entries is ok.
@@ -124,13 +684,28 @@ mod tests {
 
         for (key, expected_shard) in shards {
             assert_eq!(
-                shard_for(&key, 73),
+                shard_for(key, 73),
                 expected_shard,
                 "Incorrect shard for {key}"
             );
         }
     }
 
+    #[test]
+    fn pinning_stable_sea_hasher() {
+        // Do not change these values. See `pinning_default_shard`.
+        let default = seahash::StableSeaHasher::new();
+        assert_eq!(
+            default.hash_one("foo"),
+            BuildHasherDefault::<::seahash::SeaHasher>::default().hash_one("foo")
+        );
+        assert_eq!(shard_with_hasher("foo", 73, &default), 49);
+
+        let seeded = seahash::StableSeaHasher::with_seeds([1, 2, 3, 4]);
+        assert_eq!(shard_with_hasher("foo", 73, &seeded), 58);
+        assert_eq!(shard_with_hasher("bar", 73, &seeded), 44);
+    }
+
     #[test]
     fn distributes_to_every_shard() {
         let locations = 16;
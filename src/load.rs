@@ -0,0 +1,101 @@
+use crate::shard_with_hasher;
+use std::hash::*;
+
+/// A source of current per-shard load, used to steer assignments away from busy shards.
+pub trait LoadProvider {
+    /// Returns the current load of `shard`, in whatever unit the provider measures.
+    fn load(&self, shard: u64) -> u64;
+}
+
+/// Estimates per-shard request counts with a count-min sketch.
+///
+/// Estimates never undercount. They may overcount when shards collide in every row of the sketch, which becomes unlikely as `sketch_width` and `sketch_depth` grow.
+pub struct ShardLoadEstimator<S> {
+    total_destinations: u64,
+    width: usize,
+    counters: Vec<Vec<u64>>,
+    hasher: S,
+}
+
+impl<S: BuildHasher> ShardLoadEstimator<S> {
+    /// Creates an estimator with `sketch_depth` rows of `sketch_width` counters.
+    ///
+    /// # Panics
+    ///
+    /// If any argument is 0.
+    pub fn new(
+        total_destinations: u64,
+        sketch_width: usize,
+        sketch_depth: usize,
+        hasher: S,
+    ) -> Self {
+        assert_ne!(total_destinations, 0, "total_destinations must be > 0");
+        assert_ne!(sketch_width, 0, "sketch_width must be > 0");
+        assert_ne!(sketch_depth, 0, "sketch_depth must be > 0");
+
+        ShardLoadEstimator {
+            total_destinations,
+            width: sketch_width,
+            counters: vec![vec![0; sketch_width]; sketch_depth],
+            hasher,
+        }
+    }
+
+    /// Records one request for `key` against its shard.
+    pub fn record_request<K: Hash>(&mut self, key: &K) {
+        let shard = shard_with_hasher(key, self.total_destinations, &self.hasher);
+        for row in 0..self.counters.len() {
+            let column = self.column(row, shard);
+            self.counters[row][column] += 1;
+        }
+    }
+
+    /// Returns the estimated number of requests recorded for `shard`.
+    pub fn estimated_load_for_shard(&self, shard: u64) -> u64 {
+        (0..self.counters.len())
+            .map(|row| self.counters[row][self.column(row, shard)])
+            .min()
+            .unwrap_or_default()
+    }
+
+    fn column(&self, row: usize, shard: u64) -> usize {
+        (self.hasher.hash_one((row, shard)) % self.width as u64) as usize
+    }
+}
+
+impl<S: BuildHasher> LoadProvider for ShardLoadEstimator<S> {
+    fn load(&self, shard: u64) -> u64 {
+        self.estimated_load_for_shard(shard)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BuildSeaHasher;
+
+    #[test]
+    fn never_undercounts() {
+        let mut estimator = ShardLoadEstimator::new(32, 16, 4, BuildSeaHasher);
+        let mut exact = [0; 32];
+        for i in 0..10_000u64 {
+            estimator.record_request(&(i % 500));
+            exact[shard_with_hasher(i % 500, 32, &BuildSeaHasher) as usize] += 1;
+        }
+
+        for (shard, &count) in exact.iter().enumerate() {
+            assert!(estimator.load(shard as u64) >= count);
+        }
+    }
+
+    #[test]
+    fn wide_sketch_is_exact() {
+        let mut estimator = ShardLoadEstimator::new(4, 1024, 4, BuildSeaHasher);
+        for _ in 0..7 {
+            estimator.record_request(&"foo");
+        }
+
+        let shard = shard_with_hasher("foo", 4, &BuildSeaHasher);
+        assert_eq!(estimator.estimated_load_for_shard(shard), 7);
+    }
+}
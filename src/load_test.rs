@@ -0,0 +1,127 @@
+use crate::{shard_with_hasher, stats};
+use std::hash::*;
+
+/// Distribution measurements taken partway through a [ShardingLoadTest].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LoadTestSnapshot {
+    /// Number of keys sharded when the snapshot was taken.
+    pub iteration: u64,
+    /// See [stats::imbalance_ratio].
+    pub imbalance_ratio: f64,
+    /// See [stats::entropy_efficiency].
+    pub entropy_efficiency: f64,
+}
+
+/// Shards a long stream of generated keys and records how the distribution evolves.
+pub struct ShardingLoadTest<S, F> {
+    hasher: S,
+    key_generator: F,
+    shard_counts: Vec<u64>,
+    iteration: u64,
+    snapshots: Vec<LoadTestSnapshot>,
+}
+
+impl<S, F, K> ShardingLoadTest<S, F>
+where
+    S: BuildHasher,
+    F: Fn(u64) -> K,
+    K: Hash,
+{
+    /// Creates a load test where `key_generator(i)` produces the `i`th key.
+    ///
+    /// # Panics
+    ///
+    /// If total_destinations == 0.
+    pub fn new(total_destinations: u64, hasher: S, key_generator: F) -> Self {
+        assert_ne!(total_destinations, 0, "total_destinations must be > 0");
+
+        ShardingLoadTest {
+            hasher,
+            key_generator,
+            shard_counts: vec![0; total_destinations as usize],
+            iteration: 0,
+            snapshots: Vec::new(),
+        }
+    }
+
+    /// Shards `iterations` more keys, taking a snapshot after every `report_every` keys.
+    ///
+    /// Returns the snapshots taken during this call. Calling `run` again continues where the last call stopped.
+    ///
+    /// # Panics
+    ///
+    /// If report_every == 0.
+    pub fn run(&mut self, iterations: u64, report_every: u64) -> Vec<LoadTestSnapshot> {
+        assert_ne!(report_every, 0, "report_every must be > 0");
+
+        let first_new = self.snapshots.len();
+        let total_destinations = self.shard_counts.len() as u64;
+        for _ in 0..iterations {
+            let key = (self.key_generator)(self.iteration);
+            let shard = shard_with_hasher(key, total_destinations, &self.hasher);
+            self.shard_counts[shard as usize] += 1;
+            self.iteration += 1;
+
+            if self.iteration.is_multiple_of(report_every) {
+                self.snapshots.push(LoadTestSnapshot {
+                    iteration: self.iteration,
+                    imbalance_ratio: stats::imbalance_ratio(&self.shard_counts),
+                    entropy_efficiency: stats::entropy_efficiency(&self.shard_counts),
+                });
+            }
+        }
+
+        self.snapshots[first_new..].to_vec()
+    }
+
+    /// All snapshots taken so far.
+    pub fn snapshots(&self) -> &[LoadTestSnapshot] {
+        &self.snapshots
+    }
+
+    /// Checks every snapshot taken so far against the given bounds.
+    ///
+    /// # Panics
+    ///
+    /// If any snapshot has an imbalance ratio above `max_imbalance` or an entropy efficiency below `min_entropy`.
+    pub fn assert_bounds(&self, max_imbalance: f64, min_entropy: f64) {
+        for snapshot in &self.snapshots {
+            assert!(
+                snapshot.imbalance_ratio <= max_imbalance,
+                "imbalance ratio {} exceeds {max_imbalance} at iteration {}",
+                snapshot.imbalance_ratio,
+                snapshot.iteration
+            );
+            assert!(
+                snapshot.entropy_efficiency >= min_entropy,
+                "entropy efficiency {} below {min_entropy} at iteration {}",
+                snapshot.entropy_efficiency,
+                snapshot.iteration
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BuildSeaHasher;
+
+    #[test]
+    fn distribution_stays_within_bounds() {
+        let mut test = ShardingLoadTest::new(16, BuildSeaHasher, |i| i);
+        let snapshots = test.run(20_000, 5_000);
+
+        assert_eq!(snapshots.len(), 4);
+        assert_eq!(snapshots[3].iteration, 20_000);
+        test.assert_bounds(1.2, 0.99);
+    }
+
+    #[test]
+    #[should_panic(expected = "imbalance ratio")]
+    fn panics_when_out_of_bounds() {
+        let mut test = ShardingLoadTest::new(16, BuildSeaHasher, |_| "same key");
+        test.run(100, 100);
+        test.assert_bounds(1.2, 0.99);
+    }
+}
@@ -0,0 +1,108 @@
+use crate::shard_bytes_with_hasher;
+use core::hash::*;
+
+/// Error returned when parsing a MAC address string.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MacError {
+    /// The string is not six two-digit groups separated consistently by `:` or `-`.
+    InvalidFormat,
+    /// A group contains a character that is not a hex digit.
+    InvalidHex,
+}
+
+impl core::fmt::Display for MacError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            MacError::InvalidFormat => write!(f, "mac address must look like aa:bb:cc:dd:ee:ff"),
+            MacError::InvalidHex => write!(f, "mac address contains invalid hex"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MacError {}
+
+/// [crate::shard_for] for a MAC address. See [shard_mac_with_hasher].
+#[cfg(feature = "seahash")]
+pub fn shard_for_mac(mac: &[u8; 6], total_destinations: u64) -> u64 {
+    shard_mac_with_hasher(
+        mac,
+        total_destinations,
+        &crate::seahash::StableSeaHasher::new(),
+    )
+}
+
+/// [shard_for_mac] for a MAC address written as `aa:bb:cc:dd:ee:ff` or `aa-bb-cc-dd-ee-ff`.
+#[cfg(feature = "seahash")]
+pub fn shard_for_mac_str(mac_str: &str, total_destinations: u64) -> Result<u64, MacError> {
+    Ok(shard_for_mac(&parse_mac(mac_str)?, total_destinations))
+}
+
+/// Returns the shard for a MAC address.
+///
+/// The six bytes are written to the hasher exactly as they appear on the wire, without the length prefix `Hash` adds for slices.
+pub fn shard_mac_with_hasher(
+    mac: &[u8; 6],
+    total_destinations: u64,
+    hasher: &impl BuildHasher,
+) -> u64 {
+    shard_bytes_with_hasher(mac, total_destinations, hasher)
+}
+
+/// Parses a colon- or hyphen-separated MAC address.
+pub fn parse_mac(mac_str: &str) -> Result<[u8; 6], MacError> {
+    let separator = if mac_str.contains(':') { ':' } else { '-' };
+
+    let mut mac = [0; 6];
+    let mut groups = mac_str.split(separator);
+    for byte in &mut mac {
+        let group = groups.next().ok_or(MacError::InvalidFormat)?;
+        if group.len() != 2 {
+            return Err(MacError::InvalidFormat);
+        }
+        if !group.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(MacError::InvalidHex);
+        }
+        *byte = u8::from_str_radix(group, 16).map_err(|_| MacError::InvalidHex)?;
+    }
+
+    if groups.next().is_some() {
+        return Err(MacError::InvalidFormat);
+    }
+    Ok(mac)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pinning_mac_shards() {
+        // Do not change these values. See `pinning_default_shard`.
+        assert_eq!(shard_for_mac(&[0x00, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e], 73), 16);
+        assert_eq!(shard_for_mac(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff], 73), 38);
+    }
+
+    #[test]
+    fn parses_both_separators() {
+        let expected = Ok([0x00, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e]);
+        assert_eq!(parse_mac("00:1a:2b:3c:4d:5e"), expected);
+        assert_eq!(parse_mac("00-1A-2B-3C-4D-5E"), expected);
+        assert_eq!(
+            shard_for_mac_str("00:1a:2b:3c:4d:5e", 73),
+            expected.map(|mac| shard_for_mac(&mac, 73))
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_strings() {
+        assert_eq!(parse_mac("00:1a:2b:3c:4d"), Err(MacError::InvalidFormat));
+        assert_eq!(
+            parse_mac("00:1a:2b:3c:4d:5e:6f"),
+            Err(MacError::InvalidFormat)
+        );
+        assert_eq!(parse_mac("00:1a-2b:3c:4d:5e"), Err(MacError::InvalidFormat));
+        assert_eq!(parse_mac("00:1a:2b:3c:4d:zz"), Err(MacError::InvalidHex));
+        assert_eq!(parse_mac("00:1a:2b:3c:4d:+5"), Err(MacError::InvalidHex));
+    }
+}
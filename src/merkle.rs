@@ -0,0 +1,39 @@
+use crate::shard_with_hasher;
+use core::hash::*;
+
+/// Returns the shard for a Merkle tree leaf, keyed by the first `depth` bytes of its hash.
+///
+/// All leaves that agree on those leading bytes share a subtree, and therefore a shard. Depths above 32 use the whole hash.
+pub fn shard_for_merkle_path(
+    leaf_hash: &[u8; 32],
+    depth: u8,
+    total_destinations: u64,
+    hasher: &impl BuildHasher,
+) -> u64 {
+    let prefix = &leaf_hash[..usize::from(depth).min(leaf_hash.len())];
+    shard_with_hasher(prefix, total_destinations, hasher)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BuildSeaHasher;
+
+    #[test]
+    fn same_subtree_shares_a_shard() {
+        let a = [0xab; 32];
+        let mut b = a;
+        b[4..].fill(0x00);
+
+        for depth in 0..=4 {
+            assert_eq!(
+                shard_for_merkle_path(&a, depth, 1024, &BuildSeaHasher),
+                shard_for_merkle_path(&b, depth, 1024, &BuildSeaHasher),
+            );
+        }
+        assert_ne!(
+            shard_for_merkle_path(&a, 32, 1024, &BuildSeaHasher),
+            shard_for_merkle_path(&b, 32, 1024, &BuildSeaHasher),
+        );
+    }
+}
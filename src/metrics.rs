@@ -0,0 +1,152 @@
+//! Lookup metrics, reported through the `metrics` crate's facade.
+//!
+//! With the `metrics` feature, [crate::Sharder], [crate::PoolRouter], and `tower::ShardRoute` count every lookup to whichever recorder is installed, such as `metrics-exporter-prometheus`. Counting allocates nothing, and without a recorder it is a no-op.
+//!
+//! Every metric has a `component` label naming the type that did the lookup: `sharder`, `pool_router`, or `shard_route`. The facade cannot tell whether a recorder is installed, so the costlier metrics are opt-in: [record_latency] times each lookup, and [record_shard_labels] labels [LOOKUPS] with the selected shard.
+
+use ::metrics::{Key, Label, Level, Metadata};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+/// Counter of lookups, labeled with `component`, and with the selected `shard` after [record_shard_labels].
+///
+/// Comparing shards shows real assignment skew, including skew from hot keys.
+pub const LOOKUPS: &str = "faro_sharding_lookups_total";
+
+/// Histogram of lookup latency in seconds, labeled with `component`. Includes hashing the key. Only recorded after [record_latency].
+pub const LOOKUP_DURATION: &str = "faro_sharding_lookup_duration_seconds";
+
+static RECORD_LATENCY: AtomicBool = AtomicBool::new(false);
+static RECORD_SHARD_LABELS: AtomicBool = AtomicBool::new(false);
+
+/// Whether lookups are timed into [LOOKUP_DURATION]. Off by default, since it reads the clock twice per lookup.
+pub fn record_latency(enabled: bool) {
+    RECORD_LATENCY.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether [LOOKUPS] is labeled with the selected `shard`. Off by default, since it formats the shard on every lookup and adds a series per shard, which is unbounded for large destination counts.
+pub fn record_shard_labels(enabled: bool) {
+    RECORD_SHARD_LABELS.store(enabled, Ordering::Relaxed);
+}
+
+static METADATA: Metadata<'static> =
+    Metadata::new(module_path!(), Level::INFO, Some(module_path!()));
+
+static COMPONENTS: [[Label; 1]; 3] = [
+    [Label::from_static_parts("component", "sharder")],
+    [Label::from_static_parts("component", "pool_router")],
+    [Label::from_static_parts("component", "shard_route")],
+];
+
+fn component_labels(component: &'static str) -> &'static [Label] {
+    COMPONENTS
+        .iter()
+        .find(|labels| labels[0].value() == component)
+        .expect("lookups come from a known component")
+}
+
+pub(crate) fn observe_lookup(component: &'static str, lookup: impl FnOnce() -> u64) -> u64 {
+    let labels = component_labels(component);
+
+    let shard = if RECORD_LATENCY.load(Ordering::Relaxed) {
+        let started = Instant::now();
+        let shard = lookup();
+        let key = Key::from_static_labels(LOOKUP_DURATION, labels);
+        ::metrics::with_recorder(|recorder| recorder.register_histogram(&key, &METADATA))
+            .record(started.elapsed());
+        shard
+    } else {
+        lookup()
+    };
+
+    if RECORD_SHARD_LABELS.load(Ordering::Relaxed) {
+        ::metrics::counter!(LOOKUPS, "component" => component, "shard" => shard.to_string())
+            .increment(1);
+    } else {
+        let key = Key::from_static_labels(LOOKUPS, labels);
+        ::metrics::with_recorder(|recorder| recorder.register_counter(&key, &METADATA))
+            .increment(1);
+    }
+    shard
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{shard_for, Algorithm, BuildSeaHasher, PoolRouter, ShardRouter, Sharder};
+    use ::metrics::{SharedString, Unit};
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+    use metrics_util::{CompositeKey, MetricKind};
+
+    type Snapshot = Vec<(CompositeKey, Option<Unit>, Option<SharedString>, DebugValue)>;
+
+    /// The [LOOKUPS] count with exactly these labels.
+    fn lookups(snapshot: &Snapshot, component: &str, shard: Option<u64>) -> u64 {
+        let mut expected = vec![("component".to_string(), component.to_string())];
+        expected.extend(shard.map(|shard| ("shard".to_string(), shard.to_string())));
+        snapshot
+            .iter()
+            .find_map(|(key, _, _, value)| {
+                let labels = key
+                    .key()
+                    .labels()
+                    .map(|l| (l.key().to_string(), l.value().to_string()))
+                    .collect::<Vec<_>>();
+                let matches = key.kind() == MetricKind::Counter
+                    && key.key().name() == LOOKUPS
+                    && labels == expected;
+                match value {
+                    DebugValue::Counter(count) if matches => Some(*count),
+                    _ => None,
+                }
+            })
+            .unwrap_or(0)
+    }
+
+    fn durations(snapshot: &Snapshot) -> usize {
+        snapshot
+            .iter()
+            .filter_map(|(key, _, _, value)| match value {
+                DebugValue::Histogram(values) if key.key().name() == LOOKUP_DURATION => {
+                    Some(values.len())
+                }
+                _ => None,
+            })
+            .sum()
+    }
+
+    // One test, since the options are global and tests run concurrently.
+    #[test]
+    fn counts_lookups_and_opts_in_to_the_rest() {
+        let sharder = Sharder::new(Algorithm::FaroV1, BuildSeaHasher);
+        let router = PoolRouter::new(vec!["db0", "db1", "db2"], BuildSeaHasher);
+        let observe = || {
+            let recorder = DebuggingRecorder::new();
+            ::metrics::with_local_recorder(&recorder, || {
+                assert_eq!(sharder.shard_for("foo", 50), 49);
+                sharder.shard_for_key_hash(7, 1_000);
+                router.route("foo");
+            });
+            recorder.snapshotter().snapshot().into_vec()
+        };
+
+        let snapshot = observe();
+        assert_eq!(lookups(&snapshot, "sharder", None), 2);
+        assert_eq!(lookups(&snapshot, "pool_router", None), 1);
+        assert_eq!(durations(&snapshot), 0);
+
+        record_latency(true);
+        record_shard_labels(true);
+        let snapshot = observe();
+        record_latency(false);
+        record_shard_labels(false);
+
+        assert_eq!(lookups(&snapshot, "sharder", None), 0);
+        assert_eq!(lookups(&snapshot, "sharder", Some(49)), 1);
+        assert_eq!(
+            lookups(&snapshot, "pool_router", Some(shard_for("foo", 3))),
+            1
+        );
+        assert_eq!(durations(&snapshot), 3);
+    }
+}
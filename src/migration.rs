@@ -0,0 +1,737 @@
+//! Planning which keys move when the number of destinations changes.
+
+#[cfg(feature = "serde")]
+use crate::format_version::FormatVersion;
+use crate::{ShardState, Sharder};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::hash::*;
+use std::time::Duration;
+
+/// Computes a [MigrationPlan] for scaling from one destination count to another.
+#[derive(Clone, Debug)]
+pub struct Planner<S> {
+    from_destinations: u64,
+    to_destinations: u64,
+    hasher: S,
+    record_keys: bool,
+}
+
+impl<S: BuildHasher> Planner<S> {
+    /// Plans a scale from `from_destinations` to `to_destinations`. Scaling down is also supported.
+    ///
+    /// # Panics
+    ///
+    /// If either destination count == 0.
+    pub fn new(from_destinations: u64, to_destinations: u64, hasher: S) -> Self {
+        assert_ne!(from_destinations, 0, "total_destinations must be > 0");
+        assert_ne!(to_destinations, 0, "total_destinations must be > 0");
+
+        Planner {
+            from_destinations,
+            to_destinations,
+            hasher,
+            record_keys: false,
+        }
+    }
+
+    /// Whether plans should list every moved key, not just counts. Off by default.
+    pub fn record_keys(mut self, record_keys: bool) -> Self {
+        self.record_keys = record_keys;
+        self
+    }
+
+    /// Plans the migration of `keys`.
+    pub fn plan<K: Hash>(&self, keys: impl IntoIterator<Item = K>) -> MigrationPlan<K> {
+        self.plan_states(
+            keys.into_iter()
+                .map(|key| (ShardState::new(&key, &self.hasher), key, 0)),
+        )
+    }
+
+    /// Plans the migration of `(key, size_bytes)` pairs, so the plan also reports bytes moved.
+    pub fn plan_sized<K: Hash>(
+        &self,
+        keys: impl IntoIterator<Item = (K, u64)>,
+    ) -> MigrationPlan<K> {
+        self.plan_states(
+            keys.into_iter()
+                .map(|(key, size)| (ShardState::new(&key, &self.hasher), key, size)),
+        )
+    }
+
+    /// Plans the migration of keys already hashed with `hasher`, as in [crate::shard_for_key_hash].
+    pub fn plan_digests(&self, key_hashes: impl IntoIterator<Item = u64>) -> MigrationPlan<u64> {
+        self.plan_states(
+            key_hashes
+                .into_iter()
+                .map(|key_hash| (ShardState::from_key_hash(key_hash), key_hash, 0)),
+        )
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            name = "migration_plan",
+            skip_all,
+            fields(
+                from_destinations = self.from_destinations,
+                to_destinations = self.to_destinations,
+            )
+        )
+    )]
+    fn plan_states<K>(
+        &self,
+        states: impl Iterator<Item = (ShardState, K, u64)>,
+    ) -> MigrationPlan<K> {
+        let low = self.from_destinations.min(self.to_destinations);
+        let high = self.from_destinations.max(self.to_destinations);
+
+        let mut plan = MigrationPlan::empty(self.from_destinations, self.to_destinations);
+
+        for (mut state, key, size) in states {
+            plan.total_keys += 1;
+            plan.total_bytes += size;
+
+            let low_shard = state.extend_to(low, &self.hasher);
+            let high_shard = state.extend_to(high, &self.hasher);
+            if low_shard == high_shard {
+                continue;
+            }
+
+            let route = if self.from_destinations <= self.to_destinations {
+                (low_shard, high_shard)
+            } else {
+                (high_shard, low_shard)
+            };
+            #[cfg(feature = "tracing")]
+            tracing::trace!(source = route.0, destination = route.1, "key moves");
+            plan.add_move(route, key, size, self.record_keys);
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            total_keys = plan.total_keys,
+            moved_keys = plan.moved_keys(),
+            groups = plan.groups.len(),
+            "planned migration"
+        );
+        plan
+    }
+}
+
+/// Keys moving from one source shard to one destination shard.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MigrationGroup<K> {
+    /// Number of keys moving.
+    pub count: u64,
+    /// Total size of the keys moving, if planned with [Planner::plan_sized]. Otherwise 0.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub bytes: u64,
+    /// The moving keys, if the [Planner] was asked to record them. Otherwise empty.
+    pub keys: Vec<K>,
+}
+
+/// The result of [Planner::plan]: moved keys grouped by `(source, destination)` shard.
+///
+/// Keys that stay put are counted in [MigrationPlan::total_keys] but not grouped.
+///
+/// The [Display](fmt::Display) form is one tab-separated `source destination count` line per group, for handing to other tools.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "K: serde::Serialize",
+        deserialize = "K: serde::Deserialize<'de>"
+    ))
+)]
+pub struct MigrationPlan<K> {
+    #[cfg(feature = "serde")]
+    version: FormatVersion,
+    from_destinations: u64,
+    to_destinations: u64,
+    total_keys: u64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    total_bytes: u64,
+    #[cfg_attr(feature = "serde", serde(with = "groups_as_seq"))]
+    groups: Groups<K>,
+}
+
+type Groups<K> = BTreeMap<(u64, u64), MigrationGroup<K>>;
+
+impl<K> MigrationPlan<K> {
+    fn empty(from_destinations: u64, to_destinations: u64) -> Self {
+        MigrationPlan {
+            #[cfg(feature = "serde")]
+            version: FormatVersion,
+            from_destinations,
+            to_destinations,
+            total_keys: 0,
+            total_bytes: 0,
+            groups: BTreeMap::new(),
+        }
+    }
+
+    fn add_move(&mut self, route: (u64, u64), key: K, size: u64, record_key: bool) {
+        let group = self.groups.entry(route).or_insert_with(|| MigrationGroup {
+            count: 0,
+            bytes: 0,
+            keys: Vec::new(),
+        });
+        group.count += 1;
+        group.bytes += size;
+        if record_key {
+            group.keys.push(key);
+        }
+    }
+
+    /// The destination count being migrated from.
+    pub fn from_destinations(&self) -> u64 {
+        self.from_destinations
+    }
+
+    /// The destination count being migrated to.
+    pub fn to_destinations(&self) -> u64 {
+        self.to_destinations
+    }
+
+    /// Number of keys planned, moved or not.
+    pub fn total_keys(&self) -> u64 {
+        self.total_keys
+    }
+
+    /// Number of keys that change shard.
+    pub fn moved_keys(&self) -> u64 {
+        self.groups.values().map(|g| g.count).sum()
+    }
+
+    /// The fraction of planned keys that change shard, or `0.0` if there are none.
+    pub fn moved_fraction(&self) -> f64 {
+        if self.total_keys == 0 {
+            return 0.0;
+        }
+        self.moved_keys() as f64 / self.total_keys as f64
+    }
+
+    /// Total size of the keys planned, moved or not. 0 unless planned with [Planner::plan_sized].
+    pub fn total_bytes(&self) -> u64 {
+        self.total_bytes
+    }
+
+    /// Total size of the keys that change shard. 0 unless planned with [Planner::plan_sized].
+    pub fn moved_bytes(&self) -> u64 {
+        self.groups.values().map(|g| g.bytes).sum()
+    }
+
+    /// How long copying [MigrationPlan::moved_bytes] takes at `bytes_per_second`.
+    ///
+    /// This assumes one transfer at a time. If `(source, destination)` pairs copy in parallel, divide by their number, or take the largest [MigrationGroup::bytes] when each pair has its own link.
+    ///
+    /// # Panics
+    ///
+    /// If bytes_per_second == 0.
+    pub fn transfer_time(&self, bytes_per_second: u64) -> Duration {
+        assert_ne!(bytes_per_second, 0, "bytes_per_second must be > 0");
+        Duration::from_secs_f64(self.moved_bytes() as f64 / bytes_per_second as f64)
+    }
+
+    /// The keys moving from `source` to `destination`, if any.
+    pub fn group(&self, source: u64, destination: u64) -> Option<&MigrationGroup<K>> {
+        self.groups.get(&(source, destination))
+    }
+
+    /// Every `((source, destination), group)`, ordered by source then destination.
+    pub fn groups(&self) -> impl Iterator<Item = ((u64, u64), &MigrationGroup<K>)> {
+        self.groups.iter().map(|(&route, group)| (route, group))
+    }
+
+    /// Every recorded `(key, source, destination)`, in [MigrationPlan::groups] order.
+    ///
+    /// Empty unless the [Planner] was asked to record keys.
+    pub fn moves(&self) -> impl Iterator<Item = (&K, u64, u64)> {
+        self.groups().flat_map(|((source, destination), group)| {
+            group.keys.iter().map(move |key| (key, source, destination))
+        })
+    }
+}
+
+impl<K> fmt::Display for MigrationPlan<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for ((source, destination), group) in self.groups() {
+            writeln!(f, "{}\t{}\t{}", source, destination, group.count)?;
+        }
+        Ok(())
+    }
+}
+
+/// Plans switching `keys` from the `old` [Sharder] to the `new` one across `total_destinations`, such as when changing the hasher or [crate::Algorithm]. Every moved key is recorded.
+///
+/// Unlike growing, a new hasher or algorithm moves about `1 - 1 / total_destinations` of keys between arbitrary shards. [MigrationPlan::moved_fraction] is the blast radius.
+///
+/// ```
+/// # use faro_sharding::migration::diff_assignments;
+/// # use faro_sharding::{Algorithm, Sharder};
+/// # use std::hash::BuildHasherDefault;
+/// # use std::collections::hash_map::DefaultHasher;
+/// let hasher = BuildHasherDefault::<DefaultHasher>::default();
+/// let old = Sharder::new(Algorithm::FaroV1, hasher.clone());
+/// let new = Sharder::new(Algorithm::FaroV2, hasher);
+///
+/// let plan = diff_assignments(0..10_000u64, 16, &old, &new);
+/// assert!(plan.moved_fraction() > 0.8);
+/// ```
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub fn diff_assignments<K: Hash>(
+    keys: impl IntoIterator<Item = K>,
+    total_destinations: u64,
+    old: &Sharder<impl BuildHasher>,
+    new: &Sharder<impl BuildHasher>,
+) -> MigrationPlan<K> {
+    diff_assignments_by(
+        keys,
+        total_destinations,
+        |key, n| old.shard_for(key, n),
+        |key, n| new.shard_for(key, n),
+    )
+}
+
+/// [diff_assignments] for any pair of sharding functions, called with a key and `total_destinations`.
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub fn diff_assignments_by<K>(
+    keys: impl IntoIterator<Item = K>,
+    total_destinations: u64,
+    old: impl Fn(&K, u64) -> u64,
+    new: impl Fn(&K, u64) -> u64,
+) -> MigrationPlan<K> {
+    assert_ne!(total_destinations, 0, "total_destinations must be > 0");
+
+    let mut plan = MigrationPlan::empty(total_destinations, total_destinations);
+    for key in keys {
+        plan.total_keys += 1;
+        let route = (old(&key, total_destinations), new(&key, total_destinations));
+        if route.0 != route.1 {
+            plan.add_move(route, key, 0, true);
+        }
+    }
+    plan
+}
+
+/// [shrink_plan_with_hasher] using the default hasher of [crate::shard_for].
+///
+/// # Panics
+///
+/// If to_destinations == 0 or to_destinations > from_destinations.
+#[cfg(feature = "seahash")]
+pub fn shrink_plan<K: Hash>(
+    keys: impl IntoIterator<Item = K>,
+    from_destinations: u64,
+    to_destinations: u64,
+) -> MigrationPlan<K> {
+    shrink_plan_with_hasher(
+        keys,
+        from_destinations,
+        to_destinations,
+        crate::seahash::StableSeaHasher::new(),
+    )
+}
+
+/// Plans decommissioning destinations `to_destinations..from_destinations`, recording every key that moves.
+///
+/// Exactly the keys on the decommissioned destinations move, each to the shard it has after the shrink, so every group's source is at least `to_destinations`. Route writes with [crate::shard_with_hasher_shrinking] while the keys are copied.
+///
+/// # Panics
+///
+/// If to_destinations == 0 or to_destinations > from_destinations.
+pub fn shrink_plan_with_hasher<K: Hash, S: BuildHasher>(
+    keys: impl IntoIterator<Item = K>,
+    from_destinations: u64,
+    to_destinations: u64,
+    hasher: S,
+) -> MigrationPlan<K> {
+    assert!(
+        to_destinations <= from_destinations,
+        "to_destinations must be <= from_destinations"
+    );
+    Planner::new(from_destinations, to_destinations, hasher)
+        .record_keys(true)
+        .plan(keys)
+}
+
+/// Builds a [MovementGraph] of how `keys` move at each step through `destination_counts`.
+///
+/// Each consecutive pair of counts is one step, so `[8, 9, 10, 12]` has three steps. Counts may go down as well as up.
+///
+/// ```
+/// # use faro_sharding::migration::movement_graph;
+/// # use std::hash::BuildHasherDefault;
+/// # use std::collections::hash_map::DefaultHasher;
+/// let hasher = BuildHasherDefault::<DefaultHasher>::default();
+/// let graph = movement_graph(0..1_000u64, &[4, 5, 6], &hasher);
+///
+/// assert_eq!(graph.steps().len(), 2);
+/// assert!(graph.to_dot().starts_with("digraph movement {"));
+/// ```
+///
+/// # Panics
+///
+/// If any destination count == 0.
+pub fn movement_graph<K: Hash>(
+    keys: impl IntoIterator<Item = K>,
+    destination_counts: &[u64],
+    hasher: &impl BuildHasher,
+) -> MovementGraph {
+    for &count in destination_counts {
+        assert_ne!(count, 0, "total_destinations must be > 0");
+    }
+
+    let mut ascending = (0..destination_counts.len()).collect::<Vec<_>>();
+    ascending.sort_by_key(|&i| destination_counts[i]);
+
+    let mut steps = destination_counts
+        .windows(2)
+        .map(|pair| (pair[0], pair[1], 0, BTreeMap::new()))
+        .collect::<Vec<_>>();
+    let mut shards = vec![0; destination_counts.len()];
+    for key in keys {
+        let mut state = ShardState::new(&key, hasher);
+        for &i in &ascending {
+            shards[i] = state.extend_to(destination_counts[i], hasher);
+        }
+
+        for (i, (_, _, total_keys, flows)) in steps.iter_mut().enumerate() {
+            *total_keys += 1;
+            if shards[i] != shards[i + 1] {
+                *flows.entry((shards[i], shards[i + 1])).or_insert(0) += 1;
+            }
+        }
+    }
+
+    MovementGraph {
+        steps: steps
+            .into_iter()
+            .map(
+                |(from_destinations, to_destinations, total_keys, flows)| MovementStep {
+                    from_destinations,
+                    to_destinations,
+                    total_keys,
+                    flows: flows
+                        .into_iter()
+                        .map(|((source, destination), count)| Flow {
+                            source,
+                            destination,
+                            count,
+                        })
+                        .collect(),
+                },
+            )
+            .collect(),
+    }
+}
+
+/// Keys moving from one shard to another in one [MovementStep].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Flow {
+    /// The shard keys move off.
+    pub source: u64,
+    /// The shard keys move onto.
+    pub destination: u64,
+    /// Number of keys moving.
+    pub count: u64,
+}
+
+/// The flows of one change in destination count.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct MovementStep {
+    /// The destination count before the step.
+    pub from_destinations: u64,
+    /// The destination count after the step.
+    pub to_destinations: u64,
+    /// Number of keys, moved or not.
+    pub total_keys: u64,
+    /// Every shard to shard flow of moved keys, ordered by source then destination.
+    pub flows: Vec<Flow>,
+}
+
+impl MovementStep {
+    /// Number of keys that change shard in this step.
+    pub fn moved_keys(&self) -> u64 {
+        self.flows.iter().map(|f| f.count).sum()
+    }
+}
+
+/// The result of [movement_graph]: shard to shard flows at each step, for diagrams and capacity reviews.
+///
+/// With the `serde` feature it serializes as an array of its [MovementStep]s.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize), serde(transparent))]
+pub struct MovementGraph {
+    steps: Vec<MovementStep>,
+}
+
+impl MovementGraph {
+    /// The steps, in the order of the destination counts.
+    pub fn steps(&self) -> &[MovementStep] {
+        &self.steps
+    }
+
+    /// The graph in Graphviz DOT.
+    ///
+    /// Node `"N/s"` is shard `s` with `N` destinations, and each flow is an edge labeled with its key count. Consecutive steps share nodes, so a key's path through the whole growth can be followed.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph movement {\n    rankdir=LR;\n");
+        for step in &self.steps {
+            for f in &step.flows {
+                dot += &format!(
+                    "    \"{}/{}\" -> \"{}/{}\" [label=\"{}\"];\n",
+                    step.from_destinations, f.source, step.to_destinations, f.destination, f.count
+                );
+            }
+        }
+        dot += "}\n";
+        dot
+    }
+}
+
+/// Formats with non-string map keys can't hold `(source, destination)`, so groups are persisted as a list.
+#[cfg(feature = "serde")]
+mod groups_as_seq {
+    use super::{Groups, MigrationGroup};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize)]
+    struct EntryRef<'a, K> {
+        source: u64,
+        destination: u64,
+        #[serde(flatten)]
+        group: &'a MigrationGroup<K>,
+    }
+
+    #[derive(Deserialize)]
+    struct Entry<K> {
+        source: u64,
+        destination: u64,
+        #[serde(flatten)]
+        group: MigrationGroup<K>,
+    }
+
+    pub fn serialize<K: Serialize, Ser: Serializer>(
+        groups: &Groups<K>,
+        serializer: Ser,
+    ) -> Result<Ser::Ok, Ser::Error> {
+        serializer.collect_seq(
+            groups
+                .iter()
+                .map(|(&(source, destination), group)| EntryRef {
+                    source,
+                    destination,
+                    group,
+                }),
+        )
+    }
+
+    pub fn deserialize<'de, K: Deserialize<'de>, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Groups<K>, D::Error> {
+        let entries = Vec::<Entry<K>>::deserialize(deserializer)?;
+        Ok(entries
+            .into_iter()
+            .map(|e| ((e.source, e.destination), e.group))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{shard_for, BuildSeaHasher};
+
+    #[test]
+    fn groups_agree_with_shard_for() {
+        let plan = Planner::new(40, 48, BuildSeaHasher)
+            .record_keys(true)
+            .plan(0..10_000u64);
+
+        assert_eq!(plan.total_keys(), 10_000);
+        for ((source, destination), group) in plan.groups() {
+            assert_eq!(group.count, group.keys.len() as u64);
+            for key in &group.keys {
+                assert_eq!(shard_for(key, 40), source);
+                assert_eq!(shard_for(key, 48), destination);
+            }
+        }
+        assert_eq!(plan.moves().count() as u64, plan.moved_keys());
+
+        let expected_moved = (0..10_000u64)
+            .filter(|k| shard_for(k, 40) != shard_for(k, 48))
+            .count();
+        assert_eq!(plan.moved_keys(), expected_moved as u64);
+    }
+
+    #[test]
+    fn scaling_up_only_moves_to_new_shards() {
+        let plan = Planner::new(40, 48, BuildSeaHasher).plan(0..10_000u64);
+        assert!(plan.groups().all(|((_, destination), _)| destination >= 40));
+        assert!(plan.groups().all(|(_, group)| group.keys.is_empty()));
+    }
+
+    #[test]
+    fn scaling_down_is_the_reverse() {
+        let up = Planner::new(40, 48, BuildSeaHasher).plan(0..1_000u64);
+        let down = Planner::new(48, 40, BuildSeaHasher).plan(0..1_000u64);
+
+        assert_eq!(up.moved_keys(), down.moved_keys());
+        for ((source, destination), group) in up.groups() {
+            assert_eq!(down.group(destination, source).unwrap().count, group.count);
+        }
+    }
+
+    #[test]
+    fn shrinking_moves_exactly_the_decommissioned_keys() {
+        let plan = shrink_plan(0..10_000u64, 48, 40);
+
+        let on_removed = (0..10_000u64)
+            .filter(|k| shard_for(k, 48) >= 40)
+            .collect::<Vec<_>>();
+        let mut moved = plan.moves().map(|(&key, _, _)| key).collect::<Vec<_>>();
+        moved.sort_unstable();
+        assert_eq!(moved, on_removed);
+        for (&key, source, destination) in plan.moves() {
+            assert!(source >= 40);
+            assert_eq!(destination, shard_for(key, 40));
+        }
+    }
+
+    #[test]
+    fn diffs_hashers() {
+        let old = Sharder::new(crate::Algorithm::FaroV1, BuildSeaHasher);
+        let new = Sharder::new(
+            crate::Algorithm::FaroV1,
+            crate::seahash::StableSeaHasher::with_seeds([1, 2, 3, 4]),
+        );
+        let plan = diff_assignments(0..10_000u64, 20, &old, &new);
+
+        assert_eq!(plan.total_keys(), 10_000);
+        assert!((plan.moved_fraction() - 0.95).abs() < 0.01);
+        for (&key, source, destination) in plan.moves() {
+            assert_eq!(source, old.shard_for(key, 20));
+            assert_eq!(destination, new.shard_for(key, 20));
+        }
+        assert_eq!(diff_assignments(0..100u64, 20, &old, &old).moved_keys(), 0);
+    }
+
+    #[test]
+    fn sized_plans_sum_bytes() {
+        let sized = (0..10_000u64).map(|key| (key, key % 100));
+        let plan = Planner::new(40, 48, BuildSeaHasher).plan_sized(sized);
+
+        assert_eq!(
+            plan.total_bytes(),
+            (0..10_000u64).map(|key| key % 100).sum::<u64>()
+        );
+        let mut expected = BTreeMap::new();
+        for key in 0..10_000u64 {
+            let route = (shard_for(key, 40), shard_for(key, 48));
+            if route.0 != route.1 {
+                *expected.entry(route).or_insert(0) += key % 100;
+            }
+        }
+        let bytes = plan.groups().map(|(route, group)| (route, group.bytes));
+        assert_eq!(bytes.collect::<BTreeMap<_, _>>(), expected);
+        assert_eq!(
+            plan.transfer_time(1_000),
+            Duration::from_secs_f64(plan.moved_bytes() as f64 / 1_000.)
+        );
+        assert_eq!(
+            Planner::new(40, 48, BuildSeaHasher)
+                .plan(0..10_000u64)
+                .to_string(),
+            plan.to_string()
+        );
+    }
+
+    #[test]
+    fn digests_match_keys() {
+        let keys = (0..500).map(|i| format!("key-{}", i)).collect::<Vec<_>>();
+        let digests = Planner::new(3, 7, BuildSeaHasher)
+            .plan_digests(keys.iter().map(|k| BuildSeaHasher.hash_one(k)));
+        let plan = Planner::new(3, 7, BuildSeaHasher).plan(keys);
+
+        assert_eq!(plan.to_string(), digests.to_string());
+    }
+
+    #[test]
+    fn movement_steps_match_plans() {
+        let counts = [8, 9, 10, 12, 6];
+        let graph = movement_graph(0..5_000u64, &counts, &BuildSeaHasher);
+
+        assert_eq!(graph.steps().len(), 4);
+        for (step, pair) in graph.steps().iter().zip(counts.windows(2)) {
+            let plan = Planner::new(pair[0], pair[1], BuildSeaHasher).plan(0..5_000u64);
+            assert_eq!(
+                (step.from_destinations, step.to_destinations),
+                (pair[0], pair[1])
+            );
+            assert_eq!(step.total_keys, 5_000);
+            assert_eq!(step.moved_keys(), plan.moved_keys());
+            for flow in &step.flows {
+                let group = plan.group(flow.source, flow.destination).unwrap();
+                assert_eq!(flow.count, group.count);
+            }
+        }
+    }
+
+    #[test]
+    fn movement_renders() {
+        let graph = movement_graph(0..100u64, &[2, 3], &BuildSeaHasher);
+        let flows = &graph.steps()[0].flows;
+        assert_eq!(flows.len(), 2);
+        assert!(flows.iter().all(|f| f.destination == 2));
+
+        assert_eq!(
+            graph.to_dot(),
+            format!(
+                "digraph movement {{\n    rankdir=LR;\n    \"2/0\" -> \"3/2\" [label=\"{}\"];\n    \"2/1\" -> \"3/2\" [label=\"{}\"];\n}}\n",
+                flows[0].count, flows[1].count
+            )
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn movement_serializes() {
+        let graph = movement_graph(0..100u64, &[2, 3], &BuildSeaHasher);
+        let json = serde_json::to_value(&graph).unwrap();
+        assert_eq!(json[0]["to_destinations"], 3);
+        assert_eq!(json[0]["total_keys"], 100);
+        assert_eq!(json[0]["flows"][1]["source"], 1);
+        assert_eq!(
+            json[0]["flows"][1]["count"],
+            graph.steps()[0].flows[1].count
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn plan_round_trips() {
+        let plan = Planner::new(3, 5, BuildSeaHasher)
+            .record_keys(true)
+            .plan(0..100u64);
+
+        let json = serde_json::to_string(&plan).unwrap();
+        assert_eq!(
+            serde_json::from_str::<MigrationPlan<u64>>(&json).unwrap(),
+            plan
+        );
+    }
+}
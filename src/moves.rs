@@ -0,0 +1,226 @@
+use crate::ShardState;
+use core::hash::*;
+
+/// Returns whether `key` changes shard when scaling from `from_destinations` to `to_destinations`.
+///
+/// Works in either direction, and walks the hash chain only once.
+///
+/// # Panics
+///
+/// If either destination count == 0.
+pub fn moves_between(
+    key: impl Hash,
+    from_destinations: u64,
+    to_destinations: u64,
+    hasher: &impl BuildHasher,
+) -> bool {
+    let (from, to) = shards_between(key, from_destinations, to_destinations, hasher);
+    from != to
+}
+
+/// Returns the shard for `key` at `from_destinations` and at `to_destinations`, in that order.
+///
+/// # Panics
+///
+/// If either destination count == 0.
+pub fn shards_between(
+    key: impl Hash,
+    from_destinations: u64,
+    to_destinations: u64,
+    hasher: &impl BuildHasher,
+) -> (u64, u64) {
+    assert_ne!(from_destinations, 0, "total_destinations must be > 0");
+    assert_ne!(to_destinations, 0, "total_destinations must be > 0");
+
+    let mut state = ShardState::new(key, hasher);
+    let low = state.extend_to(from_destinations.min(to_destinations), hasher);
+    let high = state.extend_to(from_destinations.max(to_destinations), hasher);
+
+    if from_destinations <= to_destinations {
+        (low, high)
+    } else {
+        (high, low)
+    }
+}
+
+/// The fraction of keys expected to change shard when scaling from `from_destinations` to `to_destinations`, in either direction.
+///
+/// A key stays put through step `n` with probability `n / (n + 1)`, so scaling between `a` and `b > a` destinations keeps `a / b` of keys and moves `1 - a / b`. This is the least any scheme can move while staying balanced.
+///
+/// ```
+/// # use faro_sharding::*;
+/// // Scaling from 40 to 55 nodes moves about 27% of data.
+/// assert!((expected_moved_fraction(40, 55) - 0.2727).abs() < 0.0001);
+/// ```
+///
+/// # Panics
+///
+/// If either destination count == 0.
+pub fn expected_moved_fraction(from_destinations: u64, to_destinations: u64) -> f64 {
+    assert_ne!(from_destinations, 0, "total_destinations must be > 0");
+    assert_ne!(to_destinations, 0, "total_destinations must be > 0");
+
+    let low = from_destinations.min(to_destinations);
+    let high = from_destinations.max(to_destinations);
+    1.0 - low as f64 / high as f64
+}
+
+/// The fraction of `keys` that change shard when scaling from `from_destinations` to `to_destinations`, measured with [moves_between].
+///
+/// Returns `0.0` if there are no keys. Compare with [expected_moved_fraction] to check a sample or hasher.
+///
+/// # Panics
+///
+/// If either destination count == 0.
+pub fn measured_moved_fraction<K: Hash>(
+    keys: impl IntoIterator<Item = K>,
+    from_destinations: u64,
+    to_destinations: u64,
+    hasher: &impl BuildHasher,
+) -> f64 {
+    assert_ne!(from_destinations, 0, "total_destinations must be > 0");
+    assert_ne!(to_destinations, 0, "total_destinations must be > 0");
+
+    let (mut total, mut moved) = (0u64, 0u64);
+    for key in keys {
+        total += 1;
+        moved += u64::from(moves_between(
+            key,
+            from_destinations,
+            to_destinations,
+            hasher,
+        ));
+    }
+
+    if total == 0 {
+        return 0.0;
+    }
+    moved as f64 / total as f64
+}
+
+/// Where a key lives before and after a resize. See [shard_pair].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ShardPair {
+    /// The shard at the old destination count.
+    pub old: u64,
+    /// The shard at the new destination count.
+    pub new: u64,
+    /// Whether `old != new`, so dual reads and writes are needed.
+    pub changed: bool,
+}
+
+/// [shards_between] as a [ShardPair], for dual reads and writes during a live migration.
+///
+/// # Panics
+///
+/// If either destination count == 0.
+pub fn shard_pair(
+    key: impl Hash,
+    old_destinations: u64,
+    new_destinations: u64,
+    hasher: &impl BuildHasher,
+) -> ShardPair {
+    let (old, new) = shards_between(key, old_destinations, new_destinations, hasher);
+    ShardPair {
+        old,
+        new,
+        changed: old != new,
+    }
+}
+
+/// [shard_with_hasher_shrinking] using the default hasher of [crate::shard_for].
+///
+/// # Panics
+///
+/// If to_destinations == 0 or to_destinations > from_destinations.
+#[cfg(feature = "seahash")]
+pub fn shard_for_shrinking(
+    key: impl Hash,
+    from_destinations: u64,
+    to_destinations: u64,
+) -> ShardPair {
+    shard_with_hasher_shrinking(
+        key,
+        from_destinations,
+        to_destinations,
+        &crate::seahash::StableSeaHasher::new(),
+    )
+}
+
+/// Routes `key` while destinations `to_destinations..from_destinations` are drained before being decommissioned.
+///
+/// Write to [ShardPair::new], the shard the key has after the shrink. Read from [ShardPair::old] too while [ShardPair::changed], which is only for keys on a decommissioned destination. Other keys keep their shard. See [crate::migration::shrink_plan_with_hasher] for the keys to copy.
+///
+/// # Panics
+///
+/// If to_destinations == 0 or to_destinations > from_destinations.
+pub fn shard_with_hasher_shrinking(
+    key: impl Hash,
+    from_destinations: u64,
+    to_destinations: u64,
+    hasher: &impl BuildHasher,
+) -> ShardPair {
+    assert!(
+        to_destinations <= from_destinations,
+        "to_destinations must be <= from_destinations"
+    );
+    shard_pair(key, from_destinations, to_destinations, hasher)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shard_for;
+    use crate::BuildSeaHasher;
+
+    use proptest::*;
+
+    proptest! {
+        #[test]
+        fn agrees_with_shard_for(key: String, from in 1u64..=256, to in 1u64..=256) {
+            let expected = (shard_for(&key, from), shard_for(&key, to));
+            prop_assert_eq!(shards_between(&key, from, to, &BuildSeaHasher), expected);
+            prop_assert_eq!(moves_between(&key, from, to, &BuildSeaHasher), expected.0 != expected.1);
+            prop_assert_eq!(
+                shard_pair(&key, from, to, &BuildSeaHasher),
+                ShardPair { old: expected.0, new: expected.1, changed: expected.0 != expected.1 }
+            );
+        }
+    }
+
+    #[test]
+    fn measured_agrees_with_expected() {
+        for (from, to) in [(40, 55), (55, 40), (1, 2), (10, 11), (100, 1_000), (7, 7)] {
+            let measured = measured_moved_fraction(0..50_000u64, from, to, &BuildSeaHasher);
+            let expected = expected_moved_fraction(from, to);
+            assert!(
+                (measured - expected).abs() < 0.01,
+                "{from} -> {to}: {measured} vs {expected}"
+            );
+        }
+        assert_eq!(measured_moved_fraction(0..0u64, 3, 4, &BuildSeaHasher), 0.0);
+    }
+
+    #[test]
+    fn shrinking_only_moves_keys_off_removed_destinations() {
+        for key in 0..10_000u64 {
+            let pair = shard_for_shrinking(key, 48, 40);
+            assert_eq!(pair.old, shard_for(key, 48));
+            assert_eq!(pair.new, shard_for(key, 40));
+            assert_eq!(pair.changed, pair.old >= 40);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "to_destinations must be <= from_destinations")]
+    fn shrinking_cannot_grow() {
+        shard_for_shrinking("foo", 40, 48);
+    }
+
+    #[test]
+    fn documented_example() {
+        assert!(!moves_between("foo", 4, 49, &BuildSeaHasher));
+        assert!(moves_between("foo", 49, 50, &BuildSeaHasher));
+        assert_eq!(shards_between("foo", 50, 4, &BuildSeaHasher), (49, 2));
+    }
+}
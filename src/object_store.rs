@@ -0,0 +1,277 @@
+//! Sharded object layouts for [object_store](https://docs.rs/object_store), such as S3 or GCS buckets.
+//!
+//! [PrefixSharder] puts each object under a `shard=NNNN/` prefix by its key, so a shard can be listed or compacted on its own. [ShardedObjectStore] applies it to every request on an inner store.
+
+use crate::shard_bytes_with_hasher;
+use ::futures_util::TryStreamExt;
+use ::object_store::path::{Path, PathPart};
+use ::object_store::{
+    GetResult, ObjectMeta, ObjectStore, ObjectStoreExt, PutPayload, PutResult, Result,
+};
+use std::hash::*;
+
+const PREFIX: &str = "shard=";
+
+/// Maps object keys to `shard=NNNN/key` paths.
+///
+/// Keys are sharded as the bytes of their path with [shard_bytes_with_hasher].
+#[derive(Clone, Debug)]
+pub struct PrefixSharder<S> {
+    shard_count: u64,
+    hasher: S,
+}
+
+impl<S: BuildHasher> PrefixSharder<S> {
+    /// Shards across `shard_count` prefixes.
+    ///
+    /// # Panics
+    ///
+    /// If shard_count == 0.
+    pub fn new(shard_count: u64, hasher: S) -> Self {
+        assert_ne!(shard_count, 0, "shard_count must be > 0");
+
+        PrefixSharder {
+            shard_count,
+            hasher,
+        }
+    }
+
+    /// Number of shard prefixes.
+    pub fn shard_count(&self) -> u64 {
+        self.shard_count
+    }
+
+    /// The shard `key` is in.
+    pub fn shard_of(&self, key: &Path) -> u64 {
+        self.shard_at(key, self.shard_count)
+    }
+
+    fn shard_at(&self, key: &Path, shard_count: u64) -> u64 {
+        shard_bytes_with_hasher(key.as_ref().as_bytes(), shard_count, &self.hasher)
+    }
+
+    /// The prefix of `shard`, like `shard=0007`.
+    pub fn prefix(&self, shard: u64) -> Path {
+        Path::from(format!("{}{:04}", PREFIX, shard))
+    }
+
+    /// Where `key` is stored, like `shard=0007/key`.
+    pub fn path_for(&self, key: &Path) -> Path {
+        self.path_at(key, self.shard_of(key))
+    }
+
+    fn path_at(&self, key: &Path, shard: u64) -> Path {
+        let prefix = format!("{}{:04}", PREFIX, shard);
+        std::iter::once(PathPart::from(prefix.as_str()))
+            .chain(key.parts())
+            .collect()
+    }
+
+    /// The shard and key of a stored path, or `None` if it does not start with a shard prefix.
+    pub fn key_of(&self, path: &Path) -> Option<(u64, Path)> {
+        let mut parts = path.parts();
+        let shard = parts.next()?.as_ref().strip_prefix(PREFIX)?.parse().ok()?;
+        Some((shard, parts.collect()))
+    }
+
+    /// The prefixes that can hold objects needing to move when resizing to `shard_count`.
+    ///
+    /// Growing moves objects out of every existing prefix, but only into the new ones, so the new prefixes never need listing. Shrinking only moves objects out of the removed prefixes.
+    pub fn affected_prefixes(&self, shard_count: u64) -> Vec<Path> {
+        let sources = if shard_count >= self.shard_count {
+            0..self.shard_count
+        } else {
+            shard_count..self.shard_count
+        };
+        sources.map(|shard| self.prefix(shard)).collect()
+    }
+
+    /// Lists [PrefixSharder::affected_prefixes] in `store` and returns the objects that move when resizing to `shard_count`.
+    ///
+    /// # Panics
+    ///
+    /// If shard_count == 0.
+    pub async fn plan_resize(
+        &self,
+        store: &(impl ObjectStore + ?Sized),
+        shard_count: u64,
+    ) -> Result<Vec<ObjectMove>> {
+        assert_ne!(shard_count, 0, "shard_count must be > 0");
+
+        let mut moves = Vec::new();
+        for prefix in self.affected_prefixes(shard_count) {
+            let objects = store.list(Some(&prefix)).try_collect::<Vec<_>>().await?;
+            for object in objects {
+                let Some((from_shard, key)) = self.key_of(&object.location) else {
+                    continue;
+                };
+                let to_shard = self.shard_at(&key, shard_count);
+                if to_shard == from_shard {
+                    continue;
+                }
+                moves.push(ObjectMove {
+                    from: object.location,
+                    to: self.path_at(&key, to_shard),
+                    key,
+                });
+            }
+        }
+        Ok(moves)
+    }
+}
+
+/// An object that must move. See [PrefixSharder::plan_resize].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ObjectMove {
+    /// The object's key, without a shard prefix.
+    pub key: Path,
+    /// Where the object is.
+    pub from: Path,
+    /// Where the object belongs after resizing.
+    pub to: Path,
+}
+
+impl ObjectMove {
+    /// Renames the object into place.
+    pub async fn apply(&self, store: &(impl ObjectStore + ?Sized)) -> Result<()> {
+        store.rename(&self.from, &self.to).await
+    }
+}
+
+/// Wraps an [ObjectStore] so every key is stored under its [PrefixSharder] path.
+#[derive(Clone, Debug)]
+pub struct ShardedObjectStore<T, S> {
+    inner: T,
+    sharder: PrefixSharder<S>,
+}
+
+impl<T: ObjectStore, S: BuildHasher> ShardedObjectStore<T, S> {
+    /// Shards the objects in `inner` across `shard_count` prefixes.
+    ///
+    /// # Panics
+    ///
+    /// If shard_count == 0.
+    pub fn new(inner: T, shard_count: u64, hasher: S) -> Self {
+        ShardedObjectStore {
+            inner,
+            sharder: PrefixSharder::new(shard_count, hasher),
+        }
+    }
+
+    /// The underlying store.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// How keys map to paths.
+    pub fn sharder(&self) -> &PrefixSharder<S> {
+        &self.sharder
+    }
+
+    /// Stores `payload` at `key`.
+    pub async fn put(&self, key: &Path, payload: PutPayload) -> Result<PutResult> {
+        self.inner.put(&self.sharder.path_for(key), payload).await
+    }
+
+    /// Fetches the object at `key`.
+    pub async fn get(&self, key: &Path) -> Result<GetResult> {
+        self.inner.get(&self.sharder.path_for(key)).await
+    }
+
+    /// Fetches the metadata of the object at `key`.
+    pub async fn head(&self, key: &Path) -> Result<ObjectMeta> {
+        self.inner.head(&self.sharder.path_for(key)).await
+    }
+
+    /// Deletes the object at `key`.
+    pub async fn delete(&self, key: &Path) -> Result<()> {
+        self.inner.delete(&self.sharder.path_for(key)).await
+    }
+
+    /// Lists every object in `shard`, with their stored paths.
+    pub async fn list_shard(&self, shard: u64) -> Result<Vec<ObjectMeta>> {
+        self.inner
+            .list(Some(&self.sharder.prefix(shard)))
+            .try_collect()
+            .await
+    }
+
+    /// Moves the objects that change shard and switches to `shard_count` prefixes, returning the moves.
+    ///
+    /// If moving fails partway, the store keeps the old shard count and resizing again finishes the job.
+    ///
+    /// # Panics
+    ///
+    /// If shard_count == 0.
+    pub async fn resize(&mut self, shard_count: u64) -> Result<Vec<ObjectMove>> {
+        let moves = self.sharder.plan_resize(&self.inner, shard_count).await?;
+        for object_move in &moves {
+            object_move.apply(&self.inner).await?;
+        }
+        self.sharder.shard_count = shard_count;
+        Ok(moves)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{shard_for_bytes, BuildSeaHasher};
+    use ::object_store::memory::InMemory;
+
+    #[test]
+    fn paths_round_trip() {
+        let sharder = PrefixSharder::new(50, BuildSeaHasher);
+        let key = Path::from("events/2024/01/a.parquet");
+        let shard = shard_for_bytes(b"events/2024/01/a.parquet", 50);
+
+        let path = sharder.path_for(&key);
+        assert_eq!(
+            path.as_ref(),
+            format!("shard={:04}/events/2024/01/a.parquet", shard)
+        );
+        assert_eq!(sharder.key_of(&path), Some((shard, key)));
+        assert_eq!(sharder.key_of(&Path::from("other/a")), None);
+    }
+
+    #[test]
+    fn shrinking_only_affects_removed_prefixes() {
+        let sharder = PrefixSharder::new(6, BuildSeaHasher);
+        assert_eq!(sharder.affected_prefixes(8).len(), 6);
+        assert_eq!(
+            sharder.affected_prefixes(4),
+            [Path::from("shard=0004"), Path::from("shard=0005")]
+        );
+    }
+
+    #[::tokio::test]
+    async fn resize_moves_objects_into_new_prefixes() {
+        let mut store = ShardedObjectStore::new(InMemory::new(), 4, BuildSeaHasher);
+        let keys = (0..100)
+            .map(|i| Path::from(format!("data/{}", i)))
+            .collect::<Vec<_>>();
+        for key in &keys {
+            store
+                .put(key, PutPayload::from(key.to_string()))
+                .await
+                .unwrap();
+        }
+
+        let moves = store.resize(7).await.unwrap();
+        assert!(!moves.is_empty());
+        for object_move in &moves {
+            let (shard, _) = store.sharder().key_of(&object_move.to).unwrap();
+            assert!(shard >= 4);
+        }
+
+        for key in &keys {
+            let bytes = store.get(key).await.unwrap().bytes().await.unwrap();
+            assert_eq!(bytes, key.to_string());
+        }
+        let mut total = 0;
+        for shard in 0..7 {
+            total += store.list_shard(shard).await.unwrap().len();
+        }
+        assert_eq!(total, 100);
+    }
+}
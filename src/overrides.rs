@@ -0,0 +1,175 @@
+use crate::{shard_for_key_hash, TombstoneTopology};
+use std::collections::HashMap;
+use std::hash::*;
+
+/// A pinned key whose shard is no longer available. See [Overrides].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct OverrideConflict {
+    /// Hash of the pinned key.
+    pub key_hash: u64,
+    /// The shard it is pinned to.
+    pub shard: u64,
+}
+
+impl std::fmt::Display for OverrideConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "key with hash {:#018x} is pinned to unavailable shard {}",
+            self.key_hash, self.shard
+        )
+    }
+}
+
+impl std::error::Error for OverrideConflict {}
+
+/// Pins specific keys to specific shards, in front of Faro Sharding.
+///
+/// Pins are stored by key hash, so the keys themselves are not kept. A pin applies as long as its shard exists. Lookups for a key pinned to a removed shard return an [OverrideConflict] rather than silently falling back, so the pin can be revisited.
+#[derive(Clone, Debug)]
+pub struct Overrides<S> {
+    pins: HashMap<u64, u64>,
+    hasher: S,
+}
+
+impl<S: BuildHasher> Overrides<S> {
+    /// Creates an empty override table. Use the same hasher as the sharding it sits in front of.
+    pub fn new(hasher: S) -> Self {
+        Overrides {
+            pins: HashMap::new(),
+            hasher,
+        }
+    }
+
+    /// Pins `key` to `shard`, returning its previous pin.
+    pub fn pin(&mut self, key: impl Hash, shard: u64) -> Option<u64> {
+        self.pins.insert(self.hasher.hash_one(key), shard)
+    }
+
+    /// Removes the pin for `key`, returning it.
+    pub fn unpin(&mut self, key: impl Hash) -> Option<u64> {
+        self.pins.remove(&self.hasher.hash_one(key))
+    }
+
+    /// The shard `key` is pinned to, if any.
+    pub fn pinned(&self, key: impl Hash) -> Option<u64> {
+        self.pins.get(&self.hasher.hash_one(key)).copied()
+    }
+
+    /// Number of pinned keys.
+    pub fn len(&self) -> usize {
+        self.pins.len()
+    }
+
+    /// Whether no keys are pinned.
+    pub fn is_empty(&self) -> bool {
+        self.pins.is_empty()
+    }
+
+    /// Returns the pinned shard for `key` if there is one, otherwise [crate::shard_with_hasher]'s shard.
+    ///
+    /// # Panics
+    ///
+    /// If total_destinations == 0.
+    pub fn shard_for(
+        &self,
+        key: impl Hash,
+        total_destinations: u64,
+    ) -> Result<u64, OverrideConflict> {
+        assert_ne!(total_destinations, 0, "total_destinations must be > 0");
+
+        let key_hash = self.hasher.hash_one(key);
+        match self.pins.get(&key_hash) {
+            Some(&shard) if shard < total_destinations => Ok(shard),
+            Some(&shard) => Err(OverrideConflict { key_hash, shard }),
+            None => Ok(shard_for_key_hash(
+                key_hash,
+                total_destinations,
+                &self.hasher,
+            )),
+        }
+    }
+
+    /// Returns the pinned shard for `key` if there is one, otherwise `topology`'s shard.
+    ///
+    /// # Panics
+    ///
+    /// If the topology has no live destinations.
+    pub fn shard_in<T: BuildHasher>(
+        &self,
+        key: impl Hash,
+        topology: &TombstoneTopology<T>,
+    ) -> Result<u64, OverrideConflict> {
+        let key_hash = self.hasher.hash_one(&key);
+        match self.pins.get(&key_hash) {
+            Some(&shard) if topology.is_removed(shard) => Err(OverrideConflict { key_hash, shard }),
+            Some(&shard) => Ok(shard),
+            None => Ok(topology.shard_for(key)),
+        }
+    }
+
+    /// Every pin whose shard is not `available`, ordered by shard.
+    pub fn conflicts(&self, available: impl Fn(u64) -> bool) -> Vec<OverrideConflict> {
+        let mut conflicts = self
+            .pins
+            .iter()
+            .filter(|(_, &shard)| !available(shard))
+            .map(|(&key_hash, &shard)| OverrideConflict { key_hash, shard })
+            .collect::<Vec<_>>();
+        conflicts.sort_by_key(|c| (c.shard, c.key_hash));
+        conflicts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{shard_for, BuildSeaHasher};
+
+    #[test]
+    fn pins_take_precedence_and_survive_growth() {
+        let mut overrides = Overrides::new(BuildSeaHasher);
+        assert_eq!(overrides.pin("hot", 1), None);
+
+        for total in 2..100 {
+            assert_eq!(overrides.shard_for("hot", total), Ok(1));
+            assert_eq!(
+                overrides.shard_for("foo", total),
+                Ok(shard_for("foo", total))
+            );
+        }
+
+        assert_eq!(overrides.unpin("hot"), Some(1));
+        assert_eq!(overrides.shard_for("hot", 50), Ok(shard_for("hot", 50)));
+    }
+
+    #[test]
+    fn removed_pinned_shards_conflict() {
+        let mut overrides = Overrides::new(BuildSeaHasher);
+        overrides.pin("a", 2);
+        overrides.pin("b", 3);
+
+        let mut topology = TombstoneTopology::new(5, BuildSeaHasher);
+        assert_eq!(overrides.shard_in("a", &topology), Ok(2));
+
+        topology.remove(2);
+        let conflict = OverrideConflict {
+            key_hash: BuildSeaHasher.hash_one("a"),
+            shard: 2,
+        };
+        assert_eq!(overrides.shard_in("a", &topology), Err(conflict));
+        assert_eq!(overrides.shard_in("b", &topology), Ok(3));
+        assert_eq!(
+            overrides.conflicts(|s| !topology.is_removed(s)),
+            vec![conflict]
+        );
+
+        assert_eq!(
+            overrides.shard_for("b", 3),
+            Err(OverrideConflict {
+                key_hash: BuildSeaHasher.hash_one("b"),
+                shard: 3
+            })
+        );
+    }
+}
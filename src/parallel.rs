@@ -0,0 +1,82 @@
+use crate::shard_with_hasher;
+use rayon::prelude::*;
+use std::hash::*;
+
+/// Pairs every key in `keys` with its shard, in parallel.
+///
+/// Equivalent to calling [shard_with_hasher] on each key.
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub fn par_shards<'h, K: Hash + Send>(
+    keys: impl IntoParallelIterator<Item = K> + 'h,
+    total_destinations: u64,
+    hasher: &'h (impl BuildHasher + Sync),
+) -> impl ParallelIterator<Item = (K, u64)> + 'h {
+    assert_ne!(total_destinations, 0, "total_destinations must be > 0");
+
+    keys.into_par_iter().map(move |key| {
+        let shard = shard_with_hasher(&key, total_destinations, hasher);
+        (key, shard)
+    })
+}
+
+/// Groups `keys` by shard in parallel. The returned `Vec` has one group per destination, indexed by shard.
+///
+/// Keys from an ordered source, such as a `Vec` or a range, keep their relative order within each group.
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub fn par_group_by_shard<K: Hash + Send>(
+    keys: impl IntoParallelIterator<Item = K>,
+    total_destinations: u64,
+    hasher: &(impl BuildHasher + Sync),
+) -> Vec<Vec<K>> {
+    let empty = || {
+        (0..total_destinations)
+            .map(|_| Vec::new())
+            .collect::<Vec<_>>()
+    };
+
+    par_shards(keys, total_destinations, hasher)
+        .fold(empty, |mut groups, (key, shard)| {
+            groups[shard as usize].push(key);
+            groups
+        })
+        .reduce(empty, |mut left, right| {
+            for (group, mut keys) in left.iter_mut().zip(right) {
+                group.append(&mut keys);
+            }
+            left
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{shard_for, BuildSeaHasher};
+
+    #[test]
+    fn matches_individual_calls() {
+        let shards = par_shards(0..10_000u64, 73, &BuildSeaHasher).collect::<Vec<_>>();
+        assert_eq!(shards.len(), 10_000);
+        for (key, shard) in shards {
+            assert_eq!(shard_for(key, 73), shard);
+        }
+    }
+
+    #[test]
+    fn groups_keep_input_order() {
+        let groups = par_group_by_shard(0..10_000u64, 7, &BuildSeaHasher);
+        assert_eq!(groups.len(), 7);
+
+        for (shard, group) in groups.iter().enumerate() {
+            let expected = (0..10_000u64)
+                .filter(|key| shard_for(key, 7) == shard as u64)
+                .collect::<Vec<_>>();
+            assert_eq!(group, &expected);
+        }
+    }
+}
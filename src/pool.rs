@@ -0,0 +1,191 @@
+use crate::shard_with_hasher;
+use std::hash::*;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex, RwLock};
+
+type Idle<C> = Arc<Mutex<Vec<C>>>;
+
+/// One connection pool per destination, checked out by key.
+///
+/// Connections are created on demand by `connect(shard)` and returned to their shard's pool when the [PooledConnection] is dropped, up to `max_idle` per shard. Adding a destination creates an empty pool without touching the others, and only the keys that move onto it check out from it.
+pub struct ShardedPool<C, F, S> {
+    pools: RwLock<Vec<Idle<C>>>,
+    connect: F,
+    max_idle: usize,
+    hasher: S,
+}
+
+impl<C, E, F, S> ShardedPool<C, F, S>
+where
+    F: Fn(u64) -> Result<C, E>,
+    S: BuildHasher,
+{
+    /// Creates empty pools for `total_destinations` destinations.
+    ///
+    /// # Panics
+    ///
+    /// If total_destinations == 0.
+    pub fn new(total_destinations: u64, max_idle: usize, connect: F, hasher: S) -> Self {
+        assert_ne!(total_destinations, 0, "total_destinations must be > 0");
+
+        ShardedPool {
+            pools: RwLock::new((0..total_destinations).map(|_| Idle::default()).collect()),
+            connect,
+            max_idle,
+            hasher,
+        }
+    }
+
+    /// Number of destinations.
+    pub fn total_destinations(&self) -> u64 {
+        self.pools.read().unwrap().len() as u64
+    }
+
+    /// Adds an empty pool for a new last destination, returning its shard.
+    pub fn add_destination(&self) -> u64 {
+        let mut pools = self.pools.write().unwrap();
+        pools.push(Idle::default());
+        pools.len() as u64 - 1
+    }
+
+    /// The shard `key` checks out from.
+    pub fn shard_of(&self, key: impl Hash) -> u64 {
+        shard_with_hasher(key, self.total_destinations(), &self.hasher)
+    }
+
+    /// Checks out an idle connection to `key`'s shard, connecting a new one if there is none.
+    pub fn checkout(&self, key: impl Hash) -> Result<PooledConnection<C>, E> {
+        let (shard, idle) = {
+            let pools = self.pools.read().unwrap();
+            let shard = shard_with_hasher(key, pools.len() as u64, &self.hasher);
+            (shard, Arc::clone(&pools[shard as usize]))
+        };
+
+        let existing = idle.lock().unwrap().pop();
+        let connection = match existing {
+            Some(connection) => connection,
+            None => (self.connect)(shard)?,
+        };
+
+        Ok(PooledConnection {
+            connection: Some(connection),
+            shard,
+            idle,
+            max_idle: self.max_idle,
+        })
+    }
+
+    /// Number of idle connections in `shard`'s pool. 0 if `shard` is out of range.
+    pub fn idle(&self, shard: u64) -> usize {
+        self.pools
+            .read()
+            .unwrap()
+            .get(shard as usize)
+            .map_or(0, |idle| idle.lock().unwrap().len())
+    }
+}
+
+/// A connection checked out of a [ShardedPool]. Returned to its shard's pool on drop.
+pub struct PooledConnection<C> {
+    connection: Option<C>,
+    shard: u64,
+    idle: Idle<C>,
+    max_idle: usize,
+}
+
+impl<C> PooledConnection<C> {
+    /// The shard this connection belongs to.
+    pub fn shard(&self) -> u64 {
+        self.shard
+    }
+
+    /// Drops the connection instead of returning it to the pool, such as after an error left it unusable.
+    pub fn discard(mut self) {
+        self.connection = None;
+    }
+}
+
+impl<C> Deref for PooledConnection<C> {
+    type Target = C;
+
+    fn deref(&self) -> &C {
+        self.connection.as_ref().expect("present until dropped")
+    }
+}
+
+impl<C> DerefMut for PooledConnection<C> {
+    fn deref_mut(&mut self) -> &mut C {
+        self.connection.as_mut().expect("present until dropped")
+    }
+}
+
+impl<C> Drop for PooledConnection<C> {
+    fn drop(&mut self) {
+        if let Some(connection) = self.connection.take() {
+            let mut idle = self.idle.lock().unwrap();
+            if idle.len() < self.max_idle {
+                idle.push(connection);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{shard_for, BuildSeaHasher};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    #[test]
+    fn reuses_connections_per_shard() {
+        let connects = AtomicU64::new(0);
+        let pool = ShardedPool::new(
+            4,
+            1,
+            |shard| {
+                connects.fetch_add(1, Ordering::Relaxed);
+                Ok::<_, ()>(format!("conn to {}", shard))
+            },
+            BuildSeaHasher,
+        );
+
+        let shard = shard_for("foo", 4);
+        {
+            let a = pool.checkout("foo").unwrap();
+            let b = pool.checkout("foo").unwrap();
+            assert_eq!((a.shard(), &*a), (shard, &format!("conn to {}", shard)));
+            b.discard();
+        }
+        assert_eq!(connects.load(Ordering::Relaxed), 2);
+        assert_eq!(pool.idle(shard), 1);
+
+        drop(pool.checkout("foo").unwrap());
+        assert_eq!(connects.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn growth_keeps_existing_pools() {
+        let pool = ShardedPool::new(3, 8, Ok::<_, ()>, BuildSeaHasher);
+        for key in 0..100u64 {
+            drop(pool.checkout(key).unwrap());
+        }
+        let idle_before = (0..3).map(|shard| pool.idle(shard)).collect::<Vec<_>>();
+
+        assert_eq!(pool.add_destination(), 3);
+        assert_eq!(
+            (0..3).map(|shard| pool.idle(shard)).collect::<Vec<_>>(),
+            idle_before
+        );
+        for key in 0..100u64 {
+            let connection = pool.checkout(key).unwrap();
+            assert_eq!(*connection, shard_for(key, 4));
+            assert!(connection.shard() == shard_for(key, 3) || connection.shard() == 3);
+        }
+    }
+
+    #[test]
+    fn connect_errors_are_returned() {
+        let pool = ShardedPool::new(2, 1, |_| Err::<(), _>("refused"), BuildSeaHasher);
+        assert_eq!(pool.checkout("foo").err(), Some("refused"));
+    }
+}
@@ -0,0 +1,95 @@
+use crate::shard_with_hasher;
+use std::collections::BinaryHeap;
+use std::hash::*;
+use std::sync::Mutex;
+
+/// A priority queue split across shards, each guarded by its own lock.
+///
+/// Items are routed to a shard by a separate key, so pushes for different keys rarely contend.
+pub struct ShardedPriorityQueue<T, S> {
+    shards: Vec<Mutex<BinaryHeap<T>>>,
+    hasher: S,
+}
+
+impl<T: Ord, S: BuildHasher> ShardedPriorityQueue<T, S> {
+    /// Creates an empty queue with `total_shards` heaps.
+    ///
+    /// # Panics
+    ///
+    /// If total_shards == 0.
+    pub fn new(total_shards: u64, hasher: S) -> Self {
+        assert_ne!(total_shards, 0, "total_shards must be > 0");
+
+        ShardedPriorityQueue {
+            shards: (0..total_shards).map(|_| Default::default()).collect(),
+            hasher,
+        }
+    }
+
+    /// Pushes `item` onto the heap of the shard for `key`.
+    pub fn push<K: Hash>(&self, key: K, item: T) {
+        let shard = shard_with_hasher(key, self.shards.len() as u64, &self.hasher);
+        self.shards[shard as usize].lock().unwrap().push(item);
+    }
+
+    /// Removes and returns the largest item in `shard`, or `None` if it is empty or out of range.
+    pub fn pop_max_from_shard(&self, shard: u64) -> Option<T> {
+        self.shards.get(shard as usize)?.lock().unwrap().pop()
+    }
+
+    /// Returns the largest item across all shards without removing it.
+    ///
+    /// Shard locks are acquired one at a time, so concurrent pushes may or may not be observed.
+    pub fn global_max(&self) -> Option<T>
+    where
+        T: Clone,
+    {
+        self.shards
+            .iter()
+            .filter_map(|shard| shard.lock().unwrap().peek().cloned())
+            .max()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn concurrent_pushes_and_pops_lose_nothing() {
+        let queue = ShardedPriorityQueue::new(8, crate::BuildSeaHasher);
+
+        std::thread::scope(|s| {
+            for t in 0..4u64 {
+                let queue = &queue;
+                s.spawn(move || {
+                    for i in 0..250 {
+                        queue.push(i, t * 250 + i);
+                    }
+                });
+            }
+        });
+
+        assert_eq!(queue.global_max(), Some(999));
+
+        let popped = std::sync::Mutex::new(Vec::new());
+        std::thread::scope(|s| {
+            for shard in 0..8 {
+                let (queue, popped) = (&queue, &popped);
+                s.spawn(move || {
+                    let mut last = None;
+                    while let Some(item) = queue.pop_max_from_shard(shard) {
+                        assert!(last.is_none_or(|l| l >= item));
+                        last = Some(item);
+                        popped.lock().unwrap().push(item);
+                    }
+                });
+            }
+        });
+
+        let mut popped = popped.into_inner().unwrap();
+        popped.sort();
+        assert_eq!(popped, (0..1000).collect::<Vec<_>>());
+        assert_eq!(queue.global_max(), None);
+    }
+}
@@ -0,0 +1,89 @@
+use crate::ShardingStrategy;
+use std::collections::HashMap;
+use std::hash::*;
+
+/// Wraps a [ShardingStrategy] with a table of forced shard assignments.
+///
+/// Useful for pinning a specific key to a specific shard in tests without changing how any other key is distributed.
+pub struct ShardingProxy<'a, S> {
+    inner: &'a dyn ShardingStrategy,
+    total_destinations: u64,
+    hasher: S,
+    overrides: HashMap<u64, u64>,
+}
+
+impl<'a, S: BuildHasher> ShardingProxy<'a, S> {
+    /// Creates a proxy with no overrides.
+    ///
+    /// Keys are hashed with `hasher` before being checked against the override table and passed to `inner`.
+    pub fn new(inner: &'a dyn ShardingStrategy, total_destinations: u64, hasher: S) -> Self {
+        ShardingProxy {
+            inner,
+            total_destinations,
+            hasher,
+            overrides: HashMap::new(),
+        }
+    }
+
+    /// Forces keys hashing to `key_hash` onto `shard`.
+    ///
+    /// # Panics
+    ///
+    /// If shard >= the total_destinations this proxy was created with.
+    pub fn force_shard(&mut self, key_hash: u64, shard: u64) {
+        assert!(
+            shard < self.total_destinations,
+            "shard {shard} out of range for {} destinations",
+            self.total_destinations
+        );
+        self.overrides.insert(key_hash, shard);
+    }
+
+    /// Removes the override for `key_hash`, if any.
+    pub fn clear_override(&mut self, key_hash: u64) {
+        self.overrides.remove(&key_hash);
+    }
+
+    /// Returns the hash of `key` used for override lookups.
+    pub fn key_hash(&self, key: impl Hash) -> u64 {
+        self.hasher.hash_one(key)
+    }
+
+    /// Returns the forced shard for `key` if there is one, otherwise the inner strategy's shard.
+    ///
+    /// Overrides that do not fit within `total_destinations` are ignored.
+    pub fn shard_for(&self, key: impl Hash, total_destinations: u64) -> u64 {
+        let key_hash = self.key_hash(key);
+        match self.overrides.get(&key_hash) {
+            Some(&shard) if shard < total_destinations => shard,
+            _ => self.inner.shard_for_hash(key_hash, total_destinations),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{shard_with_hasher, BuildSeaHasher, FaroStrategy};
+
+    #[test]
+    fn overrides_only_the_forced_key() {
+        let faro = FaroStrategy(BuildSeaHasher);
+        let mut proxy = ShardingProxy::new(&faro, 16, BuildSeaHasher);
+
+        let forced = (shard_with_hasher("foo", 16, &BuildSeaHasher) + 1) % 16;
+        proxy.force_shard(proxy.key_hash("foo"), forced);
+
+        assert_eq!(proxy.shard_for("foo", 16), forced);
+        assert_eq!(
+            proxy.shard_for("bar", 16),
+            shard_with_hasher("bar", 16, &BuildSeaHasher)
+        );
+
+        proxy.clear_override(proxy.key_hash("foo"));
+        assert_eq!(
+            proxy.shard_for("foo", 16),
+            shard_with_hasher("foo", 16, &BuildSeaHasher)
+        );
+    }
+}
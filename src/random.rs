@@ -0,0 +1,44 @@
+use crate::shard_for_key_hash;
+use core::hash::*;
+
+/// Returns a pseudo-random shard for `key` that depends on `sample_seed`.
+///
+/// `sample_seed` is XORed into the key's hash before running the Faro chain, so each seed gives an independent uniform assignment of keys to shards. Varying the seed over `0..N` samples `N` assignments for the same key, as in Monte Carlo simulations. For a fixed seed, adding destinations still only moves keys onto the new destination.
+pub fn random_shard(
+    key: impl Hash,
+    total_destinations: u64,
+    sample_seed: u64,
+    hasher: &impl BuildHasher,
+) -> u64 {
+    shard_for_key_hash(
+        hasher.hash_one(key) ^ sample_seed,
+        total_destinations,
+        hasher,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BuildSeaHasher;
+
+    use proptest::*;
+
+    proptest! {
+        #[test]
+        fn seeds_distribute_uniformly(key: String, total_destinations in 2u64..=8) {
+            let mut counts = vec![0u64; total_destinations as usize];
+            for seed in 0..1000 {
+                counts[random_shard(&key, total_destinations, seed, &BuildSeaHasher) as usize] += 1;
+            }
+
+            // Chi-square test at roughly p = 1e-7 for up to 7 degrees of freedom, so 256 cases essentially never fail by chance.
+            let expected = 1000. / total_destinations as f64;
+            let chi_square: f64 = counts
+                .iter()
+                .map(|&c| (c as f64 - expected).powi(2) / expected)
+                .sum();
+            prop_assert!(chi_square < 45., "{counts:?}");
+        }
+    }
+}
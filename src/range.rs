@@ -0,0 +1,134 @@
+use crate::shard_for_key_hash;
+use core::hash::*;
+
+/// The part of a key that [RangeSharder] shards by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum KeyPrefix {
+    /// The first this many bytes. Shorter keys are used whole.
+    Length(usize),
+    /// Everything before the `count`th `delimiter`, so `Segments { delimiter: b'/', count: 2 }` takes `tenant/2024` from `tenant/2024/06/01`. Keys with fewer delimiters are used whole.
+    Segments {
+        /// The byte separating segments.
+        delimiter: u8,
+        /// How many segments to keep.
+        count: usize,
+    },
+}
+
+/// Shards keys by a prefix, so keys that share it stay together for range scans.
+///
+/// Faro Sharding is applied to the prefix, hashed as its bytes as in [crate::shard_bytes_with_hasher], so growing still only moves prefixes onto the new destination. This is a deliberate trade-off against balance: a shard holds whole prefixes, so a prefix with many keys makes its shard that much bigger. Choose a prefix with many distinct values, each with a bounded number of keys.
+///
+/// [RangeSharder::key_hash] is the hash of a key's prefix, for use with any `_for_key_hash` API, such as [crate::TombstoneTopology::shard_for_key_hash] or [crate::migration::Planner::plan_digests], given the same hasher.
+///
+/// ```
+/// # use faro_sharding::{KeyPrefix, RangeSharder};
+/// # use std::hash::BuildHasherDefault;
+/// # use std::collections::hash_map::DefaultHasher;
+/// let hasher = BuildHasherDefault::<DefaultHasher>::default();
+/// let sharder = RangeSharder::new(KeyPrefix::Segments { delimiter: b'/', count: 2 }, hasher);
+///
+/// assert_eq!(
+///     sharder.shard_for("tenant-4/2024-06/events/1", 16),
+///     sharder.shard_for("tenant-4/2024-06/events/2", 16),
+/// );
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct RangeSharder<S> {
+    prefix: KeyPrefix,
+    hasher: S,
+}
+
+impl<S: BuildHasher> RangeSharder<S> {
+    /// Creates a sharder that shards by `prefix`.
+    pub fn new(prefix: KeyPrefix, hasher: S) -> Self {
+        RangeSharder { prefix, hasher }
+    }
+
+    /// The part of `key` that decides its shard.
+    pub fn prefix<'k>(&self, key: &'k [u8]) -> &'k [u8] {
+        match self.prefix {
+            KeyPrefix::Length(len) => &key[..len.min(key.len())],
+            KeyPrefix::Segments { delimiter, count } => key
+                .iter()
+                .enumerate()
+                .filter(|&(_, &b)| b == delimiter)
+                .nth(count.wrapping_sub(1))
+                .map_or(key, |(end, _)| &key[..end]),
+        }
+    }
+
+    /// The hash of `key`'s prefix.
+    pub fn key_hash(&self, key: impl AsRef<[u8]>) -> u64 {
+        let mut state = self.hasher.build_hasher();
+        state.write(self.prefix(key.as_ref()));
+        state.finish()
+    }
+
+    /// Returns the shard for `key`, which is the shard of its prefix.
+    ///
+    /// # Panics
+    ///
+    /// If total_destinations == 0.
+    pub fn shard_for(&self, key: impl AsRef<[u8]>, total_destinations: u64) -> u64 {
+        shard_for_key_hash(self.key_hash(key), total_destinations, &self.hasher)
+    }
+
+    /// The hasher keys are sharded with.
+    pub fn hasher(&self) -> &S {
+        &self.hasher
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{shard_bytes_with_hasher, BuildSeaHasher};
+
+    #[test]
+    fn takes_prefixes() {
+        let by_length = RangeSharder::new(KeyPrefix::Length(3), BuildSeaHasher);
+        assert_eq!(by_length.prefix(b"abcdef"), b"abc");
+        assert_eq!(by_length.prefix(b"ab"), b"ab");
+
+        let segments = |count| {
+            RangeSharder::new(
+                KeyPrefix::Segments {
+                    delimiter: b'/',
+                    count,
+                },
+                BuildSeaHasher,
+            )
+        };
+        assert_eq!(segments(2).prefix(b"a/b/c/d"), b"a/b");
+        assert_eq!(segments(1).prefix(b"a/b/c/d"), b"a");
+        assert_eq!(segments(2).prefix(b"a/b"), b"a/b");
+        assert_eq!(segments(0).prefix(b"a/b"), b"a/b");
+    }
+
+    #[test]
+    fn shards_prefixes_as_bytes() {
+        let sharder = RangeSharder::new(KeyPrefix::Length(8), BuildSeaHasher);
+        for i in 0..1_000u64 {
+            let key = format!("{:08}:{}", i / 10, i);
+            assert_eq!(
+                sharder.shard_for(&key, 73),
+                shard_bytes_with_hasher(&key.as_bytes()[..8], 73, &BuildSeaHasher)
+            );
+        }
+    }
+
+    #[test]
+    fn plans_with_key_hashes() {
+        let sharder = RangeSharder::new(KeyPrefix::Length(4), BuildSeaHasher);
+        let keys = (0..1_000).map(|i| format!("{:04}-{}", i % 50, i));
+        let plan = crate::migration::Planner::new(10, 12, BuildSeaHasher)
+            .plan_digests(keys.clone().map(|k| sharder.key_hash(k)));
+
+        let moved = keys
+            .filter(|k| sharder.shard_for(k, 10) != sharder.shard_for(k, 12))
+            .count();
+        assert_eq!(plan.moved_keys(), moved as u64);
+        assert_eq!(moved % 20, 0);
+    }
+}
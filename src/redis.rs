@@ -0,0 +1,181 @@
+//! Assigning Redis Cluster hash slots to your own nodes with Faro Sharding.
+//!
+//! Keys map to slots exactly as Redis does, with CRC16 and hash tags, so clients and `CLUSTER KEYSLOT` agree. Slots are then assigned to nodes with [crate::shard_with_hasher], so adding a node only migrates slots onto it.
+
+use core::hash::BuildHasher;
+
+/// Number of hash slots in a Redis Cluster.
+pub const SLOT_COUNT: u16 = 16384;
+
+/// The Redis Cluster hash slot of `key`, as returned by `CLUSTER KEYSLOT`.
+///
+/// If `key` contains a non-empty `{...}` hash tag, only the tag is hashed.
+pub fn key_slot(key: &[u8]) -> u16 {
+    crc16(hash_tag(key)) % SLOT_COUNT
+}
+
+fn hash_tag(key: &[u8]) -> &[u8] {
+    let Some(open) = key.iter().position(|&b| b == b'{') else {
+        return key;
+    };
+    match key[open + 1..].iter().position(|&b| b == b'}') {
+        Some(len) if len > 0 => &key[open + 1..open + 1 + len],
+        _ => key,
+    }
+}
+
+/// CRC16-CCITT (XMODEM), the variant Redis uses.
+fn crc16(bytes: &[u8]) -> u16 {
+    let mut crc = 0u16;
+    for &byte in bytes {
+        crc ^= u16::from(byte) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// The node that owns `slot` among `total_nodes`.
+///
+/// # Panics
+///
+/// If total_nodes == 0.
+pub fn slot_node(slot: u16, total_nodes: u64, hasher: &impl BuildHasher) -> u64 {
+    crate::shard_with_hasher(slot, total_nodes, hasher)
+}
+
+/// The owner of every slot for a fixed number of nodes.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SlotMap {
+    owners: Vec<u64>,
+    total_nodes: u64,
+}
+
+#[cfg(feature = "std")]
+impl SlotMap {
+    /// Assigns all [SLOT_COUNT] slots among `total_nodes`.
+    ///
+    /// # Panics
+    ///
+    /// If total_nodes == 0.
+    pub fn new(total_nodes: u64, hasher: &impl BuildHasher) -> Self {
+        assert_ne!(total_nodes, 0, "total_nodes must be > 0");
+
+        SlotMap {
+            owners: (0..SLOT_COUNT)
+                .map(|slot| slot_node(slot, total_nodes, hasher))
+                .collect(),
+            total_nodes,
+        }
+    }
+
+    /// Number of nodes slots are assigned to.
+    pub fn total_nodes(&self) -> u64 {
+        self.total_nodes
+    }
+
+    /// The node owning `slot`.
+    ///
+    /// # Panics
+    ///
+    /// If slot >= [SLOT_COUNT].
+    pub fn node_for_slot(&self, slot: u16) -> u64 {
+        self.owners[usize::from(slot)]
+    }
+
+    /// The node owning the slot of `key`.
+    pub fn node_for_key(&self, key: &[u8]) -> u64 {
+        self.node_for_slot(key_slot(key))
+    }
+
+    /// Contiguous runs of slots with the same owner, in slot order.
+    pub fn slot_ranges(&self) -> Vec<(core::ops::RangeInclusive<u16>, u64)> {
+        let mut ranges = Vec::new();
+        let mut start = 0;
+        for slot in 1..=SLOT_COUNT {
+            let node = self.owners[usize::from(start)];
+            if slot == SLOT_COUNT || self.owners[usize::from(slot)] != node {
+                ranges.push((start..=slot - 1, node));
+                start = slot;
+            }
+        }
+        ranges
+    }
+
+    /// Slots whose owner differs in `other`, as `(slot, self's node, other's node)`.
+    pub fn changed_slots<'a>(
+        &'a self,
+        other: &'a SlotMap,
+    ) -> impl Iterator<Item = (u16, u64, u64)> + 'a {
+        (0..SLOT_COUNT)
+            .map(|slot| (slot, self.node_for_slot(slot), other.node_for_slot(slot)))
+            .filter(|(_, from, to)| from != to)
+    }
+
+    /// One `CLUSTER SETSLOT <slot> NODE <node-id>` command per slot, where `node_ids[n]` is the Redis node id of node `n`.
+    ///
+    /// # Panics
+    ///
+    /// If node_ids.len() != total_nodes.
+    pub fn setslot_commands<'a>(
+        &'a self,
+        node_ids: &'a [&str],
+    ) -> impl Iterator<Item = String> + 'a {
+        assert_eq!(
+            node_ids.len() as u64,
+            self.total_nodes,
+            "need one node id per node"
+        );
+
+        self.owners.iter().enumerate().map(move |(slot, &node)| {
+            format!("CLUSTER SETSLOT {} NODE {}", slot, node_ids[node as usize])
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BuildSeaHasher;
+
+    #[test]
+    fn matches_redis() {
+        assert_eq!(crc16(b"123456789"), 0x31c3);
+        assert_eq!(key_slot(b"foo"), 12182);
+        assert_eq!(key_slot(b"bar"), 5061);
+        assert_eq!(key_slot(b"{user1000}.following"), key_slot(b"user1000"));
+        assert_eq!(key_slot(b"foo{}{bar}"), crc16(b"foo{}{bar}") % SLOT_COUNT);
+        assert_eq!(key_slot(b"foo{{bar}}zap"), key_slot(b"{bar"));
+    }
+
+    #[test]
+    fn growing_only_moves_slots_to_the_new_node() {
+        let before = SlotMap::new(5, &BuildSeaHasher);
+        let after = SlotMap::new(6, &BuildSeaHasher);
+
+        let moved = before.changed_slots(&after).collect::<Vec<_>>();
+        assert!(!moved.is_empty());
+        assert!(moved.iter().all(|&(_, _, to)| to == 5));
+    }
+
+    #[test]
+    fn ranges_cover_every_slot() {
+        let map = SlotMap::new(3, &BuildSeaHasher);
+        let ranges = map.slot_ranges();
+        assert_eq!(ranges.first().unwrap().0.start(), &0);
+        assert_eq!(ranges.last().unwrap().0.end(), &(SLOT_COUNT - 1));
+        for (slots, node) in ranges {
+            assert!(slots.clone().all(|slot| map.node_for_slot(slot) == node));
+        }
+
+        let commands = map.setslot_commands(&["a", "b", "c"]).collect::<Vec<_>>();
+        assert_eq!(commands.len(), usize::from(SLOT_COUNT));
+        assert!(commands[0].starts_with("CLUSTER SETSLOT 0 NODE "));
+    }
+}
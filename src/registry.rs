@@ -0,0 +1,154 @@
+use crate::shard_with_hasher;
+use std::collections::HashMap;
+use std::hash::*;
+
+/// Configuration for a single named shard space in a [ShardingRegistry].
+#[derive(Clone, Debug)]
+pub struct ShardingConfig<S> {
+    /// Number of destinations keys in this space are sharded across.
+    pub total_destinations: u64,
+    /// Hasher used for keys in this space.
+    pub hasher: S,
+}
+
+/// Error returned by [ShardingRegistry] operations.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RegistryError {
+    /// A space with this name is already registered.
+    DuplicateSpace(String),
+    /// No space with this name is registered.
+    UnknownSpace(String),
+    /// A space was configured with zero destinations.
+    ZeroDestinations,
+}
+
+impl std::fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RegistryError::DuplicateSpace(name) => write!(f, "space {name:?} already registered"),
+            RegistryError::UnknownSpace(name) => write!(f, "space {name:?} is not registered"),
+            RegistryError::ZeroDestinations => write!(f, "total_destinations must be > 0"),
+        }
+    }
+}
+
+impl std::error::Error for RegistryError {}
+
+/// A collection of independent, named shard spaces, each with its own destination count.
+#[derive(Clone, Debug)]
+pub struct ShardingRegistry<S> {
+    spaces: HashMap<String, ShardingConfig<S>>,
+}
+
+impl<S> Default for ShardingRegistry<S> {
+    fn default() -> Self {
+        ShardingRegistry {
+            spaces: HashMap::new(),
+        }
+    }
+}
+
+impl<S: BuildHasher> ShardingRegistry<S> {
+    /// Creates a registry with no spaces.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a new space called `name`.
+    pub fn register(&mut self, name: &str, config: ShardingConfig<S>) -> Result<(), RegistryError> {
+        if config.total_destinations == 0 {
+            return Err(RegistryError::ZeroDestinations);
+        }
+        if self.spaces.contains_key(name) {
+            return Err(RegistryError::DuplicateSpace(name.to_string()));
+        }
+
+        self.spaces.insert(name.to_string(), config);
+        Ok(())
+    }
+
+    /// Returns the shard for `key` within `space`.
+    pub fn shard_for(&self, space: &str, key: impl Hash) -> Result<u64, RegistryError> {
+        let config = self.config(space)?;
+        Ok(shard_with_hasher(
+            key,
+            config.total_destinations,
+            &config.hasher,
+        ))
+    }
+
+    /// Changes the number of destinations for `space`.
+    pub fn rescale(&mut self, space: &str, new_destinations: u64) -> Result<(), RegistryError> {
+        if new_destinations == 0 {
+            return Err(RegistryError::ZeroDestinations);
+        }
+
+        let config = self
+            .spaces
+            .get_mut(space)
+            .ok_or_else(|| RegistryError::UnknownSpace(space.to_string()))?;
+        config.total_destinations = new_destinations;
+        Ok(())
+    }
+
+    /// Returns the configuration for `space`.
+    pub fn config(&self, space: &str) -> Result<&ShardingConfig<S>, RegistryError> {
+        self.spaces
+            .get(space)
+            .ok_or_else(|| RegistryError::UnknownSpace(space.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BuildSeaHasher;
+
+    fn config(total_destinations: u64) -> ShardingConfig<BuildSeaHasher> {
+        ShardingConfig {
+            total_destinations,
+            hasher: BuildSeaHasher,
+        }
+    }
+
+    #[test]
+    fn spaces_are_independent() {
+        let mut registry = ShardingRegistry::new();
+        registry.register("users", config(8)).unwrap();
+        registry.register("sessions", config(64)).unwrap();
+
+        assert_eq!(
+            registry.shard_for("users", "foo"),
+            Ok(shard_with_hasher("foo", 8, &BuildSeaHasher))
+        );
+        assert_eq!(
+            registry.shard_for("sessions", "foo"),
+            Ok(shard_with_hasher("foo", 64, &BuildSeaHasher))
+        );
+
+        registry.rescale("users", 64).unwrap();
+        assert_eq!(
+            registry.shard_for("users", "foo"),
+            registry.shard_for("sessions", "foo")
+        );
+    }
+
+    #[test]
+    fn reports_errors() {
+        let mut registry = ShardingRegistry::new();
+        registry.register("users", config(8)).unwrap();
+
+        assert_eq!(
+            registry.register("users", config(8)),
+            Err(RegistryError::DuplicateSpace("users".to_string()))
+        );
+        assert_eq!(
+            registry.shard_for("products", "foo"),
+            Err(RegistryError::UnknownSpace("products".to_string()))
+        );
+        assert_eq!(
+            registry.rescale("users", 0),
+            Err(RegistryError::ZeroDestinations)
+        );
+    }
+}
@@ -0,0 +1,323 @@
+use crate::LoadProvider;
+use std::hash::*;
+
+/// [shards_with_hasher] using the default hasher of [crate::shard_for].
+#[cfg(feature = "seahash")]
+pub fn shards_for(key: impl Hash, total_destinations: u64, k: usize) -> Vec<u64> {
+    shards_with_hasher(
+        key,
+        total_destinations,
+        k,
+        &crate::seahash::StableSeaHasher::new(),
+    )
+}
+
+/// Returns `k` distinct shards for `key` in preference order, for placing replicas of a key or copies of a whole dataset.
+///
+/// The first shard is always [crate::shard_with_hasher]'s shard. If `k >= total_destinations`, every destination is returned.
+///
+/// The order depends only on the key, the hasher, and `total_destinations`, so every client agrees on it without coordinating. A shorter list is always a prefix of a longer one, so the shards after the first are also a failover order: clients asking for different lengths agree on the backups they share.
+///
+/// The shards can also spread a single hot key, such as one found by [crate::HotKeyDetector], across `k` destinations: write to `shards[write_id % shards.len()]`, where `write_id` is a request id, a counter, or a random number, and read from every shard, merging the results.
+///
+/// With `k == total_destinations as usize`, the list is a per-key permutation of every destination, for ordering candidates such as the nodes a scheduler tries for a job.
+///
+/// This extends the Faro chain to a reservoir of `k` slots: on step `n`, `hash % (n + 1)` picks a slot, and if that slot exists the new destination `n` takes it. Slot 0 follows exactly the single-shard rule. Once there are at least `k` destinations, adding one either leaves the replica set untouched or replaces exactly one replica with the new destination, keeping the others in their positions.
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub fn shards_with_hasher(
+    key: impl Hash,
+    total_destinations: u64,
+    k: usize,
+    hasher: &impl BuildHasher,
+) -> Vec<u64> {
+    assert_ne!(total_destinations, 0, "total_destinations must be > 0");
+    if k == 0 {
+        return Vec::new();
+    }
+
+    let mut slots = Vec::with_capacity(k.min(total_destinations as usize));
+    slots.push(0);
+
+    let mut last_hash = hasher.hash_one(key);
+    for n in 1..total_destinations {
+        let hash = hasher.hash_one(last_hash);
+        let slot = (hash % (n + 1)) as usize;
+
+        if slots.len() < k {
+            // Inside-out shuffle while the reservoir fills up.
+            if slot == slots.len() {
+                slots.push(n);
+            } else {
+                let displaced = std::mem::replace(&mut slots[slot], n);
+                slots.push(displaced);
+            }
+        } else if slot < k {
+            slots[slot] = n;
+        }
+
+        last_hash = hash;
+    }
+
+    slots
+}
+
+/// [least_loaded_with_hasher] using the default hasher of [crate::shard_for].
+///
+/// # Panics
+///
+/// If total_destinations == 0, or replicas > candidates.
+#[cfg(feature = "seahash")]
+pub fn least_loaded_for(
+    key: impl Hash,
+    total_destinations: u64,
+    replicas: usize,
+    candidates: usize,
+    loads: &impl LoadProvider,
+) -> Vec<u64> {
+    least_loaded_with_hasher(
+        key,
+        total_destinations,
+        replicas,
+        candidates,
+        loads,
+        &crate::seahash::StableSeaHasher::new(),
+    )
+}
+
+/// Returns the `replicas` least-loaded shards among `key`'s first `candidates` [shards_with_hasher], in preference order.
+///
+/// With one replica and two candidates this is the power of two choices: each key goes to the less busy of two stable shards, which evens out load far better than one choice. Results never leave the candidate set, so a client that cannot see the loads can still find the key by checking its `candidates` shards. Ties go to the earlier candidate, so with equal loads this is the first `replicas` of the preference list.
+///
+/// # Panics
+///
+/// If total_destinations == 0, or replicas > candidates.
+pub fn least_loaded_with_hasher(
+    key: impl Hash,
+    total_destinations: u64,
+    replicas: usize,
+    candidates: usize,
+    loads: &impl LoadProvider,
+    hasher: &impl BuildHasher,
+) -> Vec<u64> {
+    assert!(replicas <= candidates, "replicas must be <= candidates");
+
+    let candidates = shards_with_hasher(key, total_destinations, candidates, hasher);
+    let mut ranked = (0..candidates.len()).collect::<Vec<_>>();
+    ranked.sort_by_key(|&i| (loads.load(candidates[i]), i));
+    ranked.truncate(replicas);
+    ranked.sort_unstable();
+    ranked.into_iter().map(|i| candidates[i]).collect()
+}
+
+/// [shard_with_hasher_available] using the default hasher of [crate::shard_for].
+#[cfg(feature = "seahash")]
+pub fn shard_for_available(
+    key: impl Hash,
+    total_destinations: u64,
+    available: &impl Fn(u64) -> bool,
+) -> Option<u64> {
+    shard_with_hasher_available(
+        key,
+        total_destinations,
+        available,
+        &crate::seahash::StableSeaHasher::new(),
+    )
+}
+
+/// Returns the first shard in `key`'s [shards_with_hasher] order for which `available` is true, or `None` if none are.
+///
+/// Keys whose primary is available never move. When a shard goes down, only its keys move, each to its next available backup. When a shard comes back, only keys move onto it: those that prefer it to where they are now, which includes every key whose primary it is.
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub fn shard_with_hasher_available(
+    key: impl Hash,
+    total_destinations: u64,
+    available: &impl Fn(u64) -> bool,
+    hasher: &impl BuildHasher,
+) -> Option<u64> {
+    let primary = crate::shard_with_hasher(&key, total_destinations, hasher);
+    if available(primary) {
+        return Some(primary);
+    }
+
+    shards_with_hasher(key, total_destinations, total_destinations as usize, hasher)
+        .into_iter()
+        .find(|&shard| available(shard))
+}
+
+/// Like [shards_with_hasher], but never returns two shards in the same failure domain.
+///
+/// Candidates are taken in the key's preference order, skipping any whose domain (from `domain_of`) already holds a replica. The first shard is still [crate::shard_with_hasher]'s shard. If there are fewer than `k` distinct domains, one shard per domain is returned.
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub fn shards_with_domains<D: Eq>(
+    key: impl Hash,
+    total_destinations: u64,
+    k: usize,
+    domain_of: impl Fn(u64) -> D,
+    hasher: &impl BuildHasher,
+) -> Vec<u64> {
+    let preference =
+        shards_with_hasher(key, total_destinations, total_destinations as usize, hasher);
+
+    let mut domains = Vec::with_capacity(k);
+    let mut shards = Vec::with_capacity(k);
+    for shard in preference {
+        if shards.len() == k {
+            break;
+        }
+
+        let domain = domain_of(shard);
+        if !domains.contains(&domain) {
+            domains.push(domain);
+            shards.push(shard);
+        }
+    }
+    shards
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shard_for;
+
+    use proptest::*;
+
+    struct Loads(Vec<u64>);
+
+    impl LoadProvider for Loads {
+        fn load(&self, shard: u64) -> u64 {
+            self.0[shard as usize]
+        }
+    }
+
+    #[test]
+    fn least_loaded_stays_within_candidates() {
+        let loads = Loads((0..20).map(|shard| (shard * 7) % 11).collect());
+        for key in 0..1_000u64 {
+            let candidates = shards_for(key, 20, 4);
+            let chosen = least_loaded_for(key, 20, 2, 4, &loads);
+
+            assert_eq!(chosen.len(), 2);
+            assert!(chosen.iter().all(|shard| candidates.contains(shard)));
+            let worst_chosen = chosen.iter().map(|&s| loads.load(s)).max().unwrap();
+            for shard in candidates.iter().filter(|s| !chosen.contains(s)) {
+                assert!(loads.load(*shard) >= worst_chosen);
+            }
+
+            let positions = chosen
+                .iter()
+                .map(|s| candidates.iter().position(|c| c == s))
+                .collect::<Vec<_>>();
+            assert!(positions.windows(2).all(|w| w[0] < w[1]));
+        }
+    }
+
+    #[test]
+    fn equal_loads_follow_the_preference_list() {
+        let loads = Loads(vec![0; 20]);
+        for key in 0..100u64 {
+            assert_eq!(
+                least_loaded_for(key, 20, 1, 2, &loads),
+                [shard_for(key, 20)]
+            );
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn shorter_lists_are_prefixes(key: String, total in 1u64..=200, len in 0usize..20) {
+            let full = shards_for(&key, total, total as usize);
+            let list = shards_for(&key, total, len);
+            prop_assert_eq!(&full[..len.min(total as usize)], &list[..]);
+        }
+
+        #[test]
+        fn replica_sets_only_gain_the_new_destination(key: String, k in 1usize..6) {
+            let mut last = shards_for(&key, 1, k);
+            for n in 2..=256 {
+                let next = shards_for(&key, n, k);
+                prop_assert_eq!(next.len(), k.min(n as usize));
+                prop_assert_eq!(next[0], shard_for(&key, n));
+
+                let mut distinct = next.clone();
+                distinct.sort();
+                distinct.dedup();
+                prop_assert_eq!(distinct.len(), next.len());
+
+                let gained = next.iter().filter(|s| !last.contains(s)).collect::<Vec<_>>();
+                prop_assert!(gained.is_empty() || gained == [&(n - 1)]);
+
+                if n as usize > k {
+                    let changed_positions = last.iter().zip(&next).filter(|(a, b)| a != b).count();
+                    prop_assert!(changed_positions <= 1);
+                }
+                last = next;
+            }
+        }
+
+        #[test]
+        fn full_lists_cover_every_shard(key: String, len in 1u64..100) {
+            let mut permutation = shards_for(&key, len, len as usize);
+            prop_assert_eq!(permutation[0], shard_for(&key, len));
+            permutation.sort();
+            prop_assert_eq!(permutation, (0..len).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn single_replica_is_the_shard() {
+        for key in ["foo", "bar", "baz", "qux", "quux"] {
+            assert_eq!(shards_for(key, 73, 1), vec![shard_for(key, 73)]);
+        }
+        assert_eq!(shards_for("foo", 73, 0), Vec::<u64>::new());
+    }
+
+    #[test]
+    fn replicas_span_distinct_domains() {
+        let rack = |shard: u64| shard % 4;
+        for key in 0..1_000u64 {
+            let shards = shards_with_domains(key, 20, 3, rack, &crate::BuildSeaHasher);
+            assert_eq!(shards.len(), 3);
+            assert_eq!(shards[0], shard_for(key, 20));
+
+            let mut racks = shards.iter().map(|&s| rack(s)).collect::<Vec<_>>();
+            racks.sort();
+            racks.dedup();
+            assert_eq!(racks.len(), 3);
+        }
+
+        assert_eq!(
+            shards_with_domains("foo", 20, 5, rack, &crate::BuildSeaHasher).len(),
+            4
+        );
+    }
+
+    #[test]
+    fn availability_only_moves_keys_off_down_shards_and_onto_recovered_ones() {
+        let all_up = |_| true;
+        let three_down = |shard| shard != 3;
+        let three_and_five_down = |shard| shard != 3 && shard != 5;
+
+        for key in 0..2_000u64 {
+            let up = shard_for_available(key, 10, &all_up).unwrap();
+            let one_down = shard_for_available(key, 10, &three_down).unwrap();
+            let two_down = shard_for_available(key, 10, &three_and_five_down).unwrap();
+
+            assert_eq!(up, shard_for(key, 10));
+            assert!(one_down == up || up == 3);
+            // Going from both down to only 3 down is 5 recovering.
+            assert!(one_down == two_down || one_down == 5);
+            assert_ne!(two_down, 3);
+            assert_ne!(two_down, 5);
+        }
+        assert_eq!(shard_for_available("foo", 10, &|_| false), None);
+    }
+}
@@ -0,0 +1,85 @@
+use crate::shard_with_hasher;
+use std::hash::BuildHasher;
+use std::time::{Duration, Instant};
+
+/// Finds up to `count` keys of the form `{prefix}{i}` that shard to `shard` of `total_destinations`, for generating targeted load.
+///
+/// Suffixes are tried in order from 0, so the same arguments always give the same keys. About `total_destinations` suffixes are tried per key found. Stops early when `time_budget` runs out, returning the keys found so far; the budget is checked before every try, so it is overrun by at most one lookup.
+///
+/// # Panics
+///
+/// If total_destinations == 0 or shard >= total_destinations.
+pub fn keys_for_shard(
+    prefix: &str,
+    shard: u64,
+    total_destinations: u64,
+    count: usize,
+    time_budget: Duration,
+    hasher: &impl BuildHasher,
+) -> Vec<String> {
+    assert_ne!(total_destinations, 0, "total_destinations must be > 0");
+    assert!(
+        shard < total_destinations,
+        "shard {} out of range for {} destinations",
+        shard,
+        total_destinations
+    );
+
+    let deadline = Instant::now() + time_budget;
+    let mut keys = Vec::new();
+    for suffix in 0u64.. {
+        if keys.len() == count {
+            break;
+        }
+        if Instant::now() >= deadline {
+            break;
+        }
+
+        let key = format!("{}{}", prefix, suffix);
+        if shard_with_hasher(&key, total_destinations, hasher) == shard {
+            keys.push(key);
+        }
+    }
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{shard_for, BuildSeaHasher};
+
+    #[test]
+    fn finds_keys_on_the_shard() {
+        let keys = keys_for_shard(
+            "load-",
+            17,
+            64,
+            20,
+            Duration::from_secs(60),
+            &BuildSeaHasher,
+        );
+        assert_eq!(keys.len(), 20);
+        for key in &keys {
+            assert!(key.starts_with("load-"));
+            assert_eq!(shard_for(key, 64), 17);
+        }
+    }
+
+    #[test]
+    fn stops_at_the_time_budget() {
+        let keys = keys_for_shard("load-", 3, 4, usize::MAX, Duration::ZERO, &BuildSeaHasher);
+        assert!(keys.is_empty());
+
+        // A lookup at this many destinations takes milliseconds, so a budget checked only every 1024 tries would overrun by seconds.
+        let start = Instant::now();
+        keys_for_shard(
+            "load-",
+            0,
+            1_000_000,
+            1_000,
+            Duration::from_millis(10),
+            &BuildSeaHasher,
+        );
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+}
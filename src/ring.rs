@@ -0,0 +1,194 @@
+#[cfg(feature = "serde")]
+use crate::format_version::FormatVersion;
+use crate::{shard_for_key_hash, ShardId};
+use std::hash::*;
+
+/// An ordered list of destinations that keys are sharded across.
+///
+/// Destinations can only be appended, so pushing a new one only moves keys onto it.
+///
+/// `D` can be any metadata describing a destination, such as its address, zone, health, and labels. It is stored alongside the destination's shard, so [ShardRing::lookup] returns both and [ShardRing::destination_mut] updates it in place without a parallel list to keep in sync.
+///
+/// ```
+/// # use faro_sharding::{ShardId, ShardRing};
+/// # use std::hash::BuildHasherDefault;
+/// # use std::collections::hash_map::DefaultHasher;
+/// struct Node {
+///     address: &'static str,
+///     zone: &'static str,
+///     healthy: bool,
+/// }
+///
+/// let hasher = BuildHasherDefault::<DefaultHasher>::default();
+/// let mut ring = ShardRing::new(hasher);
+/// ring.push(Node { address: "10.0.0.1:6379", zone: "us-east-1a", healthy: true });
+/// ring.push(Node { address: "10.0.0.2:6379", zone: "us-east-1b", healthy: true });
+///
+/// let (shard, node) = ring.lookup("user-17");
+/// assert!(node.healthy);
+///
+/// ring.destination_mut(shard).unwrap().healthy = false;
+/// assert!(!ring.lookup("user-17").1.healthy);
+/// ```
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ShardRing<D, S> {
+    #[cfg(feature = "serde")]
+    version: FormatVersion,
+    destinations: Vec<D>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    hasher: S,
+}
+
+impl<D, S: BuildHasher> ShardRing<D, S> {
+    /// Creates an empty ring.
+    pub fn new(hasher: S) -> Self {
+        Self::with_destinations(Vec::new(), hasher)
+    }
+
+    /// Creates a ring over `destinations`, in order.
+    pub fn with_destinations(destinations: Vec<D>, hasher: S) -> Self {
+        ShardRing {
+            #[cfg(feature = "serde")]
+            version: FormatVersion,
+            destinations,
+            hasher,
+        }
+    }
+
+    /// Appends a destination.
+    pub fn push(&mut self, destination: D) {
+        self.destinations.push(destination);
+    }
+
+    /// Returns the index of the destination for `key`.
+    ///
+    /// # Panics
+    ///
+    /// If the ring is empty.
+    pub fn index_of(&self, key: impl Hash) -> u64 {
+        let key_hash = self.hasher.hash_one(key);
+        let total_destinations = self.destinations.len() as u64;
+        let shard = shard_for_key_hash(key_hash, total_destinations, &self.hasher);
+
+        #[cfg(feature = "tracing")]
+        tracing::trace!(key_hash, total_destinations, shard, "ring lookup");
+        shard
+    }
+
+    /// Returns the destination for `key`.
+    ///
+    /// # Panics
+    ///
+    /// If the ring is empty.
+    pub fn get(&self, key: impl Hash) -> &D {
+        &self.destinations[self.index_of(key) as usize]
+    }
+
+    /// Returns the shard for `key` and its destination.
+    ///
+    /// # Panics
+    ///
+    /// If the ring is empty.
+    pub fn lookup(&self, key: impl Hash) -> (ShardId, &D) {
+        let shard = ShardId::new(self.index_of(key));
+        (shard, &self.destinations[shard.index()])
+    }
+
+    /// The destination of `shard`, or None if it is out of range.
+    pub fn destination(&self, shard: ShardId) -> Option<&D> {
+        self.destinations.get(shard.index())
+    }
+
+    /// The destination of `shard` for updating in place, or None if it is out of range.
+    ///
+    /// Changing a destination does not move any keys.
+    pub fn destination_mut(&mut self, shard: ShardId) -> Option<&mut D> {
+        self.destinations.get_mut(shard.index())
+    }
+
+    /// Iterates the destinations in order.
+    pub fn iter(&self) -> std::slice::Iter<'_, D> {
+        self.destinations.iter()
+    }
+
+    /// The number of destinations.
+    pub fn len(&self) -> usize {
+        self.destinations.len()
+    }
+
+    /// Whether the ring has no destinations.
+    pub fn is_empty(&self) -> bool {
+        self.destinations.is_empty()
+    }
+
+    /// The hasher keys are sharded with.
+    pub fn hasher(&self) -> &S {
+        &self.hasher
+    }
+}
+
+impl<'a, D, S: BuildHasher> IntoIterator for &'a ShardRing<D, S> {
+    type Item = &'a D;
+    type IntoIter = std::slice::Iter<'a, D>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{shard_with_hasher, BuildSeaHasher};
+    use std::net::SocketAddr;
+
+    #[test]
+    fn maps_keys_to_destinations() {
+        let addrs = (0..8)
+            .map(|i| SocketAddr::from(([10, 0, 0, i], 6379)))
+            .collect::<Vec<_>>();
+        let mut ring = ShardRing::with_destinations(addrs.clone(), BuildSeaHasher);
+
+        let shard = shard_with_hasher("foo", 8, &BuildSeaHasher);
+        assert_eq!(ring.get("foo"), &addrs[shard as usize]);
+
+        let before = (0..1000).map(|k| *ring.get(k)).collect::<Vec<_>>();
+        let added = SocketAddr::from(([10, 0, 0, 8], 6379));
+        ring.push(added);
+        for (key, before) in (0..1000).zip(before) {
+            let after = *ring.get(key);
+            assert!(after == before || after == added);
+        }
+
+        assert_eq!(ring.iter().count(), 9);
+    }
+
+    #[test]
+    fn lookup_returns_shard_and_destination() {
+        #[derive(Debug, PartialEq)]
+        struct Meta {
+            zone: &'static str,
+            labels: Vec<&'static str>,
+        }
+
+        let mut ring = ShardRing::new(BuildSeaHasher);
+        for zone in ["a", "b", "c"] {
+            ring.push(Meta {
+                zone,
+                labels: Vec::new(),
+            });
+        }
+
+        for key in 0..100 {
+            let (shard, meta) = ring.lookup(key);
+            assert_eq!(shard.get(), ring.index_of(key));
+            assert_eq!(ring.destination(shard), Some(meta));
+        }
+
+        let (shard, _) = ring.lookup("foo");
+        ring.destination_mut(shard).unwrap().labels.push("hot");
+        assert_eq!(ring.lookup("foo").1.labels, ["hot"]);
+        assert_eq!(ring.destination(ShardId::new(3)), None);
+    }
+}
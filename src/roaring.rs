@@ -0,0 +1,146 @@
+//! Availability masks as [roaring](https://docs.rs/roaring) bitmaps, for clusters with very many destinations.
+//!
+//! A [RoaringTreemap] holds the destinations that are up, and stays small and fast to update even with 100k+ destinations. The functions here agree with their closure-based counterparts, [crate::shard_with_hasher_available] and [crate::shard_with_bounded_load], but use the mask's cardinality to stop early.
+
+use crate::{shard_with_hasher, shards_with_hasher};
+use ::roaring::RoaringTreemap;
+use std::hash::*;
+
+/// [crate::shard_with_hasher_available] with destinations `available` if they are in the mask.
+///
+/// If `d` of the destinations are down, one of the first `d + 1` entries in the key's preference order must be up, so only that many are computed.
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub fn shard_with_hasher_available(
+    key: impl Hash,
+    total_destinations: u64,
+    available: &RoaringTreemap,
+    hasher: &impl BuildHasher,
+) -> Option<u64> {
+    let primary = shard_with_hasher(&key, total_destinations, hasher);
+    if available.contains(primary) {
+        return Some(primary);
+    }
+
+    let down = total_destinations - available.range_cardinality(..total_destinations);
+    if down == total_destinations {
+        return None;
+    }
+
+    shards_with_hasher(key, total_destinations, (down + 1) as usize, hasher)
+        .into_iter()
+        .find(|&shard| available.contains(shard))
+}
+
+/// [crate::shard_with_bounded_load] over only the destinations in `available`, or `None` if there are none.
+///
+/// The cap is computed from the load and count of available destinations, so load on destinations that are down is ignored.
+///
+/// # Panics
+///
+/// If total_destinations == 0, `loads.len() != total_destinations`, or cap_factor < 1.
+pub fn shard_with_bounded_load(
+    key: impl Hash,
+    total_destinations: u64,
+    loads: &[u64],
+    cap_factor: f64,
+    available: &RoaringTreemap,
+    hasher: &impl BuildHasher,
+) -> Option<u64> {
+    assert_ne!(total_destinations, 0, "total_destinations must be > 0");
+    assert_eq!(
+        loads.len() as u64,
+        total_destinations,
+        "loads must have one entry per destination"
+    );
+    assert!(cap_factor >= 1.0, "cap_factor must be >= 1");
+
+    let up = available.range_cardinality(..total_destinations);
+    if up == 0 {
+        return None;
+    }
+    let total_load: u64 = available
+        .iter()
+        .take_while(|&shard| shard < total_destinations)
+        .map(|shard| loads[shard as usize])
+        .sum();
+    let cap = (cap_factor * (total_load + 1) as f64 / up as f64).ceil() as u64;
+    let usable = |shard: u64| available.contains(shard) && loads[shard as usize] < cap;
+
+    let preferred = shard_with_hasher(&key, total_destinations, hasher);
+    if usable(preferred) {
+        return Some(preferred);
+    }
+
+    // With cap_factor >= 1, some available shard is below the average and therefore below the cap.
+    let shard = shards_with_hasher(
+        &key,
+        total_destinations,
+        total_destinations as usize,
+        hasher,
+    )
+    .into_iter()
+    .find(|&shard| usable(shard))
+    .expect("some available shard is below average load");
+    Some(shard)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BuildSeaHasher;
+
+    #[test]
+    fn agrees_with_closure_masks() {
+        let total = 1_000;
+        let mut available = RoaringTreemap::new();
+        available.insert_range(0..total);
+        for down in [3, 17, 400, 401, 402, 999] {
+            available.remove(down);
+        }
+        available.remove_range(500..900);
+
+        for key in 0..2_000u64 {
+            assert_eq!(
+                shard_with_hasher_available(key, total, &available, &BuildSeaHasher),
+                crate::shard_with_hasher_available(
+                    key,
+                    total,
+                    &|shard| available.contains(shard),
+                    &BuildSeaHasher
+                ),
+            );
+        }
+
+        assert_eq!(
+            shard_with_hasher_available("foo", total, &RoaringTreemap::new(), &BuildSeaHasher),
+            None
+        );
+    }
+
+    #[test]
+    fn bounded_load_skips_unavailable_and_overloaded() {
+        let mut available = RoaringTreemap::new();
+        available.insert_range(0..8);
+        available.remove(2);
+
+        let mut loads = [0u64; 8];
+        loads[2] = 1_000;
+        for key in 0..8_000u64 {
+            let shard =
+                shard_with_bounded_load(key, 8, &loads, 1.25, &available, &BuildSeaHasher).unwrap();
+            assert_ne!(shard, 2);
+            loads[shard as usize] += 1;
+        }
+
+        let live = loads
+            .iter()
+            .enumerate()
+            .filter(|&(shard, _)| shard != 2)
+            .map(|(_, &load)| load)
+            .collect::<Vec<_>>();
+        assert!(crate::stats::imbalance_ratio(&live) <= 1.25, "{:?}", loads);
+    }
+}
@@ -0,0 +1,80 @@
+use crate::shard_with_hasher;
+use std::hash::*;
+
+/// Picks the connection pool (or client, or handle) responsible for a key.
+pub trait ShardRouter<K: ?Sized> {
+    /// What keys are routed to.
+    type Pool;
+
+    /// The pool responsible for `key`.
+    fn route(&self, key: &K) -> &Self::Pool;
+}
+
+/// [ShardRouter] over one pool per shard, routing with [shard_with_hasher].
+///
+/// Adding a pool only reroutes keys onto it.
+#[derive(Clone, Debug)]
+pub struct PoolRouter<P, S> {
+    pools: Vec<P>,
+    hasher: S,
+}
+
+impl<P, S: BuildHasher> PoolRouter<P, S> {
+    /// Routes across `pools`, where `pools[i]` serves shard `i`.
+    ///
+    /// # Panics
+    ///
+    /// If pools is empty.
+    pub fn new(pools: Vec<P>, hasher: S) -> Self {
+        assert!(!pools.is_empty(), "pools must not be empty");
+
+        PoolRouter { pools, hasher }
+    }
+
+    /// The pools, in shard order.
+    pub fn pools(&self) -> &[P] {
+        &self.pools
+    }
+
+    /// Adds a pool as the new last shard.
+    pub fn push(&mut self, pool: P) {
+        self.pools.push(pool);
+    }
+
+    /// The shard `key` is routed to.
+    pub fn shard_of<K: Hash + ?Sized>(&self, key: &K) -> u64 {
+        shard_with_hasher(key, self.pools.len() as u64, &self.hasher)
+    }
+}
+
+impl<K: Hash + ?Sized, P, S: BuildHasher> ShardRouter<K> for PoolRouter<P, S> {
+    type Pool = P;
+
+    fn route(&self, key: &K) -> &P {
+        let shard = crate::observe_lookup("pool_router", || self.shard_of(key));
+        &self.pools[shard as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{shard_for, BuildSeaHasher};
+
+    #[test]
+    fn routes_by_shard() {
+        let mut router = PoolRouter::new(vec!["db0", "db1", "db2"], BuildSeaHasher);
+        for user_id in 0..100u64 {
+            assert_eq!(
+                *router.route(&user_id),
+                router.pools()[shard_for(user_id, 3) as usize]
+            );
+        }
+
+        router.push("db3");
+        for user_id in 0..100u64 {
+            let pool = *router.route(&user_id);
+            assert!(pool == "db3" || pool == ["db0", "db1", "db2"][shard_for(user_id, 3) as usize]);
+        }
+    }
+}
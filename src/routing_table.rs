@@ -0,0 +1,317 @@
+use crate::shard_with_hasher;
+use std::fmt;
+use std::hash::*;
+
+const MAGIC: &[u8; 4] = b"FARO";
+const FORMAT: u32 = 1;
+const HEADER_LEN: usize = 32;
+
+/// A snapshot of a [crate::VirtualShards] vshard to node table, for handing to other processes and languages.
+///
+/// [RoutingTable::export] encodes it as:
+///
+/// | Offset | Size | Field |
+/// |---|---|---|
+/// | 0 | 4 | `FARO` |
+/// | 4 | 4 | Format, currently 1 |
+/// | 8 | 8 | Topology version |
+/// | 16 | 8 | Node count |
+/// | 24 | 8 | Vshard count `n` |
+/// | 32 | `4 * n` | Node of each vshard |
+///
+/// Every integer is little-endian and unsigned. [RoutingTableView::load] reads this without copying, and consumers in other languages can read it the same way. Keys map to vshards with [crate::shard_with_hasher] over the vshard count.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RoutingTable {
+    version: u64,
+    node_count: u64,
+    nodes: Vec<u64>,
+}
+
+impl RoutingTable {
+    /// A table at topology `version`, where `nodes[vshard]` is the node of each vshard.
+    ///
+    /// # Panics
+    ///
+    /// If nodes is empty, node_count does not fit in a `u32`, or any node is >= node_count.
+    pub fn new(version: u64, node_count: u64, nodes: Vec<u64>) -> Self {
+        assert!(!nodes.is_empty(), "nodes must not be empty");
+        assert!(
+            node_count <= u64::from(u32::MAX) + 1,
+            "node_count must fit in a u32"
+        );
+        assert!(
+            nodes.iter().all(|&node| node < node_count),
+            "node must be < node_count"
+        );
+
+        RoutingTable {
+            version,
+            node_count,
+            nodes,
+        }
+    }
+
+    /// The topology version this table was taken at.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Number of nodes.
+    pub fn node_count(&self) -> u64 {
+        self.node_count
+    }
+
+    /// The node of every vshard, indexed by vshard.
+    pub fn nodes(&self) -> &[u64] {
+        &self.nodes
+    }
+
+    /// Encodes the table in the binary format described on [RoutingTable].
+    pub fn export(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + 4 * self.nodes.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&FORMAT.to_le_bytes());
+        bytes.extend_from_slice(&self.version.to_le_bytes());
+        bytes.extend_from_slice(&self.node_count.to_le_bytes());
+        bytes.extend_from_slice(&(self.nodes.len() as u64).to_le_bytes());
+        for &node in &self.nodes {
+            bytes.extend_from_slice(&(node as u32).to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Encodes the table as JSON: `{"format":1,"version":..,"node_count":..,"nodes":[..]}`.
+    #[cfg(feature = "serde_json")]
+    pub fn export_json(&self) -> String {
+        serde_json::to_string(&JsonTable {
+            format: FORMAT,
+            version: self.version,
+            node_count: self.node_count,
+            nodes: &self.nodes,
+        })
+        .expect("a routing table always serializes")
+    }
+}
+
+/// The fields of [RoutingTable::export_json].
+#[cfg(feature = "serde_json")]
+#[derive(serde::Serialize)]
+struct JsonTable<'a> {
+    format: u32,
+    version: u64,
+    node_count: u64,
+    nodes: &'a [u64],
+}
+
+/// A [RoutingTable] read in place from [RoutingTable::export]'s bytes.
+#[derive(Clone, Copy, Debug)]
+pub struct RoutingTableView<'a> {
+    version: u64,
+    node_count: u64,
+    entries: &'a [u8],
+}
+
+impl<'a> RoutingTableView<'a> {
+    /// Validates `bytes` and reads the table from them without copying.
+    pub fn load(bytes: &'a [u8]) -> Result<Self, RoutingTableError> {
+        let field =
+            |offset: usize| u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+
+        if bytes.len() < HEADER_LEN {
+            return Err(RoutingTableError::Truncated);
+        }
+        if &bytes[..4] != MAGIC {
+            return Err(RoutingTableError::BadMagic);
+        }
+        let format = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+        if format != FORMAT {
+            return Err(RoutingTableError::UnsupportedFormat(format));
+        }
+
+        let (version, node_count, vshard_count) = (field(8), field(16), field(24));
+        let entries = &bytes[HEADER_LEN..];
+        if vshard_count == 0 || entries.len() as u64 != vshard_count.saturating_mul(4) {
+            return Err(RoutingTableError::Truncated);
+        }
+
+        let view = RoutingTableView {
+            version,
+            node_count,
+            entries,
+        };
+        if let Some(vshard) = (0..vshard_count).find(|&v| view.node_of_vshard(v) >= node_count) {
+            return Err(RoutingTableError::NodeOutOfRange { vshard });
+        }
+        Ok(view)
+    }
+
+    /// The topology version the table was taken at.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Number of nodes.
+    pub fn node_count(&self) -> u64 {
+        self.node_count
+    }
+
+    /// Number of vshards.
+    pub fn vshard_count(&self) -> u64 {
+        self.entries.len() as u64 / 4
+    }
+
+    /// Returns the node `vshard` is on.
+    ///
+    /// # Panics
+    ///
+    /// If vshard is out of range.
+    pub fn node_of_vshard(&self, vshard: u64) -> u64 {
+        let offset = vshard as usize * 4;
+        u64::from(u32::from_le_bytes(
+            self.entries[offset..offset + 4].try_into().unwrap(),
+        ))
+    }
+
+    /// Returns the node for `key`, with `hasher` matching the one the table's [crate::VirtualShards] used.
+    pub fn node_for(&self, key: impl Hash, hasher: &impl BuildHasher) -> u64 {
+        self.node_of_vshard(shard_with_hasher(key, self.vshard_count(), hasher))
+    }
+
+    /// Copies the table out of the buffer.
+    pub fn to_table(&self) -> RoutingTable {
+        RoutingTable {
+            version: self.version,
+            node_count: self.node_count,
+            nodes: (0..self.vshard_count())
+                .map(|v| self.node_of_vshard(v))
+                .collect(),
+        }
+    }
+}
+
+/// Error returned by [RoutingTableView::load].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RoutingTableError {
+    /// The bytes are too short for the header, or do not hold exactly the vshards it declares.
+    Truncated,
+    /// The bytes do not start with `FARO`.
+    BadMagic,
+    /// The format is newer than this version of the crate understands.
+    UnsupportedFormat(u32),
+    /// A vshard is on a node >= the node count.
+    NodeOutOfRange {
+        /// The first such vshard.
+        vshard: u64,
+    },
+}
+
+impl fmt::Display for RoutingTableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RoutingTableError::Truncated => write!(f, "routing table is truncated"),
+            RoutingTableError::BadMagic => write!(f, "not a routing table"),
+            RoutingTableError::UnsupportedFormat(format) => {
+                write!(f, "unsupported routing table format {}", format)
+            }
+            RoutingTableError::NodeOutOfRange { vshard } => {
+                write!(f, "vshard {} is on a node out of range", vshard)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RoutingTableError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BuildSeaHasher, VirtualShards};
+
+    #[test]
+    fn round_trips_through_a_view() {
+        let mut shards = VirtualShards::new(1024, 7, BuildSeaHasher);
+        shards.set_node_count(9);
+        let table = shards.routing_table();
+        assert_eq!(table.version(), 1);
+
+        let bytes = table.export();
+        let view = RoutingTableView::load(&bytes).unwrap();
+        assert_eq!(view.version(), 1);
+        assert_eq!(view.node_count(), 9);
+        assert_eq!(view.vshard_count(), 1024);
+        assert_eq!(view.to_table(), table);
+        for key in 0..1_000u64 {
+            assert_eq!(view.node_for(key, &BuildSeaHasher), shards.node_for(key));
+        }
+    }
+
+    #[test]
+    fn pinned_encoding() {
+        // Other languages decode this format. Do not change these values.
+        let table = RoutingTable::new(5, 3, vec![0, 2, 1, 2]);
+        assert_eq!(
+            table.export(),
+            [
+                b"FARO".as_slice(),
+                &[1, 0, 0, 0],
+                &[5, 0, 0, 0, 0, 0, 0, 0],
+                &[3, 0, 0, 0, 0, 0, 0, 0],
+                &[4, 0, 0, 0, 0, 0, 0, 0],
+                &[0, 0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0],
+            ]
+            .concat()
+        );
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn exports_json() {
+        let table = RoutingTable::new(5, 3, vec![0, 2, 1, 2]);
+        let json: serde_json::Value = serde_json::from_str(&table.export_json()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "format": 1,
+                "version": 5,
+                "node_count": 3,
+                "nodes": [0, 2, 1, 2],
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_bytes() {
+        let bytes = RoutingTable::new(5, 3, vec![0, 2, 1, 2]).export();
+
+        assert_eq!(
+            RoutingTableView::load(&bytes[..20]).unwrap_err(),
+            RoutingTableError::Truncated
+        );
+        assert_eq!(
+            RoutingTableView::load(&bytes[..bytes.len() - 1]).unwrap_err(),
+            RoutingTableError::Truncated
+        );
+
+        let mut bad = bytes.clone();
+        bad[0] = b'X';
+        assert_eq!(
+            RoutingTableView::load(&bad).unwrap_err(),
+            RoutingTableError::BadMagic
+        );
+
+        let mut bad = bytes.clone();
+        bad[4] = 2;
+        assert_eq!(
+            RoutingTableView::load(&bad).unwrap_err(),
+            RoutingTableError::UnsupportedFormat(2)
+        );
+
+        let mut bad = bytes;
+        bad[HEADER_LEN + 8] = 3;
+        assert_eq!(
+            RoutingTableView::load(&bad).unwrap_err(),
+            RoutingTableError::NodeOutOfRange { vshard: 2 }
+        );
+    }
+}
@@ -0,0 +1,74 @@
+use core::hash::*;
+
+/// Parts per million in a sampling rate of 100%.
+pub const PPM: u32 = 1_000_000;
+
+/// [in_sample_with_hasher] using the default hasher of [crate::shard_for].
+#[cfg(feature = "seahash")]
+pub fn in_sample(key: impl Hash, rate_ppm: u32, seed: u64) -> bool {
+    in_sample_with_hasher(key, rate_ppm, seed, &crate::seahash::StableSeaHasher::new())
+}
+
+/// Returns whether `key` is in a `rate_ppm` parts-per-million sample, identified by `seed`.
+///
+/// Each key has a fixed position in `0..PPM` for a seed, and is sampled when that position is below `rate_ppm`. So raising the rate only adds keys to the sample and lowering it only removes them, and different seeds sample independently. Rates of [PPM] or more sample every key.
+///
+/// ```
+/// # use faro_sharding::*;
+/// # use std::hash::BuildHasherDefault;
+/// # use std::collections::hash_map::DefaultHasher;
+/// let hasher = BuildHasherDefault::<DefaultHasher>::default();
+/// // Every request traced at 1% is still traced at 5%.
+/// if in_sample_with_hasher("request-17", 10_000, 0, &hasher) {
+///     assert!(in_sample_with_hasher("request-17", 50_000, 0, &hasher));
+/// }
+/// ```
+pub fn in_sample_with_hasher(
+    key: impl Hash,
+    rate_ppm: u32,
+    seed: u64,
+    hasher: &impl BuildHasher,
+) -> bool {
+    hasher.hash_one((seed, key)) % u64::from(PPM) < u64::from(rate_ppm)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raising_the_rate_only_adds_keys() {
+        for key in 0..10_000u64 {
+            let mut sampled = false;
+            for rate_ppm in (0..=PPM).step_by(10_000) {
+                let now = in_sample(key, rate_ppm, 3);
+                assert!(
+                    now || !sampled,
+                    "key {} left the sample at {}",
+                    key,
+                    rate_ppm
+                );
+                sampled = now;
+            }
+            assert!(sampled);
+        }
+    }
+
+    #[test]
+    fn samples_about_the_rate() {
+        let sampled = (0..100_000u64)
+            .filter(|&key| in_sample(key, 50_000, 3))
+            .count();
+        assert!((4_700..5_300).contains(&sampled), "{}", sampled);
+        assert!((0..1_000u64).all(|key| !in_sample(key, 0, 3)));
+    }
+
+    #[test]
+    fn seeds_are_independent() {
+        let both = (0..100_000u64)
+            .filter(|&key| in_sample(key, 100_000, 1) && in_sample(key, 100_000, 2))
+            .count();
+        // About 1 in 100 by chance.
+        assert!((800..1_200).contains(&both), "{}", both);
+    }
+}
@@ -0,0 +1,70 @@
+use crate::shard_with_hasher;
+use core::hash::*;
+
+/// A sharding algorithm selected at runtime without dynamic dispatch.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ShardingScheme {
+    /// Faro Sharding, as in [shard_with_hasher].
+    Faro,
+    /// `hash(key) % total_destinations`. Reshuffles most keys whenever the destination count changes.
+    Modular,
+    /// Every key goes to this shard, wrapped to the destination count.
+    Fixed(u64),
+}
+
+impl ShardingScheme {
+    /// Returns the shard for `key` under this scheme.
+    ///
+    /// # Panics
+    ///
+    /// If total_destinations == 0.
+    pub fn shard_for<K: Hash>(
+        &self,
+        key: K,
+        total_destinations: u64,
+        hasher: &impl BuildHasher,
+    ) -> u64 {
+        assert_ne!(total_destinations, 0, "total_destinations must be > 0");
+
+        match *self {
+            ShardingScheme::Faro => shard_with_hasher(key, total_destinations, hasher),
+            ShardingScheme::Modular => hasher.hash_one(key) % total_destinations,
+            ShardingScheme::Fixed(shard) => shard % total_destinations,
+        }
+    }
+
+    /// Whether adding destinations only ever moves keys onto the new destinations.
+    pub fn is_stable(&self) -> bool {
+        matches!(self, ShardingScheme::Faro)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BuildSeaHasher;
+
+    #[test]
+    fn dispatches_to_each_scheme() {
+        assert_eq!(
+            ShardingScheme::Faro.shard_for("foo", 73, &BuildSeaHasher),
+            shard_with_hasher("foo", 73, &BuildSeaHasher)
+        );
+        assert_eq!(
+            ShardingScheme::Modular.shard_for("foo", 73, &BuildSeaHasher),
+            BuildSeaHasher.hash_one("foo") % 73
+        );
+        assert_eq!(
+            ShardingScheme::Fixed(80).shard_for("foo", 73, &BuildSeaHasher),
+            7
+        );
+    }
+
+    #[test]
+    fn only_faro_is_stable() {
+        assert!(ShardingScheme::Faro.is_stable());
+        assert!(!ShardingScheme::Modular.is_stable());
+        assert!(!ShardingScheme::Fixed(0).is_stable());
+    }
+}
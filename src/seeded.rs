@@ -0,0 +1,60 @@
+use crate::shard_with_hasher;
+use core::hash::*;
+
+/// [shard_with_hasher_seeded] using the default hasher of [crate::shard_for].
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+#[cfg(feature = "seahash")]
+pub fn shard_for_seeded(key: impl Hash, total_destinations: u64, seed: u64) -> u64 {
+    shard_with_hasher_seeded(
+        key,
+        total_destinations,
+        seed,
+        &crate::seahash::StableSeaHasher::new(),
+    )
+}
+
+/// Returns the shard for `key` within the keyspace identified by `seed`.
+///
+/// The seed is hashed together with the key, so the same key in different keyspaces lands on unrelated shards. Within one seed this is ordinary Faro Sharding: adding destinations only moves keys onto the new destination.
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub fn shard_with_hasher_seeded(
+    key: impl Hash,
+    total_destinations: u64,
+    seed: u64,
+    hasher: &impl BuildHasher,
+) -> u64 {
+    shard_with_hasher((seed, key), total_destinations, hasher)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeds_are_independent() {
+        let same = (0..10_000u64)
+            .filter(|&key| shard_for_seeded(key, 10, 1) == shard_for_seeded(key, 10, 2))
+            .count();
+        // About 1 in 10 by chance.
+        assert!((800..1_200).contains(&same), "{}", same);
+    }
+
+    #[test]
+    fn growth_only_moves_to_the_new_destination() {
+        let keys = (0..1_000u64).map(|key| (7u64, key));
+        assert_eq!(
+            crate::verify_stability(&crate::BuildSeaHasher, keys, 50),
+            Ok(())
+        );
+        assert_eq!(
+            shard_for_seeded(3u64, 50, 7),
+            crate::shard_for((7u64, 3u64), 50)
+        );
+    }
+}
@@ -0,0 +1,84 @@
+use crate::shard_with_hasher;
+use std::hash::*;
+
+/// Error returned when a version string is not valid semver.
+#[derive(Debug)]
+pub struct SemverError(pub ::semver::Error);
+
+impl std::fmt::Display for SemverError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid version: {}", self.0)
+    }
+}
+
+impl std::error::Error for SemverError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.0)
+    }
+}
+
+/// [crate::shard_for] for a package version. See [shard_semver_with_hasher].
+#[cfg(feature = "seahash")]
+pub fn shard_for_semver(
+    package: &str,
+    version: &str,
+    major_only: bool,
+    total_destinations: u64,
+) -> Result<u64, SemverError> {
+    shard_semver_with_hasher(
+        package,
+        version,
+        major_only,
+        total_destinations,
+        &crate::seahash::StableSeaHasher::new(),
+    )
+}
+
+/// Returns the shard for a package at a given version.
+///
+/// With `major_only`, the key is `"{package}@{major}"`, so every `mylib@2.x.x` lands on the same shard. Otherwise the key is `"{package}@{major}.{minor}.{patch}"`. Pre-release and build metadata are never part of the key.
+pub fn shard_semver_with_hasher(
+    package: &str,
+    version: &str,
+    major_only: bool,
+    total_destinations: u64,
+    hasher: &impl BuildHasher,
+) -> Result<u64, SemverError> {
+    let version = ::semver::Version::parse(version).map_err(SemverError)?;
+
+    let key = if major_only {
+        format!("{package}@{}", version.major)
+    } else {
+        format!(
+            "{package}@{}.{}.{}",
+            version.major, version.minor, version.patch
+        )
+    };
+    Ok(shard_with_hasher(key, total_destinations, hasher))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shard_for;
+
+    #[test]
+    fn major_only_groups_a_major_version() {
+        let expected = shard_for("mylib@2", 64);
+        for version in ["2.0.0", "2.3.1", "2.10.0-beta.1"] {
+            assert_eq!(
+                shard_for_semver("mylib", version, true, 64).unwrap(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn full_version_uses_major_minor_patch() {
+        assert_eq!(
+            shard_for_semver("mylib", "2.3.1+build.5", false, 64).unwrap(),
+            shard_for("mylib@2.3.1", 64)
+        );
+        assert!(shard_for_semver("mylib", "2.3", false, 64).is_err());
+    }
+}
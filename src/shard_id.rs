@@ -0,0 +1,154 @@
+use crate::shard_with_hasher;
+use core::fmt;
+use core::hash::*;
+
+/// A shard number, kept apart from other `u64`s such as node or slot ids.
+///
+/// Returned by [shard_id_with_hasher]. Convert with [ShardId::get] or `u64::from` where a raw index is needed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct ShardId(u64);
+
+impl ShardId {
+    /// Wraps a raw shard number.
+    pub const fn new(shard: u64) -> Self {
+        ShardId(shard)
+    }
+
+    /// The raw shard number.
+    pub const fn get(self) -> u64 {
+        self.0
+    }
+
+    /// The shard number as an index into a per-shard slice.
+    pub const fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl From<u64> for ShardId {
+    fn from(shard: u64) -> Self {
+        ShardId(shard)
+    }
+}
+
+impl From<ShardId> for u64 {
+    fn from(shard: ShardId) -> Self {
+        shard.0
+    }
+}
+
+impl fmt::Display for ShardId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// [shard_with_hasher] returning a [ShardId].
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub fn shard_id_with_hasher(
+    key: impl Hash,
+    total_destinations: u64,
+    hasher: &impl BuildHasher,
+) -> ShardId {
+    ShardId(shard_with_hasher(key, total_destinations, hasher))
+}
+
+/// A shard out of exactly `N` destinations.
+///
+/// Shards from differently sized clusters have different types, so passing one where the other is expected does not compile. Every value is `< N`.
+///
+/// ```
+/// # use faro_sharding::*;
+/// # use std::hash::BuildHasherDefault;
+/// # use std::collections::hash_map::DefaultHasher;
+/// let hasher = BuildHasherDefault::<DefaultHasher>::default();
+/// let shard: FixedShardId<16> = FixedShardId::of("foo", &hasher);
+/// assert_eq!(shard.get(), shard_with_hasher("foo", 16, &hasher));
+/// assert_eq!(FixedShardId::<16>::new(16), None);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FixedShardId<const N: u64>(u64);
+
+impl<const N: u64> FixedShardId<N> {
+    /// The number of destinations, `N`.
+    pub const TOTAL_DESTINATIONS: u64 = N;
+
+    /// The shard for `key` out of `N` destinations. Fails to compile if `N == 0`.
+    pub fn of(key: impl Hash, hasher: &impl BuildHasher) -> Self {
+        const { assert!(N > 0, "total_destinations must be > 0") };
+        FixedShardId(shard_with_hasher(key, N, hasher))
+    }
+
+    /// Wraps a raw shard number, or `None` if it is not `< N`.
+    pub const fn new(shard: u64) -> Option<Self> {
+        if shard < N {
+            Some(FixedShardId(shard))
+        } else {
+            None
+        }
+    }
+
+    /// The raw shard number.
+    pub const fn get(self) -> u64 {
+        self.0
+    }
+
+    /// The shard number as an index into a per-shard slice, such as a `[T; N]`.
+    pub const fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl<const N: u64> From<FixedShardId<N>> for ShardId {
+    fn from(shard: FixedShardId<N>) -> Self {
+        ShardId(shard.0)
+    }
+}
+
+impl<const N: u64> From<FixedShardId<N>> for u64 {
+    fn from(shard: FixedShardId<N>) -> Self {
+        shard.0
+    }
+}
+
+impl<const N: u64> fmt::Display for FixedShardId<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{shard_for, BuildSeaHasher};
+
+    #[test]
+    fn agrees_with_shard_for() {
+        for key in 0..100u64 {
+            let shard = shard_id_with_hasher(key, 50, &BuildSeaHasher);
+            assert_eq!(u64::from(shard), shard_for(key, 50));
+
+            let fixed = FixedShardId::<50>::of(key, &BuildSeaHasher);
+            assert_eq!(ShardId::from(fixed), shard);
+        }
+        assert_eq!(
+            shard_id_with_hasher("foo", 50, &BuildSeaHasher).to_string(),
+            "49"
+        );
+    }
+
+    #[test]
+    fn fixed_is_in_range() {
+        assert_eq!(FixedShardId::<4>::new(3).map(FixedShardId::get), Some(3));
+        assert_eq!(FixedShardId::<4>::new(4), None);
+        assert_eq!(FixedShardId::<4>::TOTAL_DESTINATIONS, 4);
+    }
+}
@@ -0,0 +1,217 @@
+use crate::shard_bytes_with_hasher;
+use std::hash::*;
+
+/// The first byte of every [shard_key_bytes] encoding. It changes only if the encoding does.
+pub const SHARD_KEY_ENCODING_VERSION: u8 = 1;
+
+/// A key with a canonical byte encoding, so its shard is stable across Rust versions, platforms, and field reorderings.
+///
+/// [Hash] output is not guaranteed to be stable, and derived impls depend on field order. Keys that are sharded with [shard_key_with_hasher] instead depend only on this encoding:
+///
+/// - Integers are little-endian at their full width. `usize` and `isize` are widened to 64 bits.
+/// - `bool` is one byte, and `char` is its `u32`.
+/// - Strings, slices, and `Vec`s are a `u64` length followed by their bytes or elements.
+/// - `Option` is a `0` byte for `None`, or a `1` byte and the value.
+/// - Tuples and arrays are their elements in order. Arrays are not length-prefixed.
+///
+/// With the `derive` feature, `#[derive(ShardKey)]` encodes structs by field name rather than position: named fields are sorted by name and each is encoded as its name followed by its value. Enums are encoded as the variant name followed by its fields. `#[shard_key(rename = "...")]` keeps an old name after renaming a field or variant, and `#[shard_key(skip)]` leaves a field out. Adding a field changes the encoding, so skip new fields that should not affect placement.
+///
+/// Floats do not implement ShardKey, as equal values can have different bits.
+pub trait ShardKey {
+    /// Appends the canonical encoding of `self` to `out`.
+    fn encode_shard_key(&self, out: &mut Vec<u8>);
+}
+
+/// Returns the versioned canonical encoding of `key`.
+pub fn shard_key_bytes(key: &(impl ShardKey + ?Sized)) -> Vec<u8> {
+    let mut out = vec![SHARD_KEY_ENCODING_VERSION];
+    key.encode_shard_key(&mut out);
+    out
+}
+
+/// [shard_key_with_hasher] using the default hasher of [crate::shard_for].
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+#[cfg(feature = "seahash")]
+pub fn shard_for_shard_key(key: &(impl ShardKey + ?Sized), total_destinations: u64) -> u64 {
+    shard_key_with_hasher(
+        key,
+        total_destinations,
+        &crate::seahash::StableSeaHasher::new(),
+    )
+}
+
+/// Returns the shard for [shard_key_bytes] of `key`, as [shard_bytes_with_hasher].
+///
+/// ```
+/// # use faro_sharding::shard_key_with_hasher;
+/// # use std::hash::BuildHasherDefault;
+/// # use std::collections::hash_map::DefaultHasher;
+/// let hasher = BuildHasherDefault::<DefaultHasher>::default();
+/// let key = ("tenant-4", 17u64);
+/// let shard = shard_key_with_hasher(&key, 16, &hasher);
+/// assert!(shard < 16);
+/// ```
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub fn shard_key_with_hasher(
+    key: &(impl ShardKey + ?Sized),
+    total_destinations: u64,
+    hasher: &impl BuildHasher,
+) -> u64 {
+    shard_bytes_with_hasher(&shard_key_bytes(key), total_destinations, hasher)
+}
+
+macro_rules! impl_int {
+    ($($int:ty),*) => {
+        $(
+            impl ShardKey for $int {
+                fn encode_shard_key(&self, out: &mut Vec<u8>) {
+                    out.extend_from_slice(&self.to_le_bytes());
+                }
+            }
+        )*
+    };
+}
+
+impl_int!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+impl ShardKey for usize {
+    fn encode_shard_key(&self, out: &mut Vec<u8>) {
+        (*self as u64).encode_shard_key(out);
+    }
+}
+
+impl ShardKey for isize {
+    fn encode_shard_key(&self, out: &mut Vec<u8>) {
+        (*self as i64).encode_shard_key(out);
+    }
+}
+
+impl ShardKey for bool {
+    fn encode_shard_key(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8);
+    }
+}
+
+impl ShardKey for char {
+    fn encode_shard_key(&self, out: &mut Vec<u8>) {
+        (*self as u32).encode_shard_key(out);
+    }
+}
+
+impl ShardKey for str {
+    fn encode_shard_key(&self, out: &mut Vec<u8>) {
+        (self.len() as u64).encode_shard_key(out);
+        out.extend_from_slice(self.as_bytes());
+    }
+}
+
+impl ShardKey for String {
+    fn encode_shard_key(&self, out: &mut Vec<u8>) {
+        self.as_str().encode_shard_key(out);
+    }
+}
+
+impl<T: ShardKey> ShardKey for [T] {
+    fn encode_shard_key(&self, out: &mut Vec<u8>) {
+        (self.len() as u64).encode_shard_key(out);
+        for item in self {
+            item.encode_shard_key(out);
+        }
+    }
+}
+
+impl<T: ShardKey> ShardKey for Vec<T> {
+    fn encode_shard_key(&self, out: &mut Vec<u8>) {
+        self.as_slice().encode_shard_key(out);
+    }
+}
+
+impl<T: ShardKey, const N: usize> ShardKey for [T; N] {
+    fn encode_shard_key(&self, out: &mut Vec<u8>) {
+        for item in self {
+            item.encode_shard_key(out);
+        }
+    }
+}
+
+impl<T: ShardKey> ShardKey for Option<T> {
+    fn encode_shard_key(&self, out: &mut Vec<u8>) {
+        match self {
+            None => out.push(0),
+            Some(value) => {
+                out.push(1);
+                value.encode_shard_key(out);
+            }
+        }
+    }
+}
+
+impl<T: ShardKey + ?Sized> ShardKey for &T {
+    fn encode_shard_key(&self, out: &mut Vec<u8>) {
+        (**self).encode_shard_key(out);
+    }
+}
+
+impl<T: ShardKey + ?Sized> ShardKey for Box<T> {
+    fn encode_shard_key(&self, out: &mut Vec<u8>) {
+        (**self).encode_shard_key(out);
+    }
+}
+
+impl ShardKey for () {
+    fn encode_shard_key(&self, _: &mut Vec<u8>) {}
+}
+
+macro_rules! impl_tuple {
+    ($($name:ident)+) => {
+        impl<$($name: ShardKey),+> ShardKey for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn encode_shard_key(&self, out: &mut Vec<u8>) {
+                let ($($name,)+) = self;
+                $($name.encode_shard_key(out);)+
+            }
+        }
+    };
+}
+
+impl_tuple!(A);
+impl_tuple!(A B);
+impl_tuple!(A B C);
+impl_tuple!(A B C D);
+impl_tuple!(A B C D E);
+impl_tuple!(A B C D E F);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pinned_encoding() {
+        // Do not change these values. Persisted shard assignments depend on them.
+        assert_eq!(
+            shard_key_bytes(&("ab", 7u16, Some(true), -1i8)),
+            [1, 2, 0, 0, 0, 0, 0, 0, 0, b'a', b'b', 7, 0, 1, 1, 0xff]
+        );
+        assert_eq!(shard_key_bytes(&[1u8, 2]), [1, 1, 2]);
+        assert_eq!(shard_key_bytes(&vec![1u8]), [1, 1, 0, 0, 0, 0, 0, 0, 0, 1]);
+        assert_eq!(shard_key_bytes(&7usize), shard_key_bytes(&7u64));
+    }
+
+    #[cfg(feature = "seahash")]
+    #[test]
+    fn pinned_values() {
+        // Do not change these values. See `pinning_default_shard`.
+        let bytes = shard_key_bytes("foo");
+        assert_eq!(
+            shard_for_shard_key("foo", 73),
+            crate::shard_for_bytes(&bytes, 73)
+        );
+        assert_eq!(shard_for_shard_key(&("foo", 1u64), 73), 67);
+    }
+}
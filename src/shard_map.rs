@@ -0,0 +1,161 @@
+use crate::shard_with_hasher;
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::*;
+
+/// A map whose entries are partitioned into buckets by Faro Sharding.
+///
+/// Growing the bucket count only moves entries into the new buckets, so existing buckets can be handed out as stable partitions.
+#[derive(Clone, Debug)]
+pub struct ShardMap<K, V, S> {
+    buckets: Vec<HashMap<K, V>>,
+    hasher: S,
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher> ShardMap<K, V, S> {
+    /// Creates an empty map with `bucket_count` buckets.
+    ///
+    /// # Panics
+    ///
+    /// If bucket_count == 0.
+    pub fn new(bucket_count: u64, hasher: S) -> Self {
+        assert_ne!(bucket_count, 0, "bucket_count must be > 0");
+
+        ShardMap {
+            buckets: (0..bucket_count).map(|_| HashMap::new()).collect(),
+            hasher,
+        }
+    }
+
+    /// Number of buckets.
+    pub fn bucket_count(&self) -> u64 {
+        self.buckets.len() as u64
+    }
+
+    /// Returns the bucket `key` belongs in, whether or not it is present.
+    pub fn bucket_of<Q: Hash + ?Sized>(&self, key: &Q) -> u64 {
+        shard_with_hasher(key, self.bucket_count(), &self.hasher)
+    }
+
+    /// Inserts `value` for `key`, returning the previous value if there was one.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let bucket = self.bucket_of(&key);
+        self.buckets[bucket as usize].insert(key, value)
+    }
+
+    /// Returns the value for `key`.
+    pub fn get<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+    {
+        self.buckets[self.bucket_of(key) as usize].get(key)
+    }
+
+    /// Returns the value for `key` mutably.
+    pub fn get_mut<Q: Hash + Eq + ?Sized>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+    {
+        let bucket = self.bucket_of(key);
+        self.buckets[bucket as usize].get_mut(key)
+    }
+
+    /// Removes and returns the value for `key`.
+    pub fn remove<Q: Hash + Eq + ?Sized>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+    {
+        let bucket = self.bucket_of(key);
+        self.buckets[bucket as usize].remove(key)
+    }
+
+    /// Total number of entries across all buckets.
+    pub fn len(&self) -> usize {
+        self.buckets.iter().map(HashMap::len).sum()
+    }
+
+    /// Whether every bucket is empty.
+    pub fn is_empty(&self) -> bool {
+        self.buckets.iter().all(HashMap::is_empty)
+    }
+
+    /// Increases the number of buckets to `new_bucket_count`, moving only the entries that now belong in a new bucket.
+    ///
+    /// # Panics
+    ///
+    /// If new_bucket_count is less than the current bucket count.
+    pub fn grow(&mut self, new_bucket_count: u64) {
+        let old_bucket_count = self.bucket_count();
+        assert!(
+            new_bucket_count >= old_bucket_count,
+            "cannot shrink from {} to {} buckets",
+            old_bucket_count,
+            new_bucket_count
+        );
+
+        self.buckets
+            .resize_with(new_bucket_count as usize, HashMap::new);
+
+        for old in 0..old_bucket_count as usize {
+            let moving = self.buckets[old]
+                .extract_if(|key, _| {
+                    shard_with_hasher(key, new_bucket_count, &self.hasher) != old as u64
+                })
+                .collect::<Vec<_>>();
+
+            for (key, value) in moving {
+                let bucket = shard_with_hasher(&key, new_bucket_count, &self.hasher);
+                self.buckets[bucket as usize].insert(key, value);
+            }
+        }
+    }
+
+    /// Iterates the entries in `bucket`. Empty if `bucket` is out of range.
+    pub fn bucket(&self, bucket: u64) -> impl Iterator<Item = (&K, &V)> {
+        self.buckets.get(bucket as usize).into_iter().flatten()
+    }
+
+    /// Iterates every entry, bucket by bucket.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.buckets.iter().flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BuildSeaHasher;
+
+    #[test]
+    fn grow_only_moves_into_new_buckets() {
+        let mut map = ShardMap::new(4, BuildSeaHasher);
+        for i in 0..1_000u64 {
+            map.insert(i, i * 2);
+        }
+        let before = (0..1_000u64).map(|i| map.bucket_of(&i)).collect::<Vec<_>>();
+
+        map.grow(7);
+
+        assert_eq!(map.len(), 1_000);
+        for i in 0..1_000u64 {
+            assert_eq!(map.get(&i), Some(&(i * 2)));
+            let after = map.bucket_of(&i);
+            assert!(after == before[i as usize] || after >= 4);
+            assert!(map.bucket(after).any(|(k, _)| *k == i));
+        }
+    }
+
+    #[test]
+    fn insert_get_remove() {
+        let mut map = ShardMap::new(3, BuildSeaHasher);
+        assert!(map.is_empty());
+
+        assert_eq!(map.insert("foo".to_string(), 1), None);
+        assert_eq!(map.insert("foo".to_string(), 2), Some(1));
+        *map.get_mut("foo").unwrap() += 1;
+        assert_eq!(map.get("foo"), Some(&3));
+
+        assert_eq!(map.remove("foo"), Some(3));
+        assert!(map.is_empty());
+    }
+}
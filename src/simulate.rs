@@ -0,0 +1,212 @@
+//! Replaying a scaling plan over a keyset before running it for real.
+//!
+//! ```
+//! # use faro_sharding::simulate::{Event, Simulation};
+//! # use std::hash::BuildHasherDefault;
+//! # use std::collections::hash_map::DefaultHasher;
+//! let hasher = BuildHasherDefault::<DefaultHasher>::default();
+//! let mut simulation = Simulation::new(10, 0..100_000u64, hasher);
+//! let report = simulation.run(&[Event::GrowTo(20), Event::Drain(3), Event::GrowTo(35)]);
+//!
+//! assert_eq!(report.steps.len(), 3);
+//! assert_eq!(report.churn[3].moved_in, 0);
+//! println!("peak imbalance {:.3}", report.peak_imbalance);
+//! ```
+
+use crate::stats::imbalance_ratio;
+use crate::{DestinationState, TombstoneTopology};
+use std::hash::*;
+
+/// A change to the cluster.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// Adds nodes until there are this many, counting drained and removed ones.
+    GrowTo(u64),
+    /// Stops writing to a node. Its keys move to where they would go if it were removed.
+    Drain(u64),
+    /// Removes a node.
+    Remove(u64),
+}
+
+/// Keys moved onto and off of one node over a simulation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NodeChurn {
+    /// Keys that moved to this node.
+    pub moved_in: u64,
+    /// Keys that moved off this node.
+    pub moved_out: u64,
+}
+
+/// The effect of one [Event].
+#[derive(Clone, Debug, PartialEq)]
+pub struct StepReport {
+    /// The event applied.
+    pub event: Event,
+    /// Keys whose node changed.
+    pub keys_moved: u64,
+    /// Keys on each node afterwards, indexed by node. Drained and removed nodes have 0.
+    pub node_counts: Vec<u64>,
+    /// [imbalance_ratio] across the nodes still being written to.
+    pub imbalance: f64,
+}
+
+/// The result of [Simulation::run].
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimulationReport {
+    /// One report per event, in order.
+    pub steps: Vec<StepReport>,
+    /// Total keys moved across every step.
+    pub total_moved: u64,
+    /// The highest [StepReport::imbalance] of any step.
+    pub peak_imbalance: f64,
+    /// Keys moved onto and off of each node, indexed by node.
+    pub churn: Vec<NodeChurn>,
+}
+
+/// A keyset placed on a simulated cluster. Keys go where [TombstoneTopology::assignment] writes them.
+#[derive(Clone, Debug)]
+pub struct Simulation<S> {
+    topology: TombstoneTopology<S>,
+    key_hashes: Vec<u64>,
+    placement: Vec<u64>,
+}
+
+impl<S: BuildHasher> Simulation<S> {
+    /// Places `keys` on `initial_nodes` nodes.
+    ///
+    /// # Panics
+    ///
+    /// If initial_nodes == 0.
+    pub fn new<K: Hash>(initial_nodes: u64, keys: impl IntoIterator<Item = K>, hasher: S) -> Self {
+        assert_ne!(initial_nodes, 0, "initial_nodes must be > 0");
+
+        let topology = TombstoneTopology::new(initial_nodes, hasher);
+        let key_hashes = keys
+            .into_iter()
+            .map(|key| topology.hasher().hash_one(key))
+            .collect::<Vec<_>>();
+        let placement = key_hashes
+            .iter()
+            .map(|&key_hash| topology.assignment_for_key_hash(key_hash).write)
+            .collect();
+
+        Simulation {
+            topology,
+            key_hashes,
+            placement,
+        }
+    }
+
+    /// The simulated cluster in its current state.
+    pub fn topology(&self) -> &TombstoneTopology<S> {
+        &self.topology
+    }
+
+    /// Applies every event in order, reporting on each and on the whole run.
+    ///
+    /// # Panics
+    ///
+    /// If an event names a node that does not exist, or leaves no node to write to.
+    pub fn run(&mut self, events: &[Event]) -> SimulationReport {
+        let mut churn = Vec::new();
+        let steps = events
+            .iter()
+            .map(|&event| self.step(event, &mut churn))
+            .collect::<Vec<_>>();
+
+        churn.resize(
+            self.topology.total_destinations() as usize,
+            NodeChurn::default(),
+        );
+        SimulationReport {
+            total_moved: steps.iter().map(|s| s.keys_moved).sum(),
+            peak_imbalance: steps.iter().map(|s| s.imbalance).fold(0.0, f64::max),
+            steps,
+            churn,
+        }
+    }
+
+    fn step(&mut self, event: Event, churn: &mut Vec<NodeChurn>) -> StepReport {
+        match event {
+            Event::GrowTo(nodes) => {
+                while self.topology.total_destinations() < nodes {
+                    self.topology.push();
+                }
+            }
+            Event::Drain(node) => {
+                self.topology.drain(node);
+            }
+            Event::Remove(node) => {
+                self.topology.remove(node);
+            }
+        }
+
+        let total = self.topology.total_destinations() as usize;
+        churn.resize(total, NodeChurn::default());
+
+        let mut keys_moved = 0;
+        let mut node_counts = vec![0; total];
+        for (&key_hash, placed) in self.key_hashes.iter().zip(&mut self.placement) {
+            let node = self.topology.assignment_for_key_hash(key_hash).write;
+            if node != *placed {
+                keys_moved += 1;
+                churn[*placed as usize].moved_out += 1;
+                churn[node as usize].moved_in += 1;
+                *placed = node;
+            }
+            node_counts[node as usize] += 1;
+        }
+
+        let writable = node_counts
+            .iter()
+            .enumerate()
+            .filter(|&(node, _)| self.topology.state(node as u64) == DestinationState::Live)
+            .map(|(_, &count)| count)
+            .collect::<Vec<_>>();
+
+        StepReport {
+            event,
+            keys_moved,
+            imbalance: imbalance_ratio(&writable),
+            node_counts,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{shard_for, BuildSeaHasher};
+
+    #[test]
+    fn growth_matches_shard_for() {
+        let mut simulation = Simulation::new(10, 0..10_000u64, BuildSeaHasher);
+        let report = simulation.run(&[Event::GrowTo(20)]);
+
+        let expected_moved = (0..10_000u64)
+            .filter(|k| shard_for(k, 10) != shard_for(k, 20))
+            .count();
+        assert_eq!(report.total_moved, expected_moved as u64);
+        assert!(report.churn[..10].iter().all(|c| c.moved_in == 0));
+        assert!(report.churn[10..].iter().all(|c| c.moved_out == 0));
+    }
+
+    #[test]
+    fn draining_only_moves_the_drained_nodes_keys() {
+        let mut simulation = Simulation::new(10, 0..10_000u64, BuildSeaHasher);
+        let before = (0..10_000u64).filter(|k| shard_for(k, 10) == 3).count() as u64;
+
+        let report = simulation.run(&[Event::Drain(3), Event::GrowTo(12), Event::Remove(3)]);
+        assert_eq!(report.steps[0].keys_moved, before);
+        assert_eq!(report.steps[0].node_counts[3], 0);
+        assert_eq!(report.steps[2].keys_moved, 0);
+        assert_eq!(
+            report.churn[3],
+            NodeChurn {
+                moved_in: 0,
+                moved_out: before
+            }
+        );
+        assert!(report.peak_imbalance >= 1.0);
+    }
+}
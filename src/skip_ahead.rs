@@ -0,0 +1,88 @@
+use core::hash::*;
+
+/// Faro Sharding in `O(log total_destinations)` time, for very large destination counts.
+///
+/// [crate::shard_with_hasher] walks every step of the chain. On step `n` a key moves to shard `n` with probability `1 / (n + 1)`, so the chance a key currently on shard `b` makes no move before step `j` is `(b + 1) / j`. Like [JumpHash](https://arxiv.org/abs/1406.2294), this samples the next moving step directly from that distribution instead of visiting every step in between.
+///
+/// The set of steps a key moves on does not depend on the destination count, so this has the same guarantee: adding destinations only moves keys onto the new destinations. The steps are sampled from random draws rather than found by hashing each one, so they are not the steps [crate::shard_with_hasher] would pick; data placed by one cannot be found with the other.
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub fn shard_with_hasher_fast(
+    key: impl Hash,
+    total_destinations: u64,
+    hasher: &impl BuildHasher,
+) -> u64 {
+    assert_ne!(total_destinations, 0, "total_destinations must be > 0");
+    skip_ahead_from_key_hash(hasher.hash_one(key), total_destinations, hasher)
+}
+
+pub(crate) fn skip_ahead_from_key_hash(
+    key_hash: u64,
+    total_destinations: u64,
+    hasher: &impl BuildHasher,
+) -> u64 {
+    let mut shard = 0;
+    let mut last_hash = key_hash;
+    loop {
+        last_hash = hasher.hash_one(last_hash);
+
+        // Uniform in (0, 1].
+        let r = ((last_hash >> 11) + 1) as f64 / (1u64 << 53) as f64;
+        let next = (shard + 1) as f64 / r;
+        if next >= total_destinations as f64 {
+            break;
+        }
+        // Truncation is floor for positive values, and keeps this usable without std.
+        shard = next as u64;
+    }
+
+    debug_assert!(shard < total_destinations);
+    shard
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BuildSeaHasher;
+
+    use proptest::*;
+
+    proptest! {
+        #[test]
+        fn does_not_move_between_existing_destinations(key: String) {
+            let mut last_shard = shard_with_hasher_fast(&key, 1, &BuildSeaHasher);
+            for n in 2..=1024 {
+                let next_shard = shard_with_hasher_fast(&key, n, &BuildSeaHasher);
+                prop_assert!(next_shard == last_shard || next_shard == n - 1);
+                last_shard = next_shard;
+            }
+        }
+
+        #[test]
+        fn only_moves_to_new_destinations_at_large_counts(key: String, from in 1u64..1 << 40, added in 1u64..1 << 20) {
+            let before = shard_with_hasher_fast(&key, from, &BuildSeaHasher);
+            let after = shard_with_hasher_fast(&key, from + added, &BuildSeaHasher);
+            prop_assert!(after == before || after >= from);
+        }
+    }
+
+    #[test]
+    fn distributes_evenly() {
+        let locations = 16;
+        let mut counts = vec![0; locations as usize];
+        for i in 0..100_000 {
+            counts[shard_with_hasher_fast(i, locations, &BuildSeaHasher) as usize] += 1;
+        }
+
+        assert!(crate::stats::imbalance_ratio(&counts) < 1.05, "{counts:?}");
+    }
+
+    #[test]
+    fn handles_huge_destination_counts() {
+        for key in 0..100 {
+            assert!(shard_with_hasher_fast(key, u64::MAX, &BuildSeaHasher) < u64::MAX);
+        }
+    }
+}
@@ -0,0 +1,52 @@
+//! Slot bookkeeping shared by [crate::WeightedSharder] and [crate::Topology], where each unit of weight is a slot that is tombstoned rather than deleted.
+
+/// The most slots either can hold, tombstoned ones included.
+pub(crate) const MAX_SLOTS: u64 = 1 << 24;
+
+/// # Panics
+///
+/// If adding `additional` slots to `existing` would make more than [MAX_SLOTS].
+pub(crate) fn check_slots(existing: usize, additional: u64) {
+    assert!(
+        additional <= MAX_SLOTS.saturating_sub(existing as u64),
+        "slot count must be <= {}",
+        MAX_SLOTS
+    );
+}
+
+/// A change of one destination's weight, as slots to toggle and slots to add.
+pub(crate) struct Reweigh {
+    /// Indices of the slots to revive, when raising, or tombstone, when lowering.
+    pub(crate) toggled: Vec<usize>,
+    /// How many new slots to append for the destination after reviving.
+    pub(crate) added: u64,
+}
+
+/// Plans changing `destination`'s weight from `current` to `weight`, given every slot's `(owner, live)` in the order they were added.
+///
+/// Raising revives the destination's tombstoned slots in the order they were added, which is the reverse of the order lowering tombstones them, and only then adds slots. Lowering tombstones its most recently added live slots.
+pub(crate) fn reweigh<I>(slots: I, destination: u64, current: u64, weight: u64) -> Reweigh
+where
+    I: DoubleEndedIterator<Item = (u64, bool)> + ExactSizeIterator,
+{
+    let owned = slots
+        .enumerate()
+        .filter(|&(_, (owner, _))| owner == destination);
+    if weight >= current {
+        let toggled = owned
+            .filter(|&(_, (_, live))| !live)
+            .map(|(slot, _)| slot)
+            .take((weight - current) as usize)
+            .collect::<Vec<_>>();
+        let added = weight - current - toggled.len() as u64;
+        Reweigh { toggled, added }
+    } else {
+        let toggled = owned
+            .rev()
+            .filter(|&(_, (_, live))| live)
+            .map(|(slot, _)| slot)
+            .take((current - weight) as usize)
+            .collect();
+        Reweigh { toggled, added: 0 }
+    }
+}
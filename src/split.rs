@@ -0,0 +1,262 @@
+#[cfg(feature = "serde")]
+use crate::format_version::FormatVersion;
+use crate::shard_for_key_hash;
+use std::hash::*;
+
+/// A node in a [SplitTopology]'s split tree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SplitNode {
+    /// A shard that holds keys.
+    Leaf(u64),
+    /// A shard that was split. Its keys are sharded again across `children`.
+    Split {
+        /// Seeds the hash that places keys among the children, so nested splits place keys independently. It is the leaf created by the split.
+        seed: u64,
+        /// The shards the split shard's keys are spread across.
+        children: Vec<SplitNode>,
+    },
+}
+
+/// Shards keys across root shards, and lets any one shard be split in two without disturbing the others.
+///
+/// Splitting leaf `L` keeps `L` as the first half and creates a new leaf for the second half. Only keys on `L` are affected, and about half of them move to the new leaf. Leaves can be split again, building a tree. Each split places keys with Faro Sharding on the key hash and the split's seed.
+///
+/// Adding a root shard with [SplitTopology::push_root] moves keys onto the new root from every root, as with [crate::shard_with_hasher].
+///
+/// ```
+/// # use faro_sharding::SplitTopology;
+/// # use std::hash::BuildHasherDefault;
+/// # use std::collections::hash_map::DefaultHasher;
+/// let hasher = BuildHasherDefault::<DefaultHasher>::default();
+/// let mut topology = SplitTopology::new(4, hasher);
+/// let before = topology.leaf_for("user-17");
+///
+/// let new_leaf = topology.split(before);
+/// assert_eq!(new_leaf, 4);
+/// assert!([before, new_leaf].contains(&topology.leaf_for("user-17")));
+/// ```
+///
+/// With the `serde` feature, loading checks that there is at least one root, that every split has children, and that the leaves are exactly `0..leaf_count`.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "PersistedSplitTopology"))]
+pub struct SplitTopology<S> {
+    #[cfg(feature = "serde")]
+    version: FormatVersion,
+    roots: Vec<SplitNode>,
+    leaf_count: u64,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    hasher: S,
+}
+
+/// The persisted fields of a [SplitTopology], checked before use.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct PersistedSplitTopology {
+    #[allow(dead_code)]
+    version: FormatVersion,
+    roots: Vec<SplitNode>,
+    leaf_count: u64,
+}
+
+#[cfg(feature = "serde")]
+impl<S: Default> TryFrom<PersistedSplitTopology> for SplitTopology<S> {
+    type Error = String;
+
+    fn try_from(persisted: PersistedSplitTopology) -> Result<Self, String> {
+        fn collect_leaves(nodes: &[SplitNode], leaves: &mut Vec<u64>) -> Result<(), String> {
+            if nodes.is_empty() {
+                return Err("split tree has a node with no children".to_string());
+            }
+            for node in nodes {
+                match node {
+                    SplitNode::Leaf(leaf) => leaves.push(*leaf),
+                    SplitNode::Split { children, .. } => collect_leaves(children, leaves)?,
+                }
+            }
+            Ok(())
+        }
+
+        let mut leaves = Vec::new();
+        collect_leaves(&persisted.roots, &mut leaves)?;
+        leaves.sort_unstable();
+        if !leaves.iter().copied().eq(0..persisted.leaf_count) {
+            return Err(format!(
+                "leaves must be exactly 0..{}",
+                persisted.leaf_count
+            ));
+        }
+
+        Ok(SplitTopology {
+            version: FormatVersion,
+            roots: persisted.roots,
+            leaf_count: persisted.leaf_count,
+            hasher: S::default(),
+        })
+    }
+}
+
+impl<S: BuildHasher> SplitTopology<S> {
+    /// Creates a topology of `root_count` unsplit shards, which are leaves `0..root_count`.
+    ///
+    /// # Panics
+    ///
+    /// If root_count == 0.
+    pub fn new(root_count: u64, hasher: S) -> Self {
+        assert_ne!(root_count, 0, "root_count must be > 0");
+
+        SplitTopology {
+            #[cfg(feature = "serde")]
+            version: FormatVersion,
+            roots: (0..root_count).map(SplitNode::Leaf).collect(),
+            leaf_count: root_count,
+            hasher,
+        }
+    }
+
+    /// The root shards, each a leaf or the tree it was split into.
+    pub fn roots(&self) -> &[SplitNode] {
+        &self.roots
+    }
+
+    /// Number of leaves. Leaves are numbered `0..leaf_count` in the order they were created.
+    pub fn leaf_count(&self) -> u64 {
+        self.leaf_count
+    }
+
+    /// The leaf `key` is on.
+    pub fn leaf_for(&self, key: impl Hash) -> u64 {
+        let key_hash = self.hasher.hash_one(key);
+        let mut nodes = &self.roots;
+        let mut index = shard_for_key_hash(key_hash, nodes.len() as u64, &self.hasher);
+        loop {
+            match &nodes[index as usize] {
+                SplitNode::Leaf(leaf) => return *leaf,
+                SplitNode::Split { seed, children } => {
+                    let child_hash = self.hasher.hash_one((key_hash, seed));
+                    index = shard_for_key_hash(child_hash, children.len() as u64, &self.hasher);
+                    nodes = children;
+                }
+            }
+        }
+    }
+
+    /// Splits `leaf` in two, returning the new leaf that takes about half of its keys.
+    ///
+    /// # Panics
+    ///
+    /// If leaf is not a leaf of this topology.
+    pub fn split(&mut self, leaf: u64) -> u64 {
+        let new_leaf = self.leaf_count;
+        let node =
+            find_leaf(&mut self.roots, leaf).unwrap_or_else(|| panic!("{} is not a leaf", leaf));
+        *node = SplitNode::Split {
+            seed: new_leaf,
+            children: vec![SplitNode::Leaf(leaf), SplitNode::Leaf(new_leaf)],
+        };
+        self.leaf_count += 1;
+        new_leaf
+    }
+
+    /// Adds an unsplit root shard, returning its leaf.
+    pub fn push_root(&mut self) -> u64 {
+        let new_leaf = self.leaf_count;
+        self.roots.push(SplitNode::Leaf(new_leaf));
+        self.leaf_count += 1;
+        new_leaf
+    }
+}
+
+fn find_leaf(nodes: &mut [SplitNode], leaf: u64) -> Option<&mut SplitNode> {
+    for node in nodes {
+        match node {
+            SplitNode::Leaf(l) if *l == leaf => return Some(node),
+            SplitNode::Leaf(_) => {}
+            SplitNode::Split { children, .. } => {
+                if let Some(found) = find_leaf(children, leaf) {
+                    return Some(found);
+                }
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{shard_for, BuildSeaHasher};
+
+    #[test]
+    fn splitting_only_moves_keys_off_the_split_leaf() {
+        let mut topology = SplitTopology::new(5, BuildSeaHasher);
+        for key in 0..1_000u64 {
+            assert_eq!(topology.leaf_for(key), shard_for(key, 5));
+        }
+
+        for (leaf, expected_new) in [(2, 5), (2, 6), (5, 7)] {
+            let before = (0..10_000u64)
+                .map(|key| topology.leaf_for(key))
+                .collect::<Vec<_>>();
+            assert_eq!(topology.split(leaf), expected_new);
+
+            let mut moved = 0;
+            for (key, &was) in before.iter().enumerate() {
+                let now = topology.leaf_for(key as u64);
+                if now != was {
+                    assert_eq!((was, now), (leaf, expected_new));
+                    moved += 1;
+                }
+            }
+            let on_leaf = before.iter().filter(|&&l| l == leaf).count();
+            assert!(moved > on_leaf / 3 && moved < on_leaf * 2 / 3);
+        }
+        assert_eq!(topology.leaf_count(), 8);
+    }
+
+    #[test]
+    #[should_panic(expected = "9 is not a leaf")]
+    fn cannot_split_unknown_leaf() {
+        SplitTopology::new(5, BuildSeaHasher).split(9);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn tree_round_trips() {
+        let mut topology = SplitTopology::new(3, BuildSeaHasher);
+        topology.split(1);
+        topology.split(3);
+        topology.push_root();
+
+        let json = serde_json::to_string(&topology).unwrap();
+        let restored: SplitTopology<BuildSeaHasher> = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.roots(), topology.roots());
+        for key in 0..1_000u64 {
+            assert_eq!(restored.leaf_for(key), topology.leaf_for(key));
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn rejects_invalid_trees() {
+        for (json, error) in [
+            (r#"{"version":1,"roots":[],"leaf_count":0}"#, "no children"),
+            (
+                r#"{"version":1,"roots":[{"Split":{"seed":1,"children":[]}}],"leaf_count":0}"#,
+                "no children",
+            ),
+            (
+                r#"{"version":1,"roots":[{"Leaf":0},{"Leaf":1}],"leaf_count":1}"#,
+                "leaves must be exactly 0..1",
+            ),
+            (
+                r#"{"version":1,"roots":[{"Leaf":0},{"Leaf":0}],"leaf_count":2}"#,
+                "leaves must be exactly 0..2",
+            ),
+        ] {
+            let err = serde_json::from_str::<SplitTopology<BuildSeaHasher>>(json).unwrap_err();
+            assert!(err.to_string().contains(error), "{}: {}", json, err);
+        }
+    }
+}
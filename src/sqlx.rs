@@ -0,0 +1,32 @@
+//! [crate::ShardRouter] for [sqlx](https://docs.rs/sqlx) pools.
+//!
+//! ```ignore
+//! let router = faro_sharding::sqlx::connect_lazy::<Postgres, _>(&urls, hasher)?;
+//! let user = sqlx::query_as("SELECT * FROM users WHERE id = $1")
+//!     .bind(user_id)
+//!     .fetch_one(router.route(&user_id))
+//!     .await?;
+//! ```
+
+use crate::PoolRouter;
+use sqlx::{Database, Pool};
+use std::hash::BuildHasher;
+
+/// A [PoolRouter] with one sqlx [Pool] per shard.
+pub type SqlxRouter<DB, S> = PoolRouter<Pool<DB>, S>;
+
+/// Creates a router with a lazily connecting pool for each of `urls`, in shard order.
+///
+/// # Panics
+///
+/// If urls is empty.
+pub fn connect_lazy<DB: Database, S: BuildHasher>(
+    urls: &[&str],
+    hasher: S,
+) -> Result<SqlxRouter<DB, S>, sqlx::Error> {
+    let pools = urls
+        .iter()
+        .map(|url| Pool::connect_lazy(url))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(PoolRouter::new(pools, hasher))
+}
@@ -0,0 +1,163 @@
+use crate::{shard_with_hasher, ShardingStrategy};
+use core::fmt;
+use core::hash::*;
+
+/// A broken guarantee found by [verify_stability] or [verify_strategy_stability].
+///
+/// Keys are identified by their position in the iterator that was checked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StabilityViolation {
+    /// The shard was not in `0..total_destinations`.
+    OutOfRange {
+        /// Position of the key.
+        key_index: usize,
+        /// The destination count.
+        total_destinations: u64,
+        /// The shard returned.
+        shard: u64,
+    },
+    /// Going from `total_destinations` to `total_destinations + 1` moved the key somewhere other than the new destination.
+    Moved {
+        /// Position of the key.
+        key_index: usize,
+        /// The destination count before adding one.
+        total_destinations: u64,
+        /// The shard before adding a destination.
+        from: u64,
+        /// The shard after adding a destination.
+        to: u64,
+    },
+}
+
+impl fmt::Display for StabilityViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            StabilityViolation::OutOfRange {
+                key_index,
+                total_destinations,
+                shard,
+            } => write!(
+                f,
+                "key #{} was sharded to {} of {} destinations",
+                key_index, shard, total_destinations
+            ),
+            StabilityViolation::Moved {
+                key_index,
+                total_destinations,
+                from,
+                to,
+            } => write!(
+                f,
+                "key #{} moved from shard {} to {} going from {} to {} destinations",
+                key_index,
+                from,
+                to,
+                total_destinations,
+                total_destinations + 1
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for StabilityViolation {}
+
+/// Checks that [shard_with_hasher] with `hasher` keeps every key in range and only moves keys onto the new destination, for every destination count in `1..=max_destinations`.
+///
+/// Meant for downstream test suites that plug in their own hasher. This calls [shard_with_hasher] directly for each count rather than reusing the chain, so it costs `O(max_destinations^2)` hashes per key.
+pub fn verify_stability<K: Hash>(
+    hasher: &impl BuildHasher,
+    keys: impl IntoIterator<Item = K>,
+    max_destinations: u64,
+) -> Result<(), StabilityViolation> {
+    verify_with(keys, max_destinations, |key, n| {
+        shard_with_hasher(key, n, hasher)
+    })
+}
+
+/// [verify_stability] for any [ShardingStrategy], such as a [crate::Sharder] running another [crate::Algorithm].
+///
+/// Keys are hashed with `hasher` and passed to the strategy as key hashes.
+pub fn verify_strategy_stability<K: Hash>(
+    strategy: &(impl ShardingStrategy + ?Sized),
+    hasher: &impl BuildHasher,
+    keys: impl IntoIterator<Item = K>,
+    max_destinations: u64,
+) -> Result<(), StabilityViolation> {
+    verify_with(keys, max_destinations, |key, n| {
+        strategy.shard_for_hash(hasher.hash_one(key), n)
+    })
+}
+
+/// Checks `shard` for every key and destination count in `1..=max_destinations`, for mappings that fit neither [verify_stability] nor [verify_strategy_stability].
+pub(crate) fn verify_with<K>(
+    keys: impl IntoIterator<Item = K>,
+    max_destinations: u64,
+    shard: impl Fn(&K, u64) -> u64,
+) -> Result<(), StabilityViolation> {
+    for (key_index, key) in keys.into_iter().enumerate() {
+        let mut previous = None;
+        for total_destinations in 1..=max_destinations {
+            let current = shard(&key, total_destinations);
+            if current >= total_destinations {
+                return Err(StabilityViolation::OutOfRange {
+                    key_index,
+                    total_destinations,
+                    shard: current,
+                });
+            }
+            if let Some(from) = previous {
+                if current != from && current != total_destinations - 1 {
+                    return Err(StabilityViolation::Moved {
+                        key_index,
+                        total_destinations: total_destinations - 1,
+                        from,
+                        to: current,
+                    });
+                }
+            }
+            previous = Some(current);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Algorithm, BuildSeaHasher, Sharder};
+
+    /// Modulo sharding, which moves most keys when a destination is added.
+    struct Modulo;
+
+    impl ShardingStrategy for Modulo {
+        fn shard_for_hash(&self, key_hash: u64, total_destinations: u64) -> u64 {
+            key_hash % total_destinations
+        }
+    }
+
+    #[test]
+    fn faro_is_stable() {
+        assert_eq!(verify_stability(&BuildSeaHasher, 0..200u64, 100), Ok(()));
+        for &algorithm in Algorithm::ALL {
+            let sharder = Sharder::new(algorithm, BuildSeaHasher);
+            assert_eq!(
+                verify_strategy_stability(&sharder, &BuildSeaHasher, 0..50u64, 100),
+                Ok(()),
+                "{}",
+                algorithm
+            );
+        }
+    }
+
+    #[test]
+    fn modulo_is_not() {
+        let violation =
+            verify_strategy_stability(&Modulo, &BuildSeaHasher, 0..10u64, 10).unwrap_err();
+        assert!(
+            matches!(violation, StabilityViolation::Moved { .. }),
+            "{}",
+            violation
+        );
+    }
+}
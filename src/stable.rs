@@ -0,0 +1,83 @@
+use crate::shard_with_hasher;
+use core::hash::*;
+
+/// A [BuildHasher] whose hashers return the same hash for the same input in every process, on every platform, and in every version.
+///
+/// Shards are only stable if the hasher is. [std::collections::hash_map::RandomState] is seeded randomly per process, so passing it to [shard_with_hasher] moves keys on every restart. [shard_with_stable_hasher] requires this trait to rule that out at compile time:
+///
+/// ```compile_fail
+/// # use faro_sharding::*;
+/// let shard = shard_with_stable_hasher("foo", 50, &std::collections::hash_map::RandomState::new());
+/// ```
+///
+/// The bundled hashers implement it. To opt in your own, implement it after checking that the hasher has fixed seeds and a specified output, and pin some shards in a test (see [crate::golden]):
+///
+/// ```
+/// # use faro_sharding::{shard_with_stable_hasher, StableBuildHasher};
+/// # use std::hash::BuildHasher;
+/// use seahash::SeaHasher;
+///
+/// struct MyHasher;
+///
+/// impl BuildHasher for MyHasher {
+///     type Hasher = SeaHasher;
+///
+///     fn build_hasher(&self) -> SeaHasher {
+///         // SeaHash's output is specified, and these seeds never change.
+///         SeaHasher::with_seeds(1, 2, 3, 4)
+///     }
+/// }
+///
+/// impl StableBuildHasher for MyHasher {}
+///
+/// let shard = shard_with_stable_hasher("foo", 50, &MyHasher);
+/// ```
+///
+/// Hashers that cannot implement this trait, such as a foreign type, can still be used through [shard_with_hasher_unchecked].
+pub trait StableBuildHasher: BuildHasher {}
+
+/// [shard_with_hasher] for hashers known to be stable. Prefer this to [shard_with_hasher], which accepts any hasher unchecked.
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub fn shard_with_stable_hasher(
+    key: impl Hash,
+    total_destinations: u64,
+    hasher: &impl StableBuildHasher,
+) -> u64 {
+    shard_with_hasher(key, total_destinations, hasher)
+}
+
+/// [shard_with_hasher] for any hasher, named for the check it skips. The caller is responsible for the hasher being stable.
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub fn shard_with_hasher_unchecked(
+    key: impl Hash,
+    total_destinations: u64,
+    hasher: &impl BuildHasher,
+) -> u64 {
+    shard_with_hasher(key, total_destinations, hasher)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{shard_for, BuildSeaHasher};
+
+    #[test]
+    fn agrees_with_shard_with_hasher() {
+        for key in 0..1_000u64 {
+            assert_eq!(
+                shard_with_stable_hasher(key, 73, &BuildSeaHasher),
+                shard_for(key, 73)
+            );
+            assert_eq!(
+                shard_with_hasher_unchecked(key, 73, &BuildSeaHasher),
+                shard_for(key, 73)
+            );
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use crate::shard_bytes_with_hasher;
+use core::hash::*;
+
+/// A key whose bytes are fixed by this crate rather than by its [Hash] impl.
+///
+/// `impl Hash` for `usize` depends on pointer width, and std may change how other types feed a hasher. [shard_stable_key_with_hasher] only accepts StableKey, and hashes exactly [StableKey::stable_bytes]:
+///
+/// - Integers are their little-endian bytes. `usize` and `isize` are widened to 64 bits.
+/// - `bool` is one byte, and `char` is its `u32`.
+/// - Strings and byte slices are their bytes, so they shard the same as [crate::shard_bytes_with_hasher], and as any other language hashing the same bytes.
+/// - With `std`, tuples are each element's bytes preceded by their length as a `u64`.
+/// - With the `uuid` feature, `Uuid` is its 16 bytes.
+///
+/// For structs, see [crate::ShardKey].
+pub trait StableKey {
+    /// The bytes hashed to shard this key.
+    fn stable_bytes(&self) -> impl AsRef<[u8]>;
+}
+
+/// [shard_stable_key_with_hasher] using the default hasher of [crate::shard_for].
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+#[cfg(feature = "seahash")]
+pub fn shard_for_stable_key(key: &(impl StableKey + ?Sized), total_destinations: u64) -> u64 {
+    shard_stable_key_with_hasher(
+        key,
+        total_destinations,
+        &crate::seahash::StableSeaHasher::new(),
+    )
+}
+
+/// Returns the shard for [StableKey::stable_bytes] of `key`, as [shard_bytes_with_hasher].
+///
+/// ```
+/// # use faro_sharding::{shard_bytes_with_hasher, shard_stable_key_with_hasher};
+/// # use std::hash::BuildHasherDefault;
+/// # use std::collections::hash_map::DefaultHasher;
+/// let hasher = BuildHasherDefault::<DefaultHasher>::default();
+/// assert_eq!(
+///     shard_stable_key_with_hasher(&17usize, 16, &hasher),
+///     shard_bytes_with_hasher(&17u64.to_le_bytes(), 16, &hasher),
+/// );
+/// ```
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub fn shard_stable_key_with_hasher(
+    key: &(impl StableKey + ?Sized),
+    total_destinations: u64,
+    hasher: &impl BuildHasher,
+) -> u64 {
+    shard_bytes_with_hasher(key.stable_bytes().as_ref(), total_destinations, hasher)
+}
+
+macro_rules! impl_int {
+    ($($int:ty),*) => {
+        $(
+            impl StableKey for $int {
+                fn stable_bytes(&self) -> impl AsRef<[u8]> {
+                    self.to_le_bytes()
+                }
+            }
+        )*
+    };
+}
+
+impl_int!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+impl StableKey for usize {
+    fn stable_bytes(&self) -> impl AsRef<[u8]> {
+        (*self as u64).to_le_bytes()
+    }
+}
+
+impl StableKey for isize {
+    fn stable_bytes(&self) -> impl AsRef<[u8]> {
+        (*self as i64).to_le_bytes()
+    }
+}
+
+impl StableKey for bool {
+    fn stable_bytes(&self) -> impl AsRef<[u8]> {
+        [*self as u8]
+    }
+}
+
+impl StableKey for char {
+    fn stable_bytes(&self) -> impl AsRef<[u8]> {
+        (*self as u32).to_le_bytes()
+    }
+}
+
+impl StableKey for str {
+    fn stable_bytes(&self) -> impl AsRef<[u8]> {
+        self.as_bytes()
+    }
+}
+
+impl StableKey for [u8] {
+    fn stable_bytes(&self) -> impl AsRef<[u8]> {
+        self
+    }
+}
+
+impl<const N: usize> StableKey for [u8; N] {
+    fn stable_bytes(&self) -> impl AsRef<[u8]> {
+        self
+    }
+}
+
+impl<T: StableKey + ?Sized> StableKey for &T {
+    fn stable_bytes(&self) -> impl AsRef<[u8]> {
+        (**self).stable_bytes()
+    }
+}
+
+#[cfg(feature = "std")]
+impl StableKey for String {
+    fn stable_bytes(&self) -> impl AsRef<[u8]> {
+        self.as_bytes()
+    }
+}
+
+#[cfg(feature = "std")]
+impl StableKey for Vec<u8> {
+    fn stable_bytes(&self) -> impl AsRef<[u8]> {
+        self.as_slice()
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl StableKey for ::uuid::Uuid {
+    fn stable_bytes(&self) -> impl AsRef<[u8]> {
+        *self.as_bytes()
+    }
+}
+
+macro_rules! impl_tuple {
+    ($($name:ident)+) => {
+        #[cfg(feature = "std")]
+        impl<$($name: StableKey),+> StableKey for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn stable_bytes(&self) -> impl AsRef<[u8]> {
+                let ($($name,)+) = self;
+                let mut bytes = Vec::new();
+                $(
+                    let element = $name.stable_bytes();
+                    let element = element.as_ref();
+                    bytes.extend_from_slice(&(element.len() as u64).to_le_bytes());
+                    bytes.extend_from_slice(element);
+                )+
+                bytes
+            }
+        }
+    };
+}
+
+impl_tuple!(A);
+impl_tuple!(A B);
+impl_tuple!(A B C);
+impl_tuple!(A B C D);
+
+#[cfg(all(test, feature = "std", feature = "seahash"))]
+mod tests {
+    use super::*;
+    use crate::shard_for_bytes;
+
+    #[test]
+    fn strings_shard_as_their_bytes() {
+        assert_eq!(shard_for_stable_key("foo", 73), shard_for_bytes(b"foo", 73));
+        assert_eq!(
+            shard_for_stable_key(&String::from("foo"), 73),
+            shard_for_bytes(b"foo", 73)
+        );
+    }
+
+    #[test]
+    fn pinned_values() {
+        // Do not change these values. See `pinning_default_shard`.
+        assert_eq!(shard_for_stable_key(&42u64, 73), 34);
+        assert_eq!(shard_for_stable_key(&42usize, 73), 34);
+        assert_eq!(shard_for_stable_key(&("a", 1u8), 73), 26);
+    }
+
+    #[test]
+    fn tuples_are_unambiguous() {
+        assert_ne!(
+            ("ab", "c").stable_bytes().as_ref(),
+            ("a", "bc").stable_bytes().as_ref()
+        );
+    }
+}
@@ -0,0 +1,125 @@
+use core::hash::*;
+
+/// A key's position in the Faro hash chain, which can be resumed as destinations are added.
+///
+/// Computing the shard for `n` destinations walks `n - 1` chain steps. Keeping a `ShardState` around lets growing from `n` to `m` destinations cost only `m - n` steps.
+///
+/// ```
+/// # use faro_sharding::*;
+/// # use std::hash::BuildHasherDefault;
+/// # use std::collections::hash_map::DefaultHasher;
+/// let hasher = BuildHasherDefault::<DefaultHasher>::default();
+/// let mut state = ShardState::new("foo", &hasher);
+/// state.extend_to(40, &hasher);
+/// assert_eq!(state.extend_to(48, &hasher), shard_with_hasher("foo", 48, &hasher));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ShardState {
+    last_hash: u64,
+    shard: u64,
+    destinations: u64,
+}
+
+impl ShardState {
+    /// Starts the chain for `key` at a single destination.
+    pub fn new(key: impl Hash, hasher: &impl BuildHasher) -> Self {
+        Self::from_key_hash(hasher.hash_one(key))
+    }
+
+    /// Starts the chain for a key that hashes to `key_hash`, at a single destination.
+    pub fn from_key_hash(key_hash: u64) -> Self {
+        ShardState {
+            last_hash: key_hash,
+            shard: 0,
+            destinations: 1,
+        }
+    }
+
+    /// Restores a state previously taken apart with [ShardState::into_parts].
+    ///
+    /// # Panics
+    ///
+    /// If shard >= destinations.
+    pub fn from_parts(last_hash: u64, shard: u64, destinations: u64) -> Self {
+        assert!(shard < destinations, "shard must be < destinations");
+        ShardState {
+            last_hash,
+            shard,
+            destinations,
+        }
+    }
+
+    /// Returns `(last_hash, shard, destinations)`, suitable for persisting and passing to [ShardState::from_parts].
+    pub fn into_parts(self) -> (u64, u64, u64) {
+        (self.last_hash, self.shard, self.destinations)
+    }
+
+    /// The shard for the current destination count.
+    pub fn shard(&self) -> u64 {
+        self.shard
+    }
+
+    /// The destination count this state has been extended to.
+    pub fn destinations(&self) -> u64 {
+        self.destinations
+    }
+
+    /// Advances the chain to `new_destinations` and returns the new shard.
+    ///
+    /// The hasher must be the one the state was created with.
+    ///
+    /// # Panics
+    ///
+    /// If new_destinations is less than the current destination count.
+    pub fn extend_to(&mut self, new_destinations: u64, hasher: &impl BuildHasher) -> u64 {
+        assert!(
+            new_destinations >= self.destinations,
+            "cannot extend from {} down to {new_destinations} destinations",
+            self.destinations
+        );
+
+        for n in self.destinations..new_destinations {
+            let hash = hasher.hash_one(self.last_hash);
+            if hash.is_multiple_of(n + 1) {
+                self.shard = n;
+            }
+            self.last_hash = hash;
+        }
+        self.destinations = new_destinations;
+
+        self.shard
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{shard_with_hasher, BuildSeaHasher};
+
+    use proptest::*;
+
+    proptest! {
+        #[test]
+        fn resuming_matches_from_scratch(key: String, steps in collection::vec(0u64..64, 1..8)) {
+            let mut state = ShardState::new(&key, &BuildSeaHasher);
+            for step in steps {
+                let target = state.destinations() + step;
+                let (last_hash, shard, destinations) = state.into_parts();
+                state = ShardState::from_parts(last_hash, shard, destinations);
+
+                prop_assert_eq!(
+                    state.extend_to(target, &BuildSeaHasher),
+                    shard_with_hasher(&key, target, &BuildSeaHasher)
+                );
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot extend from 10 down to 9 destinations")]
+    fn cannot_shrink() {
+        let mut state = ShardState::new("foo", &BuildSeaHasher);
+        state.extend_to(10, &BuildSeaHasher);
+        state.extend_to(9, &BuildSeaHasher);
+    }
+}
@@ -0,0 +1,489 @@
+//! Measures of how evenly keys are spread across shards, [recommend_weights] to even them out, and [recommend_destinations] to size a cluster.
+
+use crate::{shard_with_hasher, WeightedSharder};
+use std::hash::*;
+
+/// Ratio of the fullest shard to the mean shard size. `1.0` is perfectly balanced.
+///
+/// Returns `0.0` if there are no shards or no keys.
+pub fn imbalance_ratio(shard_counts: &[u64]) -> f64 {
+    let total: u64 = shard_counts.iter().sum();
+    if total == 0 {
+        return 0.0;
+    }
+
+    let max = shard_counts.iter().copied().max().unwrap_or_default();
+    let mean = total as f64 / shard_counts.len() as f64;
+    max as f64 / mean
+}
+
+/// Shannon entropy of the distribution divided by the maximum possible entropy for this many shards.
+///
+/// `1.0` means keys are spread perfectly evenly. Returns `1.0` for a single shard and `0.0` if there are no keys.
+pub fn entropy_efficiency(shard_counts: &[u64]) -> f64 {
+    let total: u64 = shard_counts.iter().sum();
+    if total == 0 {
+        return 0.0;
+    }
+    if shard_counts.len() == 1 {
+        return 1.0;
+    }
+
+    let entropy: f64 = shard_counts
+        .iter()
+        .filter(|&&c| c > 0)
+        .map(|&c| {
+            let p = c as f64 / total as f64;
+            -p * p.log2()
+        })
+        .sum();
+    entropy / (shard_counts.len() as f64).log2()
+}
+
+/// Summary statistics of how a set of keys is spread across shards.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DistributionReport {
+    /// Number of keys on each shard, indexed by shard.
+    pub shard_counts: Vec<u64>,
+    /// Fewest keys on any shard.
+    pub min: u64,
+    /// Most keys on any shard.
+    pub max: u64,
+    /// Mean keys per shard.
+    pub mean: f64,
+    /// Population standard deviation of keys per shard.
+    pub stddev: f64,
+    /// `stddev / mean`. `0.0` is perfectly balanced.
+    pub coefficient_of_variation: f64,
+    /// Pearson's chi-square statistic against a uniform distribution, with `shard_counts.len() - 1` degrees of freedom.
+    pub chi_square: f64,
+}
+
+impl DistributionReport {
+    /// Shards every key in `keys` across `locations` and reports the result.
+    ///
+    /// # Panics
+    ///
+    /// If locations == 0.
+    pub fn measure<K: Hash>(
+        keys: impl IntoIterator<Item = K>,
+        locations: u64,
+        hasher: &impl BuildHasher,
+    ) -> Self {
+        assert_ne!(locations, 0, "total_destinations must be > 0");
+
+        let mut shard_counts = vec![0; locations as usize];
+        for key in keys {
+            shard_counts[shard_with_hasher(key, locations, hasher) as usize] += 1;
+        }
+        Self::from_counts(shard_counts)
+    }
+
+    /// Reports on already-counted keys per shard.
+    ///
+    /// # Panics
+    ///
+    /// If shard_counts is empty.
+    pub fn from_counts(shard_counts: Vec<u64>) -> Self {
+        assert!(!shard_counts.is_empty(), "shard_counts must not be empty");
+
+        let total: u64 = shard_counts.iter().sum();
+        let mean = total as f64 / shard_counts.len() as f64;
+        let sum_squares: f64 = shard_counts
+            .iter()
+            .map(|&c| (c as f64 - mean).powi(2))
+            .sum();
+        let stddev = (sum_squares / shard_counts.len() as f64).sqrt();
+
+        DistributionReport {
+            min: shard_counts.iter().copied().min().unwrap_or_default(),
+            max: shard_counts.iter().copied().max().unwrap_or_default(),
+            mean,
+            stddev,
+            coefficient_of_variation: if mean == 0.0 { 0.0 } else { stddev / mean },
+            chi_square: if mean == 0.0 { 0.0 } else { sum_squares / mean },
+            shard_counts,
+        }
+    }
+
+    /// Total number of keys measured.
+    pub fn total_keys(&self) -> u64 {
+        self.shard_counts.iter().sum()
+    }
+
+    /// See [imbalance_ratio].
+    pub fn imbalance_ratio(&self) -> f64 {
+        imbalance_ratio(&self.shard_counts)
+    }
+
+    /// See [entropy_efficiency].
+    pub fn entropy_efficiency(&self) -> f64 {
+        entropy_efficiency(&self.shard_counts)
+    }
+}
+
+/// Weights that would balance measured load. Returned by [recommend_weights].
+#[derive(Clone, Debug, PartialEq)]
+pub struct WeightRecommendation {
+    /// Recommended weight of each destination, indexed by destination. Sums to the current total weight.
+    pub weights: Vec<u64>,
+    /// Expected load of each destination after applying [WeightRecommendation::weights].
+    pub projected_loads: Vec<f64>,
+    /// Estimated fraction of keys that change destination when the weights are applied with [WeightRecommendation::apply_to].
+    ///
+    /// Measured by applying them to a copy of the sharder over [MOVEMENT_SAMPLE_KEYS] synthetic keys. This is more than the change in shares alone, because appending slots also reshuffles keys that were rehashed off tombstoned slots.
+    pub estimated_moved_fraction: f64,
+    /// How many more destinations of the same capacity are needed to bring every destination under the target utilization, even when balanced.
+    pub additional_destinations: u64,
+}
+
+impl WeightRecommendation {
+    /// Sets every recommended weight on `sharder` with [WeightedSharder::set_weight], in destination order.
+    ///
+    /// # Panics
+    ///
+    /// If `sharder` has fewer destinations than the recommendation.
+    pub fn apply_to<S: BuildHasher>(&self, sharder: &mut WeightedSharder<S>) {
+        for (destination, &weight) in self.weights.iter().enumerate() {
+            sharder.set_weight(destination as u64, weight);
+        }
+    }
+}
+
+/// Number of synthetic keys [recommend_weights] measures movement over.
+pub const MOVEMENT_SAMPLE_KEYS: u64 = 10_000;
+
+/// Recommends weights for `sharder` that spread `loads` evenly across its destinations.
+///
+/// `loads[i]` is the measured load of destination `i` in any unit, such as bytes or QPS, for example from [crate::ShardLoadEstimator]. Destinations are assumed to have the same capacity, and a destination's load to scale with its share of keys, so weights are set inversely to load per unit of weight. Destinations with no load or no weight keep their weight.
+///
+/// `capacity` is the load one destination can take, in the same unit, and `target_utilization` the fraction of it to plan for. If the total load needs more destinations than there are at that utilization, [WeightRecommendation::additional_destinations] says how many to add.
+///
+/// The total weight is kept, because scaling every weight moves keys without changing shares. Small totals limit how precisely the load can be balanced, so give destinations weights of 100 or so from the start if they may need tuning.
+///
+/// ```
+/// # use faro_sharding::WeightedSharder;
+/// # use faro_sharding::stats::recommend_weights;
+/// # use std::hash::BuildHasherDefault;
+/// # use std::collections::hash_map::DefaultHasher;
+/// let hasher = BuildHasherDefault::<DefaultHasher>::default();
+/// let mut sharder = WeightedSharder::new(&[100, 100, 100], hasher);
+///
+/// // Destination 1 is twice as hot as the others.
+/// let recommendation = recommend_weights(&sharder, &[1_000, 2_000, 1_000], 2_000, 0.8);
+/// assert_eq!(recommendation.weights, vec![120, 60, 120]);
+/// assert_eq!(recommendation.additional_destinations, 0);
+///
+/// recommendation.apply_to(&mut sharder);
+/// ```
+///
+/// # Panics
+///
+/// If `loads` does not have one entry per destination, capacity == 0, or target_utilization is not in `(0, 1]`.
+pub fn recommend_weights<S: BuildHasher + Clone>(
+    sharder: &WeightedSharder<S>,
+    loads: &[u64],
+    capacity: u64,
+    target_utilization: f64,
+) -> WeightRecommendation {
+    let weights = sharder.weights();
+    assert_eq!(
+        weights.len(),
+        loads.len(),
+        "loads must have one entry per destination"
+    );
+    assert_ne!(capacity, 0, "capacity must be > 0");
+    assert!(
+        target_utilization > 0.0 && target_utilization <= 1.0,
+        "target_utilization must be in (0, 1]"
+    );
+    let total_weight: u64 = weights.iter().sum();
+
+    let adjustable = |i: usize| weights[i] > 0 && loads[i] > 0;
+    let fixed_weight: u64 = (0..weights.len())
+        .filter(|&i| !adjustable(i))
+        .map(|i| weights[i])
+        .sum();
+    let inverse_density = |i: usize| weights[i] as f64 / loads[i] as f64;
+    let inverse_total: f64 = (0..weights.len())
+        .filter(|&i| adjustable(i))
+        .map(inverse_density)
+        .sum();
+
+    let ideal = (0..weights.len())
+        .map(|i| {
+            if adjustable(i) {
+                (total_weight - fixed_weight) as f64 * inverse_density(i) / inverse_total
+            } else {
+                weights[i] as f64
+            }
+        })
+        .collect::<Vec<_>>();
+    let recommended = round_preserving_sum(&ideal, total_weight, weights);
+
+    let projected_loads = (0..weights.len())
+        .map(|i| {
+            if weights[i] == 0 {
+                loads[i] as f64
+            } else {
+                loads[i] as f64 * recommended[i] as f64 / weights[i] as f64
+            }
+        })
+        .collect::<Vec<_>>();
+    let live = weights.iter().filter(|&&w| w > 0).count() as u64;
+    let total_load: u64 = loads.iter().sum();
+    let needed = (total_load as f64 / (capacity as f64 * target_utilization)).ceil() as u64;
+
+    let mut recommendation = WeightRecommendation {
+        weights: recommended,
+        projected_loads,
+        estimated_moved_fraction: 0.0,
+        additional_destinations: needed.saturating_sub(live),
+    };
+
+    let mut applied = sharder.clone();
+    recommendation.apply_to(&mut applied);
+    let moved = (0..MOVEMENT_SAMPLE_KEYS)
+        .filter(|&key| sharder.shard_for(key) != applied.shard_for(key))
+        .count();
+    recommendation.estimated_moved_fraction = moved as f64 / MOVEMENT_SAMPLE_KEYS as f64;
+    recommendation
+}
+
+/// A destination count for a cluster. Returned by [recommend_destinations].
+#[derive(Clone, Debug, PartialEq)]
+pub struct DestinationRecommendation {
+    /// Recommended number of destinations.
+    pub destinations: u64,
+    /// Mean keys per destination at that count, for the planned key count without headroom.
+    pub keys_per_destination: f64,
+    /// Expected keys on the fullest destination, from [DestinationRecommendation::projection]'s imbalance.
+    ///
+    /// When the projection is over fewer keys per destination than planned, relative imbalance is larger than at full scale, so this errs high. When it is over fewer destinations, the fullest of the real destinations is somewhat fuller than projected.
+    pub projected_max_keys: f64,
+    /// How synthetic keys spread over `min(destinations, PROJECTION_MAX_DESTINATIONS)` destinations, at the planned keys per destination, up to [PROJECTION_SAMPLE_KEYS] keys in all.
+    pub projection: DistributionReport,
+}
+
+/// Most synthetic keys [recommend_destinations] shards for its projection.
+pub const PROJECTION_SAMPLE_KEYS: u64 = 1_000_000;
+
+/// Most destinations [recommend_destinations] shards across for its projection. Larger clusters are projected from this many destinations, since the cost of sharding grows with the destination count.
+pub const PROJECTION_MAX_DESTINATIONS: u64 = 64;
+
+/// Recommends how many destinations hold `total_keys` at about `target_keys_per_destination` each, leaving `headroom` for growth.
+///
+/// `headroom` is the fraction of growth to plan for, so `0.25` sizes for `1.25 * total_keys`. The recommendation comes with a projection of how evenly keys spread at that count.
+///
+/// ```
+/// # use faro_sharding::stats::recommend_destinations;
+/// # use std::hash::BuildHasherDefault;
+/// # use std::collections::hash_map::DefaultHasher;
+/// let hasher = BuildHasherDefault::<DefaultHasher>::default();
+/// let recommendation = recommend_destinations(400_000, 10_000, 0.25, &hasher);
+/// assert_eq!(recommendation.destinations, 50);
+/// assert!(recommendation.projected_max_keys < 1.1 * recommendation.keys_per_destination);
+/// ```
+///
+/// # Panics
+///
+/// If target_keys_per_destination == 0, or headroom is negative or not finite.
+pub fn recommend_destinations(
+    total_keys: u64,
+    target_keys_per_destination: u64,
+    headroom: f64,
+    hasher: &impl BuildHasher,
+) -> DestinationRecommendation {
+    assert_ne!(
+        target_keys_per_destination, 0,
+        "target_keys_per_destination must be > 0"
+    );
+    assert!(
+        headroom.is_finite() && headroom >= 0.0,
+        "headroom must be >= 0"
+    );
+
+    let planned = total_keys as f64 * (1.0 + headroom);
+    let destinations = ((planned / target_keys_per_destination as f64).ceil() as u64).max(1);
+    let keys_per_destination = total_keys as f64 / destinations as f64;
+
+    let measured = destinations.min(PROJECTION_MAX_DESTINATIONS);
+    let sample_keys =
+        ((keys_per_destination * measured as f64).round() as u64).min(PROJECTION_SAMPLE_KEYS);
+    let projection = DistributionReport::measure(0..sample_keys, measured, hasher);
+    DestinationRecommendation {
+        destinations,
+        keys_per_destination,
+        projected_max_keys: projection.imbalance_ratio() * keys_per_destination,
+        projection,
+    }
+}
+
+/// Rounds `ideal` to integers summing to `total`, by largest remainder. Destinations that had weight keep at least 1.
+fn round_preserving_sum(ideal: &[f64], total: u64, current: &[u64]) -> Vec<u64> {
+    let mut rounded = ideal
+        .iter()
+        .zip(current)
+        .map(|(&x, &c)| if c > 0 { (x.floor() as u64).max(1) } else { 0 })
+        .collect::<Vec<_>>();
+
+    let mut by_remainder = (0..ideal.len())
+        .filter(|&i| current[i] > 0)
+        .collect::<Vec<_>>();
+    by_remainder.sort_by(|&a, &b| {
+        let remainder = |i: usize| ideal[i] - rounded[i] as f64;
+        remainder(b).total_cmp(&remainder(a)).then(a.cmp(&b))
+    });
+
+    let mut sum: u64 = rounded.iter().sum();
+    for &i in by_remainder.iter().cycle().take(by_remainder.len() * 2) {
+        if sum == total {
+            break;
+        }
+        if sum < total {
+            rounded[i] += 1;
+            sum += 1;
+        } else if rounded[i] > 1 {
+            rounded[i] -= 1;
+            sum -= 1;
+        }
+    }
+    rounded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BuildSeaHasher;
+
+    #[test]
+    fn even_distribution_is_ideal() {
+        assert_eq!(imbalance_ratio(&[5, 5, 5, 5]), 1.0);
+        assert_eq!(entropy_efficiency(&[5, 5, 5, 5]), 1.0);
+    }
+
+    #[test]
+    fn single_full_shard_is_worst() {
+        assert_eq!(imbalance_ratio(&[0, 0, 0, 8]), 4.0);
+        assert_eq!(entropy_efficiency(&[0, 0, 0, 8]), 0.0);
+    }
+
+    #[test]
+    fn report_on_known_counts() {
+        let report = DistributionReport::from_counts(vec![2, 4, 4, 4, 5, 5, 7, 9]);
+        assert_eq!(report.total_keys(), 40);
+        assert_eq!(report.min, 2);
+        assert_eq!(report.max, 9);
+        assert_eq!(report.mean, 5.0);
+        assert_eq!(report.stddev, 2.0);
+        assert_eq!(report.coefficient_of_variation, 0.4);
+        assert_eq!(report.chi_square, 6.4);
+    }
+
+    #[test]
+    fn faro_is_balanced() {
+        let report = DistributionReport::measure(0..100_000u64, 100, &BuildSeaHasher);
+        assert_eq!(report.total_keys(), 100_000);
+        assert!(report.coefficient_of_variation < 0.05, "{:?}", report);
+        // 99 degrees of freedom, p = 0.0001.
+        assert!(report.chi_square < 160.0, "{:?}", report);
+    }
+
+    #[test]
+    fn small_counts_are_at_the_sampling_floor() {
+        // Each shard gets exactly 1/N of keys in expectation, so the remaining spread is the
+        // multinomial noise any per-key function has. See "Distribution" in the crate docs.
+        for locations in 8..=16 {
+            let report = DistributionReport::measure(0..100_000u64, locations, &BuildSeaHasher);
+            // At most 15 degrees of freedom, p = 0.0005.
+            assert!(report.chi_square < 40.0, "{:?}", report);
+        }
+    }
+
+    #[test]
+    fn recommends_inverse_to_load() {
+        let sharder = WeightedSharder::new(&[100, 100, 100], BuildSeaHasher);
+        let recommendation = recommend_weights(&sharder, &[1_000, 2_000, 1_000], 2_000, 0.8);
+        assert_eq!(recommendation.weights, vec![120, 60, 120]);
+        assert_eq!(recommendation.projected_loads, vec![1_200., 1_200., 1_200.]);
+        assert_eq!(recommendation.additional_destinations, 0);
+        assert!(recommendation.estimated_moved_fraction > 0.0);
+    }
+
+    #[test]
+    fn balanced_load_keeps_weights() {
+        let sharder = WeightedSharder::new(&[3, 1, 0, 2], BuildSeaHasher);
+        let recommendation = recommend_weights(&sharder, &[20, 20, 0, 20], 100, 1.0);
+        assert_eq!(recommendation.weights, vec![3, 1, 0, 2]);
+        assert_eq!(recommendation.estimated_moved_fraction, 0.0);
+    }
+
+    #[test]
+    fn recommends_more_destinations_when_overloaded() {
+        let sharder = WeightedSharder::new(&[10, 10], BuildSeaHasher);
+        let recommendation = recommend_weights(&sharder, &[900, 900], 1_000, 0.6);
+        // 1800 load at 600 per destination.
+        assert_eq!(recommendation.additional_destinations, 1);
+    }
+
+    #[test]
+    fn recommends_destinations_with_headroom() {
+        let recommendation = recommend_destinations(10_000, 1_000, 0.0, &BuildSeaHasher);
+        assert_eq!(recommendation.destinations, 10);
+        assert_eq!(recommendation.keys_per_destination, 1_000.0);
+        assert_eq!(recommendation.projection.total_keys(), 10_000);
+        assert_eq!(
+            recommendation.projected_max_keys,
+            recommendation.projection.max as f64
+        );
+
+        let recommendation = recommend_destinations(10_001, 1_000, 0.5, &BuildSeaHasher);
+        assert_eq!(recommendation.destinations, 16);
+
+        let recommendation = recommend_destinations(0, 1_000, 0.5, &BuildSeaHasher);
+        assert_eq!(recommendation.destinations, 1);
+        assert_eq!(recommendation.projected_max_keys, 0.0);
+    }
+
+    #[test]
+    fn projects_huge_clusters_from_a_sample() {
+        let recommendation = recommend_destinations(10_000_000_000, 1, 0.0, &BuildSeaHasher);
+        assert_eq!(recommendation.destinations, 10_000_000_000);
+        assert_eq!(
+            recommendation.projection.shard_counts.len() as u64,
+            PROJECTION_MAX_DESTINATIONS
+        );
+        assert_eq!(
+            recommendation.projection.total_keys(),
+            PROJECTION_MAX_DESTINATIONS
+        );
+
+        let recommendation = recommend_destinations(100_000_000, 1_000, 0.0, &BuildSeaHasher);
+        assert_eq!(recommendation.projection.total_keys(), 64_000);
+    }
+
+    #[test]
+    fn estimated_movement_matches_applying() {
+        let mut sharder = WeightedSharder::new(&[100, 100, 100, 100], BuildSeaHasher);
+        let before = (0..100_000u64)
+            .map(|k| format!("key-{}", k))
+            .map(|k| (sharder.shard_for(&k), k))
+            .collect::<Vec<_>>();
+
+        let recommendation = recommend_weights(&sharder, &[10, 30, 10, 10], 100, 1.0);
+        recommendation.apply_to(&mut sharder);
+        assert_eq!(sharder.weights(), &recommendation.weights[..]);
+
+        let moved = before
+            .iter()
+            .filter(|(shard, key)| sharder.shard_for(key) != *shard)
+            .count();
+        let expected = 100_000. * recommendation.estimated_moved_fraction;
+        assert!(
+            (moved as f64 / expected - 1.).abs() < 0.05,
+            "{} vs {}",
+            moved,
+            expected
+        );
+    }
+}
@@ -0,0 +1,275 @@
+use crate::{shard_with_hasher, shards_between};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::hash::*;
+
+/// A key-value store whose entries are partitioned across backends by Faro Sharding.
+///
+/// Reads take `&mut self` so that implementations can repair entries found on the wrong backend while the backend count is changing.
+pub trait ShardedStore<K, V> {
+    /// Error from a backend.
+    type Error;
+
+    /// Returns the value for `key`.
+    fn get(&mut self, key: &K) -> Result<Option<V>, Self::Error>;
+
+    /// Sets the value for `key`, returning the previous value if there was one.
+    fn put(&mut self, key: K, value: V) -> Result<Option<V>, Self::Error>;
+
+    /// Removes and returns the value for `key`.
+    fn delete(&mut self, key: &K) -> Result<Option<V>, Self::Error>;
+}
+
+/// Called when a read finds an entry on the backend it belonged to before a resize, and moves it.
+///
+/// Closures taking `(key, value, source, destination)` implement this.
+pub trait RepairHook<K, V> {
+    /// `key` was moved from backend `source` to `destination`.
+    fn repaired(&mut self, key: &K, value: &V, source: u64, destination: u64);
+}
+
+impl<K, V> RepairHook<K, V> for () {
+    fn repaired(&mut self, _: &K, _: &V, _: u64, _: u64) {}
+}
+
+impl<K, V, F: FnMut(&K, &V, u64, u64)> RepairHook<K, V> for F {
+    fn repaired(&mut self, key: &K, value: &V, source: u64, destination: u64) {
+        self(key, value, source, destination)
+    }
+}
+
+/// In-memory [ShardedStore] over one `HashMap` per backend, as a reference for real backends.
+///
+/// [MemoryStore::resize] does not move anything. Until [MemoryStore::finish_migration], reads that miss fall back to the backend the key was on before the resize, and move the entry over if found. Writes and deletes clear the old backend, so stale values are never read back.
+#[derive(Clone, Debug)]
+pub struct MemoryStore<K, V, S, H = ()> {
+    backends: Vec<HashMap<K, V>>,
+    backend_count: u64,
+    previous_backend_count: Option<u64>,
+    hasher: S,
+    hook: H,
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher> MemoryStore<K, V, S> {
+    /// Creates an empty store with `backend_count` backends.
+    ///
+    /// # Panics
+    ///
+    /// If backend_count == 0.
+    pub fn new(backend_count: u64, hasher: S) -> Self {
+        assert_ne!(backend_count, 0, "backend_count must be > 0");
+
+        MemoryStore {
+            backends: (0..backend_count).map(|_| HashMap::new()).collect(),
+            backend_count,
+            previous_backend_count: None,
+            hasher,
+            hook: (),
+        }
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher, H: RepairHook<K, V>> MemoryStore<K, V, S, H> {
+    /// Calls `hook` for every entry a read repairs.
+    pub fn with_repair_hook<H2: RepairHook<K, V>>(self, hook: H2) -> MemoryStore<K, V, S, H2> {
+        MemoryStore {
+            backends: self.backends,
+            backend_count: self.backend_count,
+            previous_backend_count: self.previous_backend_count,
+            hasher: self.hasher,
+            hook,
+        }
+    }
+
+    /// Number of backends keys are routed to.
+    pub fn backend_count(&self) -> u64 {
+        self.backend_count
+    }
+
+    /// The backend `key` belongs on.
+    pub fn shard_of(&self, key: &K) -> u64 {
+        shard_with_hasher(key, self.backend_count, &self.hasher)
+    }
+
+    /// The entries on `backend`. Empty if `backend` is out of range.
+    pub fn backend(&self, backend: u64) -> impl Iterator<Item = (&K, &V)> {
+        self.backends.get(backend as usize).into_iter().flatten()
+    }
+
+    /// Whether a resize is still being migrated.
+    pub fn is_migrating(&self) -> bool {
+        self.previous_backend_count.is_some()
+    }
+
+    /// Routes keys to `backend_count` backends, leaving entries where they are until they are read or [MemoryStore::finish_migration].
+    ///
+    /// # Panics
+    ///
+    /// If backend_count == 0, or a previous resize is still migrating.
+    pub fn resize(&mut self, backend_count: u64) {
+        assert_ne!(backend_count, 0, "backend_count must be > 0");
+        assert!(
+            !self.is_migrating(),
+            "finish the current migration before resizing again"
+        );
+
+        if backend_count > self.backends.len() as u64 {
+            self.backends
+                .resize_with(backend_count as usize, HashMap::new);
+        }
+        self.previous_backend_count = Some(self.backend_count);
+        self.backend_count = backend_count;
+    }
+
+    /// Moves every entry not yet on its backend and drops backends that are no longer used.
+    pub fn finish_migration(&mut self) {
+        if self.previous_backend_count.take().is_none() {
+            return;
+        }
+
+        for old in 0..self.backends.len() {
+            let moving = self.backends[old]
+                .extract_if(|key, _| {
+                    shard_with_hasher(key, self.backend_count, &self.hasher) != old as u64
+                })
+                .collect::<Vec<_>>();
+
+            for (key, value) in moving {
+                let backend = shard_with_hasher(&key, self.backend_count, &self.hasher);
+                self.backends[backend as usize].insert(key, value);
+            }
+        }
+        self.backends.truncate(self.backend_count as usize);
+    }
+
+    /// The backend `key` is on now, and the backend it was on before the migrating resize if different.
+    fn locate(&self, key: &K) -> (u64, Option<u64>) {
+        match self.previous_backend_count {
+            None => (self.shard_of(key), None),
+            Some(previous) => {
+                let (old, new) = shards_between(key, previous, self.backend_count, &self.hasher);
+                (new, Some(old).filter(|&old| old != new))
+            }
+        }
+    }
+}
+
+impl<K, V, S, H> ShardedStore<K, V> for MemoryStore<K, V, S, H>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher,
+    H: RepairHook<K, V>,
+{
+    type Error = Infallible;
+
+    fn get(&mut self, key: &K) -> Result<Option<V>, Infallible> {
+        let (backend, old) = self.locate(key);
+        if let Some(value) = self.backends[backend as usize].get(key) {
+            return Ok(Some(value.clone()));
+        }
+
+        let Some(old) = old else {
+            return Ok(None);
+        };
+        let Some((key, value)) = self.backends[old as usize].remove_entry(key) else {
+            return Ok(None);
+        };
+        self.hook.repaired(&key, &value, old, backend);
+        self.backends[backend as usize].insert(key, value.clone());
+        Ok(Some(value))
+    }
+
+    fn put(&mut self, key: K, value: V) -> Result<Option<V>, Infallible> {
+        let (backend, old) = self.locate(&key);
+        let stale = old.and_then(|old| self.backends[old as usize].remove(&key));
+        let previous = self.backends[backend as usize].insert(key, value);
+        Ok(previous.or(stale))
+    }
+
+    fn delete(&mut self, key: &K) -> Result<Option<V>, Infallible> {
+        let (backend, old) = self.locate(key);
+        let stale = old.and_then(|old| self.backends[old as usize].remove(key));
+        Ok(self.backends[backend as usize].remove(key).or(stale))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BuildSeaHasher;
+
+    fn filled(backend_count: u64) -> MemoryStore<u64, u64, BuildSeaHasher> {
+        let mut store = MemoryStore::new(backend_count, BuildSeaHasher);
+        for key in 0..1_000 {
+            store.put(key, key * 2).unwrap();
+        }
+        store
+    }
+
+    #[test]
+    fn routes_by_shard() {
+        let mut store = filled(5);
+        for key in 0..1_000u64 {
+            assert!(store.backend(store.shard_of(&key)).any(|(k, _)| *k == key));
+            assert_eq!(store.get(&key).unwrap(), Some(key * 2));
+        }
+
+        assert_eq!(store.put(3, 0).unwrap(), Some(6));
+        assert_eq!(store.delete(&3).unwrap(), Some(0));
+        assert_eq!(store.get(&3).unwrap(), None);
+    }
+
+    #[test]
+    fn reads_repair_during_growth() {
+        let mut repaired = Vec::new();
+        let mut store = filled(4).with_repair_hook(|key: &u64, _: &u64, source, destination| {
+            repaired.push((*key, source, destination))
+        });
+        store.resize(6);
+
+        for key in 0..1_000u64 {
+            assert_eq!(store.get(&key).unwrap(), Some(key * 2));
+            assert!(store.backend(store.shard_of(&key)).any(|(k, _)| *k == key));
+        }
+        drop(store);
+
+        let expected = (0..1_000u64)
+            .filter_map(|key| {
+                let (old, new) = shards_between(key, 4, 6, &BuildSeaHasher);
+                (old != new).then_some((key, old, new))
+            })
+            .collect::<Vec<_>>();
+        assert!(!expected.is_empty());
+        assert_eq!(repaired, expected);
+    }
+
+    #[test]
+    fn writes_during_migration_shadow_the_old_backend() {
+        let mut store = filled(6);
+        store.resize(4);
+        let moving = (0..1_000u64)
+            .filter(|key| shards_between(key, 6, 4, &BuildSeaHasher).0 >= 4)
+            .collect::<Vec<_>>();
+        let (put, deleted) = (moving[0], moving[1]);
+
+        assert_eq!(store.put(put, 0).unwrap(), Some(put * 2));
+        assert_eq!(store.delete(&deleted).unwrap(), Some(deleted * 2));
+        store.finish_migration();
+
+        assert!(!store.is_migrating());
+        assert_eq!(store.get(&put).unwrap(), Some(0));
+        assert_eq!(store.get(&deleted).unwrap(), None);
+        assert_eq!(store.backend(4).count(), 0);
+        let total = (0..4).map(|b| store.backend(b).count()).sum::<usize>();
+        assert_eq!(total, 999);
+    }
+
+    #[test]
+    #[should_panic(expected = "finish the current migration")]
+    fn resize_while_migrating_panics() {
+        let mut store = filled(2);
+        store.resize(3);
+        store.resize(4);
+    }
+}
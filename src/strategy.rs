@@ -0,0 +1,20 @@
+use crate::shard_for_key_hash;
+use core::hash::*;
+
+/// An object-safe sharding algorithm operating on pre-hashed keys.
+pub trait ShardingStrategy {
+    /// Returns the shard in `0..total_destinations` for a key whose hash is `key_hash`.
+    fn shard_for_hash(&self, key_hash: u64, total_destinations: u64) -> u64;
+}
+
+/// [ShardingStrategy] implementing Faro Sharding with the wrapped hasher.
+///
+/// Given `key_hash = hasher.hash_one(key)`, this agrees with [crate::shard_with_hasher].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FaroStrategy<S>(pub S);
+
+impl<S: BuildHasher> ShardingStrategy for FaroStrategy<S> {
+    fn shard_for_hash(&self, key_hash: u64, total_destinations: u64) -> u64 {
+        shard_for_key_hash(key_hash, total_destinations, &self.0)
+    }
+}
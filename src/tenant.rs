@@ -0,0 +1,165 @@
+use crate::shard_with_hasher;
+use std::collections::HashMap;
+use std::hash::*;
+
+/// Shards keys within per-tenant keyspaces over shared destinations.
+///
+/// The tenant id is hashed together with the key, as in [crate::shard_with_hasher_seeded], so each tenant's keys are spread independently of every other tenant's. A tenant can be restricted to a subset of destinations. Its keys are then sharded across that subset in the order given, so allowing it one more destination only moves its keys onto that destination.
+///
+/// ```
+/// # use faro_sharding::TenantSharder;
+/// # use std::hash::BuildHasherDefault;
+/// # use std::collections::hash_map::DefaultHasher;
+/// let mut sharder = TenantSharder::new(64, BuildHasherDefault::<DefaultHasher>::default());
+/// sharder.restrict("tenant-a", 0..16);
+///
+/// assert!(sharder.shard_for(&"tenant-a", "user:42") < 16);
+/// assert!(sharder.shard_for(&"tenant-b", "user:42") < 64);
+/// ```
+#[derive(Clone, Debug)]
+pub struct TenantSharder<T, S> {
+    total_destinations: u64,
+    subsets: HashMap<T, Vec<u64>>,
+    hasher: S,
+}
+
+impl<T: Hash + Eq, S: BuildHasher> TenantSharder<T, S> {
+    /// Creates a sharder over `total_destinations`, with every tenant allowed on all of them.
+    ///
+    /// # Panics
+    ///
+    /// If total_destinations == 0.
+    pub fn new(total_destinations: u64, hasher: S) -> Self {
+        assert_ne!(total_destinations, 0, "total_destinations must be > 0");
+
+        TenantSharder {
+            total_destinations,
+            subsets: HashMap::new(),
+            hasher,
+        }
+    }
+
+    /// Number of shared destinations.
+    pub fn total_destinations(&self) -> u64 {
+        self.total_destinations
+    }
+
+    /// Changes the number of shared destinations. Unrestricted tenants' keys move as with [crate::shard_with_hasher].
+    ///
+    /// # Panics
+    ///
+    /// If total_destinations == 0, or some tenant is allowed on a destination that would no longer exist.
+    pub fn set_total_destinations(&mut self, total_destinations: u64) {
+        assert_ne!(total_destinations, 0, "total_destinations must be > 0");
+        assert!(
+            self.subsets
+                .values()
+                .flatten()
+                .all(|&shard| shard < total_destinations),
+            "a tenant is allowed on a destination >= {}",
+            total_destinations
+        );
+
+        self.total_destinations = total_destinations;
+    }
+
+    /// Allows `tenant` only on `shards`, in the order given. Duplicates are ignored.
+    ///
+    /// Keeping the existing shards as a prefix, and appending new ones, only moves the tenant's keys onto the new shards.
+    ///
+    /// # Panics
+    ///
+    /// If shards is empty or any shard is out of range.
+    pub fn restrict(&mut self, tenant: T, shards: impl IntoIterator<Item = u64>) {
+        let mut subset = Vec::new();
+        for shard in shards {
+            assert!(
+                shard < self.total_destinations,
+                "shard {} is out of range",
+                shard
+            );
+            if !subset.contains(&shard) {
+                subset.push(shard);
+            }
+        }
+        assert!(!subset.is_empty(), "shards must not be empty");
+
+        self.subsets.insert(tenant, subset);
+    }
+
+    /// Allows `tenant` on every destination again, returning its previous subset.
+    pub fn unrestrict(&mut self, tenant: &T) -> Option<Vec<u64>> {
+        self.subsets.remove(tenant)
+    }
+
+    /// The destinations `tenant` is restricted to, or `None` if it may use all of them.
+    pub fn allowed(&self, tenant: &T) -> Option<&[u64]> {
+        self.subsets.get(tenant).map(Vec::as_slice)
+    }
+
+    /// Returns the destination for `key` within `tenant`'s keyspace.
+    pub fn shard_for(&self, tenant: &T, key: impl Hash) -> u64 {
+        let scoped = (tenant, key);
+        match self.subsets.get(tenant) {
+            Some(subset) => {
+                subset[shard_with_hasher(scoped, subset.len() as u64, &self.hasher) as usize]
+            }
+            None => shard_with_hasher(scoped, self.total_destinations, &self.hasher),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BuildSeaHasher;
+
+    #[test]
+    fn tenants_are_independent() {
+        let sharder = TenantSharder::new(10, BuildSeaHasher);
+        let same = (0..10_000u64)
+            .filter(|&key| sharder.shard_for(&1u64, key) == sharder.shard_for(&2u64, key))
+            .count();
+        // About 1 in 10 by chance.
+        assert!((800..1_200).contains(&same), "{}", same);
+
+        let mut counts = [0u64; 10];
+        for key in 0..10_000u64 {
+            counts[sharder.shard_for(&1u64, key) as usize] += 1;
+        }
+        assert!(crate::stats::imbalance_ratio(&counts) < 1.1, "{:?}", counts);
+    }
+
+    #[test]
+    fn restricted_tenants_stay_in_their_subset() {
+        let mut sharder = TenantSharder::new(64, BuildSeaHasher);
+        sharder.restrict("a", 0..16);
+        assert_eq!(
+            sharder.allowed(&"a"),
+            Some(&(0..16).collect::<Vec<_>>()[..])
+        );
+        assert_eq!(sharder.allowed(&"b"), None);
+
+        let before = (0..1_000u64)
+            .map(|key| sharder.shard_for(&"a", key))
+            .collect::<Vec<_>>();
+        assert!(before.iter().all(|&shard| shard < 16));
+
+        sharder.restrict("a", (0..16).chain([40]));
+        for (key, before) in (0..1_000u64).zip(before) {
+            let after = sharder.shard_for(&"a", key);
+            assert!(after == before || after == 40);
+        }
+
+        sharder.unrestrict(&"a");
+        assert!((0..1_000u64).any(|key| sharder.shard_for(&"a", key) >= 16));
+    }
+
+    #[test]
+    #[should_panic(expected = "a tenant is allowed on a destination >= 8")]
+    fn cannot_shrink_below_a_subset() {
+        let mut sharder = TenantSharder::new(16, BuildSeaHasher);
+        sharder.restrict("a", [3, 12]);
+        sharder.set_total_destinations(8);
+    }
+}
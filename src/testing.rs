@@ -0,0 +1,171 @@
+//! [proptest](mod@proptest) strategies and properties for testing code built on this crate.
+//!
+//! A crate that wraps sharding can check that its wrapper keeps Faro Sharding's guarantees in a few lines:
+//!
+//! ```
+//! # use faro_sharding::{shard_for, testing};
+//! # use proptest::prelude::*;
+//! proptest!(|(key in testing::keys(), n in testing::destination_counts())| {
+//!     // Replace shard_for with the wrapper under test.
+//!     testing::check_stability(&key, n, |key, n| shard_for(key, n))?;
+//! });
+//! ```
+//!
+//! Event sequences from [events] are always valid for [crate::simulate::Simulation::run].
+
+use crate::simulate::Event;
+use crate::stats::imbalance_ratio;
+use proptest::prelude::*;
+
+/// Arbitrary byte keys, from empty up to 64 bytes.
+///
+/// `Vec<u8>` implements [Hash](std::hash::Hash), [crate::StableKey], and [crate::ShardKey], so these keys work with every sharding function.
+pub fn keys() -> impl Strategy<Value = Vec<u8>> {
+    proptest::collection::vec(any::<u8>(), 0..=64)
+}
+
+/// Destination counts up to 1024, weighted towards the small counts where off-by-one mistakes show up.
+///
+/// [crate::shard_for] takes time linear in the destination count, so larger counts would slow every case down.
+pub fn destination_counts() -> impl Strategy<Value = u64> {
+    prop_oneof![
+        3 => 1..=16u64,
+        1 => 17..=1024u64,
+    ]
+}
+
+/// Sequences of up to `max_events` cluster changes for a cluster of `initial_nodes`.
+///
+/// Drained and removed nodes exist, and at least one node is always left to write to.
+///
+/// # Panics
+///
+/// If initial_nodes == 0.
+pub fn events(initial_nodes: u64, max_events: usize) -> impl Strategy<Value = Vec<Event>> {
+    assert_ne!(initial_nodes, 0, "initial_nodes must be > 0");
+
+    proptest::collection::vec((0..3u8, any::<u64>()), 0..=max_events).prop_map(move |raw| {
+        let mut nodes = initial_nodes;
+        let mut writable = vec![true; initial_nodes as usize];
+        raw.into_iter()
+            .map(|(kind, n)| {
+                let node = n % nodes;
+                let is_last_writable =
+                    writable[node as usize] && writable.iter().filter(|&&w| w).count() == 1;
+                if kind == 0 || is_last_writable {
+                    nodes += 1 + n % 4;
+                    writable.resize(nodes as usize, true);
+                    return Event::GrowTo(nodes);
+                }
+
+                writable[node as usize] = false;
+                if kind == 1 {
+                    Event::Drain(node)
+                } else {
+                    Event::Remove(node)
+                }
+            })
+            .collect()
+    })
+}
+
+/// Checks that `shard` puts `key` in `0..total_destinations`, and that adding a destination leaves it in place or moves it to the new destination.
+///
+/// `shard` is the function under test, called with `key` and a destination count.
+pub fn check_stability<K: ?Sized>(
+    key: &K,
+    total_destinations: u64,
+    shard: impl Fn(&K, u64) -> u64,
+) -> Result<(), TestCaseError> {
+    let before = shard(key, total_destinations);
+    prop_assert!(
+        before < total_destinations,
+        "sharded to {} of {} destinations",
+        before,
+        total_destinations
+    );
+
+    let after = shard(key, total_destinations + 1);
+    prop_assert!(
+        after == before || after == total_destinations,
+        "moved from shard {} to {} going from {} to {} destinations",
+        before,
+        after,
+        total_destinations,
+        total_destinations + 1
+    );
+    Ok(())
+}
+
+/// Checks that `shard` spreads `keys` across `total_destinations` with an [imbalance_ratio] of at most `max_imbalance`.
+///
+/// Use enough keys for the bound to hold by chance: with `k` keys per destination, shards typically vary by a few multiples of `sqrt(k)`.
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub fn check_distribution<K>(
+    keys: impl IntoIterator<Item = K>,
+    total_destinations: u64,
+    max_imbalance: f64,
+    shard: impl Fn(&K, u64) -> u64,
+) -> Result<(), TestCaseError> {
+    assert_ne!(total_destinations, 0, "total_destinations must be > 0");
+
+    let mut counts = vec![0; total_destinations as usize];
+    for key in keys {
+        let s = shard(&key, total_destinations);
+        prop_assert!(
+            s < total_destinations,
+            "sharded to {} of {} destinations",
+            s,
+            total_destinations
+        );
+        counts[s as usize] += 1;
+    }
+
+    let imbalance = imbalance_ratio(&counts);
+    prop_assert!(
+        imbalance <= max_imbalance,
+        "imbalance {:.3} exceeds {:.3} across {} destinations",
+        imbalance,
+        max_imbalance,
+        total_destinations
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulate::Simulation;
+    use crate::{shard_for, BuildSeaHasher};
+
+    proptest! {
+        #[test]
+        fn shard_for_is_stable(key in keys(), n in destination_counts()) {
+            check_stability(&key, n, |key, n| shard_for(key, n))?;
+        }
+
+        #[test]
+        fn events_are_valid(
+            (initial, events) in (1..=8u64).prop_flat_map(|n| (Just(n), events(n, 20)))
+        ) {
+            let mut simulation = Simulation::new(initial, 0..100u64, BuildSeaHasher);
+            simulation.run(&events);
+        }
+    }
+
+    #[test]
+    fn shard_for_is_balanced() {
+        for n in 1..=16u64 {
+            check_distribution(0..10_000 * n, n, 1.05, |key, n| shard_for(key, n)).unwrap();
+        }
+    }
+
+    #[test]
+    fn catches_unstable_sharding() {
+        assert!(check_stability(&7u64, 4, |_, n| n % 2).is_err());
+        assert!(check_distribution(0..100u64, 4, 1.5, |_, _| 0).is_err());
+    }
+}
@@ -0,0 +1,139 @@
+use crate::shard_with_hasher;
+use std::hash::*;
+
+/// Shards time-series data by time window first, then by key with Faro Sharding within the window.
+///
+/// Node count changes take effect from an explicit cutover bucket, so buckets before it keep their layout and never move. Only buckets from the cutover onwards use the new count.
+///
+/// Timestamps are any `u64` unit, such as Unix seconds, and bucket `b` covers `[b * window, (b + 1) * window)`.
+#[derive(Clone, Debug)]
+pub struct TimeBucketSharder<S> {
+    window: u64,
+    cutovers: Vec<(u64, u64)>,
+    hasher: S,
+}
+
+impl<S: BuildHasher> TimeBucketSharder<S> {
+    /// Shards windows of `window` time units across `node_count` nodes, from bucket 0.
+    ///
+    /// # Panics
+    ///
+    /// If window == 0 or node_count == 0.
+    pub fn new(window: u64, node_count: u64, hasher: S) -> Self {
+        assert_ne!(window, 0, "window must be > 0");
+        assert_ne!(node_count, 0, "node_count must be > 0");
+
+        TimeBucketSharder {
+            window,
+            cutovers: vec![(0, node_count)],
+            hasher,
+        }
+    }
+
+    /// Length of each bucket, in time units.
+    pub fn window(&self) -> u64 {
+        self.window
+    }
+
+    /// The bucket `timestamp` is in.
+    pub fn bucket_of(&self, timestamp: u64) -> u64 {
+        timestamp / self.window
+    }
+
+    /// The first timestamp in `bucket`.
+    pub fn bucket_start(&self, bucket: u64) -> u64 {
+        bucket.saturating_mul(self.window)
+    }
+
+    /// Node count used for `bucket`.
+    pub fn node_count_at(&self, bucket: u64) -> u64 {
+        let index = self.cutovers.partition_point(|&(first, _)| first <= bucket);
+        self.cutovers[index - 1].1
+    }
+
+    /// Every `(first_bucket, node_count)`, in bucket order.
+    pub fn cutovers(&self) -> &[(u64, u64)] {
+        &self.cutovers
+    }
+
+    /// Uses `node_count` nodes for `first_bucket` and every later bucket.
+    ///
+    /// To leave every bucket already written untouched, pass a bucket after the current one, such as `bucket_of(now) + 1`.
+    ///
+    /// # Panics
+    ///
+    /// If node_count == 0, or first_bucket is before the latest cutover.
+    pub fn set_node_count_from(&mut self, first_bucket: u64, node_count: u64) {
+        assert_ne!(node_count, 0, "node_count must be > 0");
+        let &(latest, _) = self.cutovers.last().unwrap();
+        assert!(
+            first_bucket >= latest,
+            "cutover at bucket {} is before the latest cutover at {}",
+            first_bucket,
+            latest
+        );
+
+        if first_bucket == latest {
+            self.cutovers.pop();
+        }
+        if self.cutovers.last().map(|&(_, count)| count) != Some(node_count) {
+            self.cutovers.push((first_bucket, node_count));
+        }
+    }
+
+    /// The shard for `key` within `bucket`.
+    pub fn shard_in_bucket(&self, key: impl Hash, bucket: u64) -> u64 {
+        shard_with_hasher(key, self.node_count_at(bucket), &self.hasher)
+    }
+
+    /// Returns `(bucket, shard)` for `key` written at `timestamp`.
+    pub fn shard_for(&self, key: impl Hash, timestamp: u64) -> (u64, u64) {
+        let bucket = self.bucket_of(timestamp);
+        (bucket, self.shard_in_bucket(key, bucket))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{shard_for, BuildSeaHasher};
+
+    #[test]
+    fn only_buckets_after_the_cutover_change() {
+        let mut sharder = TimeBucketSharder::new(3_600, 4, BuildSeaHasher);
+        let now = 10 * 3_600 + 1_234;
+        assert_eq!(sharder.bucket_of(now), 10);
+
+        sharder.set_node_count_from(sharder.bucket_of(now) + 1, 6);
+        assert_eq!(sharder.cutovers(), [(0, 4), (11, 6)]);
+
+        for key in 0..500u64 {
+            assert_eq!(sharder.shard_for(key, now), (10, shard_for(key, 4)));
+            assert_eq!(sharder.shard_for(key, 0), (0, shard_for(key, 4)));
+            assert_eq!(
+                sharder.shard_for(key, sharder.bucket_start(11)),
+                (11, shard_for(key, 6))
+            );
+        }
+    }
+
+    #[test]
+    fn cutovers_replace_and_merge() {
+        let mut sharder = TimeBucketSharder::new(60, 4, BuildSeaHasher);
+        sharder.set_node_count_from(5, 6);
+        sharder.set_node_count_from(5, 8);
+        sharder.set_node_count_from(9, 8);
+        assert_eq!(sharder.cutovers(), [(0, 4), (5, 8)]);
+        assert_eq!(sharder.node_count_at(4), 4);
+        assert_eq!(sharder.node_count_at(5), 8);
+        assert_eq!(sharder.node_count_at(u64::MAX), 8);
+    }
+
+    #[test]
+    #[should_panic(expected = "before the latest cutover")]
+    fn cannot_rewrite_earlier_buckets() {
+        let mut sharder = TimeBucketSharder::new(60, 4, BuildSeaHasher);
+        sharder.set_node_count_from(5, 6);
+        sharder.set_node_count_from(4, 8);
+    }
+}
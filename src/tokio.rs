@@ -0,0 +1,587 @@
+//! Running a [MigrationPlan] on [tokio](https://docs.rs/tokio).
+//!
+//! [MigrationExecutor] calls an async `move_key(key, source, destination)` for every recorded move, with bounded concurrency and an optional rate limit. A [MigrationControl] pauses and resumes it, and reports a [Checkpoint] to resume from after a restart.
+//!
+//! [shard_reader_with_hasher] shards a key streamed from an [AsyncRead], such as a large upload.
+//!
+//! [ShardedSender] routes messages across tokio [mpsc] channels by key, like [crate::ShardedSender].
+
+use crate::bytes::READ_CHUNK;
+use crate::channel::ChannelRouter;
+use crate::migration::MigrationPlan;
+use crate::shard_for_key_hash;
+use ::tokio::io::{AsyncRead, AsyncReadExt};
+use ::tokio::sync::mpsc::{self, error::SendError, Receiver, Sender};
+use ::tokio::sync::watch;
+use ::tokio::task::JoinSet;
+use ::tokio::time::{self, Duration, MissedTickBehavior};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::future::Future;
+use std::hash::*;
+use std::io;
+use std::pin::pin;
+use std::sync::{Arc, Mutex};
+
+/// How far an executor has got, in [MigrationPlan::moves] order.
+///
+/// Moves run concurrently, so some after the checkpoint may also have finished. Resuming runs those again, so `move_key` must be idempotent.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Checkpoint {
+    /// Every move before this index has finished.
+    pub completed: u64,
+}
+
+/// Progress of a running [MigrationExecutor].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Progress {
+    /// Moves finished, including those finished before resuming from a [Checkpoint].
+    pub completed: u64,
+    /// Moves in the plan.
+    pub total: u64,
+}
+
+/// The first move that failed. See [MigrationExecutor::run].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MoveError<K, E> {
+    /// The key being moved.
+    pub key: K,
+    /// The shard it was moving from.
+    pub source: u64,
+    /// The shard it was moving to.
+    pub destination: u64,
+    /// The error `move_key` returned.
+    pub error: E,
+}
+
+impl<K: fmt::Debug, E: fmt::Display> fmt::Display for MoveError<K, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "moving {:?} from shard {} to {} failed: {}",
+            self.key, self.source, self.destination, self.error
+        )
+    }
+}
+
+impl<K: fmt::Debug, E: std::error::Error> std::error::Error for MoveError<K, E> {}
+
+type MoveTasks<K, E> = JoinSet<(u64, Result<(), MoveError<K, E>>)>;
+
+#[derive(Debug)]
+struct Finished {
+    watermark: u64,
+    beyond: BTreeSet<u64>,
+}
+
+#[derive(Debug)]
+struct Shared {
+    paused: watch::Sender<bool>,
+    finished: Mutex<Finished>,
+    total: u64,
+}
+
+impl Shared {
+    fn finish(&self, index: u64) {
+        let mut finished = self.finished.lock().unwrap();
+        finished.beyond.insert(index);
+        loop {
+            let watermark = finished.watermark;
+            if !finished.beyond.remove(&watermark) {
+                break;
+            }
+            finished.watermark += 1;
+        }
+    }
+}
+
+/// Pauses, resumes, and reports on a [MigrationExecutor], from any task.
+#[derive(Clone, Debug)]
+pub struct MigrationControl {
+    shared: Arc<Shared>,
+}
+
+impl MigrationControl {
+    /// Stops starting new moves. Moves already running finish.
+    pub fn pause(&self) {
+        self.shared.paused.send_replace(true);
+    }
+
+    /// Starts moves again after [MigrationControl::pause].
+    pub fn resume(&self) {
+        self.shared.paused.send_replace(false);
+    }
+
+    /// Whether the executor is paused.
+    pub fn is_paused(&self) -> bool {
+        *self.shared.paused.borrow()
+    }
+
+    /// How many moves have finished.
+    pub fn progress(&self) -> Progress {
+        let finished = self.shared.finished.lock().unwrap();
+        Progress {
+            completed: finished.watermark + finished.beyond.len() as u64,
+            total: self.shared.total,
+        }
+    }
+
+    /// Where to resume from with [MigrationExecutor::resume_from] if the process stops now.
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            completed: self.shared.finished.lock().unwrap().watermark,
+        }
+    }
+}
+
+/// Runs every move in a [MigrationPlan] through an async callback.
+///
+/// ```
+/// # use faro_sharding::migration::Planner;
+/// # use faro_sharding::tokio::MigrationExecutor;
+/// # use std::hash::BuildHasherDefault;
+/// # use std::collections::hash_map::DefaultHasher;
+/// # ::tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap().block_on(async {
+/// let hasher = BuildHasherDefault::<DefaultHasher>::default();
+/// let plan = Planner::new(4, 5, hasher).record_keys(true).plan(0..1_000u64);
+///
+/// let executor = MigrationExecutor::new(&plan).concurrency(16).rate_limit(10_000);
+/// let control = executor.control();
+/// executor
+///     .run(|key, source, destination| async move {
+///         // Copy `key` from `source` to `destination`, then delete it from `source`.
+///         Ok::<_, std::io::Error>(())
+///     })
+///     .await
+///     .unwrap();
+/// assert_eq!(control.progress().completed, plan.moved_keys());
+/// # });
+/// ```
+#[derive(Debug)]
+pub struct MigrationExecutor<K> {
+    moves: Vec<(K, u64, u64)>,
+    concurrency: usize,
+    rate_limit: Option<u32>,
+    start: u64,
+    shared: Arc<Shared>,
+}
+
+impl<K: Clone + Send + 'static> MigrationExecutor<K> {
+    /// Runs the moves of `plan` one at a time, without a rate limit.
+    ///
+    /// # Panics
+    ///
+    /// If the plan was made without recording keys and moves any.
+    pub fn new(plan: &MigrationPlan<K>) -> Self {
+        let moves = plan
+            .moves()
+            .map(|(key, source, destination)| (key.clone(), source, destination))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            moves.len() as u64,
+            plan.moved_keys(),
+            "plan must be made with record_keys"
+        );
+
+        let total = moves.len() as u64;
+        MigrationExecutor {
+            moves,
+            concurrency: 1,
+            rate_limit: None,
+            start: 0,
+            shared: Arc::new(Shared {
+                paused: watch::Sender::new(false),
+                finished: Mutex::new(Finished {
+                    watermark: 0,
+                    beyond: BTreeSet::new(),
+                }),
+                total,
+            }),
+        }
+    }
+
+    /// Runs up to `concurrency` moves at once.
+    ///
+    /// # Panics
+    ///
+    /// If concurrency == 0.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        assert_ne!(concurrency, 0, "concurrency must be > 0");
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// Starts at most `moves_per_second` moves per second.
+    ///
+    /// Moves are spaced at least a nanosecond apart, so rates above a billion per second act as a billion.
+    ///
+    /// # Panics
+    ///
+    /// If moves_per_second == 0.
+    pub fn rate_limit(mut self, moves_per_second: u32) -> Self {
+        assert_ne!(moves_per_second, 0, "moves_per_second must be > 0");
+        self.rate_limit = Some(moves_per_second);
+        self
+    }
+
+    /// Skips the moves before `checkpoint`, which a previous run of the same plan finished.
+    pub fn resume_from(mut self, checkpoint: Checkpoint) -> Self {
+        let start = checkpoint.completed.min(self.shared.total);
+        self.start = start;
+        self.shared.finished.lock().unwrap().watermark = start;
+        self
+    }
+
+    /// A handle to pause, resume, and checkpoint this executor.
+    pub fn control(&self) -> MigrationControl {
+        MigrationControl {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+
+    /// Calls `move_key(key, source, destination)` for every move, on spawned tasks.
+    ///
+    /// After a move fails, no new moves start. Running moves finish, and the first failure is returned. [MigrationControl::checkpoint] then says where to resume from.
+    ///
+    /// # Panics
+    ///
+    /// If `move_key` panics.
+    pub async fn run<F, Fut, E>(self, move_key: F) -> Result<(), MoveError<K, E>>
+    where
+        F: Fn(K, u64, u64) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), E>> + Send + 'static,
+        E: Send + 'static,
+    {
+        let move_key = Arc::new(move_key);
+        let mut paused = self.shared.paused.subscribe();
+        let mut ticks = self.rate_limit.map(|moves_per_second| {
+            let period = (Duration::from_secs(1) / moves_per_second).max(Duration::from_nanos(1));
+            let mut ticks = time::interval(period);
+            ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            ticks
+        });
+        let mut tasks = JoinSet::new();
+        let mut failure = None;
+
+        for (index, (key, source, destination)) in
+            self.moves.into_iter().enumerate().skip(self.start as usize)
+        {
+            while tasks.len() >= self.concurrency && failure.is_none() {
+                failure = join_next(&self.shared, &mut tasks).await;
+            }
+            if failure.is_some() {
+                break;
+            }
+
+            // The sender lives in `self.shared`, so this cannot fail.
+            let _ = paused.wait_for(|paused| !paused).await;
+            if let Some(ticks) = &mut ticks {
+                ticks.tick().await;
+            }
+
+            let move_key = Arc::clone(&move_key);
+            tasks.spawn(async move {
+                let result = move_key(key.clone(), source, destination).await;
+                let result = result.map_err(|error| MoveError {
+                    key,
+                    source,
+                    destination,
+                    error,
+                });
+                (index as u64, result)
+            });
+        }
+
+        while !tasks.is_empty() {
+            let next = join_next(&self.shared, &mut tasks).await;
+            failure = failure.or(next);
+        }
+        failure.map_or(Ok(()), Err)
+    }
+}
+
+async fn join_next<K: 'static, E: 'static>(
+    shared: &Shared,
+    tasks: &mut MoveTasks<K, E>,
+) -> Option<MoveError<K, E>> {
+    match tasks.join_next().await? {
+        Ok((index, Ok(()))) => {
+            shared.finish(index);
+            None
+        }
+        Ok((_, Err(error))) => Some(error),
+        Err(error) => std::panic::resume_unwind(error.into_panic()),
+    }
+}
+
+/// [shard_reader_with_hasher] using the default hasher of [crate::shard_for].
+#[cfg(feature = "seahash")]
+pub async fn shard_for_reader(reader: impl AsyncRead, total_destinations: u64) -> io::Result<u64> {
+    shard_reader_with_hasher(
+        reader,
+        total_destinations,
+        &crate::seahash::StableSeaHasher::new(),
+    )
+    .await
+}
+
+/// [crate::shard_reader_with_hasher] for an [AsyncRead]. Returns the same shard for the same bytes.
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub async fn shard_reader_with_hasher(
+    reader: impl AsyncRead,
+    total_destinations: u64,
+    hasher: &impl BuildHasher,
+) -> io::Result<u64> {
+    assert_ne!(total_destinations, 0, "total_destinations must be > 0");
+
+    let mut reader = pin!(reader);
+    let mut state = hasher.build_hasher();
+    let mut chunk = vec![0; READ_CHUNK];
+    loop {
+        let read = reader.read(&mut chunk).await?;
+        if read == 0 {
+            break;
+        }
+        state.write(&chunk[..read]);
+    }
+    Ok(shard_for_key_hash(
+        state.finish(),
+        total_destinations,
+        hasher,
+    ))
+}
+
+/// [crate::ShardedSender] over bounded tokio [mpsc] channels.
+///
+/// Keys already sent keep their channel when one is added with [ShardedSender::grow], so each key's messages stay in order on one receiver.
+#[derive(Debug)]
+pub struct ShardedSender<T, S> {
+    senders: Vec<Sender<T>>,
+    capacity: usize,
+    router: ChannelRouter<S>,
+}
+
+impl<T, S: BuildHasher> ShardedSender<T, S> {
+    /// Creates `channels` channels holding up to `capacity` messages each, returning the sender and each channel's receiver in channel order.
+    ///
+    /// # Panics
+    ///
+    /// If channels == 0 or capacity == 0.
+    pub fn new(channels: usize, capacity: usize, hasher: S) -> (Self, Vec<Receiver<T>>) {
+        let router = ChannelRouter::new(channels, hasher);
+        let (senders, receivers) = (0..channels).map(|_| mpsc::channel(capacity)).unzip();
+        let sender = ShardedSender {
+            senders,
+            capacity,
+            router,
+        };
+        (sender, receivers)
+    }
+
+    /// The channel `key`'s messages are sent on. Pins `key` to it if it was not already pinned.
+    pub fn channel_for(&mut self, key: impl Hash) -> usize {
+        self.router.route(key)
+    }
+
+    /// Sends `message` on `key`'s channel, waiting for room if it is full.
+    ///
+    /// Fails, returning the message, if that channel's receiver was dropped.
+    pub async fn send(&mut self, key: impl Hash, message: T) -> Result<(), SendError<T>> {
+        let channel = self.router.route(key);
+        self.senders[channel].send(message).await
+    }
+
+    /// Adds a channel with the same capacity, returning its receiver.
+    pub fn grow(&mut self) -> Receiver<T> {
+        let (sender, receiver) = mpsc::channel(self.capacity);
+        self.senders.push(sender);
+        self.router.grow();
+        receiver
+    }
+
+    /// Forgets `key`'s channel, as [crate::ShardedSender::release].
+    pub fn release(&mut self, key: impl Hash) -> bool {
+        self.router.release(key)
+    }
+
+    /// The number of channels.
+    pub fn len(&self) -> usize {
+        self.senders.len()
+    }
+
+    /// Always false, as there is at least one channel.
+    pub fn is_empty(&self) -> bool {
+        self.senders.is_empty()
+    }
+
+    /// The hasher keys are sharded with.
+    pub fn hasher(&self) -> &S {
+        self.router.hasher()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::migration::Planner;
+    use crate::BuildSeaHasher;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn plan() -> MigrationPlan<u64> {
+        Planner::new(4, 6, BuildSeaHasher)
+            .record_keys(true)
+            .plan(0..1_000u64)
+    }
+
+    #[::tokio::test]
+    async fn moves_every_key_once() {
+        let plan = plan();
+        let moved = Arc::new(Mutex::new(Vec::new()));
+
+        let executor = MigrationExecutor::new(&plan).concurrency(8);
+        let control = executor.control();
+        let log = Arc::clone(&moved);
+        executor
+            .run(move |key, source, destination| {
+                let log = Arc::clone(&log);
+                async move {
+                    log.lock().unwrap().push((key, source, destination));
+                    Ok::<_, ()>(())
+                }
+            })
+            .await
+            .unwrap();
+
+        let mut moved = moved.lock().unwrap().clone();
+        moved.sort();
+        let mut expected = plan.moves().map(|(&k, s, d)| (k, s, d)).collect::<Vec<_>>();
+        expected.sort();
+        assert_eq!(moved, expected);
+        assert_eq!(
+            control.progress(),
+            Progress {
+                completed: plan.moved_keys(),
+                total: plan.moved_keys(),
+            }
+        );
+        assert_eq!(control.checkpoint().completed, plan.moved_keys());
+    }
+
+    #[::tokio::test]
+    async fn stops_at_the_first_failure_and_resumes() {
+        let plan = plan();
+        let failing = plan.moves().nth(10).map(|(&k, _, _)| k).unwrap();
+
+        let executor = MigrationExecutor::new(&plan);
+        let control = executor.control();
+        let error = executor
+            .run(move |key, _, _| async move {
+                if key == failing {
+                    Err("unreachable")
+                } else {
+                    Ok(())
+                }
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(error.key, failing);
+        assert_eq!(control.checkpoint(), Checkpoint { completed: 10 });
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&calls);
+        let executor = MigrationExecutor::new(&plan).resume_from(control.checkpoint());
+        let control = executor.control();
+        executor
+            .run(move |_, _, _| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                async { Ok::<_, ()>(()) }
+            })
+            .await
+            .unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst) as u64, plan.moved_keys() - 10);
+        assert_eq!(control.progress().completed, plan.moved_keys());
+    }
+
+    #[::tokio::test]
+    async fn pauses_and_resumes() {
+        let plan = plan();
+        let executor = MigrationExecutor::new(&plan);
+        let control = executor.control();
+
+        control.pause();
+        let run = ::tokio::spawn(executor.run(|_, _, _| async { Ok::<_, ()>(()) }));
+        for _ in 0..10 {
+            ::tokio::task::yield_now().await;
+        }
+        assert_eq!(control.progress().completed, 0);
+
+        control.resume();
+        run.await.unwrap().unwrap();
+        assert_eq!(control.progress().completed, plan.moved_keys());
+    }
+
+    #[::tokio::test(start_paused = true)]
+    async fn rate_limits() {
+        let plan = Planner::new(1, 2, BuildSeaHasher)
+            .record_keys(true)
+            .plan(0..40u64);
+        let start = time::Instant::now();
+
+        MigrationExecutor::new(&plan)
+            .concurrency(4)
+            .rate_limit(10)
+            .run(|_, _, _| async { Ok::<_, ()>(()) })
+            .await
+            .unwrap();
+
+        // The first move starts immediately.
+        let expected = Duration::from_millis(100) * (plan.moved_keys() as u32 - 1);
+        assert_eq!(start.elapsed(), expected);
+    }
+
+    #[::tokio::test(start_paused = true)]
+    async fn rate_limits_faster_than_a_nanosecond() {
+        let plan = Planner::new(1, 2, BuildSeaHasher)
+            .record_keys(true)
+            .plan(0..40u64);
+
+        MigrationExecutor::new(&plan)
+            .rate_limit(u32::MAX)
+            .run(|_, _, _| async { Ok::<_, ()>(()) })
+            .await
+            .unwrap();
+    }
+
+    #[::tokio::test]
+    async fn async_reader_matches_bytes() {
+        let key = (0..READ_CHUNK + 100)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            shard_for_reader(&key[..], 1000).await.unwrap(),
+            crate::shard_for_bytes(&key, 1000)
+        );
+    }
+
+    #[::tokio::test]
+    async fn sharded_sender_keeps_keys_on_their_channel() {
+        let (mut sender, mut receivers) = ShardedSender::new(3, 16, BuildSeaHasher);
+        let channel = sender.channel_for("foo");
+        sender.send("foo", 1).await.unwrap();
+
+        let mut new_receiver = sender.grow();
+        sender.send("foo", 2).await.unwrap();
+        let new_key = (0..100u64).find(|&k| crate::shard_for(k, 4) == 3).unwrap();
+        sender.send(new_key, 3).await.unwrap();
+        drop(sender);
+
+        assert_eq!(receivers[channel].recv().await, Some(1));
+        assert_eq!(receivers[channel].recv().await, Some(2));
+        assert_eq!(new_receiver.recv().await, Some(3));
+        assert_eq!(new_receiver.recv().await, None);
+    }
+}
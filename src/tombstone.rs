@@ -0,0 +1,311 @@
+#[cfg(feature = "serde")]
+use crate::format_version::FormatVersion;
+use crate::shard_for_key_hash;
+use std::hash::*;
+
+/// Lifecycle of a destination in a [TombstoneTopology].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DestinationState {
+    /// Receives reads and writes.
+    Live,
+    /// Being decommissioned. Existing keys are still read from it, but writes go elsewhere.
+    Draining,
+    /// Gone. Its keys are placed elsewhere for both reads and writes.
+    Removed,
+}
+
+/// Where to read and write a key in a [TombstoneTopology] with draining destinations.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Assignment {
+    /// The shard existing data for the key is on.
+    pub read: u64,
+    /// The shard new data for the key should go to. Differs from `read` only if `read` is draining.
+    pub write: u64,
+}
+
+/// A destination count that also supports removing destinations other than the last one.
+///
+/// Removed destinations are kept as tombstones so every other destination keeps its index. A key whose shard is a tombstone is rehashed and placed again, repeatedly, until it lands on a live destination. Keys on live destinations never move when another destination is removed, and adding a destination still only moves keys onto the new one.
+///
+/// Destinations can also be drained before removal. Draining destinations are still read from, but [TombstoneTopology::assignment] redirects writes for their keys exactly where removing them would.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "PersistedTopology<S>"))]
+pub struct TombstoneTopology<S> {
+    #[cfg(feature = "serde")]
+    version: FormatVersion,
+    states: Vec<DestinationState>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    live: u64,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    draining: u64,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    hasher: S,
+}
+
+/// The persisted fields of a [TombstoneTopology]. The counts are recomputed from `states` so they can never disagree.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct PersistedTopology<S> {
+    #[allow(dead_code)]
+    version: FormatVersion,
+    states: Vec<DestinationState>,
+    #[serde(skip)]
+    hasher: S,
+}
+
+#[cfg(feature = "serde")]
+impl<S> From<PersistedTopology<S>> for TombstoneTopology<S> {
+    fn from(persisted: PersistedTopology<S>) -> Self {
+        let count = |wanted: &[DestinationState]| {
+            persisted
+                .states
+                .iter()
+                .filter(|state| wanted.contains(state))
+                .count() as u64
+        };
+        TombstoneTopology {
+            version: FormatVersion,
+            live: count(&[DestinationState::Live, DestinationState::Draining]),
+            draining: count(&[DestinationState::Draining]),
+            states: persisted.states,
+            hasher: persisted.hasher,
+        }
+    }
+}
+
+impl<S: BuildHasher> TombstoneTopology<S> {
+    /// Creates a topology with `total_destinations` live destinations.
+    pub fn new(total_destinations: u64, hasher: S) -> Self {
+        TombstoneTopology {
+            #[cfg(feature = "serde")]
+            version: FormatVersion,
+            states: vec![DestinationState::Live; total_destinations as usize],
+            live: total_destinations,
+            draining: 0,
+            hasher,
+        }
+    }
+
+    /// Appends a new live destination, returning its index.
+    pub fn push(&mut self) -> u64 {
+        self.states.push(DestinationState::Live);
+        self.live += 1;
+        self.states.len() as u64 - 1
+    }
+
+    /// Marks `destination` as removed. Returns false if it was already removed.
+    ///
+    /// # Panics
+    ///
+    /// If destination is out of range.
+    pub fn remove(&mut self, destination: u64) -> bool {
+        let state = &mut self.states[destination as usize];
+        match *state {
+            DestinationState::Removed => return false,
+            DestinationState::Draining => self.draining -= 1,
+            DestinationState::Live => {}
+        }
+
+        *state = DestinationState::Removed;
+        self.live -= 1;
+        true
+    }
+
+    /// Marks a live `destination` as draining. Returns false if it was not live.
+    ///
+    /// # Panics
+    ///
+    /// If destination is out of range.
+    pub fn drain(&mut self, destination: u64) -> bool {
+        let state = &mut self.states[destination as usize];
+        if *state != DestinationState::Live {
+            return false;
+        }
+
+        *state = DestinationState::Draining;
+        self.draining += 1;
+        true
+    }
+
+    /// Returns a draining `destination` to live. Returns false if it was not draining.
+    ///
+    /// # Panics
+    ///
+    /// If destination is out of range.
+    pub fn undrain(&mut self, destination: u64) -> bool {
+        let state = &mut self.states[destination as usize];
+        if *state != DestinationState::Draining {
+            return false;
+        }
+
+        *state = DestinationState::Live;
+        self.draining -= 1;
+        true
+    }
+
+    /// The state of `destination`. Destinations out of range are considered removed.
+    pub fn state(&self, destination: u64) -> DestinationState {
+        self.states
+            .get(destination as usize)
+            .copied()
+            .unwrap_or(DestinationState::Removed)
+    }
+
+    /// Whether `destination` has been removed. Destinations out of range are considered removed.
+    pub fn is_removed(&self, destination: u64) -> bool {
+        self.state(destination) == DestinationState::Removed
+    }
+
+    /// Number of destinations ever added, including removed ones.
+    pub fn total_destinations(&self) -> u64 {
+        self.states.len() as u64
+    }
+
+    /// Number of destinations that have not been removed, including draining ones.
+    pub fn live_destinations(&self) -> u64 {
+        self.live
+    }
+
+    /// Returns the non-removed destination for `key`. This may be a draining destination.
+    ///
+    /// # Panics
+    ///
+    /// If there are no live destinations.
+    pub fn shard_for(&self, key: impl Hash) -> u64 {
+        self.shard_for_key_hash(self.hasher.hash_one(key))
+    }
+
+    /// [TombstoneTopology::shard_for] for an already hashed key.
+    ///
+    /// # Panics
+    ///
+    /// If there are no live destinations.
+    pub fn shard_for_key_hash(&self, key_hash: u64) -> u64 {
+        assert_ne!(self.live, 0, "no live destinations");
+        self.place(key_hash, |state| state != DestinationState::Removed)
+    }
+
+    /// Returns where to read and write `key`.
+    ///
+    /// Only keys whose read shard is draining get a different write shard.
+    ///
+    /// # Panics
+    ///
+    /// If every non-removed destination is draining.
+    pub fn assignment(&self, key: impl Hash) -> Assignment {
+        self.assignment_for_key_hash(self.hasher.hash_one(key))
+    }
+
+    /// [TombstoneTopology::assignment] for an already hashed key.
+    ///
+    /// # Panics
+    ///
+    /// If every non-removed destination is draining.
+    pub fn assignment_for_key_hash(&self, key_hash: u64) -> Assignment {
+        assert_ne!(self.live - self.draining, 0, "no writable destinations");
+
+        Assignment {
+            read: self.shard_for_key_hash(key_hash),
+            write: self.place(key_hash, |state| state == DestinationState::Live),
+        }
+    }
+
+    /// Places the key on the first shard in its rehash sequence that is `usable`.
+    ///
+    /// Draining and removal both only skip states, so a key's sequence is the same either way.
+    fn place(&self, key_hash: u64, usable: impl Fn(DestinationState) -> bool) -> u64 {
+        let total = self.total_destinations();
+        let mut shard = shard_for_key_hash(key_hash, total, &self.hasher);
+        let mut attempt = 0u64;
+        while !usable(self.states[shard as usize]) {
+            attempt += 1;
+            let rehash = self.hasher.hash_one((key_hash, attempt));
+            shard = shard_for_key_hash(rehash, total, &self.hasher);
+        }
+        shard
+    }
+
+    /// The hasher keys are sharded with.
+    pub fn hasher(&self) -> &S {
+        &self.hasher
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{shard_with_hasher, BuildSeaHasher};
+
+    #[test]
+    fn matches_faro_without_removals() {
+        let topology = TombstoneTopology::new(73, BuildSeaHasher);
+        assert_eq!(
+            topology.shard_for("foo"),
+            shard_with_hasher("foo", 73, &BuildSeaHasher)
+        );
+    }
+
+    #[test]
+    fn removal_only_moves_keys_from_the_removed_destination() {
+        let mut topology = TombstoneTopology::new(16, BuildSeaHasher);
+        let before = (0..10_000u64)
+            .map(|k| topology.shard_for(k))
+            .collect::<Vec<_>>();
+
+        assert!(topology.remove(5));
+        assert!(!topology.remove(5));
+        assert_eq!(topology.live_destinations(), 15);
+
+        let mut moved_to = std::collections::HashSet::new();
+        for (key, before) in (0..10_000u64).zip(&before) {
+            let after = topology.shard_for(key);
+            assert_ne!(after, 5);
+            if *before != 5 {
+                assert_eq!(after, *before);
+            } else {
+                moved_to.insert(after);
+            }
+        }
+        assert!(moved_to.len() > 1, "removed keys should spread out");
+
+        let after_removal = (0..10_000u64)
+            .map(|k| topology.shard_for(k))
+            .collect::<Vec<_>>();
+        let added = topology.push();
+        for (key, before) in (0..10_000u64).zip(after_removal) {
+            let after = topology.shard_for(key);
+            assert!(after == before || after == added);
+        }
+    }
+
+    #[test]
+    fn draining_only_redirects_writes_from_the_drained_destination() {
+        let mut topology = TombstoneTopology::new(8, BuildSeaHasher);
+        assert!(topology.drain(3));
+        assert!(!topology.drain(3));
+        assert_eq!(topology.state(3), DestinationState::Draining);
+
+        let mut redirected = 0;
+        for key in 0..10_000u64 {
+            let Assignment { read, write } = topology.assignment(key);
+            assert_eq!(read, shard_with_hasher(key, 8, &BuildSeaHasher));
+            if read == 3 {
+                assert_ne!(write, 3);
+                redirected += 1;
+            } else {
+                assert_eq!(write, read);
+            }
+        }
+        assert!(redirected > 0);
+
+        let writes = (0..10_000u64)
+            .map(|k| topology.assignment(k).write)
+            .collect::<Vec<_>>();
+        assert!(topology.remove(3));
+        for (key, write) in (0..10_000u64).zip(writes) {
+            assert_eq!(topology.shard_for(key), write);
+        }
+    }
+}
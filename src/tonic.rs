@@ -0,0 +1,90 @@
+//! Sticky-by-key routing for [tonic](https://docs.rs/tonic) clients.
+//!
+//! [route_by_metadata] wraps a list of channels in a [ShardRoute] keyed by a request metadata header. Pass the result to a generated client in place of a single `Channel`:
+//!
+//! ```ignore
+//! let channels = endpoints.iter().map(|e| e.connect_lazy()).collect();
+//! let route = route_by_metadata(channels, HeaderName::from_static("x-tenant-id"), hasher);
+//! let mut client = MyServiceClient::new(route);
+//! ```
+//!
+//! Only `http` is a dependency, so this works with any tonic version built on `http` 1.
+
+use crate::tower::ShardRoute;
+use http::{HeaderName, Request};
+use std::hash::BuildHasher;
+
+/// Routes each request to `services[shard_with_hasher(value, services.len(), hasher)]`, where `value` is the bytes of the `header` metadata.
+///
+/// Requests without the header are all routed as if it were empty. A present header agrees with `shard_with_hasher(value.as_bytes(), ..)`.
+///
+/// # Panics
+///
+/// If services is empty.
+pub fn route_by_metadata<S, B, H: BuildHasher>(
+    services: Vec<S>,
+    header: HeaderName,
+    hasher: H,
+) -> ShardRoute<S, impl Fn(&Request<B>) -> Vec<u8> + Clone, H> {
+    ShardRoute::new(
+        services,
+        move |request: &Request<B>| {
+            request
+                .headers()
+                .get(&header)
+                .map(|value| value.as_bytes().to_vec())
+                .unwrap_or_default()
+        },
+        hasher,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{shard_for, BuildSeaHasher};
+    use std::future::{ready, Future, Ready};
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
+    use tower_service::Service;
+
+    /// Answers every request with its own index.
+    struct Backend(u64);
+
+    impl Service<Request<()>> for Backend {
+        type Response = u64;
+        type Error = ();
+        type Future = Ready<Result<u64, ()>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), ()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _: Request<()>) -> Self::Future {
+            ready(Ok(self.0))
+        }
+    }
+
+    #[test]
+    fn routes_by_header() {
+        let mut route = route_by_metadata(
+            (0..7).map(Backend).collect(),
+            HeaderName::from_static("x-tenant-id"),
+            BuildSeaHasher,
+        );
+        let mut cx = Context::from_waker(Waker::noop());
+
+        for tenant in ["", "acme", "globex", "initech"] {
+            let mut request = Request::new(());
+            if !tenant.is_empty() {
+                request
+                    .headers_mut()
+                    .insert("x-tenant-id", tenant.parse().unwrap());
+            }
+
+            assert_eq!(route.poll_ready(&mut cx), Poll::Ready(Ok(())));
+            let backend = pin!(route.call(request)).poll(&mut cx);
+            assert_eq!(backend, Poll::Ready(Ok(shard_for(tenant.as_bytes(), 7))));
+        }
+    }
+}
@@ -0,0 +1,427 @@
+use crate::skip_ahead::skip_ahead_from_key_hash;
+use std::hash::*;
+
+/// A membership change recorded by [Topology]. Change `i` produced epoch `i + 1`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TopologyChange {
+    /// A destination was added.
+    Added {
+        /// Index of the new destination.
+        destination: u64,
+        /// Its initial weight.
+        weight: u64,
+    },
+    /// A destination was removed.
+    Removed {
+        /// Index of the removed destination.
+        destination: u64,
+    },
+    /// A destination's weight changed.
+    WeightChanged {
+        /// Index of the destination.
+        destination: u64,
+        /// Its new weight.
+        weight: u64,
+    },
+}
+
+/// Membership differences between two epochs of a [Topology]. See [Topology::diff].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EpochDiff {
+    /// Destinations present in the later epoch but not the earlier one.
+    pub added: Vec<u64>,
+    /// Destinations present in the earlier epoch but removed by the later one.
+    pub removed: Vec<u64>,
+    /// `(destination, old_weight, new_weight)` for destinations present in both whose weight changed.
+    pub reweighted: Vec<(u64, u64, u64)>,
+}
+
+/// A unit of weight. `toggled_at` lists the epochs it was tombstoned or revived in, alternately, starting live.
+#[derive(Clone, Debug)]
+struct Slot {
+    owner: u64,
+    toggled_at: Vec<u64>,
+}
+
+impl Slot {
+    fn is_live_at(&self, epoch: u64) -> bool {
+        self.toggled_at
+            .iter()
+            .take_while(|&&at| at <= epoch)
+            .count()
+            % 2
+            == 0
+    }
+
+    fn is_live(&self) -> bool {
+        self.toggled_at.len().is_multiple_of(2)
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Destination {
+    added_at: u64,
+    removed_at: Option<u64>,
+}
+
+/// Weighted destinations with a history of every membership change, so keys can be sharded as of any past epoch.
+///
+/// Epoch 0 is the topology passed to [Topology::new], and every change starts a new epoch. Each unit of weight is a slot, as in [crate::WeightedSharder]. Slots are never deleted, only tombstoned and later revived, and each keeps the epochs it changed in, so every epoch can be reconstructed. A key on a tombstoned slot is rehashed until it lands on a live one, as in [crate::TombstoneTopology].
+///
+/// Adding weight only moves keys onto the destination that gained it, and removing weight only moves keys off the destination that lost it.
+///
+/// Every slot ever added is kept in memory, tombstoned or not, so the slot count is limited to [Topology::MAX_SLOTS], as in [crate::WeightedSharder].
+#[derive(Clone, Debug)]
+pub struct Topology<S> {
+    slots: Vec<Slot>,
+    destinations: Vec<Destination>,
+    changes: Vec<TopologyChange>,
+    slot_counts: Vec<u64>,
+    live_slot_counts: Vec<u64>,
+    hasher: S,
+}
+
+impl<S> Topology<S> {
+    /// The most slots a topology can hold, tombstoned ones included.
+    pub const MAX_SLOTS: u64 = crate::slots::MAX_SLOTS;
+}
+
+impl<S: BuildHasher> Topology<S> {
+    /// Creates a topology at epoch 0 with one destination per weight.
+    ///
+    /// # Panics
+    ///
+    /// If the weights add up to more than [Topology::MAX_SLOTS].
+    pub fn new(weights: &[u64], hasher: S) -> Self {
+        let mut topology = Topology {
+            slots: Vec::new(),
+            destinations: Vec::new(),
+            changes: Vec::new(),
+            slot_counts: Vec::new(),
+            live_slot_counts: Vec::new(),
+            hasher,
+        };
+        for &weight in weights {
+            topology.push_destination(weight, 0);
+        }
+        topology.end_epoch();
+        topology
+    }
+
+    /// The latest epoch.
+    pub fn current_epoch(&self) -> u64 {
+        self.slot_counts.len() as u64 - 1
+    }
+
+    /// Every change so far, oldest first.
+    pub fn changes(&self) -> &[TopologyChange] {
+        &self.changes
+    }
+
+    /// Adds a destination with `weight` in a new epoch, returning its index.
+    ///
+    /// # Panics
+    ///
+    /// If this would make more than [Topology::MAX_SLOTS] slots.
+    pub fn add_destination(&mut self, weight: u64) -> u64 {
+        let epoch = self.current_epoch() + 1;
+        let destination = self.push_destination(weight, epoch);
+        self.changes.push(TopologyChange::Added {
+            destination,
+            weight,
+        });
+        self.end_epoch();
+        destination
+    }
+
+    /// Removes `destination` in a new epoch. Returns false, without starting an epoch, if it was already removed.
+    ///
+    /// # Panics
+    ///
+    /// If destination is out of range.
+    pub fn remove_destination(&mut self, destination: u64) -> bool {
+        let epoch = self.current_epoch() + 1;
+        let entry = &mut self.destinations[destination as usize];
+        if entry.removed_at.is_some() {
+            return false;
+        }
+        entry.removed_at = Some(epoch);
+
+        for slot in &mut self.slots {
+            if slot.owner == destination && slot.is_live() {
+                slot.toggled_at.push(epoch);
+            }
+        }
+        self.changes.push(TopologyChange::Removed { destination });
+        self.end_epoch();
+        true
+    }
+
+    /// Changes the weight of `destination` in a new epoch. Does nothing if the weight is unchanged.
+    ///
+    /// Decreasing tombstones the destination's most recently added live slots. Increasing first revives its tombstoned slots, in the reverse of the order they were tombstoned, and only then adds slots, so changing a weight back and forth does not grow the topology.
+    ///
+    /// # Panics
+    ///
+    /// If destination is out of range or removed, or raising the weight would make more than [Topology::MAX_SLOTS] slots.
+    pub fn set_weight(&mut self, destination: u64, weight: u64) {
+        let epoch = self.current_epoch() + 1;
+        assert!(
+            self.destinations[destination as usize].removed_at.is_none(),
+            "destination {} is removed",
+            destination
+        );
+
+        let current = self.weight_at_epoch(destination, self.current_epoch());
+        if weight == current {
+            return;
+        }
+
+        let slots = self.slots.iter().map(|slot| (slot.owner, slot.is_live()));
+        let reweigh = crate::slots::reweigh(slots, destination, current, weight);
+        crate::slots::check_slots(self.slots.len(), reweigh.added);
+        for slot in reweigh.toggled {
+            self.slots[slot].toggled_at.push(epoch);
+        }
+        self.push_slots(destination, reweigh.added);
+        self.changes.push(TopologyChange::WeightChanged {
+            destination,
+            weight,
+        });
+        self.end_epoch();
+    }
+
+    /// The weight of `destination` at `epoch`. 0 if it did not exist yet or was removed.
+    ///
+    /// # Panics
+    ///
+    /// If epoch is after the current epoch.
+    pub fn weight_at_epoch(&self, destination: u64, epoch: u64) -> u64 {
+        assert!(
+            epoch <= self.current_epoch(),
+            "epoch {} is after the current epoch {}",
+            epoch,
+            self.current_epoch()
+        );
+        let slot_count = self.slot_counts[epoch as usize] as usize;
+        self.slots[..slot_count]
+            .iter()
+            .filter(|slot| slot.owner == destination && slot.is_live_at(epoch))
+            .count() as u64
+    }
+
+    /// Returns the destination for `key` as of `epoch`.
+    ///
+    /// # Panics
+    ///
+    /// If epoch is after the current epoch, or there was no weight at that epoch.
+    pub fn shard_at_epoch(&self, key: impl Hash, epoch: u64) -> u64 {
+        assert!(
+            epoch <= self.current_epoch(),
+            "epoch {} is after the current epoch {}",
+            epoch,
+            self.current_epoch()
+        );
+        assert_ne!(
+            self.live_slot_counts[epoch as usize], 0,
+            "no weight at epoch {}",
+            epoch
+        );
+
+        let key_hash = self.hasher.hash_one(key);
+        let slot_count = self.slot_counts[epoch as usize];
+
+        let mut slot = skip_ahead_from_key_hash(key_hash, slot_count, &self.hasher);
+        let mut attempt = 0u64;
+        while !self.slots[slot as usize].is_live_at(epoch) {
+            attempt += 1;
+            let rehash = self.hasher.hash_one((key_hash, attempt));
+            slot = skip_ahead_from_key_hash(rehash, slot_count, &self.hasher);
+        }
+        self.slots[slot as usize].owner
+    }
+
+    /// Returns the destination for `key` as of the current epoch.
+    ///
+    /// # Panics
+    ///
+    /// If there is no weight.
+    pub fn current_shard(&self, key: impl Hash) -> u64 {
+        self.shard_at_epoch(key, self.current_epoch())
+    }
+
+    /// Membership changes going from epoch `from` to epoch `to`.
+    ///
+    /// # Panics
+    ///
+    /// If either epoch is after the current epoch.
+    pub fn diff(&self, from: u64, to: u64) -> EpochDiff {
+        assert!(
+            from.max(to) <= self.current_epoch(),
+            "epoch is after the current epoch {}",
+            self.current_epoch()
+        );
+
+        let mut diff = EpochDiff::default();
+        for (index, destination) in self.destinations.iter().enumerate() {
+            let index = index as u64;
+            let present = |epoch: u64| {
+                destination.added_at <= epoch && Self::is_live(destination.removed_at, epoch)
+            };
+
+            match (present(from), present(to)) {
+                (false, true) => diff.added.push(index),
+                (true, false) => diff.removed.push(index),
+                (true, true) => {
+                    let old = self.weight_at_epoch(index, from);
+                    let new = self.weight_at_epoch(index, to);
+                    if old != new {
+                        diff.reweighted.push((index, old, new));
+                    }
+                }
+                (false, false) => {}
+            }
+        }
+        diff
+    }
+
+    fn is_live(removed_at: Option<u64>, epoch: u64) -> bool {
+        removed_at.is_none_or(|removed_at| removed_at > epoch)
+    }
+
+    fn push_destination(&mut self, weight: u64, epoch: u64) -> u64 {
+        crate::slots::check_slots(self.slots.len(), weight);
+        let destination = self.destinations.len() as u64;
+        self.destinations.push(Destination {
+            added_at: epoch,
+            removed_at: None,
+        });
+        self.push_slots(destination, weight);
+        destination
+    }
+
+    fn push_slots(&mut self, owner: u64, count: u64) {
+        self.slots.extend((0..count).map(|_| Slot {
+            owner,
+            toggled_at: Vec::new(),
+        }));
+    }
+
+    fn end_epoch(&mut self) {
+        let live = self.slots.iter().filter(|slot| slot.is_live()).count();
+        self.slot_counts.push(self.slots.len() as u64);
+        self.live_slot_counts.push(live as u64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BuildSeaHasher, WeightedSharder};
+
+    #[test]
+    fn matches_weighted_sharder_without_removals() {
+        let mut topology = Topology::new(&[3, 1, 2], BuildSeaHasher);
+        let mut weighted = WeightedSharder::new(&[3, 1, 2], BuildSeaHasher);
+        topology.add_destination(4);
+        weighted.push(4);
+        topology.set_weight(1, 3);
+        weighted.grow(1, 2);
+
+        for key in 0..1_000u64 {
+            assert_eq!(topology.current_shard(key), weighted.shard_for(key));
+        }
+    }
+
+    #[test]
+    fn past_epochs_are_preserved() {
+        let mut topology = Topology::new(&[2, 2, 2], BuildSeaHasher);
+        let mut history = vec![(0..1_000u64)
+            .map(|k| topology.current_shard(k))
+            .collect::<Vec<_>>()];
+
+        topology.add_destination(3);
+        history.push((0..1_000u64).map(|k| topology.current_shard(k)).collect());
+        topology.remove_destination(1);
+        history.push((0..1_000u64).map(|k| topology.current_shard(k)).collect());
+        topology.set_weight(0, 1);
+        history.push((0..1_000u64).map(|k| topology.current_shard(k)).collect());
+        topology.set_weight(0, 3);
+        history.push((0..1_000u64).map(|k| topology.current_shard(k)).collect());
+
+        assert_eq!(topology.current_epoch(), 4);
+        assert_eq!(topology.slots.len(), 10);
+        for (epoch, shards) in history.iter().enumerate() {
+            for (key, &shard) in (0..1_000u64).zip(shards) {
+                assert_eq!(topology.shard_at_epoch(key, epoch as u64), shard);
+            }
+        }
+    }
+
+    #[test]
+    fn removing_weight_only_moves_keys_off_that_destination() {
+        let mut topology = Topology::new(&[3, 3, 3, 3], BuildSeaHasher);
+        topology.set_weight(2, 1);
+        topology.remove_destination(0);
+
+        for key in 0..10_000u64 {
+            let [before, reduced, removed] = [0, 1, 2].map(|e| topology.shard_at_epoch(key, e));
+            assert!(reduced == before || before == 2);
+            assert!(removed == reduced || reduced == 0);
+            assert_ne!(removed, 0);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "slot count must be <= 16777216")]
+    fn limits_slot_count() {
+        Topology::new(&[u64::MAX], BuildSeaHasher);
+    }
+
+    #[test]
+    #[should_panic(expected = "slot count must be <= 16777216")]
+    fn limits_slot_count_when_raising_a_weight() {
+        let mut topology = Topology::new(&[2, 2], BuildSeaHasher);
+        topology.set_weight(1, u64::MAX);
+    }
+
+    #[test]
+    #[should_panic(expected = "epoch 1 is after the current epoch 0")]
+    fn weight_at_epoch_checks_the_epoch() {
+        Topology::new(&[2, 2], BuildSeaHasher).weight_at_epoch(0, 1);
+    }
+
+    #[test]
+    fn diff_between_epochs() {
+        let mut topology = Topology::new(&[1, 1, 1], BuildSeaHasher);
+        topology.add_destination(2);
+        topology.remove_destination(0);
+        topology.set_weight(1, 4);
+        topology.set_weight(1, 4);
+
+        assert_eq!(topology.current_epoch(), 3);
+        assert_eq!(
+            topology.changes(),
+            &[
+                TopologyChange::Added {
+                    destination: 3,
+                    weight: 2
+                },
+                TopologyChange::Removed { destination: 0 },
+                TopologyChange::WeightChanged {
+                    destination: 1,
+                    weight: 4
+                },
+            ]
+        );
+        assert_eq!(
+            topology.diff(0, 3),
+            EpochDiff {
+                added: vec![3],
+                removed: vec![0],
+                reweighted: vec![(1, 1, 4)],
+            }
+        );
+        assert_eq!(topology.diff(3, 3), EpochDiff::default());
+    }
+}
@@ -0,0 +1,210 @@
+//! Client-side partitioning for [tower](https://docs.rs/tower) services.
+//!
+//! [ShardRoute] holds one inner service per shard and sends each request to the service for its key. Growing the service list only reroutes keys onto the new service.
+
+use crate::shard_with_hasher;
+use std::collections::VecDeque;
+use std::hash::*;
+use std::task::{Context, Poll};
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// Routes each request to `services[shard_with_hasher(key(&request), services.len(), hasher)]`.
+///
+/// Like `tower::steer::Steer`, [Service::poll_ready] waits for every inner service to be ready, since the target is not known until the request arrives. A clone has not polled its own copies of the services, so it starts out not ready.
+#[derive(Debug)]
+pub struct ShardRoute<S, F, H> {
+    services: Vec<S>,
+    key: F,
+    hasher: H,
+    not_ready: VecDeque<usize>,
+}
+
+impl<S: Clone, F: Clone, H: Clone> Clone for ShardRoute<S, F, H> {
+    fn clone(&self) -> Self {
+        ShardRoute::new(self.services.clone(), self.key.clone(), self.hasher.clone())
+    }
+}
+
+impl<S, F, H> ShardRoute<S, F, H> {
+    /// Routes requests across `services` by the key `key` extracts.
+    ///
+    /// # Panics
+    ///
+    /// If services is empty.
+    pub fn new(services: Vec<S>, key: F, hasher: H) -> Self {
+        assert!(!services.is_empty(), "services must not be empty");
+
+        ShardRoute {
+            not_ready: (0..services.len()).collect(),
+            services,
+            key,
+            hasher,
+        }
+    }
+
+    /// The inner services, in shard order.
+    pub fn services(&self) -> &[S] {
+        &self.services
+    }
+
+    /// Adds a service as the new last shard. Only keys that now belong to it are rerouted.
+    pub fn push_service(&mut self, service: S) {
+        self.not_ready.push_back(self.services.len());
+        self.services.push(service);
+    }
+
+    /// Removes and returns the last shard's service. Only its keys are rerouted.
+    ///
+    /// Returns `None` rather than removing the only service.
+    pub fn pop_service(&mut self) -> Option<S> {
+        if self.services.len() == 1 {
+            return None;
+        }
+
+        let removed = self.services.len() - 1;
+        self.not_ready.retain(|&i| i != removed);
+        self.services.pop()
+    }
+}
+
+impl<S, F, H, Request, K> Service<Request> for ShardRoute<S, F, H>
+where
+    S: Service<Request>,
+    F: Fn(&Request) -> K,
+    K: Hash,
+    H: BuildHasher,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        while let Some(&i) = self.not_ready.front() {
+            match self.services[i].poll_ready(cx)? {
+                Poll::Ready(()) => {
+                    self.not_ready.pop_front();
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        assert!(
+            self.not_ready.is_empty(),
+            "ShardRoute::call before poll_ready returned Ready"
+        );
+
+        let shard = crate::observe_lookup("shard_route", || {
+            shard_with_hasher(
+                (self.key)(&request),
+                self.services.len() as u64,
+                &self.hasher,
+            )
+        });
+        self.not_ready.push_back(shard as usize);
+        self.services[shard as usize].call(request)
+    }
+}
+
+/// [Layer] turning a `Vec` of services into a [ShardRoute].
+#[derive(Clone, Debug)]
+pub struct ShardRouteLayer<F, H> {
+    key: F,
+    hasher: H,
+}
+
+impl<F, H> ShardRouteLayer<F, H> {
+    /// Creates a layer routing by the key `key` extracts from each request.
+    pub fn new(key: F, hasher: H) -> Self {
+        ShardRouteLayer { key, hasher }
+    }
+}
+
+impl<S, F: Clone, H: Clone> Layer<Vec<S>> for ShardRouteLayer<F, H> {
+    type Service = ShardRoute<S, F, H>;
+
+    fn layer(&self, services: Vec<S>) -> Self::Service {
+        ShardRoute::new(services, self.key.clone(), self.hasher.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{shard_for, BuildSeaHasher};
+    use std::future::{ready, Future, Ready};
+    use std::pin::pin;
+    use std::task::Waker;
+
+    /// Answers every request with its own index.
+    #[derive(Clone)]
+    struct Backend(u64);
+
+    impl Service<String> for Backend {
+        type Response = u64;
+        type Error = ();
+        type Future = Ready<Result<u64, ()>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), ()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _: String) -> Self::Future {
+            ready(Ok(self.0))
+        }
+    }
+
+    fn route(service: &mut impl Service<String, Response = u64, Error = ()>, key: &str) -> u64 {
+        let mut cx = Context::from_waker(Waker::noop());
+        assert_eq!(service.poll_ready(&mut cx), Poll::Ready(Ok(())));
+        match pin!(service.call(key.to_string())).poll(&mut cx) {
+            Poll::Ready(Ok(backend)) => backend,
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn routes_by_key() {
+        let mut service = ShardRouteLayer::new(String::clone, BuildSeaHasher)
+            .layer((0..5).map(Backend).collect());
+
+        for key in ["foo", "bar", "baz"] {
+            assert_eq!(route(&mut service, key), shard_for(key, 5));
+        }
+    }
+
+    #[test]
+    fn growing_only_reroutes_to_the_new_service() {
+        let mut service =
+            ShardRoute::new((0..5).map(Backend).collect(), String::clone, BuildSeaHasher);
+        let keys = (0..200).map(|i| format!("key-{}", i)).collect::<Vec<_>>();
+        let before = keys
+            .iter()
+            .map(|k| route(&mut service, k))
+            .collect::<Vec<_>>();
+
+        service.push_service(Backend(5));
+        for (key, before) in keys.iter().zip(before) {
+            let after = route(&mut service, key);
+            assert!(after == before || after == 5);
+        }
+
+        assert!(service.pop_service().is_some());
+        assert_eq!(service.services().len(), 5);
+    }
+
+    #[test]
+    fn clones_must_poll_their_own_services() {
+        let mut service =
+            ShardRoute::new((0..5).map(Backend).collect(), String::clone, BuildSeaHasher);
+        let mut cx = Context::from_waker(Waker::noop());
+        assert_eq!(service.poll_ready(&mut cx), Poll::Ready(Ok(())));
+
+        let mut clone = service.clone();
+        assert_eq!(clone.not_ready.len(), 5);
+        assert_eq!(route(&mut clone, "foo"), shard_for("foo", 5));
+    }
+}
@@ -0,0 +1,131 @@
+use crate::shard_with_hasher;
+use std::collections::{BTreeMap, BTreeSet};
+use std::hash::*;
+
+/// A string-keyed trie whose keys are sharded by prefix.
+///
+/// Each key is routed by the trie node at `routing_depth` characters (or the key's own node if it is shorter), so keys sharing that prefix always share a shard. Every node tracks which shards hold keys beneath it, so a prefix scan can be routed to exactly the shards that might have matches.
+pub struct ShardedTrie<V, S> {
+    root: Node<V>,
+    total_destinations: u64,
+    routing_depth: usize,
+    hasher: S,
+}
+
+struct Node<V> {
+    children: BTreeMap<char, Node<V>>,
+    value: Option<V>,
+    shards: BTreeSet<u64>,
+}
+
+impl<V> Default for Node<V> {
+    fn default() -> Self {
+        Node {
+            children: BTreeMap::new(),
+            value: None,
+            shards: BTreeSet::new(),
+        }
+    }
+}
+
+impl<V, S: BuildHasher> ShardedTrie<V, S> {
+    /// Creates an empty trie routing keys by their first `routing_depth` characters.
+    ///
+    /// # Panics
+    ///
+    /// If total_destinations == 0.
+    pub fn new(total_destinations: u64, routing_depth: usize, hasher: S) -> Self {
+        assert_ne!(total_destinations, 0, "total_destinations must be > 0");
+
+        ShardedTrie {
+            root: Node::default(),
+            total_destinations,
+            routing_depth,
+            hasher,
+        }
+    }
+
+    /// Returns the shard `key` is routed to, whether or not it is present.
+    pub fn shard_for_key(&self, key: &str) -> u64 {
+        let (depth, prefix) = match key.char_indices().nth(self.routing_depth) {
+            Some((i, _)) => (self.routing_depth, &key[..i]),
+            None => (key.chars().count(), key),
+        };
+        shard_with_hasher((depth, prefix), self.total_destinations, &self.hasher)
+    }
+
+    /// Inserts `value` under `key`, replacing any previous value.
+    pub fn insert(&mut self, key: &str, value: V) {
+        let shard = self.shard_for_key(key);
+
+        let mut node = &mut self.root;
+        node.shards.insert(shard);
+        for c in key.chars() {
+            node = node.children.entry(c).or_default();
+            node.shards.insert(shard);
+        }
+        node.value = Some(value);
+    }
+
+    /// Returns the value stored under `key`.
+    pub fn get(&self, key: &str) -> Option<&V> {
+        self.node(key)?.value.as_ref()
+    }
+
+    /// Returns, in ascending order, every shard holding a key that starts with `prefix`.
+    pub fn shard_for_prefix(&self, prefix: &str) -> Vec<u64> {
+        self.node(prefix)
+            .map(|node| node.shards.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    fn node(&self, key: &str) -> Option<&Node<V>> {
+        key.chars()
+            .try_fold(&self.root, |node, c| node.children.get(&c))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BuildSeaHasher;
+
+    #[test]
+    fn stores_and_retrieves_values() {
+        let mut trie = ShardedTrie::new(16, 2, BuildSeaHasher);
+        trie.insert("apple", 1);
+        trie.insert("app", 2);
+
+        assert_eq!(trie.get("apple"), Some(&1));
+        assert_eq!(trie.get("app"), Some(&2));
+        assert_eq!(trie.get("ap"), None);
+        assert_eq!(trie.get("banana"), None);
+    }
+
+    #[test]
+    fn prefix_routes_to_holding_shards() {
+        let mut trie = ShardedTrie::new(64, 3, BuildSeaHasher);
+        let keys = ["user/1", "user/2", "usa", "org/1", "org/2"];
+        for key in keys {
+            trie.insert(key, ());
+        }
+
+        // Keys sharing the routing prefix share a shard.
+        assert_eq!(trie.shard_for_key("user/1"), trie.shard_for_key("use"));
+        assert_eq!(
+            trie.shard_for_prefix("user/"),
+            vec![trie.shard_for_key("use")]
+        );
+
+        let mut expected = keys
+            .iter()
+            .filter(|k| k.starts_with("us"))
+            .map(|k| trie.shard_for_key(k))
+            .collect::<Vec<_>>();
+        expected.sort();
+        expected.dedup();
+        assert_eq!(trie.shard_for_prefix("us"), expected);
+
+        assert_eq!(trie.shard_for_prefix("nope"), Vec::<u64>::new());
+    }
+}
@@ -0,0 +1,96 @@
+/// Faro Sharding for a key hash with only 8 bits of randomness.
+///
+/// See [shard_for_16bit] for how the chain works and the resulting distribution quality.
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub fn shard_for_8bit(key_hash: u8, total_destinations: u64) -> u64 {
+    truncated_chain(u32::from(key_hash), 8, total_destinations)
+}
+
+/// Faro Sharding for a key hash with only 16 bits of randomness.
+///
+/// The chain state never leaves the 16-bit domain. Each step advances the state by an odd constant and mixes it with masked multiplies, and the key moves to step `n` when the mixed value falls in the lowest `1 / (n + 1)` of the domain. The stability guarantee is the same as [crate::shard_with_hasher], but results differ from it.
+///
+/// With so few input values, balance degrades as destinations grow. Measured over every possible key hash:
+///
+/// | Destinations | 8-bit entropy efficiency | 16-bit entropy efficiency |
+/// |-------------:|-------------------------:|--------------------------:|
+/// | 4            | 0.9996                   | 1.0000                    |
+/// | 8            | 0.9985                   | 1.0000                    |
+/// | 16           | 0.9964                   | 1.0000                    |
+/// | 32           | 0.9931                   | 0.9999                    |
+///
+/// Beyond `2^bits` destinations the later shards can never be chosen. Prefer the 64-bit chain whenever a full hash is available.
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub fn shard_for_16bit(key_hash: u16, total_destinations: u64) -> u64 {
+    truncated_chain(u32::from(key_hash), 16, total_destinations)
+}
+
+fn truncated_chain(key_hash: u32, bits: u32, total_destinations: u64) -> u64 {
+    assert_ne!(total_destinations, 0, "total_destinations must be > 0");
+
+    let mask = (1 << bits) - 1;
+    let shift = bits / 2;
+
+    let mut final_shard = 0;
+    let mut state = key_hash & mask;
+    for n in 1..total_destinations {
+        state = state.wrapping_add(0x9e37) & mask;
+
+        let mut z = state;
+        z = ((z ^ (z >> shift)).wrapping_mul(0x85eb)) & mask;
+        z = ((z ^ (z >> shift)).wrapping_mul(0xc2b3)) & mask;
+        z ^= z >> shift;
+
+        if (u64::from(z) * (n + 1)) >> bits == 0 {
+            final_shard = n;
+        }
+    }
+
+    final_shard
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::entropy_efficiency;
+
+    fn efficiency(bits: u32, total_destinations: u64) -> f64 {
+        let mut counts = vec![0; total_destinations as usize];
+        for key_hash in 0..1 << bits {
+            counts[truncated_chain(key_hash, bits, total_destinations) as usize] += 1;
+        }
+        entropy_efficiency(&counts)
+    }
+
+    #[test]
+    fn does_not_move_between_existing_destinations() {
+        for key_hash in 0..=u8::MAX {
+            let mut last_shard = shard_for_8bit(key_hash, 1);
+            for n in 2..=64 {
+                let shard = shard_for_8bit(key_hash, n);
+                assert!(shard == last_shard || shard == n - 1);
+                last_shard = shard;
+            }
+        }
+    }
+
+    #[test]
+    fn documented_efficiency_holds() {
+        let documented = [
+            (4, 0.9996, 1.0000),
+            (8, 0.9985, 1.0000),
+            (16, 0.9964, 1.0000),
+            (32, 0.9931, 0.9999),
+        ];
+        for (n, eight, sixteen) in documented {
+            assert!((efficiency(8, n) - eight).abs() < 0.00005, "8-bit {n}");
+            assert!((efficiency(16, n) - sixteen).abs() < 0.00005, "16-bit {n}");
+        }
+    }
+}
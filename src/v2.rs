@@ -0,0 +1,80 @@
+//! Faro Sharding with an unbiased move decision.
+//!
+//! The root functions such as [crate::shard_with_hasher] decide whether step `n` moves a key with `hash % (n + 1) == 0`. Since `2^64` is not a multiple of `n + 1`, that favors some remainders over others by up to `n / 2^64`. This version reduces each hash with Lemire's multiply-shift and rejects the few hashes that would be biased, rehashing to draw again, so every step moves a key with probability exactly `1 / (n + 1)`.
+//!
+//! Rejection changes which steps a key moves on, so most keys land on a different shard here than with the root functions. Data sharded with one cannot be read back with the other; record which version wrote it, for example as [crate::Algorithm::FaroV2].
+
+use core::hash::*;
+
+/// [shard_with_hasher] using the default hasher of [crate::shard_for].
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+#[cfg(feature = "seahash")]
+pub fn shard_for(key: impl Hash, total_destinations: u64) -> u64 {
+    shard_with_hasher(
+        key,
+        total_destinations,
+        &crate::seahash::StableSeaHasher::new(),
+    )
+}
+
+/// Version 2 of [crate::shard_with_hasher].
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub fn shard_with_hasher(
+    key: impl Hash,
+    total_destinations: u64,
+    hasher: &impl BuildHasher,
+) -> u64 {
+    shard_for_key_hash(hasher.hash_one(key), total_destinations, hasher)
+}
+
+/// Version 2 of [crate::shard_for_key_hash].
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub fn shard_for_key_hash(
+    key_hash: u64,
+    total_destinations: u64,
+    hasher: &impl BuildHasher,
+) -> u64 {
+    assert_ne!(total_destinations, 0, "total_destinations must be > 0");
+
+    let mut shard = 0;
+    let mut last_hash = key_hash;
+    for n in 1..total_destinations {
+        let range = n + 1;
+        // 2^64 mod range. Products whose low half falls below this are the biased ones.
+        let threshold = range.wrapping_neg() % range;
+
+        let high = loop {
+            last_hash = hasher.hash_one(last_hash);
+            let product = u128::from(last_hash) * u128::from(range);
+            if (product as u64) >= threshold {
+                break (product >> 64) as u64;
+            }
+        };
+
+        if high == 0 {
+            shard = n;
+        }
+    }
+    shard
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pinned_values() {
+        // Do not change these values. See `pinning_default_shard`.
+        assert_eq!(shard_for("foo", 73), 60);
+        assert_eq!(shard_for("bar", 73), 39);
+    }
+}
@@ -0,0 +1,196 @@
+use crate::{shard_with_hasher, RoutingTable, ShardState};
+use std::hash::*;
+
+/// Keys are sharded onto a fixed number of virtual shards, which are in turn sharded onto a changeable number of nodes.
+///
+/// The vshard to node mapping is precomputed, so looking up a key's node costs one hash chain over the vshard count plus an array index. Vshards can also be moved between nodes by hand with [VirtualShards::assign].
+#[derive(Clone, Debug)]
+pub struct VirtualShards<S> {
+    nodes: Vec<u64>,
+    states: Vec<ShardState>,
+    node_count: u64,
+    version: u64,
+    hasher: S,
+}
+
+impl<S: BuildHasher> VirtualShards<S> {
+    /// A reasonable vshard count for most clusters.
+    pub const DEFAULT_VSHARD_COUNT: u64 = 4096;
+
+    /// Creates `vshard_count` vshards spread over `node_count` nodes.
+    ///
+    /// # Panics
+    ///
+    /// If vshard_count == 0 or node_count == 0.
+    pub fn new(vshard_count: u64, node_count: u64, hasher: S) -> Self {
+        assert_ne!(vshard_count, 0, "vshard_count must be > 0");
+        assert_ne!(node_count, 0, "node_count must be > 0");
+
+        let mut states = (0..vshard_count)
+            .map(|vshard| ShardState::new(vshard, &hasher))
+            .collect::<Vec<_>>();
+        let nodes = states
+            .iter_mut()
+            .map(|state| state.extend_to(node_count, &hasher))
+            .collect();
+
+        VirtualShards {
+            nodes,
+            states,
+            node_count,
+            version: 0,
+            hasher,
+        }
+    }
+
+    /// Number of vshards.
+    pub fn vshard_count(&self) -> u64 {
+        self.nodes.len() as u64
+    }
+
+    /// Number of nodes.
+    pub fn node_count(&self) -> u64 {
+        self.node_count
+    }
+
+    /// The topology version. Starts at 0 and increases with every change to the table.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Returns the vshard for `key`. Never changes for the life of this value.
+    pub fn vshard_for(&self, key: impl Hash) -> u64 {
+        shard_with_hasher(key, self.vshard_count(), &self.hasher)
+    }
+
+    /// Returns the node for `key`.
+    pub fn node_for(&self, key: impl Hash) -> u64 {
+        self.nodes[self.vshard_for(key) as usize]
+    }
+
+    /// Returns the node `vshard` is on.
+    ///
+    /// # Panics
+    ///
+    /// If vshard is out of range.
+    pub fn node_of_vshard(&self, vshard: u64) -> u64 {
+        self.nodes[vshard as usize]
+    }
+
+    /// Iterates the vshards on `node`.
+    pub fn vshards_on(&self, node: u64) -> impl Iterator<Item = u64> + '_ {
+        (0..self.vshard_count()).filter(move |&v| self.nodes[v as usize] == node)
+    }
+
+    /// The node of every vshard, indexed by vshard.
+    pub fn table(&self) -> &[u64] {
+        &self.nodes
+    }
+
+    /// A snapshot of the table at the current [VirtualShards::version], for exporting.
+    ///
+    /// # Panics
+    ///
+    /// If node_count does not fit in a `u32`.
+    pub fn routing_table(&self) -> RoutingTable {
+        RoutingTable::new(self.version, self.node_count, self.nodes.clone())
+    }
+
+    /// Moves `vshard` to `node`.
+    ///
+    /// # Panics
+    ///
+    /// If vshard or node is out of range.
+    pub fn assign(&mut self, vshard: u64, node: u64) {
+        assert!(node < self.node_count, "node must be < node_count");
+        if self.nodes[vshard as usize] != node {
+            self.nodes[vshard as usize] = node;
+            self.version += 1;
+        }
+    }
+
+    /// Changes the number of nodes.
+    ///
+    /// Growing only moves vshards onto the new nodes. Shrinking only moves vshards off the removed nodes. Other vshards, including any moved with [VirtualShards::assign], stay put.
+    ///
+    /// # Panics
+    ///
+    /// If node_count == 0.
+    pub fn set_node_count(&mut self, node_count: u64) {
+        assert_ne!(node_count, 0, "node_count must be > 0");
+        if node_count == self.node_count {
+            return;
+        }
+
+        if node_count < self.node_count {
+            for (vshard, state) in self.states.iter_mut().enumerate() {
+                *state = ShardState::new(vshard as u64, &self.hasher);
+                let shard = state.extend_to(node_count, &self.hasher);
+
+                let node = &mut self.nodes[vshard];
+                if *node >= node_count {
+                    *node = shard;
+                }
+            }
+        } else {
+            for (state, node) in self.states.iter_mut().zip(&mut self.nodes) {
+                let before = state.shard();
+                let after = state.extend_to(node_count, &self.hasher);
+                if after != before {
+                    *node = after;
+                }
+            }
+        }
+
+        self.node_count = node_count;
+        self.version += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{shard_for, BuildSeaHasher};
+
+    #[test]
+    fn agrees_with_two_faro_layers() {
+        let shards = VirtualShards::new(256, 7, BuildSeaHasher);
+        for key in 0..1_000u64 {
+            let vshard = shard_for(key, 256);
+            assert_eq!(shards.vshard_for(key), vshard);
+            assert_eq!(shards.node_for(key), shard_for(vshard, 7));
+        }
+    }
+
+    #[test]
+    fn resizing_only_moves_affected_vshards() {
+        let mut shards = VirtualShards::new(1024, 5, BuildSeaHasher);
+        shards.assign(0, 3);
+        let before = shards.table().to_vec();
+
+        shards.set_node_count(8);
+        for (before, after) in before.iter().zip(shards.table()) {
+            assert!(before == after || *after >= 5);
+        }
+        assert!(shards.vshards_on(7).count() > 0);
+
+        let before = shards.table().to_vec();
+        shards.set_node_count(6);
+        for (before, after) in before.iter().zip(shards.table()) {
+            assert!(before == after || *before >= 6);
+            assert!(*after < 6);
+        }
+    }
+
+    #[test]
+    fn shrinking_then_growing_is_plain_faro() {
+        let mut shards = VirtualShards::new(512, 9, BuildSeaHasher);
+        shards.set_node_count(4);
+        shards.set_node_count(11);
+
+        assert_eq!(
+            shards.table(),
+            VirtualShards::new(512, 11, BuildSeaHasher).table()
+        );
+    }
+}
@@ -0,0 +1,108 @@
+//! `wasm-bindgen` exports, so browsers and edge workers compute exactly the shards the server does.
+//!
+//! Every export uses the default hasher of [crate::shard_for]. Destination counts and shards are `u64`, which JS sees as `BigInt`. A destination count of 0 throws instead of panicking.
+//!
+//! [golden_vectors] lists pinned `(key, locations, shard)` triples. Checking them from JS against [shard_for] proves a given build agrees with the Rust implementation.
+
+use wasm_bindgen::prelude::*;
+
+/// [crate::shard_for] of a string key. Agrees with `shard_for(key: &str, ..)` and `shard_for(key: String, ..)` in Rust.
+#[wasm_bindgen(js_name = shardFor)]
+pub fn shard_for(key: &str, total_destinations: u64) -> Result<u64, JsError> {
+    check(total_destinations)?;
+    Ok(crate::shard_for(key, total_destinations))
+}
+
+/// [crate::shard_for_bytes] of a `Uint8Array` key.
+#[wasm_bindgen(js_name = shardForBytes)]
+pub fn shard_for_bytes(key: &[u8], total_destinations: u64) -> Result<u64, JsError> {
+    check(total_destinations)?;
+    Ok(crate::shard_for_bytes(key, total_destinations))
+}
+
+/// [shard_for] of every key, returned as a `BigUint64Array` in the same order.
+#[wasm_bindgen(js_name = shardForMany)]
+pub fn shard_for_many(keys: Vec<String>, total_destinations: u64) -> Result<Vec<u64>, JsError> {
+    check(total_destinations)?;
+    Ok(crate::shard_for_many_vec(
+        keys,
+        total_destinations,
+        &crate::seahash::StableSeaHasher::new(),
+    ))
+}
+
+fn check(total_destinations: u64) -> Result<(), JsError> {
+    if total_destinations == 0 {
+        return Err(JsError::new("total_destinations must be > 0"));
+    }
+    Ok(())
+}
+
+/// A pinned result of [shard_for]. See [golden_vectors].
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GoldenVector {
+    /// The key.
+    pub key: String,
+    /// The destination count.
+    pub locations: u64,
+    /// The shard every implementation must return.
+    pub shard: u64,
+}
+
+// Do not change these values. See `pinning_default_shard`.
+const GOLDEN_VECTORS: &[(&str, u64, u64)] = &[
+    ("foo", 1, 0),
+    ("foo", 4, 2),
+    ("foo", 50, 49),
+    ("bar", 73, 14),
+    ("", 73, 52),
+    ("user:42", 1_000, 99),
+    ("größe", 16, 9),
+];
+
+/// Pinned `(key, locations, shard)` triples for checking parity with the Rust implementation.
+#[wasm_bindgen(js_name = goldenVectors)]
+pub fn golden_vectors() -> Vec<GoldenVector> {
+    GOLDEN_VECTORS
+        .iter()
+        .map(|&(key, locations, shard)| GoldenVector {
+            key: key.to_string(),
+            locations,
+            shard,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn golden_vectors_match_shard_for() {
+        for vector in golden_vectors() {
+            assert_eq!(
+                crate::shard_for(&vector.key, vector.locations),
+                vector.shard,
+                "{:?}",
+                vector
+            );
+        }
+    }
+
+    #[test]
+    fn exports_agree_with_the_crate() {
+        let keys = ["foo", "bar", "baz"].map(String::from).to_vec();
+        assert_eq!(
+            shard_for_many(keys.clone(), 73).unwrap(),
+            keys.iter()
+                .map(|k| crate::shard_for(k, 73))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(shard_for("foo", 50).unwrap(), 49);
+        assert_eq!(
+            shard_for_bytes(b"foo", 73).unwrap(),
+            crate::shard_for_bytes(b"foo", 73)
+        );
+    }
+}
@@ -0,0 +1,396 @@
+#[cfg(feature = "serde")]
+use crate::format_version::FormatVersion;
+use crate::skip_ahead::skip_ahead_from_key_hash;
+#[cfg(feature = "serde")]
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use std::hash::*;
+
+/// Shards keys across destinations in proportion to their weights.
+///
+/// Each unit of weight is a slot, and keys are assigned to slots with [crate::shard_with_hasher_fast]. Slots are only ever appended, so adding a destination or growing one only moves keys onto the destination that gained weight. Lowering a weight tombstones slots instead of deleting them, and a key on a tombstoned slot is rehashed until it lands on a live one, as in [crate::TombstoneTopology]. See [WeightedSharder::set_weight].
+///
+/// Every slot ever added is kept in memory, tombstoned or not, so the slot count is limited to [WeightedSharder::MAX_SLOTS]. Weights of a few hundred per destination are plenty to balance load precisely.
+///
+/// The mapping depends on the order weight was added, not just the final weights. `new(&[2, 1])` and `new(&[1, 1])` followed by growing destination 0 to 2 are different mappings.
+///
+/// With the `serde` feature, the sharder is persisted as its weights, its slots as `(destination, count)` runs in the order they were added, and its tombstones. Loading checks that they agree.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "PersistedWeighted<'static>"))]
+pub struct WeightedSharder<S> {
+    weights: Vec<u64>,
+    owners: Vec<usize>,
+    tombstones: BTreeSet<u64>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    hasher: S,
+}
+
+/// The persisted fields of a [WeightedSharder].
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct PersistedWeighted<'a> {
+    version: FormatVersion,
+    weights: Cow<'a, [u64]>,
+    slots: Vec<(usize, u64)>,
+    #[serde(default, skip_serializing_if = "<[u64]>::is_empty")]
+    tombstones: Cow<'a, [u64]>,
+}
+
+#[cfg(feature = "serde")]
+impl<S> serde::Serialize for WeightedSharder<S> {
+    fn serialize<Ser: serde::Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        PersistedWeighted {
+            version: FormatVersion,
+            weights: Cow::Borrowed(&self.weights),
+            slots: self
+                .owners
+                .chunk_by(|a, b| a == b)
+                .map(|run| (run[0], run.len() as u64))
+                .collect(),
+            tombstones: self.tombstones.iter().copied().collect(),
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<S: Default> TryFrom<PersistedWeighted<'_>> for WeightedSharder<S> {
+    type Error = String;
+
+    fn try_from(persisted: PersistedWeighted<'_>) -> Result<Self, String> {
+        let weights = persisted.weights.into_owned();
+        let mut owners = Vec::new();
+        for &(destination, count) in &persisted.slots {
+            if destination >= weights.len() {
+                return Err(format!("slot owner {} out of range", destination));
+            }
+            if count > Self::MAX_SLOTS - owners.len() as u64 {
+                return Err(format!("slot count must be <= {}", Self::MAX_SLOTS));
+            }
+            owners.extend(std::iter::repeat_n(destination, count as usize));
+        }
+
+        let tombstones = persisted
+            .tombstones
+            .iter()
+            .copied()
+            .collect::<BTreeSet<_>>();
+        if let Some(&slot) = tombstones.last().filter(|&&s| s >= owners.len() as u64) {
+            return Err(format!("tombstone {} out of range", slot));
+        }
+
+        let mut live = vec![0; weights.len()];
+        for (slot, &owner) in owners.iter().enumerate() {
+            if !tombstones.contains(&(slot as u64)) {
+                live[owner] += 1;
+            }
+        }
+        if live != weights {
+            return Err("weights do not match the live slots".to_string());
+        }
+        if weights.iter().all(|&w| w == 0) {
+            return Err("at least one weight must be > 0".to_string());
+        }
+
+        Ok(WeightedSharder {
+            weights,
+            owners,
+            tombstones,
+            hasher: S::default(),
+        })
+    }
+}
+
+impl<S> WeightedSharder<S> {
+    /// The most slots a sharder can hold, tombstoned ones included.
+    pub const MAX_SLOTS: u64 = crate::slots::MAX_SLOTS;
+}
+
+impl<S: BuildHasher> WeightedSharder<S> {
+    /// Creates a sharder with one destination per weight.
+    ///
+    /// # Panics
+    ///
+    /// If every weight is 0, or the weights add up to more than [WeightedSharder::MAX_SLOTS].
+    pub fn new(weights: &[u64], hasher: S) -> Self {
+        let mut sharder = WeightedSharder {
+            weights: Vec::new(),
+            owners: Vec::new(),
+            tombstones: BTreeSet::new(),
+            hasher,
+        };
+        for &weight in weights {
+            sharder.push(weight);
+        }
+
+        assert!(
+            !sharder.owners.is_empty(),
+            "at least one weight must be > 0"
+        );
+        sharder
+    }
+
+    /// Adds a new destination with `weight`, returning its index.
+    ///
+    /// # Panics
+    ///
+    /// If this would make more than [WeightedSharder::MAX_SLOTS] slots.
+    pub fn push(&mut self, weight: u64) -> u64 {
+        crate::slots::check_slots(self.owners.len(), weight);
+        let destination = self.weights.len();
+        self.weights.push(weight);
+        self.owners
+            .extend(std::iter::repeat_n(destination, weight as usize));
+        destination as u64
+    }
+
+    /// Adds `additional` weight to an existing destination.
+    ///
+    /// # Panics
+    ///
+    /// If destination is out of range, or this would make more than [WeightedSharder::MAX_SLOTS] slots.
+    pub fn grow(&mut self, destination: u64, additional: u64) {
+        crate::slots::check_slots(self.owners.len(), additional);
+        self.weights[destination as usize] += additional;
+        self.owners.extend(std::iter::repeat_n(
+            destination as usize,
+            additional as usize,
+        ));
+    }
+
+    /// Changes the weight of `destination` at runtime, moving only the proportional slice of keys.
+    ///
+    /// Changing the weight from `w` to `w'`, with `W` the total weight before and `W'` after, moves the destination's share of keys from `w / W` to `w' / W'`. Only the difference moves:
+    ///
+    /// - Raising the weight first revives the destination's own tombstoned slots, in the reverse of the order they were tombstoned, and then adds the rest with [WeightedSharder::grow]. About `w' / W' - w / W` of keys move, all onto `destination`.
+    /// - Lowering the weight tombstones the destination's most recently added live slots. About `w / W - w' / W'` of keys move, all off `destination`, and they spread over the other destinations in proportion to their weights.
+    ///
+    /// Raising a weight back undoes lowering it exactly, so changing weights back and forth does not add slots. This matches [crate::Topology::set_weight], so the two agree after the same changes.
+    ///
+    /// # Panics
+    ///
+    /// If destination is out of range, this would make every weight 0, or raising the weight would make more than [WeightedSharder::MAX_SLOTS] slots.
+    pub fn set_weight(&mut self, destination: u64, weight: u64) {
+        let current = self.weights[destination as usize];
+        if weight < current {
+            let total: u64 = self.weights.iter().sum();
+            assert_ne!(
+                total - (current - weight),
+                0,
+                "at least one weight must be > 0"
+            );
+        }
+
+        let slots = self
+            .owners
+            .iter()
+            .enumerate()
+            .map(|(slot, &owner)| (owner as u64, !self.tombstones.contains(&(slot as u64))));
+        let reweigh = crate::slots::reweigh(slots, destination, current, weight);
+        crate::slots::check_slots(self.owners.len(), reweigh.added);
+        for slot in reweigh.toggled {
+            let slot = slot as u64;
+            if !self.tombstones.remove(&slot) {
+                self.tombstones.insert(slot);
+            }
+        }
+        self.weights[destination as usize] = weight - reweigh.added;
+        self.grow(destination, reweigh.added);
+    }
+
+    /// The weight of every destination.
+    pub fn weights(&self) -> &[u64] {
+        &self.weights
+    }
+
+    /// Returns the destination for `key`.
+    pub fn shard_for(&self, key: impl Hash) -> u64 {
+        let key_hash = self.hasher.hash_one(key);
+        let total = self.owners.len() as u64;
+
+        let mut slot = skip_ahead_from_key_hash(key_hash, total, &self.hasher);
+        let mut attempt = 0u64;
+        while self.tombstones.contains(&slot) {
+            attempt += 1;
+            let rehash = self.hasher.hash_one((key_hash, attempt));
+            slot = skip_ahead_from_key_hash(rehash, total, &self.hasher);
+        }
+        self.owners[slot as usize] as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BuildSeaHasher;
+
+    #[test]
+    fn distributes_by_weight() {
+        let sharder = WeightedSharder::new(&[1, 2, 0, 5], BuildSeaHasher);
+
+        let mut counts = [0; 4];
+        for key in 0..80_000 {
+            counts[sharder.shard_for(key) as usize] += 1;
+        }
+
+        assert_eq!(counts[2], 0);
+        for (count, expected) in [
+            (counts[0], 10_000.),
+            (counts[1], 20_000.),
+            (counts[3], 50_000.),
+        ] {
+            assert!((count as f64 / expected - 1.).abs() < 0.05, "{counts:?}");
+        }
+    }
+
+    #[test]
+    fn adding_a_destination_only_moves_keys_onto_it() {
+        let mut sharder = WeightedSharder::new(&[3, 1, 2], BuildSeaHasher);
+        let before = (0..10_000u64)
+            .map(|k| sharder.shard_for(k))
+            .collect::<Vec<_>>();
+
+        let added = sharder.push(4);
+        for (key, before) in (0..10_000u64).zip(before) {
+            let after = sharder.shard_for(key);
+            assert!(after == before || after == added);
+        }
+    }
+
+    #[test]
+    fn growing_a_destination_only_moves_keys_onto_it() {
+        let mut sharder = WeightedSharder::new(&[3, 1, 2], BuildSeaHasher);
+        let before = (0..10_000u64)
+            .map(|k| sharder.shard_for(k))
+            .collect::<Vec<_>>();
+
+        sharder.grow(1, 2);
+        assert_eq!(sharder.weights(), &[3, 3, 2]);
+
+        let mut moved = 0;
+        for (key, before) in (0..10_000u64).zip(before) {
+            let after = sharder.shard_for(key);
+            assert!(after == before || after == 1);
+            moved += usize::from(after != before);
+        }
+        assert!(moved > 0);
+    }
+
+    fn moves(before: &[u64], sharder: &WeightedSharder<BuildSeaHasher>) -> Vec<(u64, u64)> {
+        (0..before.len() as u64)
+            .zip(before)
+            .map(|(key, &before)| (before, sharder.shard_for(key)))
+            .filter(|(before, after)| before != after)
+            .collect()
+    }
+
+    #[test]
+    fn raising_a_weight_moves_the_proportional_slice_onto_it() {
+        let mut sharder = WeightedSharder::new(&[4, 4, 4, 4], BuildSeaHasher);
+        let before = (0..20_000u64)
+            .map(|k| sharder.shard_for(k))
+            .collect::<Vec<_>>();
+
+        sharder.set_weight(1, 8);
+        assert_eq!(sharder.weights(), &[4, 8, 4, 4]);
+
+        let moves = moves(&before, &sharder);
+        assert!(moves.iter().all(|&(_, after)| after == 1));
+        // 8 / 20 - 4 / 16 of keys.
+        let expected = 20_000. * (8. / 20. - 4. / 16.);
+        assert!(
+            (moves.len() as f64 / expected - 1.).abs() < 0.1,
+            "{}",
+            moves.len()
+        );
+    }
+
+    #[test]
+    fn lowering_a_weight_only_moves_keys_off_it() {
+        let mut sharder = WeightedSharder::new(&[4, 4, 4, 4], BuildSeaHasher);
+        let before = (0..20_000u64)
+            .map(|k| sharder.shard_for(k))
+            .collect::<Vec<_>>();
+
+        sharder.set_weight(2, 1);
+        assert_eq!(sharder.weights(), &[4, 4, 1, 4]);
+
+        let moves = moves(&before, &sharder);
+        assert!(moves
+            .iter()
+            .all(|&(before, after)| before == 2 && after != 2));
+        // 4 / 16 - 1 / 13 of keys, spread evenly over the other three.
+        let expected = 20_000. * (4. / 16. - 1. / 13.);
+        assert!(
+            (moves.len() as f64 / expected - 1.).abs() < 0.1,
+            "{}",
+            moves.len()
+        );
+        for destination in [0, 1, 3] {
+            let count = moves
+                .iter()
+                .filter(|&&(_, after)| after == destination)
+                .count();
+            assert!(
+                (count as f64 / (expected / 3.) - 1.).abs() < 0.15,
+                "{}",
+                count
+            );
+        }
+    }
+
+    #[test]
+    fn raising_a_weight_revives_tombstoned_slots() {
+        let mut sharder = WeightedSharder::new(&[4, 4, 4], BuildSeaHasher);
+        let before = (0..10_000u64)
+            .map(|k| sharder.shard_for(k))
+            .collect::<Vec<_>>();
+
+        for _ in 0..10 {
+            sharder.set_weight(1, 1);
+            sharder.set_weight(1, 4);
+        }
+        assert_eq!(sharder.owners.len(), 12);
+        assert!(sharder.tombstones.is_empty());
+        assert!(moves(&before, &sharder).is_empty());
+
+        sharder.set_weight(1, 2);
+        let lowered = (0..10_000u64)
+            .map(|k| sharder.shard_for(k))
+            .collect::<Vec<_>>();
+        sharder.set_weight(1, 6);
+        assert_eq!(sharder.owners.len(), 14);
+        assert!(moves(&lowered, &sharder)
+            .iter()
+            .all(|&(_, after)| after == 1));
+    }
+
+    #[test]
+    fn weight_changes_match_topology() {
+        let mut topology = crate::Topology::new(&[3, 1, 2], BuildSeaHasher);
+        let mut weighted = WeightedSharder::new(&[3, 1, 2], BuildSeaHasher);
+        for (destination, weight) in [(0, 1), (1, 4), (2, 0), (0, 2), (2, 3), (1, 2), (1, 5)] {
+            topology.set_weight(destination, weight);
+            weighted.set_weight(destination, weight);
+        }
+
+        for key in 0..1_000u64 {
+            assert_eq!(topology.current_shard(key), weighted.shard_for(key));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "slot count must be <= 16777216")]
+    fn limits_slot_count() {
+        let mut sharder = WeightedSharder::new(&[10], BuildSeaHasher);
+        sharder.push(1 << 40);
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one weight must be > 0")]
+    fn cannot_remove_all_weight() {
+        let mut sharder = WeightedSharder::new(&[0, 2], BuildSeaHasher);
+        sharder.set_weight(1, 0);
+    }
+}
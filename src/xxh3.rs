@@ -0,0 +1,156 @@
+//! Faro Sharding with XXH3, for agreement with other languages' XXH3 implementations.
+//!
+//! Every hash is 64-bit XXH3 with seed 0. A key given as bytes is hashed as those bytes exactly, and each step of the chain hashes the previous hash as 8 little-endian bytes. [shard_for] hashes keys with [Hash], which may add prefixes or terminators, so prefer [shard_for_bytes] across languages.
+
+use crate::{shard_bytes_with_hasher, shard_with_hasher};
+use core::hash::*;
+use xxhash_rust::xxh3::Xxh3;
+
+/// [BuildHasher] for 64-bit XXH3 with seed 0.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BuildXxh3Hasher;
+
+impl BuildHasher for BuildXxh3Hasher {
+    type Hasher = Xxh3Hasher;
+
+    fn build_hasher(&self) -> Self::Hasher {
+        Xxh3Hasher(Xxh3::new())
+    }
+}
+
+impl crate::StableBuildHasher for BuildXxh3Hasher {}
+
+/// [Hasher] built by [BuildXxh3Hasher].
+///
+/// Integers are written as little-endian bytes, so hashes agree across platforms.
+#[derive(Clone, Default)]
+pub struct Xxh3Hasher(Xxh3);
+
+impl core::fmt::Debug for Xxh3Hasher {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Xxh3Hasher").finish_non_exhaustive()
+    }
+}
+
+impl Hasher for Xxh3Hasher {
+    fn finish(&self) -> u64 {
+        self.0.digest()
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    fn write_u8(&mut self, n: u8) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_u16(&mut self, n: u16) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_u128(&mut self, n: u128) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_usize(&mut self, n: usize) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_i8(&mut self, n: i8) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_i16(&mut self, n: i16) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_i32(&mut self, n: i32) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_i64(&mut self, n: i64) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_i128(&mut self, n: i128) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_isize(&mut self, n: isize) {
+        self.write(&n.to_le_bytes())
+    }
+}
+
+/// [shard_with_hasher] using [BuildXxh3Hasher].
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub fn shard_for(key: impl Hash, total_destinations: u64) -> u64 {
+    shard_with_hasher(key, total_destinations, &BuildXxh3Hasher)
+}
+
+/// [shard_bytes_with_hasher] using [BuildXxh3Hasher].
+///
+/// # Panics
+///
+/// If total_destinations == 0.
+pub fn shard_for_bytes(bytes: &[u8], total_destinations: u64) -> u64 {
+    shard_bytes_with_hasher(bytes, total_destinations, &BuildXxh3Hasher)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xxhash_rust::xxh3::xxh3_64;
+
+    #[test]
+    fn pinned_values() {
+        // Do not change these values. See `pinning_default_shard`.
+        assert_eq!(shard_for_bytes(b"foo", 73), 34);
+        assert_eq!(shard_for_bytes(b"", 73), 37);
+        assert_eq!(shard_for("foo", 73), 15);
+    }
+
+    #[test]
+    fn writes_integers_little_endian() {
+        let hash = |write: fn(&mut Xxh3Hasher)| {
+            let mut hasher = BuildXxh3Hasher.build_hasher();
+            write(&mut hasher);
+            hasher.finish()
+        };
+        let le = xxh3_64(&0x0102_0304_0506_0708u64.to_le_bytes());
+        assert_eq!(hash(|h| h.write_u64(0x0102_0304_0506_0708)), le);
+        assert_eq!(hash(|h| h.write_i64(0x0102_0304_0506_0708)), le);
+        assert_eq!(hash(|h| h.write_u16(0x0102)), xxh3_64(&[2, 1]));
+        assert_eq!(hash(|h| h.write_u128(1)), xxh3_64(&1u128.to_le_bytes()));
+    }
+
+    #[test]
+    fn matches_the_documented_contract() {
+        for key in [
+            &b""[..],
+            b"foo",
+            b"a longer key that spans more than one stripe",
+        ] {
+            let mut last_hash = xxh3_64(key);
+            let mut shard = 0;
+            for n in 1..1000u64 {
+                last_hash = xxh3_64(&last_hash.to_le_bytes());
+                if last_hash.is_multiple_of(n + 1) {
+                    shard = n;
+                }
+            }
+            assert_eq!(shard_for_bytes(key, 1000), shard);
+        }
+    }
+}
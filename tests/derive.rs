@@ -0,0 +1,63 @@
+//! `#[derive(ShardKey)]` encodes by field name, so it is tested from outside the crate like its users.
+#![cfg(feature = "derive")]
+
+use faro_sharding::{shard_key_bytes, ShardKey};
+
+#[derive(ShardKey)]
+struct Order {
+    tenant: String,
+    id: u64,
+}
+
+#[derive(ShardKey)]
+#[allow(dead_code)]
+struct Reordered {
+    id: u64,
+    #[shard_key(rename = "tenant")]
+    tenant_name: String,
+    #[shard_key(skip)]
+    cached: f64,
+}
+
+#[derive(ShardKey)]
+#[allow(dead_code)]
+struct Wrapper<T>(T, #[shard_key(skip)] u8);
+
+#[derive(ShardKey)]
+enum Event {
+    Created { id: u64 },
+    Deleted(u64),
+    Cleared,
+}
+
+#[test]
+fn field_order_and_renames_do_not_matter() {
+    let order = Order {
+        tenant: "acme".into(),
+        id: 7,
+    };
+    let reordered = Reordered {
+        id: 7,
+        tenant_name: "acme".into(),
+        cached: 1.5,
+    };
+    assert_eq!(shard_key_bytes(&order), shard_key_bytes(&reordered));
+    assert_eq!(
+        shard_key_bytes(&order),
+        shard_key_bytes(&("id", 7u64, "tenant", "acme"))
+    );
+}
+
+#[test]
+fn tuple_structs_and_enums() {
+    assert_eq!(shard_key_bytes(&Wrapper(3u32, 9)), shard_key_bytes(&3u32));
+    assert_eq!(
+        shard_key_bytes(&Event::Created { id: 1 }),
+        shard_key_bytes(&("Created", "id", 1u64))
+    );
+    assert_eq!(
+        shard_key_bytes(&Event::Deleted(1)),
+        shard_key_bytes(&("Deleted", 1u64))
+    );
+    assert_eq!(shard_key_bytes(&Event::Cleared), shard_key_bytes("Cleared"));
+}
@@ -0,0 +1,50 @@
+//! The core paths must never allocate. This runs them under an allocator that counts.
+#![cfg(feature = "seahash")]
+
+use faro_sharding::*;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hash::BuildHasher;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+#[test]
+fn core_paths_do_not_allocate() {
+    let hasher = seahash::StableSeaHasher::new();
+    let keys = (0..256u64).collect::<Vec<_>>();
+    let key_hashes = keys.iter().map(|k| hasher.hash_one(k)).collect::<Vec<_>>();
+    let mut out = [0u64; 256];
+
+    let before = ALLOCATIONS.load(Ordering::SeqCst);
+
+    for &key in &keys {
+        shard_for(key, 1_000);
+        shard_with_hasher(key, 1_000, &hasher);
+        let _ = try_shard_with_hasher(key, 1_000, &hasher);
+    }
+    shard_for_key_hash(key_hashes[0], 1_000, &hasher);
+    shard_bytes_with_hasher(b"foo", 1_000, &hasher);
+    assert_eq!(shard_into(&keys, 1_000, &mut out, &hasher), 256);
+    shard_key_hashes_into(&key_hashes, 1_000, &mut out, &hasher);
+    for shard in shard_for_many(&keys, 1_000, &hasher) {
+        assert!(shard < 1_000);
+    }
+
+    assert_eq!(ALLOCATIONS.load(Ordering::SeqCst), before);
+}